@@ -395,6 +395,16 @@ pub enum syntax_node_type_t {
     /// @brief A Group of nodes that cannot be combined into a coherent atom due to a parse error,
     ///     even if some of the individual nodes could represent valid atoms
     ERROR_GROUP,
+    /// @brief A Block Comment.  All text between `#|` and a matching `|#`, block comments can be nested
+    BLOCK_COMMENT,
+    /// @brief A Raw String Literal.  All text between `"""` delimiters, escape sequences are not processed
+    RAW_STRING_TOKEN,
+    /// @brief A Quasi-quotation Prefix.  A '`', ',' or ',@' char sequence immediately preceding an atom
+    QUOTE_PREFIX,
+    /// @brief A Group of a '`' `QUOTE_PREFIX` and the quasi-quoted atom which follows it
+    QUASI_QUOTE_GROUP,
+    /// @brief A Group of a ',' or ',@' `QUOTE_PREFIX` and the unquoted atom which follows it
+    UNQUOTE_GROUP,
 }
 
 impl From<SyntaxNodeType> for syntax_node_type_t {
@@ -410,6 +420,11 @@ impl From<SyntaxNodeType> for syntax_node_type_t {
             SyntaxNodeType::LeftoverText => Self::LEFTOVER_TEXT,
             SyntaxNodeType::ExpressionGroup => Self::EXPRESSION_GROUP,
             SyntaxNodeType::ErrorGroup => Self::ERROR_GROUP,
+            SyntaxNodeType::BlockComment => Self::BLOCK_COMMENT,
+            SyntaxNodeType::RawStringToken => Self::RAW_STRING_TOKEN,
+            SyntaxNodeType::QuotePrefix => Self::QUOTE_PREFIX,
+            SyntaxNodeType::QuasiQuoteGroup => Self::QUASI_QUOTE_GROUP,
+            SyntaxNodeType::UnquoteGroup => Self::UNQUOTE_GROUP,
         }
     }
 }
@@ -521,6 +536,27 @@ pub extern "C" fn syntax_node_src_range(node: *const syntax_node_t, range_start:
     unsafe{ *range_end = node.src_range.end; }
 }
 
+/// @brief Formats the MeTTa source text trying to keep lines not longer than `width` chars
+/// @ingroup tokenizer_and_parser_group
+/// @param[in]  text  A C-style string containing the MeTTa source text
+/// @param[in]  width  The maximal width of the formatted lines
+/// @param[in]  callback  A function that will be called to provide the formatted text, or the
+///    description of the syntax error if the text cannot be parsed
+/// @param[in]  context  A pointer to a caller-defined structure to facilitate communication with the `callback` function
+/// @return `true` if the text was formatted, `false` if the text cannot be parsed
+///
+#[no_mangle]
+pub extern "C" fn format_source(text: *const c_char, width: usize,
+        callback: c_str_callback_t, context: *mut c_void) -> bool {
+    let (formatted, result) = match hyperon::metta::format::format_source(cstr_as_str(text), width) {
+        Ok(formatted) => (formatted, true),
+        Err(err) => (err, false),
+    };
+    let formatted = string_as_cstr(formatted);
+    callback(formatted.as_ptr(), context);
+    result
+}
+
 // =-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-
 // MeTTa Language and Types
 // =-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-
@@ -1088,6 +1124,29 @@ pub extern "C" fn metta_evaluate_atom(metta: *mut metta_t, atom: atom_t,
     }
 }
 
+/// @brief Checks types of all `=` definitions and `!` expressions of the MeTTa source text in the
+///    context of the runner's top module without running the code
+/// @ingroup interpreter_group
+/// @param[in]  metta  A pointer to the runner handle
+/// @param[in]  text  A C-style string containing the MeTTa source text
+/// @param[in]  callback  A function that will be called to provide the description of each error found
+/// @param[in]  context  A pointer to a caller-defined structure to facilitate communication with the `callback` function
+/// @return The number of errors found
+/// @note Modules imported by the text are not loaded into the runner, the declarations of the
+///    modules which are not loaded yet are read from their MeTTa source without running it
+///
+#[no_mangle]
+pub extern "C" fn metta_check(metta: *const metta_t, text: *const c_char,
+        callback: c_str_callback_t, context: *mut c_void) -> usize {
+    let metta = unsafe{ &*metta }.borrow();
+    let errors = metta.check(cstr_as_str(text));
+    for error in &errors {
+        let error = string_as_cstr(error.to_string());
+        callback(error.as_ptr(), context);
+    }
+    errors.len()
+}
+
 /// @brief Loads a module directly into the runner, from a mod_loader_callback_t
 /// @ingroup interpreter_group
 /// @param[in]  metta  A pointer to the handle specifying the runner into which to load the module
@@ -3,6 +3,7 @@ use std::io::{Cursor, Write};
 use std::ffi::CString;
 use std::os::raw::c_char;
 use std::ffi::CStr;
+use std::os::raw::c_void;
 use log::{error, warn, info};
 
 pub fn cstr_as_str<'a>(s: *const c_char) -> &'a str {
@@ -72,6 +73,13 @@ pub(crate) fn write_debug_into_buf<T: std::fmt::Debug>(obj: T, buf: *mut c_char,
     write_into_buf(DisplayDebug(obj), buf, buf_len)
 }
 
+/// @brief Function signature for a callback providing access to a C-style string
+/// @ingroup misc_group
+/// @param[in]  str  The C-style string being provided.  The string should not be modified or freed by the callback.
+/// @param[in]  context  The context state pointer initially passed to the upstream function initiating the callback.
+///
+pub type c_str_callback_t = extern "C" fn(str: *const c_char, context: *mut c_void);
+
 /// @brief Logs an error through the MeTTa library's logger.  Does not halt execution.
 /// @ingroup misc_group
 /// @param[in]  msg  A C-style string containing the message to log
@@ -23,6 +23,8 @@ pub trait Serializer {
     fn serialize_i64(&mut self, _v: i64) -> Result { Err(Error::NotSupported) }
     /// Serialize f64 value.
     fn serialize_f64(&mut self, _v: f64) -> Result { Err(Error::NotSupported) }
+    /// Serialize string value.
+    fn serialize_str(&mut self, _v: &str) -> Result { Err(Error::NotSupported) }
 }
 
 /// Serialization error code
@@ -0,0 +1,393 @@
+//! Algebraic data types declared by `data` expressions.
+//!
+//! `(data (Maybe $t) (Nothing) (Just $t))` declares the type constructor
+//! `Maybe` with a single type parameter and two data constructors. The first
+//! argument is a type name or an expression of the type name and distinct type
+//! variables, each next argument is a constructor name followed by the types of
+//! its fields. Constructors without fields can be written as plain symbols as
+//! well. The declaration is expanded into the regular type declarations:
+//!
+//! ```metta
+//! (: Maybe (-> Type Type))
+//! (: Nothing (Maybe $t))
+//! (: Just (-> $t (Maybe $t)))
+//! (data-constructors Maybe (Nothing (Just $t)))
+//! ```
+//!
+//! Type of the type constructor is its kind. Kinds are used to check type
+//! constructors are applied to the correct number of arguments, see
+//! [check_kinds]. The `data-constructors` expression lists all constructors of
+//! the type, it is used to check `case` and `switch` patterns cover all of
+//! them, see [missing_constructors].
+
+use super::*;
+use super::types::{get_atom_types, display_type};
+use crate::space::Space;
+
+/// Parsed `(data <type> <constructor>...)` declaration
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataDeclaration {
+    name: Atom,
+    params: Vec<Atom>,
+    constructors: Vec<(Atom, Vec<Atom>)>,
+}
+
+impl DataDeclaration {
+
+    /// Parses the `atom` as a data declaration. Returns `None` when the atom is
+    /// not a `data` expression and an error when the declaration is malformed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperon::expr;
+    /// use hyperon::metta::adt::DataDeclaration;
+    ///
+    /// let decl = DataDeclaration::parse(&expr!("data" ("Maybe" t) ("Nothing") ("Just" t)));
+    /// let decl = decl.unwrap().unwrap();
+    ///
+    /// assert_eq!(decl.type_atom(), expr!("Maybe" t));
+    /// assert_eq!(decl.kind(), expr!("->" "Type" "Type"));
+    /// assert!(DataDeclaration::parse(&expr!("Just" t)).is_none());
+    /// assert!(DataDeclaration::parse(&expr!("data" ("Maybe" "Number") ("Nothing"))).unwrap().is_err());
+    /// ```
+    pub fn parse(atom: &Atom) -> Option<Result<Self, String>> {
+        match atom {
+            Atom::Expression(expr) if expr.children().first() == Some(&DATA_SYMBOL) =>
+                Some(Self::parse_children(atom, &expr.children()[1..])),
+            _ => None,
+        }
+    }
+
+    fn parse_children(atom: &Atom, children: &[Atom]) -> Result<Self, String> {
+        let (name, params) = match children.first() {
+            Some(name @ Atom::Symbol(_)) => (name.clone(), vec![]),
+            Some(Atom::Expression(head)) => match head.children().split_first() {
+                Some((name @ Atom::Symbol(_), params)) => (name.clone(), params.to_vec()),
+                _ => return Err(format!("{} should start from the type name", head)),
+            },
+            _ => return Err(format!("{} should declare a type name or (<name> <type variables>...)", atom)),
+        };
+        for (i, param) in params.iter().enumerate() {
+            if !matches!(param, Atom::Variable(_)) || params[..i].contains(param) {
+                return Err(format!("{} should be a distinct type variable in {}", param, children[0]));
+            }
+        }
+        let mut constructors = Vec::new();
+        for constructor in &children[1..] {
+            let (ctor_name, fields) = match constructor {
+                Atom::Symbol(_) => (constructor.clone(), vec![]),
+                Atom::Expression(expr) => match expr.children().split_first() {
+                    Some((ctor_name @ Atom::Symbol(_), fields)) => (ctor_name.clone(), fields.to_vec()),
+                    _ => return Err(format!("constructor {} should start from the constructor name", constructor)),
+                },
+                _ => return Err(format!("constructor {} should be a symbol or an expression", constructor)),
+            };
+            let mut field_vars = fields.iter().flat_map(|field| field.iter()).filter(|atom| matches!(atom, Atom::Variable(_)));
+            if let Some(var) = field_vars.find(|var| !params.contains(var)) {
+                return Err(format!("variable {} of constructor {} is not a parameter of {}", var, constructor, children[0]));
+            }
+            constructors.push((ctor_name, fields));
+        }
+        Ok(Self{ name, params, constructors })
+    }
+
+    /// Returns the name of the declared type
+    pub fn name(&self) -> &Atom {
+        &self.name
+    }
+
+    /// Returns the declared type applied to its type variables
+    pub fn type_atom(&self) -> Atom {
+        if self.params.is_empty() {
+            self.name.clone()
+        } else {
+            Atom::expr([std::slice::from_ref(&self.name), &self.params[..]].concat())
+        }
+    }
+
+    /// Returns the kind of the type constructor: `Type` for the type without
+    /// parameters and `(-> Type ... Type)` otherwise
+    pub fn kind(&self) -> Atom {
+        if self.params.is_empty() {
+            ATOM_TYPE_TYPE
+        } else {
+            let mut kind = vec![ARROW_SYMBOL];
+            kind.resize(self.params.len() + 2, ATOM_TYPE_TYPE);
+            Atom::expr(kind)
+        }
+    }
+
+    /// Returns the constructors in the form they are matched by the patterns:
+    /// symbols for the constructors without fields and `(<name> <field types>...)`
+    /// expressions otherwise
+    pub fn constructors(&self) -> Vec<Atom> {
+        self.constructors.iter().map(|(name, fields)| {
+            if fields.is_empty() {
+                name.clone()
+            } else {
+                Atom::expr([std::slice::from_ref(name), &fields[..]].concat())
+            }
+        }).collect()
+    }
+
+    /// Returns the type declarations the data declaration is expanded into
+    pub fn declarations(&self) -> Vec<Atom> {
+        let typ = self.type_atom();
+        let mut declarations = vec![Atom::expr([HAS_TYPE_SYMBOL, self.name.clone(), self.kind()])];
+        for (name, fields) in &self.constructors {
+            let ctor_type = if fields.is_empty() {
+                typ.clone()
+            } else {
+                Atom::expr([&[ARROW_SYMBOL], &fields[..], std::slice::from_ref(&typ)].concat())
+            };
+            declarations.push(Atom::expr([HAS_TYPE_SYMBOL, name.clone(), ctor_type]));
+        }
+        declarations.push(Atom::expr([DATA_CONSTRUCTORS_SYMBOL, self.name.clone(), Atom::expr(self.constructors())]));
+        declarations
+    }
+
+    /// Checks type constructors are applied correctly in the types of the
+    /// fields. The declared type itself can be used in the fields, which
+    /// allows recursive types like `(data (Tree $t) (Leaf) (Node (Tree $t) $t (Tree $t)))`.
+    pub fn check_kinds(&self, space: &dyn Space) -> Result<(), String> {
+        let arity = |name: &Atom| if *name == self.name {
+            Some(self.params.len())
+        } else {
+            kind_arity(space, name)
+        };
+        self.constructors.iter().flat_map(|(_, fields)| fields.iter())
+            .try_for_each(|field| check_kinds_with(field, &arity))
+    }
+}
+
+/// Returns the number of the type arguments of the type constructor `name`
+/// when its kind is declared
+fn kind_arity(space: &dyn Space, name: &Atom) -> Option<usize> {
+    get_atom_types(space, name).iter().find_map(|kind| match kind {
+        _ if *kind == ATOM_TYPE_TYPE => Some(0),
+        Atom::Expression(expr) if expr.children().first() == Some(&ARROW_SYMBOL)
+            && expr.children()[1..].iter().all(|typ| *typ == ATOM_TYPE_TYPE) =>
+            Some(expr.children().len() - 2),
+        _ => None,
+    })
+}
+
+fn kind_error(name: &Atom, arity: usize, args: usize, typ: &Atom) -> String {
+    format!("type constructor {} expects {} type arguments but {} given in {}", name, arity, args, display_type(typ))
+}
+
+fn check_kinds_with(typ: &Atom, arity: &dyn Fn(&Atom) -> Option<usize>) -> Result<(), String> {
+    match typ {
+        Atom::Symbol(_) => match arity(typ) {
+            Some(expected) if expected > 0 => Err(kind_error(typ, expected, 0, typ)),
+            _ => Ok(()),
+        },
+        Atom::Expression(expr) => {
+            let args = match expr.children().split_first() {
+                Some((op, args)) if *op == ARROW_SYMBOL => args,
+                Some((op @ Atom::Symbol(_), args)) => match arity(op) {
+                    Some(expected) if expected != args.len() => return Err(kind_error(op, expected, args.len(), typ)),
+                    Some(_) => args,
+                    None => expr.children().as_slice(),
+                },
+                _ => expr.children().as_slice(),
+            };
+            args.iter().try_for_each(|arg| check_kinds_with(arg, arity))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks all type constructors with the declared kinds are applied to the
+/// correct number of type arguments inside of the type `typ`.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::adt::check_kinds;
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("(data (Maybe $t) (Nothing) (Just $t))")).unwrap();
+/// let space = metta.space();
+///
+/// assert_eq!(check_kinds(&space, &expr!("->" ("Maybe" "Number") "Number")), Ok(()));
+/// assert_eq!(check_kinds(&space, &expr!("->" "Maybe" "Number")),
+///     Err("type constructor Maybe expects 1 type arguments but 0 given in Maybe".into()));
+/// ```
+pub fn check_kinds(space: &dyn Space, typ: &Atom) -> Result<(), String> {
+    check_kinds_with(typ, &|name| kind_arity(space, name))
+}
+
+/// Checks kinds of the type in the `(: <atom> <type>)` declaration, other
+/// atoms are accepted as is
+pub fn check_declaration_kinds(space: &dyn Space, atom: &Atom) -> Result<(), String> {
+    match atom {
+        Atom::Expression(expr) => match expr.children().as_slice() {
+            [op, _, typ] if *op == HAS_TYPE_SYMBOL => check_kinds(space, typ),
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Returns constructors of the data type `typ` in the form returned by
+/// [DataDeclaration::constructors] or `None` when `typ` is not declared
+/// by a `data` expression.
+pub fn get_data_constructors(space: &dyn Space, typ: &Atom) -> Option<Vec<Atom>> {
+    let name = match typ {
+        Atom::Symbol(_) => typ,
+        Atom::Expression(expr) => expr.children().first().filter(|op| matches!(op, Atom::Symbol(_)))?,
+        _ => return None,
+    };
+    let var = VariableAtom::new("constructors").make_unique();
+    let query = Atom::expr([DATA_CONSTRUCTORS_SYMBOL, name.clone(), Atom::Variable(var.clone())]);
+    let constructors = space.query(&query).into_iter().next()?.resolve(&var)?;
+    match constructors {
+        Atom::Expression(expr) => Some(expr.children().iter().map(display_type).collect()),
+        _ => None,
+    }
+}
+
+/// Returns `true` when the `pattern` matches any value built by the `constructor`
+fn covers_constructor(pattern: &Atom, constructor: &Atom) -> bool {
+    match (pattern, constructor) {
+        (Atom::Variable(_), _) => true,
+        (Atom::Symbol(_), Atom::Symbol(_)) => pattern == constructor,
+        (Atom::Expression(pattern), Atom::Expression(constructor)) => {
+            let (pattern, constructor) = (pattern.children(), constructor.children());
+            pattern.len() == constructor.len() && pattern[0] == constructor[0]
+                && pattern[1..].iter().all(|field| matches!(field, Atom::Variable(_)))
+        },
+        _ => false,
+    }
+}
+
+/// Returns constructors of the data type `typ` which are not covered by the
+/// `patterns`. A constructor is covered by a variable, by its name when it has
+/// no fields, or by the constructor applied to variables. Nested patterns
+/// cover only part of the values and they are not taken into account. Empty
+/// result is returned when `typ` is not a data type.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::adt::missing_constructors;
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("(data (Maybe $t) (Nothing) (Just $t))")).unwrap();
+/// let space = metta.space();
+/// let typ = expr!("Maybe" "Number");
+///
+/// assert_eq!(missing_constructors(&space, &typ, &[expr!("Nothing"), expr!("Just" x)]), vec![]);
+/// assert_eq!(missing_constructors(&space, &typ, &[expr!("Just" {1})]), vec![expr!("Nothing"), expr!("Just" t)]);
+/// assert_eq!(missing_constructors(&space, &typ, &[expr!(x)]), vec![]);
+/// ```
+pub fn missing_constructors(space: &dyn Space, typ: &Atom, patterns: &[Atom]) -> Vec<Atom> {
+    get_data_constructors(space, typ).unwrap_or_default().into_iter()
+        .filter(|constructor| !patterns.iter().any(|pattern| covers_constructor(pattern, constructor)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metta::runner::*;
+    use crate::metta::runner::arithmetics::Number;
+    use crate::metta::runner::string::Str;
+    use crate::metta::text::{SExprParser, Tokenizer};
+
+    fn declaration(text: &str) -> Result<DataDeclaration, String> {
+        let atom = SExprParser::new(text).parse(&Tokenizer::new()).unwrap().unwrap();
+        DataDeclaration::parse(&atom).expect("data declaration is expected")
+    }
+
+    fn run(metta: &Metta, text: &str) -> Vec<Vec<Atom>> {
+        metta.run(SExprParser::new(text)).unwrap()
+    }
+
+    #[test]
+    fn data_declaration_expansion() {
+        let decl = declaration("(data (Maybe $t) (Nothing) (Just $t))").unwrap();
+        assert_eq!(decl.declarations(), vec![
+            expr!(":" "Maybe" ("->" "Type" "Type")),
+            expr!(":" "Nothing" ("Maybe" t)),
+            expr!(":" "Just" ("->" t ("Maybe" t))),
+            expr!("data-constructors" "Maybe" ("Nothing" ("Just" t))),
+        ]);
+
+        let decl = declaration("(data Color Red Green)").unwrap();
+        assert_eq!(decl.declarations(), vec![
+            expr!(":" "Color" "Type"),
+            expr!(":" "Red" "Color"),
+            expr!(":" "Green" "Color"),
+            expr!("data-constructors" "Color" ("Red" "Green")),
+        ]);
+    }
+
+    #[test]
+    fn data_declaration_malformed() {
+        assert_eq!(declaration("(data (Pair $a $a) (MkPair $a $a))"),
+            Err("$a should be a distinct type variable in (Pair $a $a)".into()));
+        assert_eq!(declaration("(data (Maybe $t) (Just $x))"),
+            Err("variable $x of constructor (Just $x) is not a parameter of (Maybe $t)".into()));
+        assert_eq!(declaration("(data (Maybe $t) ((Just) $t))"),
+            Err("constructor ((Just) $t) should start from the constructor name".into()));
+    }
+
+    #[test]
+    fn data_declaration_check_kinds() {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        run(&metta, "(data (Maybe $t) (Nothing) (Just $t))");
+        let space = metta.space();
+
+        let tree = declaration("(data (Tree $t) (Leaf) (Node (Tree $t) (Maybe $t) (Tree $t)))").unwrap();
+        assert_eq!(tree.check_kinds(&space), Ok(()));
+        let bad = declaration("(data (Tree $t) (Leaf) (Node (Tree $t $t) $t))").unwrap();
+        assert_eq!(bad.check_kinds(&space),
+            Err("type constructor Tree expects 1 type arguments but 2 given in (Tree $t $t)".into()));
+        let bad = declaration("(data Opt (Opt (Maybe Maybe)))").unwrap();
+        assert_eq!(bad.check_kinds(&space),
+            Err("type constructor Maybe expects 1 type arguments but 0 given in Maybe".into()));
+    }
+
+    #[test]
+    fn data_declaration_is_used_by_interpreter() {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        let result = run(&metta, "
+            (data (Maybe $t) (Nothing) (Just $t))
+            (: from-maybe (-> $t (Maybe $t) $t))
+            (= (from-maybe $d Nothing) $d)
+            (= (from-maybe $d (Just $x)) $x)
+            !(get-type (Just 5))
+            !(from-maybe 0 (Just 5))
+            !(from-maybe 0 Nothing)
+        ");
+        assert_eq!(result, vec![
+            vec![expr!("Maybe" "Number")],
+            vec![Atom::gnd(Number::Integer(5))],
+            vec![Atom::gnd(Number::Integer(0))],
+        ]);
+    }
+
+    #[test]
+    fn data_declaration_type_check() {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        run(&metta, "
+            !(pragma! type-check auto)
+            (data (Maybe $t) (Nothing) (Just $t))
+        ");
+        assert_eq!(run(&metta, "(data (Pair $a) (MkPair $a $b))"),
+            vec![vec![expr!("Error" ("data" ("Pair" a) ("MkPair" a b))
+                "BadType" {Str::from_str("variable $b of constructor (MkPair $a $b) is not a parameter of (Pair $a)")})]]);
+        assert_eq!(run(&metta, "(: first (-> (Maybe) Number))"),
+            vec![vec![expr!("Error" (":" "first" ("->" ("Maybe") "Number"))
+                "BadType" {Str::from_str("type constructor Maybe expects 1 type arguments but 0 given in (Maybe)")})]]);
+    }
+}
@@ -0,0 +1,294 @@
+//! Canonical formatting of MeTTa source text.
+//!
+//! [format_source] re-indents a source text using the syntax tree built by
+//! [SExprParser::parse_to_syntax_tree], so the text of tokens and comments is kept as is.
+//! An expression is written on a single line when it fits into the line width, otherwise it
+//! is broken: a symbol at the head of an expression keeps its first argument on the same line
+//! and the rest of the arguments are written one per line, indented by two spaces relative to
+//! the open parenthesis; other expressions have all their children aligned under the first one.
+//! Top level atoms are written one per line, `!` is kept on the line of the atom which follows it, and
+//! a single blank line between top level atoms is kept.
+//!
+//! # Examples
+//!
+//! ```
+//! use hyperon::metta::format::format_source;
+//!
+//! let text = "(= (fac $n)   (if (== $n 0) 1 (* $n (fac (- $n 1)))))  ; factorial\n! (fac 5)";
+//! assert_eq!(format_source(text, 40).unwrap(), "\
+//! (= (fac $n)
+//!   (if (== $n 0) 1 (* $n (fac (- $n 1))))) ; factorial
+//! !(fac 5)
+//! ");
+//! ```
+
+use super::text::{SExprParser, SyntaxNode, SyntaxNodeType, TextPosition};
+
+/// Default maximal width of the formatted lines
+pub const DEFAULT_LINE_WIDTH: usize = 80;
+
+/// Formats the MeTTa source `text` trying to keep lines not longer than `width` chars. Returns
+/// an error with the position of the first syntax error if `text` cannot be parsed.
+pub fn format_source(text: &str, width: usize) -> Result<String, String> {
+    let items = parse_items(text)?;
+    let mut writer = Writer{ out: String::new(), col: 0, width };
+    let mut prev: Option<&Item> = None;
+    for entry in items.iter() {
+        match prev {
+            None => {},
+            Some(Item::Token(token)) if token == "!" && matches!(entry.item, Item::Expr(_)) => {},
+            Some(Item::Token(token)) if token == "!" && !entry.item.is_comment() => writer.write(" "),
+            Some(_) if entry.is_trailing_comment() => writer.write(" "),
+            Some(_) => {
+                if entry.newlines_before > 1 {
+                    writer.write("\n");
+                }
+                writer.new_line(0);
+            },
+        }
+        writer.write_item(&entry.item);
+        prev = Some(&entry.item);
+    }
+    if !items.is_empty() {
+        writer.write("\n");
+    }
+    Ok(writer.out)
+}
+
+/// Returns `true` if `text` is already formatted by [format_source] with the same `width`
+pub fn is_formatted(text: &str, width: usize) -> Result<bool, String> {
+    Ok(format_source(text, width)? == text)
+}
+
+#[derive(Debug)]
+enum Item {
+    Token(String),
+    LineComment(String),
+    BlockComment(String),
+    Expr(Vec<Entry>),
+    /// Quasi-quotation prefix and the atom which follows it
+    Quoted(String, Box<Item>),
+}
+
+impl Item {
+    fn is_comment(&self) -> bool {
+        matches!(self, Item::LineComment(_) | Item::BlockComment(_))
+    }
+
+    /// Returns the single line representation of the item if it exists
+    fn flat(&self) -> Option<String> {
+        match self {
+            Item::Token(text) | Item::BlockComment(text) => (!text.contains('\n')).then(|| text.clone()),
+            Item::LineComment(_) => None,
+            Item::Expr(children) => {
+                let children: Option<Vec<String>> = children.iter()
+                    .map(|entry| entry.item.flat()).collect();
+                children.map(|children| format!("({})", children.join(" ")))
+            },
+            Item::Quoted(prefix, item) => item.flat().map(|flat| format!("{prefix}{flat}")),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    item: Item,
+    /// Number of line breaks between the item and the previous one in the source text
+    newlines_before: usize,
+}
+
+impl Entry {
+    fn is_trailing_comment(&self) -> bool {
+        matches!(self.item, Item::LineComment(_)) && self.newlines_before == 0
+    }
+}
+
+fn parse_items(text: &str) -> Result<Vec<Entry>, String> {
+    let mut parser = SExprParser::new(text);
+    let mut nodes = Vec::new();
+    while let Some(node) = parser.parse_to_syntax_tree() {
+        if let Some(error_node) = node.error_node() {
+            let position = TextPosition::from_offset(text, error_node.src_range.start);
+            return Err(format!("{}: {}", position, error_node.message.as_deref().unwrap_or("Syntax error")));
+        }
+        nodes.push(node);
+    }
+    Ok(collect_entries(text, nodes.iter()))
+}
+
+fn collect_entries<'a, I: Iterator<Item=&'a SyntaxNode>>(text: &str, nodes: I) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut newlines_before = 0;
+    for node in nodes {
+        match node.node_type {
+            SyntaxNodeType::Whitespace => {
+                newlines_before += text[node.src_range.clone()].matches('\n').count();
+            },
+            _ => if let Some(item) = node_item(text, node) {
+                entries.push(Entry{ item, newlines_before });
+                newlines_before = 0;
+            },
+        }
+    }
+    entries
+}
+
+/// Returns the item of the node, or `None` for the nodes which are not kept by formatting
+fn node_item(text: &str, node: &SyntaxNode) -> Option<Item> {
+    let source = &text[node.src_range.clone()];
+    let item = match node.node_type {
+        SyntaxNodeType::Whitespace |
+        SyntaxNodeType::OpenParen |
+        SyntaxNodeType::CloseParen |
+        SyntaxNodeType::QuotePrefix => return None,
+        SyntaxNodeType::Comment => Item::LineComment(source.trim_end().to_string()),
+        SyntaxNodeType::BlockComment => Item::BlockComment(source.to_string()),
+        SyntaxNodeType::ExpressionGroup => Item::Expr(collect_entries(text, node.sub_nodes.iter())),
+        SyntaxNodeType::QuasiQuoteGroup |
+        SyntaxNodeType::UnquoteGroup => {
+            let prefix = text[node.sub_nodes[0].src_range.clone()].to_string();
+            Item::Quoted(prefix, Box::new(node_item(text, &node.sub_nodes[1])?))
+        },
+        SyntaxNodeType::VariableToken |
+        SyntaxNodeType::StringToken |
+        SyntaxNodeType::RawStringToken |
+        SyntaxNodeType::WordToken => Item::Token(source.to_string()),
+        SyntaxNodeType::LeftoverText |
+        SyntaxNodeType::ErrorGroup => unreachable!(),
+    };
+    Some(item)
+}
+
+struct Writer {
+    out: String,
+    col: usize,
+    width: usize,
+}
+
+impl Writer {
+    fn write(&mut self, text: &str) {
+        match text.rfind('\n') {
+            Some(idx) => self.col = text[idx + 1..].chars().count(),
+            None => self.col += text.chars().count(),
+        }
+        self.out.push_str(text);
+    }
+
+    fn new_line(&mut self, indent: usize) {
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(indent));
+        self.col = indent;
+    }
+
+    fn write_item(&mut self, item: &Item) {
+        match item {
+            Item::Token(text) |
+            Item::LineComment(text) |
+            Item::BlockComment(text) => self.write(text),
+            Item::Expr(children) => {
+                match item.flat() {
+                    Some(flat) if self.col + flat.chars().count() <= self.width => self.write(&flat),
+                    _ => self.write_broken_expr(children),
+                }
+            },
+            Item::Quoted(prefix, item) => {
+                self.write(prefix);
+                self.write_item(item);
+            },
+        }
+    }
+
+    fn write_broken_expr(&mut self, children: &[Entry]) {
+        let paren_col = self.col;
+        let symbol_head = matches!(children.first(), Some(Entry{ item: Item::Token(_), .. }));
+        let indent = if symbol_head { paren_col + 2 } else { paren_col + 1 };
+        self.write("(");
+        for (i, entry) in children.iter().enumerate() {
+            if i > 0 {
+                if entry.is_trailing_comment() || (i == 1 && symbol_head && !entry.item.is_comment()) {
+                    self.write(" ");
+                } else {
+                    self.new_line(indent);
+                }
+            }
+            self.write_item(&entry.item);
+        }
+        if let Some(Entry{ item: Item::LineComment(_), .. }) = children.last() {
+            self.new_line(paren_col);
+        }
+        self.write(")");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_formatted(text: &str, width: usize, expected: &str) {
+        let formatted = format_source(text, width).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted, width).unwrap(), formatted);
+    }
+
+    #[test]
+    fn format_source_flat_expressions() {
+        assert_formatted("(a   b\n (c  $d)  \"s  t\")\n\n\n\n(e)(f)", 80, "(a b (c $d) \"s  t\")\n\n(e)\n(f)\n");
+        assert_formatted("", 80, "");
+        assert_formatted("! (foo)\n!\n(bar) !x", 80, "!(foo)\n!(bar)\n!x\n");
+        assert_formatted("!  r ; comment\n!\n$x", 80, "! r ; comment\n! $x\n");
+    }
+
+    #[test]
+    fn format_source_breaks_long_expressions() {
+        assert_formatted("(= (foo $x $y) (bar (baz $x) (qux $y)))", 20, "\
+(= (foo $x $y)
+  (bar (baz $x)
+    (qux $y)))
+");
+        assert_formatted("((a b c) (d e f) (g h i))", 12, "\
+((a b c)
+ (d e f)
+ (g h i))
+");
+    }
+
+    #[test]
+    fn format_source_keeps_comments() {
+        assert_formatted("; header\n(a ; first\n b #| inline |# c) ; trailing\n#| block\n  comment |#\n(d\n; last\n)", 80, "\
+; header
+(a ; first
+  b
+  #| inline |#
+  c) ; trailing
+#| block
+  comment |#
+(d
+  ; last
+)
+");
+    }
+
+    #[test]
+    fn format_source_keeps_multiline_strings() {
+        assert_formatted("(prompt   \"\"\"line 1\n   line 2\"\"\" x)", 80, "\
+(prompt \"\"\"line 1
+   line 2\"\"\"
+  x)
+");
+    }
+
+    #[test]
+    fn format_source_quasi_quotation() {
+        assert_formatted("`(foo   ,$x ,@(bar  $y))", 80, "`(foo ,$x ,@(bar $y))\n");
+        assert_formatted("`(foo ,(bar $x $y) ,@(baz $z))", 20, "\
+`(foo ,(bar $x $y)
+   ,@(baz $z))
+");
+    }
+
+    #[test]
+    fn format_source_syntax_error() {
+        assert_eq!(format_source("(a b)\n  (c \"d)", 80), Err("2:6: Unclosed String Literal".into()));
+        assert_eq!(format_source("(a))", 80), Err("1:4: Unexpected right bracket".into()));
+    }
+}
@@ -15,6 +15,13 @@
 //!   calculate its type is returned. When type is calculated the expression
 //!   is interpreted according its operation type. Note: few alternative
 //!   interpretations may be found here one for each type of the operation.
+//!   When operation is declared using `(dispatch <op> most-specific)` only
+//!   the most specific type which accepts the arguments is used, and only
+//!   the definitions of the operation which are bound to this type are
+//!   matched on call, see [select_overload] and
+//!   [get_definition_type](crate::metta::types::get_definition_type). If
+//!   there is no single most specific type or one of the definitions is not
+//!   bound to a type then `AmbiguousCall` error is returned.
 //!
 //! For and expression atom and its operation type:
 //! * If expected type is `Atom` or `Expression` then expression is returned as is.
@@ -72,7 +79,8 @@ use crate::space::*;
 use crate::common::collections::ListMap;
 use crate::metta::*;
 use crate::metta::types::{is_func, get_arg_types, get_type_bindings,
-    get_atom_types, match_reducted_types};
+    get_atom_types, match_reducted_types, explain_arg_type_mismatch, select_overload, get_overload_definitions};
+use crate::space::grounding::{record_matched_atoms, query_with_matched_atoms};
 
 use std::ops::Deref;
 use std::rc::Rc;
@@ -81,7 +89,7 @@ use std::collections::HashSet;
 
 /// Wrapper, So the old interpreter can present the same public interface as the new intperpreter
 pub struct InterpreterState<'a, T: SpaceRef<'a>> {
-    step_result: StepResult<'a, Results, InterpreterError>,
+    step_result: StepResult<'a, Results, InterpreterError<'a>>,
     phantom: core::marker::PhantomData<T>
 }
 
@@ -91,7 +99,7 @@ impl<'a, T: SpaceRef<'a>> InterpreterState<'a, T> {
     #[cfg(not(feature = "minimal"))]
     pub(crate) fn new_finished(_space: T, results: Vec<Atom>) -> Self {
         Self {
-            step_result: StepResult::Return(results.into_iter().map(|atom| InterpretedAtom(atom, Bindings::new())).collect()),
+            step_result: StepResult::Return(results.into_iter().map(|atom| InterpretedAtom(atom, Bindings::new(), Provenance::new())).collect()),
             phantom: <_>::default(),
         }
     }
@@ -105,7 +113,7 @@ impl<'a, T: SpaceRef<'a>> InterpreterState<'a, T> {
                 let res = res.drain(0..).map(|res| res.into_tuple().0).collect();
                 Ok(res)
             },
-            StepResult::Error((atom, err)) => Ok(vec![Atom::expr([ERROR_SYMBOL, atom, err])]),
+            StepResult::Error(err) => Ok(vec![error_to_atom(err)]),
             StepResult::Execute(_) => Err("Evaluation is not finished".into())
         }
     }
@@ -117,10 +125,14 @@ impl<'a, T: SpaceRef<'a>> Debug for InterpreterState<'a, T> {
     }
 }
 
-/// Result of atom interpretation plus variable bindings found
+/// Atoms of the space which were used to interpret an atom
+type Provenance = im::Vector<Atom>;
+
+/// Result of atom interpretation plus variable bindings found and atoms of
+/// the space used to find it
 #[derive(Clone)]
 #[cfg_attr(test, derive(PartialEq))]
-pub struct InterpretedAtom(Atom, Bindings);
+pub struct InterpretedAtom(Atom, Bindings, Provenance);
 
 impl InterpretedAtom {
     fn atom(&self) -> &Atom {
@@ -131,6 +143,10 @@ impl InterpretedAtom {
         &self.1
     }
 
+    fn provenance(&self) -> &Provenance {
+        &self.2
+    }
+
     /// Convert the instance into tuple of [Atom] and [Bindings]
     pub fn into_tuple(self) -> (Atom, Bindings) {
         (self.0, self.1)
@@ -161,8 +177,67 @@ impl Debug for InterpretedAtom {
 }
 
 type Results = Vec<InterpretedAtom>;
-type InterpreterError = (Atom, Atom);
-type NoInputPlan<'a> = Box<dyn Plan<'a, (), Results, InterpreterError> + 'a>;
+/// Erroneous atom, error code and an optional explanation of the error
+type InterpreterError<'a> = (Atom, Atom, Option<ErrorMessage<'a>>);
+type NoInputPlan<'a> = Box<dyn Plan<'a, (), Results, InterpreterError<'a>> + 'a>;
+
+/// Explanation of the error. It is calculated only when the error is returned
+/// as a result of the interpretation, because most errors are discarded when
+/// other alternatives of the interpretation succeed.
+#[derive(Clone)]
+pub struct ErrorMessage<'a>(Rc<dyn Fn() -> String + 'a>);
+
+impl<'a> ErrorMessage<'a> {
+    fn new<F: Fn() -> String + 'a>(f: F) -> Self {
+        Self(Rc::new(f))
+    }
+
+    fn text(&self) -> String {
+        (self.0)()
+    }
+}
+
+impl Debug for ErrorMessage<'_> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self.text())
+    }
+}
+
+impl PartialEq for ErrorMessage<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.text() == other.text()
+    }
+}
+
+fn error_to_atom((atom, err, message): InterpreterError) -> Atom {
+    match message {
+        Some(message) => explained_error_atom(atom, err, message.text()),
+        None => Atom::expr([ERROR_SYMBOL, atom, err]),
+    }
+}
+
+/// Position of the argument which is interpreted as a value of the type
+/// required by the operation, it is used to explain type errors
+#[derive(Clone)]
+struct ArgPosition {
+    expr: Atom,
+    index: usize,
+}
+
+/// Returns the type error, the types of the `atom` are queried from the space
+/// when `actual` types are not passed
+fn bad_type_error<'a, T: SpaceRef<'a>>(context: &InterpreterContextRef<'a, T>, position: Option<ArgPosition>,
+        atom: Atom, actual: Option<Vec<Atom>>, expected: Atom) -> InterpreterError<'a> {
+    let message = position.map(|ArgPosition{ expr, index }| {
+        let context = context.clone();
+        let arg = atom.clone();
+        ErrorMessage::new(move || {
+            let actual = actual.clone().unwrap_or_else(|| get_atom_types(&context.space, &arg));
+            explain_arg_type_mismatch(&context.space, &expr, index, &actual, &expected)
+        })
+    });
+    (atom, BAD_TYPE_SYMBOL, message)
+}
 
 /// Initialize interpreter and returns the result of the zero step.
 /// It can be error, immediate result or interpretation plan to be executed.
@@ -172,15 +247,15 @@ type NoInputPlan<'a> = Box<dyn Plan<'a, (), Results, InterpreterError> + 'a>;
 /// * `space` - atomspace to query for interpretation
 /// * `expr` - atom to interpret
 pub fn interpret_init<'a, T: Space + 'a>(space: T, expr: &Atom) -> InterpreterState<'a, T> {
-    let step_result = interpret_init_internal(space, expr);
+    let step_result = interpret_init_internal(space, expr, false);
     InterpreterState { step_result: step_result, phantom: <_>::default() }
 }
 
-fn interpret_init_internal<'a, T: Space + 'a>(space: T, expr: &Atom) -> StepResult<'a, Results, InterpreterError> {
-    let context = InterpreterContextRef::new(space);
+fn interpret_init_internal<'a, T: Space + 'a>(space: T, expr: &Atom, provenance: bool) -> StepResult<'a, Results, InterpreterError<'a>> {
+    let context = InterpreterContextRef::new(space, provenance);
     interpret_as_type_plan(context,
-        InterpretedAtom(expr.clone(), Bindings::new()),
-        ATOM_TYPE_UNDEFINED)
+        InterpretedAtom(expr.clone(), Bindings::new(), Provenance::new()),
+        ATOM_TYPE_UNDEFINED, None)
 }
 
 /// Perform next step of the interpretation plan and return the result. Panics
@@ -211,9 +286,43 @@ pub fn interpret<T: Space>(space: T, expr: &Atom) -> Result<Vec<Atom>, String> {
     }
     match step.step_result {
         StepResult::Return(mut result) => Ok(result.drain(0..)
-            .map(|InterpretedAtom(atom, _)| atom).collect()),
-        // TODO: return (Error atom err) expression
-        StepResult::Error((atom, err)) => Ok(vec![Atom::expr([ERROR_SYMBOL, atom, err])]),
+            .map(|InterpretedAtom(atom, _, _)| atom).collect()),
+        StepResult::Error(err) => Ok(vec![error_to_atom(err)]),
+        _ => panic!("Not expected step result: {:?}", step),
+    }
+}
+
+/// Interpret passed atom like [interpret] does and return each result
+/// together with the atoms of the space which were used to derive it: the
+/// `=` definitions applied and the atoms read by the grounded operations (for
+/// example by `match`). All atoms read by a grounded operation are used to
+/// derive each of its results. Atoms are returned in order of usage without
+/// duplicates. Atoms are returned as they are kept in the space (see
+/// [record_matched_atoms]), thus they can be removed from it.
+///
+/// # Arguments
+/// * `space` - atomspace to query for interpretation
+/// * `expr` - atom to interpret
+pub fn interpret_with_provenance<T: Space>(space: T, expr: &Atom) -> Result<Vec<(Atom, Vec<Atom>)>, String> {
+    let mut step = interpret_init_internal(space, expr, true);
+    while step.has_next() {
+        step = match step {
+            StepResult::Execute(plan) => plan.step(()),
+            _ => unreachable!(),
+        };
+    }
+    match step {
+        StepResult::Return(mut result) => Ok(result.drain(0..)
+            .map(|InterpretedAtom(atom, _, provenance)| {
+                let mut atoms: Vec<Atom> = Vec::new();
+                for atom in provenance {
+                    if !atoms.contains(&atom) {
+                        atoms.push(atom);
+                    }
+                }
+                (atom, atoms)
+            }).collect()),
+        StepResult::Error(err) => Ok(vec![(error_to_atom(err), Vec::new())]),
         _ => panic!("Not expected step result: {:?}", step),
     }
 }
@@ -241,7 +350,7 @@ impl InterpreterCache {
                 atom.iter_mut().filter_type::<&mut VariableAtom>()
                     .for_each(|var| *var = var_mapper.replace(var.clone()));
                 let bindings = res.bindings().clone().rename_vars(var_mapper.as_fn_mut());
-                result.push(InterpretedAtom(atom, bindings));
+                result.push(InterpretedAtom(atom, bindings, res.provenance().clone()));
             }
             result
         })
@@ -268,6 +377,30 @@ impl SpaceObserver for InterpreterCache {
     }
 }
 
+/// Operations declared using `(dispatch <op> most-specific)`, they are
+/// collected by a single query when an operation with several types is called
+/// and reset when the space is modified
+struct DispatchedOps(Option<Vec<Atom>>);
+
+impl DispatchedOps {
+    fn contains(&mut self, space: &dyn Space, op: &Atom) -> bool {
+        let ops = self.0.get_or_insert_with(|| {
+            let var = VariableAtom::new("op").make_unique();
+            let query = Atom::expr([DISPATCH_SYMBOL, Atom::Variable(var.clone()), MOST_SPECIFIC_SYMBOL]);
+            space.query(&query).into_iter()
+                .filter_map(|bindings| bindings.resolve(&var))
+                .collect()
+        });
+        ops.contains(op)
+    }
+}
+
+impl SpaceObserver for DispatchedOps {
+    fn notify(&mut self, _event: &SpaceEvent) {
+        self.0 = None;
+    }
+}
+
 use std::marker::PhantomData;
 
 pub trait SpaceRef<'a> : Space + 'a {}
@@ -276,16 +409,19 @@ impl<'a, T: Space + 'a> SpaceRef<'a> for T {}
 struct InterpreterContext<'a, T: SpaceRef<'a>> {
     space: T,
     cache: SpaceObserverRef<InterpreterCache>,
+    dispatched: SpaceObserverRef<DispatchedOps>,
+    provenance: bool,
     phantom: PhantomData<&'a T>,
 }
 
 struct InterpreterContextRef<'a, T: SpaceRef<'a>>(Rc<InterpreterContext<'a, T>>);
 
 impl<'a, T: SpaceRef<'a>> InterpreterContextRef<'a, T> {
-    fn new(space: T) -> Self {
+    fn new(space: T, provenance: bool) -> Self {
         let cache = space.common().register_observer(InterpreterCache::new());
+        let dispatched = space.common().register_observer(DispatchedOps(None));
 
-        Self(Rc::new(InterpreterContext{ space, cache, phantom: PhantomData }))
+        Self(Rc::new(InterpreterContext{ space, cache, dispatched, provenance, phantom: PhantomData }))
     }
 }
 
@@ -329,7 +465,7 @@ fn has_grounded_sub_expr(expr: &ExpressionAtom) -> bool {
 }
 
 fn interpret_as_type_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>,
-        input: InterpretedAtom, typ: Atom) -> StepResult<'a, Results, InterpreterError> {
+        input: InterpretedAtom, typ: Atom, position: Option<ArgPosition>) -> StepResult<'a, Results, InterpreterError<'a>> {
     log::debug!("interpret_as_type_plan: input: {}, type: {}", input, typ);
     match input.atom() {
 
@@ -340,16 +476,16 @@ fn interpret_as_type_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a
         Atom::Grounded(_) if typ == ATOM_TYPE_GROUNDED => StepResult::ret(vec![input]),
 
         Atom::Symbol(_) | Atom::Grounded(_) =>
-            cast_atom_to_type_plan(context, input, typ),
+            cast_atom_to_type_plan(context, input, typ, position),
 
         Atom::Expression(ref expr) if expr.children().is_empty() =>
-            cast_atom_to_type_plan(context, input, typ),
+            cast_atom_to_type_plan(context, input, typ, position),
 
         Atom::Expression(ref expr) => {
             let op = &expr.children()[0];
             StepResult::execute(SequencePlan::new(
                     get_type_of_atom_plan(context.clone(), op.clone()),
-                    interpret_expression_as_type_plan(context, input, typ)
+                    interpret_expression_as_type_plan(context, input, typ, position)
             ))
         },
 
@@ -360,7 +496,7 @@ fn interpret_as_type_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a
 }
 
 fn cast_atom_to_type_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>,
-        input: InterpretedAtom, typ: Atom) -> StepResult<'a, Results, InterpreterError> {
+        input: InterpretedAtom, typ: Atom, position: Option<ArgPosition>) -> StepResult<'a, Results, InterpreterError<'a>> {
     // TODO: implement this via interpreting of the (:cast atom typ) expression
     let typ = apply_bindings_to_atom(&typ, input.bindings());
     let mut results = get_type_bindings(&context.space, input.atom(), &typ);
@@ -368,35 +504,47 @@ fn cast_atom_to_type_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a
     if !results.is_empty() {
         log::debug!("cast_atom_to_type_plan: input: {} is casted to type: {}", input, typ);
         StepResult::ret(results.drain(0..).map(|(_match_typ, typ_bindings)| {
-            let InterpretedAtom(atom, bindings) = input.clone();
+            let InterpretedAtom(atom, bindings, provenance) = input.clone();
             // TODO: need to understand if it is needed to apply bindings
             // should we apply bindings to bindings?
             let bindings = Bindings::merge(&bindings, &typ_bindings);
             if let Some(bindings) = bindings {
                 let atom = apply_bindings_to_atom(&atom, &bindings);
-                Some(InterpretedAtom(atom, bindings))
+                Some(InterpretedAtom(atom, bindings, provenance))
             } else {
                 None
             }
         }).filter(Option::is_some).map(Option::unwrap).collect())
     } else {
         log::debug!("cast_atom_to_type_plan: input: {} cannot be casted to type: {}", input, typ);
-        StepResult::err((input.0, BAD_TYPE_SYMBOL))
+        StepResult::err(bad_type_error(&context, position, input.0, None, typ))
     }
 }
 
-fn get_type_of_atom_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, atom: Atom) -> StepResult<'a, Vec<Atom>, InterpreterError> {
+fn get_type_of_atom_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, atom: Atom) -> StepResult<'a, Vec<Atom>, InterpreterError<'a>> {
     // TODO: implement this via interpreting of the (:? atom)
     StepResult::ret(get_atom_types(&context.space, &atom))
 }
 
 fn interpret_expression_as_type_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>,
-        input: InterpretedAtom, typ: Atom) -> OperatorPlan<'a, Vec<Atom>, Results, InterpreterError> {
+        input: InterpretedAtom, typ: Atom, position: Option<ArgPosition>) -> OperatorPlan<'a, Vec<Atom>, Results, InterpreterError<'a>> {
     let descr = format!("form alternative plans for expression {} using types", input);
     OperatorPlan::new(move |op_types: Vec<Atom>| {
+        // dispatch declaration is looked up only for the operations with several types
+        let overloaded = op_types.iter().filter(|typ| is_func(typ)).count() > 1
+            && context.dispatched.borrow_mut().contains(&context.space, &get_expr(input.atom()).children()[0]);
+        let (op_types, is_overload) = match overloaded {
+            true => match select_overload(&context.space, input.atom()) {
+                Ok(Some(op_typ)) => (vec![op_typ], true),
+                Ok(None) => (op_types, false),
+                Err(message) => return StepResult::err((input.0, AMBIGUOUS_CALL_SYMBOL,
+                    Some(ErrorMessage::new(move || message.clone())))),
+            },
+            false => (op_types, false),
+        };
         make_alternives_plan(input.0.clone(), op_types, move |op_typ| {
             interpret_expression_as_type_op(context.clone(),
-                input.clone(), op_typ, typ.clone())
+                input.clone(), op_typ, typ.clone(), position.clone(), is_overload)
         })
     }, descr)
 }
@@ -416,21 +564,23 @@ fn get_expr_mut(atom: &mut Atom) -> &mut ExpressionAtom {
 }
 
 fn interpret_expression_as_type_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>,
-        input: InterpretedAtom, op_typ: Atom, ret_typ: Atom) -> NoInputPlan<'a> {
+        input: InterpretedAtom, op_typ: Atom, ret_typ: Atom, position: Option<ArgPosition>,
+        is_overload: bool) -> NoInputPlan<'a> {
     log::debug!("interpret_expression_as_type_op: input: {}, operation type: {}, expected return type: {}", input, op_typ, ret_typ);
     if ret_typ == ATOM_TYPE_ATOM || ret_typ == ATOM_TYPE_EXPRESSION {
         Box::new(StepResult::ret(vec![input]))
     } else if is_func(&op_typ) {
-        let InterpretedAtom(input_atom, mut input_bindings) = input;
+        let InterpretedAtom(input_atom, mut input_bindings, provenance) = input;
         let expr = get_expr(&input_atom);
         let (op_arg_types, op_ret_typ) = get_arg_types(&op_typ);
         // TODO: supertypes should be checked as well
         if !match_reducted_types(op_ret_typ, &ret_typ, &mut input_bindings) {
-            Box::new(StepResult::err((input_atom, BAD_TYPE_SYMBOL)))
+            let actual = vec![op_ret_typ.clone()];
+            Box::new(StepResult::err(bad_type_error(&context, position, input_atom, Some(actual), ret_typ)))
         } else if op_arg_types.len() != (expr.children().len() - 1) {
-            Box::new(StepResult::err((input_atom, INCORRECT_NUMBER_OF_ARGUMENTS_SYMBOL)))
+            Box::new(StepResult::err((input_atom, INCORRECT_NUMBER_OF_ARGUMENTS_SYMBOL, None)))
         } else {
-            let input = InterpretedAtom(input_atom, input_bindings);
+            let input = InterpretedAtom(input_atom, input_bindings, provenance);
             let expr = get_expr(input.atom());
             assert!(!expr.children().is_empty(), "Empty expression is not expected");
             let mut plan: NoInputPlan = Box::new(StepResult::ret(vec![input.clone()]));
@@ -438,6 +588,9 @@ fn interpret_expression_as_type_op<'a, T: SpaceRef<'a>>(context: InterpreterCont
                 let arg = expr.children()[expr_idx].clone();
                 let arg_typ = if expr_idx > 0 {
                     op_arg_types[expr_idx - 1].clone()
+                } else if is_overload {
+                    // operation is typed by the selected overload already
+                    ATOM_TYPE_ATOM
                 } else {
                     op_typ.clone()
                 };
@@ -447,16 +600,18 @@ fn interpret_expression_as_type_op<'a, T: SpaceRef<'a>>(context: InterpreterCont
                     OperatorPlan::new(move |results: Results| {
                         make_alternives_plan(arg.clone(), results, move |result| -> NoInputPlan {
                             let arg_typ = apply_bindings_to_atom(&arg_typ, result.bindings());
+                            let position = (expr_idx > 0).then(|| ArgPosition{ expr: result.atom().clone(), index: expr_idx });
                             Box::new(SequencePlan::new(
                                 interpret_as_type_plan(context.clone(),
-                                    InterpretedAtom(arg.clone(), result.bindings().clone()),
-                                    arg_typ),
+                                    InterpretedAtom(arg.clone(), result.bindings().clone(), result.provenance().clone()),
+                                    arg_typ, position),
                                 insert_reducted_arg_plan(result, expr_idx)))
                         })
                     }, format!("Interpret {} argument", expr_idx))
                 ))
             }
-            call_alternatives_plan(plan, context, input)
+            let overload = is_overload.then(|| op_typ.clone());
+            call_alternatives_plan(plan, context, input, overload)
         }
     } else {
         let expr = get_expr(input.atom());
@@ -470,70 +625,74 @@ fn interpret_expression_as_type_op<'a, T: SpaceRef<'a>>(context: InterpreterCont
                     make_alternives_plan(arg.clone(), results, move |result| -> NoInputPlan {
                         Box::new(SequencePlan::new(
                             interpret_as_type_plan(context.clone(),
-                                InterpretedAtom(arg.clone(), result.bindings().clone()),
-                                ATOM_TYPE_UNDEFINED),
+                                InterpretedAtom(arg.clone(), result.bindings().clone(), result.provenance().clone()),
+                                ATOM_TYPE_UNDEFINED, None),
                             insert_reducted_arg_plan(result, expr_idx)))
                     })
                 }, format!("Interpret {} argument", expr_idx))
             ))
         }
-        call_alternatives_plan(plan, context, input)
+        call_alternatives_plan(plan, context, input, None)
     }
 }
 
+/// `overload` is the type of the operation selected by [select_overload],
+/// only definitions of this overload are called
 fn call_alternatives_plan<'a, T: SpaceRef<'a>>(plan: NoInputPlan<'a>, context: InterpreterContextRef<'a, T>,
-    input: InterpretedAtom) -> NoInputPlan<'a> {
+    input: InterpretedAtom, overload: Option<Atom>) -> NoInputPlan<'a> {
     Box::new(SequencePlan::new(plan, OperatorPlan::new(move |results: Results| {
         make_alternives_plan(input.0, results, move |result| {
-            call_plan(context.clone(), result)
+            call_plan(context.clone(), result, overload.clone())
         })
     }, "interpret each alternative")))
 }
 
-fn insert_reducted_arg_plan<'a>(expr: InterpretedAtom, atom_idx: usize) -> OperatorPlan<'a, Results, Results, InterpreterError> {
+fn insert_reducted_arg_plan<'a>(expr: InterpretedAtom, atom_idx: usize) -> OperatorPlan<'a, Results, Results, InterpreterError<'a>> {
     let descr = format!("insert right element as child {} of left element", atom_idx);
     OperatorPlan::new(move |arg_variants| insert_reducted_arg_op(expr, atom_idx, arg_variants), descr)
 }
 
-fn insert_reducted_arg_op<'a>(expr: InterpretedAtom, atom_idx: usize, mut arg_variants: Results) -> StepResult<'a, Results, InterpreterError> {
+fn insert_reducted_arg_op<'a>(expr: InterpretedAtom, atom_idx: usize, mut arg_variants: Results) -> StepResult<'a, Results, InterpreterError<'a>> {
     let result = arg_variants.drain(0..).map(|arg| {
-        let InterpretedAtom(arg, bindings) = arg;
+        let InterpretedAtom(arg, bindings, provenance) = arg;
         let mut expr_with_arg = expr.atom().clone();
         get_expr_mut(&mut expr_with_arg).children_mut()[atom_idx] = arg;
-        InterpretedAtom(apply_bindings_to_atom(&expr_with_arg, &bindings), bindings)
+        InterpretedAtom(apply_bindings_to_atom(&expr_with_arg, &bindings), bindings, provenance)
     }).collect();
     log::debug!("insert_reducted_arg_op: result: {:?}", result);
     StepResult::ret(result)
 }
 
-fn call_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom) -> NoInputPlan<'a> {
+fn call_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom, overload: Option<Atom>) -> NoInputPlan<'a> {
     let descr = format!("call {}", input);
-    Box::new(OperatorPlan::new(|_| call_op(context, input), descr))
+    Box::new(OperatorPlan::new(|_| call_op(context, input, overload), descr))
 }
 
-fn call_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom) -> StepResult<'a, Results, InterpreterError> {
+fn call_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom, overload: Option<Atom>) -> StepResult<'a, Results, InterpreterError<'a>> {
     log::debug!("call_op: {}", input);
 
     let cached = context.cache.borrow().get(input.atom());
     if let Some(result) = cached {
-        let result = result.into_iter().flat_map(|InterpretedAtom(atom, bindings)| {
+        let result = result.into_iter().flat_map(|InterpretedAtom(atom, bindings, provenance)| {
+            let provenance = input.provenance().clone() + provenance;
             bindings.merge_v2(input.bindings()).into_iter()
-                .map(move |b| InterpretedAtom(atom.clone(), b))
+                .map(move |b| InterpretedAtom(atom.clone(), b, provenance.clone()))
         }).collect();
         return_cached_result_plan(result)
     } else {
         if let Atom::Expression(expr) = input.atom() {
             if !has_grounded_sub_expr(expr) {
                 let key = input.atom().clone();
+                let known = input.provenance().len();
                 StepResult::execute(SequencePlan::new(
                     OrPlan::new(
-                        interpret_reducted_plan(context.clone(), input.clone()),
+                        interpret_reducted_plan(context.clone(), input.clone(), overload),
                         StepResult::ret(vec![input])),
-                    save_result_in_cache_plan(context, key)
+                    save_result_in_cache_plan(context, key, known)
                 ))
             } else {
                 StepResult::execute(OrPlan::new(
-                        interpret_reducted_plan(context.clone(), input.clone()),
+                        interpret_reducted_plan(context.clone(), input.clone(), overload),
                         StepResult::ret(vec![input])))
             }
         } else {
@@ -542,32 +701,37 @@ fn call_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: In
     }
 }
 
-fn return_cached_result_plan<'a>(results: Results) -> StepResult<'a, Results, InterpreterError> {
+fn return_cached_result_plan<'a>(results: Results) -> StepResult<'a, Results, InterpreterError<'a>> {
     let descr = format!("return cached results {:?}", results);
     StepResult::execute(OperatorPlan::new(|_| StepResult::ret(results), descr))
 }
 
-fn save_result_in_cache_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, key: Atom) -> OperatorPlan<'a, Results, Results, InterpreterError> {
+/// Results are cached with the atoms used to derive them after the call,
+/// `known` atoms which were used before the call are skipped
+fn save_result_in_cache_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, key: Atom, known: usize) -> OperatorPlan<'a, Results, Results, InterpreterError<'a>> {
     let descr = format!("save results in cache for key {}", key);
     OperatorPlan::new(move |results: Results| {
-        context.cache.borrow_mut().insert(key, results.clone());
+        let cached = results.iter()
+            .map(|InterpretedAtom(atom, bindings, provenance)| InterpretedAtom(atom.clone(), bindings.clone(), provenance.skip(known)))
+            .collect();
+        context.cache.borrow_mut().insert(key, cached);
         StepResult::ret(results)
     }, descr)
 }
 
 fn interpret_reducted_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>,
-        input: InterpretedAtom) -> NoInputPlan<'a> {
+        input: InterpretedAtom, overload: Option<Atom>) -> NoInputPlan<'a> {
     if let Atom::Expression(ref expr) = input.atom() {
         if is_grounded_op(expr) {
             Box::new(execute_plan(context, input))
         } else if is_variable_op(expr) {
             #[cfg(feature = "variable_operation")]
-            let result = Box::new(match_plan(context, input));
+            let result = Box::new(match_plan(context, input, overload));
             #[cfg(not(feature = "variable_operation"))]
             let result = Box::new(StepResult::ret(vec![input]));
             result
         } else {
-            Box::new(match_plan(context, input))
+            Box::new(match_plan(context, input, overload))
         }
     } else {
         panic!("Only expression is expected, received: {}", input);
@@ -575,35 +739,42 @@ fn interpret_reducted_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'
 }
 
 
-fn execute_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom) -> OperatorPlan<'a, (), Results, InterpreterError> {
+fn execute_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom) -> OperatorPlan<'a, (), Results, InterpreterError<'a>> {
     let descr = format!("execute {}", input);
     OperatorPlan::new(|_| execute_op(context, input), descr)
 }
 
-fn execute_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom) -> StepResult<'a, Results, InterpreterError> {
+fn execute_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom) -> StepResult<'a, Results, InterpreterError<'a>> {
     log::debug!("execute_op: {}", input);
     match input {
-        InterpretedAtom(Atom::Expression(ref expr), ref bindings) => {
+        InterpretedAtom(Atom::Expression(ref expr), ref bindings, ref provenance) => {
             let op = expr.children().get(0);
             if let Some(Atom::Grounded(op)) = op {
                 let args = expr.children();
-                match op.execute(&args[1..]) {
+                // atoms read by the operation are used to derive each of its results
+                let (result, matched) = match context.provenance {
+                    true => record_matched_atoms(|| op.execute(&args[1..])),
+                    false => (op.execute(&args[1..]), Vec::new()),
+                };
+                let mut provenance = provenance.clone();
+                provenance.extend(matched);
+                match result {
                     Ok(mut vec) => {
                         let results: Vec<InterpretedAtom> = vec.drain(0..)
-                            .map(|atom| InterpretedAtom(atom, bindings.clone()))
+                            .map(|atom| InterpretedAtom(atom, bindings.clone(), provenance.clone()))
                             .collect();
                         if results.is_empty() {
                             StepResult::ret(results)
                         } else {
                             make_alternives_plan(input.0, results, move |result| {
                                 interpret_as_type_plan(context.clone(),
-                                    result, ATOM_TYPE_UNDEFINED)
+                                    result, ATOM_TYPE_UNDEFINED, None)
                             })
                         }
                     },
                     Err(ExecError::Runtime(msg)) => StepResult::ret(vec![InterpretedAtom(
-                           Atom::expr([ERROR_SYMBOL, input.0, Atom::sym(msg)]), input.1)]),
-                    Err(ExecError::NoReduce) => StepResult::err((input.0, NOT_REDUCIBLE_SYMBOL)),
+                           Atom::expr([ERROR_SYMBOL, input.0, Atom::sym(msg)]), input.1, provenance)]),
+                    Err(ExecError::NoReduce) => StepResult::err((input.0, NOT_REDUCIBLE_SYMBOL, None)),
                 }
             } else {
                 panic!("Trying to execute non grounded atom: {}", expr)
@@ -613,19 +784,36 @@ fn execute_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input:
     }
 }
 
-fn match_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom) -> OperatorPlan<'a, (), Results, InterpreterError> {
+fn match_plan<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom, overload: Option<Atom>) -> OperatorPlan<'a, (), Results, InterpreterError<'a>> {
     let descr = format!("match {}", input);
-    OperatorPlan::new(|_| match_op(context, input), descr)
+    OperatorPlan::new(|_| match_op(context, input, overload), descr)
 }
 
-fn match_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom) -> StepResult<'a, Results, InterpreterError> {
+fn match_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: InterpretedAtom, overload: Option<Atom>) -> StepResult<'a, Results, InterpreterError<'a>> {
     log::debug!("match_op: {}", input);
     let var_x = VariableAtom::new("X").make_unique();
     let query = Atom::expr(vec![EQUAL_SYMBOL, input.atom().clone(), Atom::Variable(var_x.clone())]);
-    let mut query_bindings = context.space.query(&query);
+    let definitions = match overload {
+        // definitions of the other overloads are skipped
+        Some(op_typ) => match get_overload_definitions(&context.space, input.atom(), &op_typ) {
+            Ok(definitions) => Some(definitions),
+            // returned as a result because errors of the call are discarded
+            Err(message) => return StepResult::ret(vec![InterpretedAtom(
+                explained_error_atom(input.0, AMBIGUOUS_CALL_SYMBOL, message), input.1, input.2)]),
+        },
+        None => None,
+    };
+    let query_bindings: Vec<(Bindings, Vec<Atom>)> = match definitions {
+        None if context.provenance => query_with_matched_atoms(&context.space, &query),
+        None => context.space.query(&query).into_iter().map(|bindings| (bindings, Vec::new())).collect(),
+        Some(definitions) => definitions.into_iter()
+            .flat_map(|definition| match_atoms(&query, &make_variables_unique(definition.clone()))
+                .map(|bindings| (bindings, vec![definition.clone()])).collect::<Vec<_>>())
+            .collect(),
+    };
     let results: Vec<InterpretedAtom> = query_bindings
-        .drain(0..)
-        .map(|query_binding| {
+        .into_iter()
+        .map(|(query_binding, definitions)| {
             let result = apply_bindings_to_atom(&Atom::Variable(var_x.clone()), &query_binding);
             // TODO: sometimes we apply bindings twice: first time here,
             // second time when inserting matched argument into nesting
@@ -635,25 +823,29 @@ fn match_op<'a, T: SpaceRef<'a>>(context: InterpreterContextRef<'a, T>, input: I
                 Bindings::merge(&query_binding, &bindings).ok_or(())
             });
             log::debug!("match_op: query: {}, bindings: {:?}, result: {}", input, bindings, result);
-            (result, bindings)
+            let mut provenance = input.provenance().clone();
+            if context.provenance {
+                provenance.extend(definitions);
+            }
+            (result, bindings, provenance)
         })
-        .filter(|(_, bindings)| bindings.is_ok())
-        .map(|(result, bindings)| InterpretedAtom(result, bindings.unwrap()))
+        .filter(|(_, bindings, _)| bindings.is_ok())
+        .map(|(result, bindings, provenance)| InterpretedAtom(result, bindings.unwrap(), provenance))
         .collect();
     make_alternives_plan(input.0, results, move |result| {
-        interpret_as_type_plan(context.clone(), result, ATOM_TYPE_UNDEFINED)
+        interpret_as_type_plan(context.clone(), result, ATOM_TYPE_UNDEFINED, None)
     })
 }
 
 fn make_alternives_plan<'a, T: Debug, F, P>(input: Atom, mut results: Vec<T>,
-    plan: F) -> StepResult<'a, Results, InterpreterError>
+    plan: F) -> StepResult<'a, Results, InterpreterError<'a>>
 where
     F: 'a + Fn(T) -> P,
-    P: 'a + Plan<'a, (), Results, InterpreterError>
+    P: 'a + Plan<'a, (), Results, InterpreterError<'a>>
 {
     log::debug!("make_alternives_plan: input: {:?}, alternatives: {:?}", input, results);
     match results.len() {
-        0 => StepResult::err((input, NO_VALID_ALTERNATIVES)),
+        0 => StepResult::err((input, NO_VALID_ALTERNATIVES, None)),
         1 => StepResult::execute(plan(results.pop().unwrap())),
         _ => {
             StepResult::execute(AlternativeInterpretationsPlan::new(
@@ -672,7 +864,7 @@ use std::collections::VecDeque;
 /// If no alternatives returned successful result the plan returns error.
 pub struct AlternativeInterpretationsPlan<'a, T> {
     atom: Atom,
-    plans: VecDeque<Box<dyn Plan<'a, (), Vec<T>, InterpreterError> + 'a>>,
+    plans: VecDeque<Box<dyn Plan<'a, (), Vec<T>, InterpreterError<'a>> + 'a>>,
     results: Vec<T>,
     success: bool,
 }
@@ -683,19 +875,19 @@ impl<'a, T> AlternativeInterpretationsPlan<'a, T> {
     /// # Arguments
     /// `atom` - atom to be printed as root of the alternative interpretations
     /// `plan` - altenative plans for the atom
-    pub fn new(atom: Atom, plans: Vec<Box<dyn Plan<'a, (), Vec<T>, InterpreterError> + 'a>>) -> Self {
+    pub fn new(atom: Atom, plans: Vec<Box<dyn Plan<'a, (), Vec<T>, InterpreterError<'a>> + 'a>>) -> Self {
         Self{ atom, plans: plans.into(), results: Vec::new(), success: false }
     }
 }
 
-impl<'a, T: Debug> Plan<'a, (), Vec<T>, InterpreterError> for AlternativeInterpretationsPlan<'a, T> {
-    fn step(mut self: Box<Self>, _: ()) -> StepResult<'a, Vec<T>, InterpreterError> {
+impl<'a, T: Debug> Plan<'a, (), Vec<T>, InterpreterError<'a>> for AlternativeInterpretationsPlan<'a, T> {
+    fn step(mut self: Box<Self>, _: ()) -> StepResult<'a, Vec<T>, InterpreterError<'a>> {
         log::debug!("AlternativeInterpretationsPlan::step: {} alternatives left", self.plans.len());
         if self.plans.len() == 0 {
             if self.success {
                 StepResult::ret(self.results)
             } else {
-                StepResult::err((self.atom, NO_VALID_ALTERNATIVES))
+                StepResult::err((self.atom, NO_VALID_ALTERNATIVES, None))
             }
         } else {
             let plan = self.plans.pop_front().unwrap();
@@ -798,7 +990,7 @@ mod tests {
             "actual: {:?} and expected: {:?} are not equivalent", actual, expected);
     }
 
-    fn test_interpret<'a, T, R: 'a, P: Plan<'a, T, R, InterpreterError> + 'a>(plan: P, arg: T) -> Result<R, InterpreterError> {
+    fn test_interpret<'a, T, R: 'a, P: Plan<'a, T, R, InterpreterError<'a>> + 'a>(plan: P, arg: T) -> Result<R, InterpreterError<'a>> {
         let mut step = Box::new(plan).step(arg);
         loop {
             match step {
@@ -816,7 +1008,7 @@ mod tests {
 
         let result = test_interpret(plan, ());
 
-        assert_eq!(Err((sym!("Test"), NO_VALID_ALTERNATIVES)), result);
+        assert_eq!(Err((sym!("Test"), NO_VALID_ALTERNATIVES, None)), result);
     }
 
     #[test]
@@ -843,7 +1035,7 @@ mod tests {
     #[test]
     fn test_alternatives_plan_error_present() {
         let plan = AlternativeInterpretationsPlan::new(sym!("Test"),
-            vec![Box::new(StepResult::err((sym!("Test"), BAD_TYPE_SYMBOL))),
+            vec![Box::new(StepResult::err((sym!("Test"), BAD_TYPE_SYMBOL, None))),
                 Box::new(StepResult::ret(vec!["C", "D"]))]);
 
         let result = test_interpret(plan, ());
@@ -855,12 +1047,12 @@ mod tests {
     fn test_alternatives_plan_only_errors() {
         let plan: AlternativeInterpretationsPlan<&'static str> =
             AlternativeInterpretationsPlan::new(sym!("Test"),
-            vec![Box::new(StepResult::err((sym!("Test"), sym!("Expected error")))),
-                Box::new(StepResult::err((sym!("Test"), sym!("Another expected error"))))]);
+            vec![Box::new(StepResult::err((sym!("Test"), sym!("Expected error"), None))),
+                Box::new(StepResult::err((sym!("Test"), sym!("Another expected error"), None)))]);
 
         let result = test_interpret(plan, ());
 
-        assert_eq!(Err((sym!("Test"), NO_VALID_ALTERNATIVES)), result);
+        assert_eq!(Err((sym!("Test"), NO_VALID_ALTERNATIVES, None)), result);
     }
 
     #[test]
@@ -1013,15 +1205,102 @@ mod tests {
 
     #[test]
     fn return_bad_type_error() {
+        use crate::metta::runner::string::Str;
         let mut space = GroundingSpace::new();
         space.add(expr!(":" "myAtom" "myType"));
         space.add(expr!(":" "id_a" ("->" "A" "A")));
         space.add(expr!("=" ("id_a" a) a));
 
         assert_eq!(interpret(&space, &expr!({ID_NUM} "myAtom")),
-            Ok(vec![Atom::expr([ERROR_SYMBOL, sym!("myAtom"), BAD_TYPE_SYMBOL])]));
+            Ok(vec![Atom::expr([ERROR_SYMBOL, sym!("myAtom"),
+                BAD_TYPE_SYMBOL, Atom::gnd(Str::from_str("argument 1 of (id_num myAtom) should have type Number, but myAtom has type myType"))])]));
         assert_eq!(interpret(&space, &expr!("id_a" "myAtom")),
-            Ok(vec![Atom::expr([ERROR_SYMBOL, sym!("myAtom"), BAD_TYPE_SYMBOL])]));
+            Ok(vec![Atom::expr([ERROR_SYMBOL, sym!("myAtom"),
+                BAD_TYPE_SYMBOL, Atom::gnd(Str::from_str("argument 1 of (id_a myAtom) should have type A, but myAtom has type myType"))])]));
+    }
+
+    #[test]
+    fn return_bad_type_error_explanation() {
+        use crate::metta::runner::string::Str;
+        let mut space = GroundingSpace::new();
+        space.add(expr!(":" "foo" ("->" "A" "B" "C")));
+        space.add(expr!(":" "bar" ("->" "D")));
+        space.add(expr!(":" "a" "A"));
+        space.add(expr!(":<" "D" "E"));
+        space.add(expr!(":<" "E" "F"));
+
+        assert_eq!(interpret(&space, &expr!("foo" "a" ("bar"))),
+            Ok(vec![Atom::expr([ERROR_SYMBOL, expr!(("bar")),
+                BAD_TYPE_SYMBOL, Atom::gnd(Str::from_str("argument 2 of (foo a (bar)) should have type B, but (bar) has type D, super types tried: D :< E :< F"))])]));
+
+        space.add(expr!(":" "c" "C"));
+        space.add(expr!(":<" "G" "B"));
+        assert_eq!(interpret(&space, &expr!("foo" "a" "c")),
+            Ok(vec![Atom::expr([ERROR_SYMBOL, sym!("c"),
+                BAD_TYPE_SYMBOL, Atom::gnd(Str::from_str("argument 2 of (foo a c) should have type B, but c has type C, no super types are declared using :<"))])]));
+    }
+
+    #[test]
+    fn interpret_most_specific_overload() {
+        let space = metta_space("
+            (:< Dog Animal) (:< Cat Animal)
+            (: rex Dog) (: tom Cat)
+            (dispatch voice most-specific)
+            (: voice (-> Animal Sound))
+            (: voice (-> Dog Sound))
+            (= (voice $x) generic)
+            (= (voice $x) dog)
+            (overload (= (voice $x) generic) (-> Animal Sound))
+            (overload (= (voice $x) dog) (-> Dog Sound))
+        ");
+
+        assert_eq!(interpret(&space, &expr!("voice" "rex")), Ok(vec![expr!("dog")]));
+        assert_eq!(interpret(&space, &expr!("voice" "tom")), Ok(vec![expr!("generic")]));
+    }
+
+    #[test]
+    fn interpret_overload_bound_by_typed_head() {
+        let space = metta_space("
+            (: a A) (: b B)
+            (dispatch show most-specific)
+            (: show (-> $t String))
+            (: show (-> A String))
+            (= (show a) shown-a)
+            (= (show $x) shown-any)
+            (overload (= (show $x) shown-any) (-> $t String))
+        ");
+
+        assert_eq!(interpret(&space, &expr!("show" "a")), Ok(vec![expr!("shown-a")]));
+        assert_eq!(interpret(&space, &expr!("show" "b")), Ok(vec![expr!("shown-any")]));
+    }
+
+    #[test]
+    fn interpret_overload_not_bound_to_type() {
+        use crate::metta::runner::string::Str;
+        let space = metta_space("
+            (:< Dog Animal)
+            (dispatch voice most-specific)
+            (: voice (-> Animal Sound))
+            (: voice (-> Dog Sound))
+            (= (voice fido) generic)
+        ");
+
+        assert_eq!(interpret(&space, &expr!("voice" "fido")),
+            Ok(vec![Atom::expr([ERROR_SYMBOL, expr!("voice" "fido"), AMBIGUOUS_CALL_SYMBOL, Atom::gnd(Str::from_str("(= (voice fido) generic) is not bound to a type of voice, it should be typed by (overload <definition> <type>)"))])]));
+    }
+
+    #[test]
+    fn interpret_ambiguous_overload() {
+        use crate::metta::runner::string::Str;
+        let space = metta_space("
+            (: a A) (: b B)
+            (dispatch foo most-specific)
+            (: foo (-> A $t C))
+            (: foo (-> $t B C))
+        ");
+
+        assert_eq!(interpret(&space, &expr!("foo" "a" "b")),
+            Ok(vec![Atom::expr([ERROR_SYMBOL, expr!("foo" "a" "b"), AMBIGUOUS_CALL_SYMBOL, Atom::gnd(Str::from_str("(foo a b) is ambiguous: types (-> $t B C) and (-> A $t C) of foo are equally specific"))])]));
     }
 
     #[test]
@@ -1035,45 +1314,55 @@ mod tests {
         assert_eq!(interpret(&space, &expr!(("foo") "a")), Ok(vec![expr!("a")]));
     }
 
+    #[test]
+    fn dispatched_ops_are_reset_when_space_is_modified() {
+        let mut space = GroundingSpace::new();
+        let dispatched = space.common().register_observer(DispatchedOps(None));
+
+        assert!(!dispatched.borrow_mut().contains(&space, &sym!("voice")));
+        space.add(expr!("dispatch" "voice" "most-specific"));
+        assert!(dispatched.borrow_mut().contains(&space, &sym!("voice")));
+    }
+
     #[test]
     fn interpreter_cache_variables_are_not_changed_when_atom_was_not_transformed() {
         let mut cache = InterpreterCache::new();
-        cache.insert(expr!("P" x), vec![InterpretedAtom(expr!("P" x), bind!{})]);
-        assert_eq!(cache.get(&expr!("P" x)), Some(vec![InterpretedAtom(expr!("P" x), bind!{})]));
+        cache.insert(expr!("P" x), vec![InterpretedAtom(expr!("P" x), bind!{}, Provenance::new())]);
+        assert_eq!(cache.get(&expr!("P" x)), Some(vec![InterpretedAtom(expr!("P" x), bind!{}, Provenance::new())]));
     }
 
     #[test]
     fn interpreter_cache_only_same_variables_are_matched() {
         let mut cache = InterpreterCache::new();
-        cache.insert(expr!("P" x), vec![InterpretedAtom(expr!("P" x), bind!{})]);
+        cache.insert(expr!("P" x), vec![InterpretedAtom(expr!("P" x), bind!{}, Provenance::new())]);
         assert_eq!(cache.get(&expr!("P" y)), None);
     }
 
     #[test]
     fn interpreter_cache_variables_from_result_are_applied() {
         let mut cache = InterpreterCache::new();
-        cache.insert(expr!("foo" "a"), vec![InterpretedAtom(expr!("P" x), bind!{ x: expr!("a") })]);
-        assert_eq!(cache.get(&expr!("foo" "a")), Some(vec![InterpretedAtom(expr!("P" "a"), bind!{})]));
+        cache.insert(expr!("foo" "a"), vec![InterpretedAtom(expr!("P" x), bind!{ x: expr!("a") }, Provenance::new())]);
+        assert_eq!(cache.get(&expr!("foo" "a")), Some(vec![InterpretedAtom(expr!("P" "a"), bind!{}, Provenance::new())]));
     }
 
     #[test]
     fn interpreter_cache_variables_from_key_are_kept_unique() {
         let mut cache = InterpreterCache::new();
-        cache.insert(expr!("bar" x), vec![InterpretedAtom(expr!("P" x), bind!{})]);
-        assert_eq!(cache.get(&expr!("bar" x)), Some(vec![InterpretedAtom(expr!("P" x), bind!{})]));
+        cache.insert(expr!("bar" x), vec![InterpretedAtom(expr!("P" x), bind!{}, Provenance::new())]);
+        assert_eq!(cache.get(&expr!("bar" x)), Some(vec![InterpretedAtom(expr!("P" x), bind!{}, Provenance::new())]));
     }
 
     #[test]
     fn interpreter_cache_variables_absent_in_key_are_removed() {
         let mut cache = InterpreterCache::new();
-        cache.insert(expr!("foo" x), vec![InterpretedAtom(expr!("bar"), bind!{ x: expr!("a"), y: expr!("Y") })]);
-        assert_eq!(cache.get(&expr!("foo" x)), Some(vec![InterpretedAtom(expr!("bar"), bind!{ x: expr!("a") })]));
+        cache.insert(expr!("foo" x), vec![InterpretedAtom(expr!("bar"), bind!{ x: expr!("a"), y: expr!("Y") }, Provenance::new())]);
+        assert_eq!(cache.get(&expr!("foo" x)), Some(vec![InterpretedAtom(expr!("bar"), bind!{ x: expr!("a") }, Provenance::new())]));
     }
 
     #[test]
     fn interpreter_cache_variables_from_result_becom_unique() {
         let mut cache = InterpreterCache::new();
-        cache.insert(expr!(("bar")), vec![InterpretedAtom(expr!("P" x), bind!{})]);
+        cache.insert(expr!(("bar")), vec![InterpretedAtom(expr!("P" x), bind!{}, Provenance::new())]);
         if let Some(results) = cache.get(&expr!(("bar"))) {
             assert_eq!(results.len(), 1);
             assert!(atoms_are_equivalent(results[0].atom(), &expr!("P" x)));
@@ -1086,9 +1375,9 @@ mod tests {
     #[test]
     fn interpreter_cache_returns_variable_from_bindings() {
         let mut cache = InterpreterCache::new();
-        cache.insert(expr!("bar" x), vec![InterpretedAtom(expr!(y), bind!{ x: expr!("A" y)})]);
+        cache.insert(expr!("bar" x), vec![InterpretedAtom(expr!(y), bind!{ x: expr!("A" y)}, Provenance::new())]);
         if let Some(mut results) = cache.get(&expr!("bar" x)) {
-            let InterpretedAtom(atom, bindings) = results.pop().unwrap();
+            let InterpretedAtom(atom, bindings, _) = results.pop().unwrap();
             let value = bindings.resolve(&VariableAtom::new("x")).unwrap();
             assert_eq!(Atom::expr([sym!("A"), atom]), value);
         } else {
@@ -1096,6 +1385,90 @@ mod tests {
         }
     }
 
+    #[test]
+    fn interpret_with_provenance_returns_used_definitions() {
+        let space = metta_space("
+            (= (grandparent $x) (parent (parent $x)))
+            (= (parent Tom) Bob)
+            (= (parent Bob) Ann)
+            (= (parent Eve) Ann)
+        ");
+
+        let results = interpret_with_provenance(&space, &expr!("grandparent" "Tom")).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, expr!("Ann"));
+        assert_eq!(results[0].1, vec![
+            expr!("=" ("grandparent" x) ("parent" ("parent" x))),
+            expr!("=" ("parent" "Tom") "Bob"),
+            expr!("=" ("parent" "Bob") "Ann"),
+        ]);
+    }
+
+    #[test]
+    fn interpret_with_provenance_separates_alternatives() {
+        let space = metta_space("
+            (= (color) red)
+            (= (color) green)
+            (= (paint $x) (painted $x))
+            (= (paint-twice $x) (both (paint $x) (paint $x)))
+        ");
+
+        let mut results = interpret_with_provenance(&space, &expr!("paint" ("color"))).unwrap();
+        results.sort_by_key(|(atom, _)| atom.to_string());
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, expr!("painted" "green"));
+        assert_eq!(results[0].1, vec![expr!("=" ("color") "green"), expr!("=" ("paint" x) ("painted" x))]);
+        assert_eq!(results[1].0, expr!("painted" "red"));
+        assert_eq!(results[1].1, vec![expr!("=" ("color") "red"), expr!("=" ("paint" x) ("painted" x))]);
+
+        let results = interpret_with_provenance(&space, &expr!("paint-twice" "red")).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1, vec![
+            expr!("=" ("paint-twice" x) ("both" ("paint" x) ("paint" x))),
+            expr!("=" ("paint" x) ("painted" x)),
+        ]);
+    }
+
+    #[test]
+    fn interpret_with_provenance_keeps_cached_results_separate() {
+        let space = metta_space("
+            (= (start) first)
+            (= (start) second)
+            (= (run first) (goal))
+            (= (run second) (goal))
+            (= (goal) done)
+        ");
+
+        let mut results = interpret_with_provenance(&space, &expr!("run" ("start"))).unwrap();
+        results.sort_by_key(|(_, provenance)| provenance[0].to_string());
+
+        assert_eq!(results, vec![
+            (expr!("done"), vec![expr!("=" ("start") "first"), expr!("=" ("run" "first") ("goal")), expr!("=" ("goal") "done")]),
+            (expr!("done"), vec![expr!("=" ("start") "second"), expr!("=" ("run" "second") ("goal")), expr!("=" ("goal") "done")]),
+        ]);
+    }
+
+    #[test]
+    fn interpret_with_provenance_removes_duplicates() {
+        let space = metta_space("
+            (= (add Z $y) $y)
+            (= (add (S $x) $y) (S (add $x $y)))
+        ");
+        let atom = expr!("add" ("S" ("S" "Z")) ("S" "Z"));
+
+        let expected = interpret(&space, &atom).unwrap();
+        let results = interpret_with_provenance(&space, &atom).unwrap();
+
+        assert_eq!(expected, vec![expr!("S" ("S" ("S" "Z")))]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, expected[0]);
+        assert_eq!(results[0].1, vec![
+            expr!("=" ("add" ("S" x) y) ("S" ("add" x y))),
+            expr!("=" ("add" "Z" y) y),
+        ]);
+    }
+
     #[test]
     fn interpret_match_variable_operation() {
         let mut space = GroundingSpace::new();
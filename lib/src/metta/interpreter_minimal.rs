@@ -12,7 +12,7 @@ use crate::metta::*;
 
 use std::fmt::{Debug, Display, Formatter};
 use std::convert::TryFrom;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::marker::PhantomData;
 use std::fmt::Write;
@@ -140,6 +140,9 @@ impl Display for Stack {
 #[cfg_attr(test, derive(PartialEq))]
 struct InterpretedAtom(Stack, Bindings);
 
+/// Atoms of the space which were used to interpret an atom
+type Provenance = im::Vector<Atom>;
+
 impl Display for InterpretedAtom {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         if self.1.is_empty() {
@@ -163,19 +166,26 @@ impl<'a, T: Space + 'a> SpaceRef<'a> for T {}
 #[derive(Debug)]
 struct InterpreterContext<'a, T: SpaceRef<'a>> {
     space: T,
+    provenance: bool,
+    /// Atoms used before each collapse-bind which is not finished yet and
+    /// atoms used by each of its finished alternatives, collapse-bind is
+    /// identified by the address of its stack
+    collapsing: RefCell<HashMap<usize, (Provenance, Vec<(Atom, Provenance)>)>>,
+    /// Atoms used by each alternative of the finished collapse-binds
+    collapsed: RefCell<Vec<Vec<(Atom, Provenance)>>>,
     phantom: PhantomData<&'a GroundingSpace>,
 }
 
 impl<'a, T: SpaceRef<'a>> InterpreterContext<'a, T> {
-    fn new(space: T) -> Self {
-        Self{ space, phantom: PhantomData }
+    fn new(space: T, provenance: bool) -> Self {
+        Self{ space, provenance, collapsing: RefCell::new(HashMap::new()), collapsed: RefCell::new(Vec::new()), phantom: PhantomData }
     }
 }
 
 #[derive(Debug)]
 pub struct InterpreterState<'a, T: SpaceRef<'a>> {
-    plan: Vec<InterpretedAtom>,
-    finished: Vec<Atom>,
+    plan: Vec<(InterpretedAtom, Provenance)>,
+    finished: Vec<(Atom, Provenance)>,
     context: InterpreterContext<'a, T>,
     vars: HashSet<VariableAtom>,
 }
@@ -199,8 +209,8 @@ impl<'a, T: SpaceRef<'a>> InterpreterState<'a, T> {
     pub(crate) fn new_finished(space: T, results: Vec<Atom>) -> Self {
         Self {
             plan: vec![],
-            finished: results,
-            context: InterpreterContext::new(space),
+            finished: results.into_iter().map(|atom| (atom, Provenance::new())).collect(),
+            context: InterpreterContext::new(space, false),
             vars: HashSet::new(),
         }
     }
@@ -213,24 +223,24 @@ impl<'a, T: SpaceRef<'a>> InterpreterState<'a, T> {
         if self.has_next() {
             Err("Evaluation is not finished".into())
         } else {
-            Ok(self.finished)
+            Ok(self.finished.into_iter().map(|(atom, _provenance)| atom).collect())
         }
     }
 
-    fn pop(&mut self) -> Option<InterpretedAtom> {
+    fn pop(&mut self) -> Option<(InterpretedAtom, Provenance)> {
         self.plan.pop()
     }
 
-    fn push(&mut self, atom: InterpretedAtom) {
+    fn push(&mut self, atom: InterpretedAtom, provenance: Provenance) {
         if atom.0.prev.is_none() && atom.0.finished {
             let InterpretedAtom(stack, bindings) = atom;
             if stack.atom != EMPTY_SYMBOL {
                 let bindings = bindings.convert_var_equalities_to_bindings(&self.vars);
                 let atom = apply_bindings_to_atom(&stack.atom, &bindings);
-                self.finished.push(atom);
+                self.finished.push((atom, provenance));
             }
         } else {
-            self.plan.push(atom);
+            self.plan.push((atom, provenance));
         }
     }
 }
@@ -249,9 +259,13 @@ impl<'a, T: SpaceRef<'a>> std::fmt::Display for InterpreterState<'a, T> {
 /// * `space` - atomspace to query for interpretation
 /// * `expr` - atom to interpret
 pub fn interpret_init<'a, T: Space + 'a>(space: T, expr: &Atom) -> InterpreterState<'a, T> {
-    let context = InterpreterContext::new(space);
+    interpret_init_internal(space, expr, false)
+}
+
+fn interpret_init_internal<'a, T: Space + 'a>(space: T, expr: &Atom, provenance: bool) -> InterpreterState<'a, T> {
+    let context = InterpreterContext::new(space, provenance);
     InterpreterState {
-        plan: vec![InterpretedAtom(atom_to_stack(expr.clone(), None), Bindings::new())],
+        plan: vec![(InterpretedAtom(atom_to_stack(expr.clone(), None), Bindings::new()), Provenance::new())],
         finished: vec![],
         context,
         vars: expr.iter().filter_type::<&VariableAtom>().cloned().collect(),
@@ -266,10 +280,10 @@ pub fn interpret_init<'a, T: Space + 'a>(space: T, expr: &Atom) -> InterpreterSt
 /// # Arguments
 /// * `step` - [StepResult::Execute] result from the previous step.
 pub fn interpret_step<'a, T: Space + 'a>(mut state: InterpreterState<'a, T>) -> InterpreterState<'a, T> {
-    let interpreted_atom = state.pop().unwrap();
+    let (interpreted_atom, provenance) = state.pop().unwrap();
     log::debug!("interpret_step:\n{}", interpreted_atom);
-    for result in interpret_root_atom(&state.context, interpreted_atom) {
-        state.push(result);
+    for (result, provenance) in interpret_root_atom(&state.context, interpreted_atom, provenance) {
+        state.push(result, provenance);
     }
     state
 }
@@ -288,6 +302,35 @@ pub fn interpret<T: Space>(space: T, expr: &Atom) -> Result<Vec<Atom>, String> {
     state.into_result()
 }
 
+/// Interpret passed atom like [interpret] does and return each result
+/// together with the atoms of the space which were used to derive it: the
+/// `=` definitions applied and the atoms read by the grounded operations.
+/// Result of `collapse-bind` is derived from the atoms used by all of its
+/// alternatives, while each result of `superpose-bind` which returns them
+/// back is derived from the atoms used by its alternative. Atoms are returned in order of usage without duplicates.
+/// Atoms are returned as they are kept in the space (see
+/// [record_matched_atoms]), thus they can be removed from it.
+///
+/// # Arguments
+/// * `space` - atomspace to query for interpretation
+/// * `expr` - atom to interpret
+pub fn interpret_with_provenance<T: Space>(space: T, expr: &Atom) -> Result<Vec<(Atom, Vec<Atom>)>, String> {
+    let mut state = interpret_init_internal(space, expr, true);
+    while state.has_next() {
+        state = interpret_step(state);
+    }
+    Ok(state.finished.into_iter()
+        .map(|(atom, provenance)| {
+            let mut atoms: Vec<Atom> = Vec::new();
+            for atom in provenance {
+                if atom.as_gnd::<CollapsedMark>().is_none() && !atoms.contains(&atom) {
+                    atoms.push(atom);
+                }
+            }
+            (atom, atoms)
+        }).collect())
+}
+
 fn is_embedded_op(atom: &Atom) -> bool {
     let expr = atom_as_slice(&atom);
     match expr {
@@ -359,16 +402,134 @@ impl Display for Variables {
     }
 }
 
-fn interpret_root_atom<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, interpreted_atom: InterpretedAtom) -> Vec<InterpretedAtom> {
+fn interpret_root_atom<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, interpreted_atom: InterpretedAtom, provenance: Provenance) -> Vec<(InterpretedAtom, Provenance)> {
     let InterpretedAtom(stack, bindings) = interpreted_atom;
-    interpret_nested_atom(context, stack, bindings)
+    if !context.provenance {
+        return interpret_nested_atom(context, stack, bindings).into_iter()
+            .map(|(result, _atoms)| (result, provenance.clone()))
+            .collect();
+    }
+    if stack.finished && stack.prev.as_ref().is_some_and(is_collapse_bind) {
+        return return_to_collapse_bind(context, stack, bindings, provenance);
+    }
+    if !stack.finished && is_op(&stack.atom, &COLLAPSE_BIND_SYMBOL) {
+        return interpret_nested_atom(context, stack, bindings).into_iter()
+            .map(|(result, _atoms)| {
+                // alternatives of collapse-bind start from the empty list of
+                // atoms, atoms used before are kept until it is finished
+                let collapse = result.0.fold(None, |found, stack| found.or_else(|| {
+                    stack.prev.as_ref().filter(|prev| is_collapse_bind(prev)).map(collapse_key)
+                })).unwrap();
+                context.collapsing.borrow_mut().insert(collapse, (provenance.clone(), Vec::new()));
+                (result, Provenance::new())
+            })
+            .collect();
+    }
+    if !stack.finished && is_op(&stack.atom, &SUPERPOSE_BIND_SYMBOL) {
+        return superpose_bind_with_provenance(context, stack, bindings, provenance);
+    }
+    interpret_nested_atom(context, stack, bindings).into_iter()
+        .map(|(result, atoms)| {
+            let mut provenance = provenance.clone();
+            provenance.extend(atoms);
+            (result, provenance)
+        })
+        .collect()
+}
+
+/// Marks the beginning and the end of the atoms used by the alternatives of
+/// the collapse-bind with the same index inside of [Provenance]
+#[derive(Debug, Clone, PartialEq)]
+struct CollapsedMark(usize);
+
+fn is_collapse_bind(stack: &Rc<RefCell<Stack>>) -> bool {
+    is_op(&stack.borrow().atom, &COLLAPSE_BIND_SYMBOL)
+}
+
+fn collapse_key(stack: &Rc<RefCell<Stack>>) -> usize {
+    Rc::as_ptr(stack) as usize
+}
+
+/// Collapse-bind result is derived from the atoms used by all of its
+/// alternatives. Atoms used by each alternative are kept to be restored when
+/// the result is superposed back.
+fn return_to_collapse_bind<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, stack: Stack, bindings: Bindings, provenance: Provenance) -> Vec<(InterpretedAtom, Provenance)> {
+    let collapse = stack.prev.as_ref().map(collapse_key).unwrap();
+    let pair = atom_bindings_into_atom(stack.atom.clone(), bindings.clone());
+    if let Some((_before, alternatives)) = context.collapsing.borrow_mut().get_mut(&collapse) {
+        alternatives.push((pair, provenance));
+    }
+    interpret_nested_atom(context, stack, bindings).into_iter()
+        .map(|(result, _atoms)| {
+            let (mut provenance, alternatives) = context.collapsing.borrow_mut()
+                .remove(&collapse).unwrap_or_default();
+            let mut collapsed = context.collapsed.borrow_mut();
+            let mark = Atom::value(CollapsedMark(collapsed.len()));
+            provenance.push_back(mark.clone());
+            alternatives.iter().for_each(|(_pair, atoms)| provenance.append(atoms.clone()));
+            provenance.push_back(mark);
+            collapsed.push(alternatives);
+            (result, provenance)
+        })
+        .collect()
+}
+
+/// Each superposed result is derived from the atoms used by its own
+/// alternative of collapse-bind instead of all of them.
+fn superpose_bind_with_provenance<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, stack: Stack, bindings: Bindings, provenance: Provenance) -> Vec<(InterpretedAtom, Provenance)> {
+    let pairs = match atom_as_slice(&stack.atom) {
+        Some([_op, Atom::Expression(collapsed)]) => collapsed.children().clone(),
+        _ => return superpose_bind(stack, bindings).into_iter()
+            .map(|result| (result, provenance.clone())).collect(),
+    };
+    pairs.into_iter()
+        .flat_map(|pair| {
+            let provenance = superposed_provenance(context, &provenance, &pair);
+            let atom = Atom::expr([SUPERPOSE_BIND_SYMBOL, Atom::expr([pair])]);
+            let stack = Stack{ prev: stack.prev.clone(), atom, ret: stack.ret, finished: false, vars: stack.vars.clone() };
+            superpose_bind(stack, bindings.clone()).into_iter()
+                .map(move |result| (result, provenance.clone()))
+        })
+        .collect()
 }
 
-fn interpret_nested_atom<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, mut stack: Stack, bindings: Bindings) -> Vec<InterpretedAtom> {
+/// Replaces the atoms used by all alternatives of the innermost collapse-bind
+/// which returned the `pair` by the atoms used by the alternative returned it.
+/// The `provenance` is returned as is when the `pair` is not found.
+fn superposed_provenance<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, provenance: &Provenance, pair: &Atom) -> Provenance {
+    let collapsed = context.collapsed.borrow();
+    let mut ends = HashMap::new();
+    for (i, atom) in provenance.iter().enumerate().rev() {
+        let id = match atom.as_gnd::<CollapsedMark>() {
+            Some(CollapsedMark(id)) => *id,
+            None => continue,
+        };
+        let end = match ends.remove(&id) {
+            Some(end) => end,
+            None => {
+                ends.insert(id, i);
+                continue;
+            },
+        };
+        let mut alternatives = collapsed[id].iter().filter(|(other, _atoms)| other == pair).peekable();
+        if alternatives.peek().is_some() {
+            let mut result = provenance.take(i);
+            alternatives.for_each(|(_pair, atoms)| result.append(atoms.clone()));
+            result.append(provenance.skip(end + 1));
+            return result;
+        }
+    }
+    provenance.clone()
+}
+
+/// Returns the results of the interpretation together with the atoms of the
+/// space used to find each of them, atoms are returned only when provenance
+/// is tracked
+fn interpret_nested_atom<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, mut stack: Stack, bindings: Bindings) -> Vec<(InterpretedAtom, Vec<Atom>)> {
     if stack.finished {
         // first executed minimal operation returned error
         if stack.prev.is_none() {
-            return vec![InterpretedAtom(stack, bindings)];
+            return vec![(InterpretedAtom(stack, bindings), Vec::new())];
         }
         let Stack{ prev, atom, ret: _, finished: _, vars: _ } = stack;
         let prev = match prev {
@@ -377,12 +538,12 @@ fn interpret_nested_atom<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T
         };
         let ret = prev.borrow().ret;
         ret(prev, atom, bindings)
-            .map_or(vec![], |(stack, bindings)| vec![InterpretedAtom(stack, bindings)])
+            .map_or(vec![], |(stack, bindings)| vec![(InterpretedAtom(stack, bindings), Vec::new())])
     } else {
         let expr = atom_as_slice(&stack.atom);
         let result = match expr {
             Some([op, ..]) if *op == EVAL_SYMBOL => {
-                eval(context, stack, bindings)
+                return eval(context, stack, bindings);
             },
             Some([op, ..]) if *op == CHAIN_SYMBOL => {
                 chain(stack, bindings)
@@ -410,7 +571,7 @@ fn interpret_nested_atom<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T
                 vec![InterpretedAtom(stack, bindings)]
             },
         };
-        result
+        with_atoms(result, Vec::new())
     }
 }
 
@@ -426,21 +587,29 @@ fn finished_result(atom: Atom, bindings: Bindings, prev: Option<Rc<RefCell<Stack
     vec![InterpretedAtom(Stack::finished(prev, atom), bindings)]
 }
 
-fn eval<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, stack: Stack, bindings: Bindings) -> Vec<InterpretedAtom> {
+fn with_atoms(results: Vec<InterpretedAtom>, atoms: Vec<Atom>) -> Vec<(InterpretedAtom, Vec<Atom>)> {
+    results.into_iter().map(|result| (result, atoms.clone())).collect()
+}
+
+fn eval<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, stack: Stack, bindings: Bindings) -> Vec<(InterpretedAtom, Vec<Atom>)> {
     let Stack{ prev, atom: eval, ret: _, finished: _, vars} = stack;
     let query_atom = match_atom!{
         eval ~ [_op, query] => query,
         _ => {
             let error = format!("expected: ({} <atom>), found: {}", EVAL_SYMBOL, eval);
-            return finished_result(error_atom(eval, error), bindings, prev);
+            return with_atoms(finished_result(error_atom(eval, error), bindings, prev), Vec::new());
         }
     };
     log::debug!("eval: query_atom: {}", query_atom);
     match atom_as_slice(&query_atom) {
         Some([Atom::Grounded(op), args @ ..]) => {
-            let exec_res = op.execute(args);
+            // atoms read by the operation are used to derive each of its results
+            let (exec_res, matched) = match context.provenance {
+                true => record_matched_atoms(|| op.execute(args)),
+                false => (op.execute(args), Vec::new()),
+            };
             log::debug!("eval: execution results: {:?}", exec_res);
-            match exec_res {
+            let results = match exec_res {
                 Ok(results) => {
                     if results.is_empty() {
                         // There is no valid reason to return empty result from
@@ -472,11 +641,12 @@ fn eval<'a, T: SpaceRef<'a>>(context: &InterpreterContext<'a, T>, stack: Stack,
                     // TODO: we could remove ExecError::NoReduce and explicitly
                     // return NOT_REDUCIBLE_SYMBOL from the grounded function instead.
                     finished_result(return_not_reducible(), bindings, prev),
-            }
+            };
+            with_atoms(results, matched)
         },
         _ if is_embedded_op(&query_atom) =>
-            vec![InterpretedAtom(atom_to_stack(query_atom, prev), bindings)],
-        _ => query(&context.space, prev, query_atom, bindings, vars),
+            vec![(InterpretedAtom(atom_to_stack(query_atom, prev), bindings), Vec::new())],
+        _ => query(&context.space, prev, query_atom, bindings, vars, context.provenance),
     }
 }
 
@@ -493,24 +663,29 @@ fn is_variable_op(atom: &Atom) -> bool {
     }
 }
 
-fn query<'a, T: SpaceRef<'a>>(space: T, prev: Option<Rc<RefCell<Stack>>>, atom: Atom, bindings: Bindings, _vars: Variables) -> Vec<InterpretedAtom> {
+/// Returns the results of the `atom` evaluation together with the `=`
+/// definitions used to find each of them when `provenance` is tracked
+fn query<'a, T: SpaceRef<'a>>(space: T, prev: Option<Rc<RefCell<Stack>>>, atom: Atom, bindings: Bindings, _vars: Variables, provenance: bool) -> Vec<(InterpretedAtom, Vec<Atom>)> {
     #[cfg(not(feature = "variable_operation"))]
     if is_variable_op(&atom) {
         // TODO: This is a hotfix. Better way of doing this is adding
         // a function which modifies minimal MeTTa interpreter code
         // in order to skip such evaluations in metta-call function.
-        return finished_result(return_not_reducible(), bindings, prev)
+        return with_atoms(finished_result(return_not_reducible(), bindings, prev), Vec::new())
     }
     let var_x = &VariableAtom::new("X").make_unique();
     let query = Atom::expr([EQUAL_SYMBOL, atom.clone(), Atom::Variable(var_x.clone())]);
-    let results = space.query(&query);
+    let results = match provenance {
+        true => query_with_matched_atoms(&space, &query),
+        false => space.query(&query).into_iter().map(|b| (b, Vec::new())).collect(),
+    };
     let atom_x = Atom::Variable(var_x.clone());
-    let results: Vec<InterpretedAtom> = {
+    let results: Vec<(InterpretedAtom, Vec<Atom>)> = {
         log::debug!("interpreter_minimal::query: query: {}", query);
-        log::debug!("interpreter_minimal::query: results.len(): {}, bindings.len(): {}, results: {} bindings: {}",
+        log::debug!("interpreter_minimal::query: results.len(): {}, bindings.len(): {}, results: {:?} bindings: {}",
             results.len(), bindings.len(), results, bindings);
         results.into_iter()
-            .flat_map(|b| {
+            .flat_map(|(b, definitions)| {
                 let res = apply_bindings_to_atom(&atom_x, &b);
                 let stack = if is_function_op(&res) {
                     let call = Stack::from_prev_add_vars(prev.clone(), atom.clone(), call_ret);
@@ -524,14 +699,14 @@ fn query<'a, T: SpaceRef<'a>>(space: T, prev: Option<Rc<RefCell<Stack>>>, atom:
                         None
                     } else {
                         b.retain(|v| stack.vars.contains(v));
-                        Some(InterpretedAtom(stack.clone(), b))
+                        Some((InterpretedAtom(stack.clone(), b), definitions.clone()))
                     }
                 })
             })
             .collect()
     };
     if results.is_empty() {
-        finished_result(return_not_reducible(), bindings, prev)
+        with_atoms(finished_result(return_not_reducible(), bindings, prev), Vec::new())
     } else {
         results
     }
@@ -1155,6 +1330,44 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn interpret_with_provenance_returns_used_definitions() {
+        let space = space("
+            (= (color) red)
+            (= (color) green)
+            (= (paint $x) (painted $x))
+        ");
+
+        let mut results = interpret_with_provenance(&space,
+            &metta_atom("(chain (eval (color)) $c (eval (paint $c)))")).unwrap();
+        results.sort_by_key(|(atom, _)| atom.to_string());
+
+        assert_eq!(results, vec![
+            (metta_atom("(painted green)"), vec![metta_atom("(= (color) green)"), metta_atom("(= (paint $x) (painted $x))")]),
+            (metta_atom("(painted red)"), vec![metta_atom("(= (color) red)"), metta_atom("(= (paint $x) (painted $x))")]),
+        ]);
+    }
+
+    #[test]
+    fn interpret_with_provenance_collapse_bind() {
+        let space = space("
+            (= (color) red)
+            (= (color) green)
+        ");
+
+        let results = interpret_with_provenance(&space, &metta_atom("(collapse-bind (eval (color)))")).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq_no_order!(results[0].1, vec![metta_atom("(= (color) red)"), metta_atom("(= (color) green)")]);
+
+        let mut results = interpret_with_provenance(&space,
+            &metta_atom("(chain (collapse-bind (eval (color))) $collapsed (superpose-bind $collapsed))")).unwrap();
+        results.sort_by_key(|(atom, _)| atom.to_string());
+        assert_eq!(results, vec![
+            (metta_atom("green"), vec![metta_atom("(= (color) green)")]),
+            (metta_atom("red"), vec![metta_atom("(= (color) red)")]),
+        ]);
+    }
+
     fn space(text: &str) -> GroundingSpace {
         metta_space(text)
     }
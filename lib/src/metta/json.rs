@@ -0,0 +1,255 @@
+//! JSON encoding of atoms.
+//!
+//! Each atom is encoded as a JSON object with a single key which names the kind of the atom:
+//! - symbol: `{"symbol": "foo"}`
+//! - variable: `{"variable": "x"}`
+//! - expression: `{"expression": [{"symbol": "foo"}, {"variable": "x"}]}`
+//! - grounded atom: `{"grounded": 42}`, the value is the JSON boolean, number or string
+//!   written by [Grounded::serialize]
+//! - token: `{"token": "+"}`, the text is converted into an atom by the [Tokenizer] in the
+//!   same way the words of S-expression text are, so `{"token": "+"}` is read as the grounded
+//!   `+` operation and `{"token": "1"}` as a number. Grounded atoms which cannot be serialized
+//!   are encoded as tokens holding their textual representation.
+//!
+//! Names of symbols are read as is, so `{"symbol": "1"}` is read as the symbol `1`.
+//! [JsonParser] reads atoms encoded this way, [atom_to_json] and [results_to_json] write
+//! atoms and results of [Metta::run](crate::metta::runner::Metta::run).
+//!
+//! # Examples
+//!
+//! ```
+//! use hyperon::expr;
+//! use hyperon::metta::text::{Parser, Tokenizer};
+//! use hyperon::metta::json::{JsonParser, atom_to_json};
+//!
+//! let mut parser = JsonParser::new(r#"{"expression": [{"symbol": "foo"}, {"variable": "x"}]}"#.as_bytes());
+//! let atom = parser.next_atom(&Tokenizer::new()).unwrap().unwrap();
+//! assert_eq!(atom, expr!("foo" x));
+//! assert_eq!(atom_to_json(&atom).to_string(), r#"{"expression":[{"symbol":"foo"},{"variable":"x"}]}"#);
+//! ```
+
+use crate::*;
+use crate::metta::text::{Parser, Tokenizer};
+use crate::metta::runner::arithmetics::{Bool, Number};
+use crate::metta::runner::string::Str;
+
+use std::collections::VecDeque;
+use std::io::Read;
+use serde_json::{Map, Value};
+use serde_json::de::IoRead;
+
+/// Returns the JSON encoding of the `atom`
+pub fn atom_to_json(atom: &Atom) -> Value {
+    let (key, value) = match atom {
+        Atom::Symbol(sym) => ("symbol", Value::from(sym.name())),
+        Atom::Variable(var) => ("variable", Value::from(var.name())),
+        Atom::Expression(expr) => ("expression", Value::Array(expr.children().iter().map(atom_to_json).collect())),
+        Atom::Grounded(gnd) => match grounded_to_json(&**gnd) {
+            Some(value) => ("grounded", value),
+            None => ("token", Value::from(atom.to_string())),
+        },
+    };
+    Value::Object(Map::from_iter([(key.to_string(), value)]))
+}
+
+/// Returns the JSON encoding of the results of running a program: an array which contains
+/// an array of result atoms for each executed expression
+pub fn results_to_json(results: &[Vec<Atom>]) -> Value {
+    Value::Array(results.iter()
+        .map(|atoms| Value::Array(atoms.iter().map(atom_to_json).collect()))
+        .collect())
+}
+
+/// Decodes the atom from its JSON encoding, tokens are converted into atoms by the `tokenizer`
+pub fn atom_from_json(value: &Value, tokenizer: &Tokenizer) -> Result<Atom, String> {
+    let invalid = || format!("Invalid JSON encoding of atom: {}", value);
+    let object = value.as_object().filter(|object| object.len() == 1).ok_or_else(invalid)?;
+    let (key, value) = object.iter().next().unwrap();
+    match (key.as_str(), value) {
+        ("symbol", Value::String(name)) => Ok(Atom::sym(name)),
+        ("token", Value::String(text)) => match tokenizer.find_token(text) {
+            Some(constr) => constr(text),
+            None => Ok(Atom::sym(text)),
+        },
+        ("variable", Value::String(name)) => Ok(Atom::Variable(variable_from_name(name))),
+        ("expression", Value::Array(children)) => {
+            let children: Result<Vec<Atom>, String> = children.iter()
+                .map(|child| atom_from_json(child, tokenizer)).collect();
+            Ok(Atom::expr(children?))
+        },
+        ("grounded", value) => grounded_from_json(value).ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+/// Returns the JSON value written by [Grounded::serialize] of the grounded
+/// atom, or `None` if the atom cannot be serialized
+pub fn grounded_to_json(gnd: &dyn GroundedAtom) -> Option<Value> {
+    let mut serializer = JsonSerializer::default();
+    match gnd.serialize(&mut serializer) {
+        Ok(()) => serializer.value,
+        Err(_) => None,
+    }
+}
+
+/// Restores the grounded atom from the JSON value returned by [grounded_to_json]
+pub fn grounded_from_json(value: &Value) -> Option<Atom> {
+    match value {
+        Value::Bool(b) => Some(Atom::gnd(Bool(*b))),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => Some(Atom::gnd(Number::Integer(n))),
+            None => n.as_f64().map(|n| Atom::gnd(Number::Float(n))),
+        },
+        Value::String(s) => Some(Atom::gnd(Str::from_string(s.clone()))),
+        _ => None,
+    }
+}
+
+/// Restores the variable from the name returned by [VariableAtom::name], which includes
+/// the id of the unique variables
+fn variable_from_name(name: &str) -> VariableAtom {
+    match name.rsplit_once('#') {
+        Some((prefix, id)) => match id.parse::<usize>() {
+            Ok(id) => VariableAtom::new_id(prefix, id),
+            Err(_) => VariableAtom::new(name),
+        },
+        None => VariableAtom::new(name),
+    }
+}
+
+#[derive(Default)]
+struct JsonSerializer {
+    value: Option<Value>,
+}
+
+impl serial::Serializer for JsonSerializer {
+    fn serialize_bool(&mut self, v: bool) -> serial::Result {
+        self.value = Some(Value::from(v));
+        Ok(())
+    }
+    fn serialize_i64(&mut self, v: i64) -> serial::Result {
+        self.value = Some(Value::from(v));
+        Ok(())
+    }
+    fn serialize_f64(&mut self, v: f64) -> serial::Result {
+        // NaN and infinite values have no JSON representation
+        self.value = serde_json::Number::from_f64(v).map(Value::Number);
+        self.value.as_ref().map(|_| ()).ok_or(serial::Error::NotSupported)
+    }
+    fn serialize_str(&mut self, v: &str) -> serial::Result {
+        self.value = Some(Value::from(v));
+        Ok(())
+    }
+}
+
+/// Reads atoms encoded in JSON from the `reader`. The input is a sequence of JSON values,
+/// each value is either an encoded atom or an array of encoded atoms. Values are read one
+/// by one, thus the input can be a stream of atoms.
+pub struct JsonParser<R: Read> {
+    values: serde_json::StreamDeserializer<'static, IoRead<R>, Value>,
+    atoms: VecDeque<Value>,
+}
+
+impl<R: Read> JsonParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self{
+            values: serde_json::Deserializer::from_reader(reader).into_iter(),
+            atoms: VecDeque::new(),
+        }
+    }
+}
+
+impl<R: Read> Parser for JsonParser<R> {
+    fn next_atom(&mut self, tokenizer: &Tokenizer) -> Result<Option<Atom>, String> {
+        while self.atoms.is_empty() {
+            match self.values.next() {
+                None => return Ok(None),
+                Some(Err(err)) => return Err(format!("Invalid JSON: {}", err)),
+                Some(Ok(Value::Array(atoms))) => self.atoms.extend(atoms),
+                Some(Ok(atom)) => self.atoms.push_back(atom),
+            }
+        }
+        let atom = self.atoms.pop_front().unwrap();
+        atom_from_json(&atom, tokenizer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metta::runner::{Metta, EnvBuilder};
+    use serde_json::json;
+
+    fn parse_json(text: &str, tokenizer: &Tokenizer) -> Result<Vec<Atom>, String> {
+        let mut parser = JsonParser::new(text.as_bytes());
+        let mut atoms = Vec::new();
+        while let Some(atom) = parser.next_atom(tokenizer)? {
+            atoms.push(atom);
+        }
+        Ok(atoms)
+    }
+
+    #[test]
+    fn json_encoding_round_trip() {
+        let unique = VariableAtom::new("y").make_unique();
+        let atom = Atom::expr([expr!("foo"), expr!(x), Atom::Variable(unique.clone()),
+            expr!({Number::Integer(1)} {Number::Float(1.5)} {Bool(true)} {Str::from_str("a\"b")})]);
+        let encoded = atom_to_json(&atom);
+        assert_eq!(encoded, json!({"expression": [
+            {"symbol": "foo"},
+            {"variable": "x"},
+            {"variable": unique.name()},
+            {"expression": [{"grounded": 1}, {"grounded": 1.5}, {"grounded": true}, {"grounded": "a\"b"}]},
+        ]}));
+        assert_eq!(atom_from_json(&encoded, &Tokenizer::new()), Ok(atom));
+    }
+
+    #[test]
+    fn json_encoding_keeps_symbols_and_variables_as_is() {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        let tokenizer = metta.tokenizer().borrow();
+        let atom = Atom::expr([Atom::sym("1"), Atom::sym("+"), Atom::var("a#b")]);
+        let encoded = atom_to_json(&atom);
+        assert_eq!(encoded, json!({"expression": [{"symbol": "1"}, {"symbol": "+"}, {"variable": "a#b"}]}));
+        assert_eq!(atom_from_json(&encoded, &tokenizer), Ok(atom));
+        assert_eq!(atom_from_json(&json!({"token": "1"}), &tokenizer), Ok(Atom::gnd(Number::Integer(1))));
+    }
+
+    #[test]
+    fn json_encoding_of_not_serializable_grounded_atom() {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        let tokenizer = metta.tokenizer().borrow();
+        let plus = tokenizer.find_token("+").unwrap()("+").unwrap();
+        assert_eq!(atom_to_json(&plus), json!({"token": "+"}));
+        assert_eq!(atom_from_json(&json!({"token": "+"}), &tokenizer), Ok(plus));
+    }
+
+    #[test]
+    fn json_parser_reads_sequence_of_atoms() {
+        let text = r#"[{"symbol": "a"}, {"symbol": "b"}] {"variable": "c"}
+            {"expression": []}"#;
+        assert_eq!(parse_json(text, &Tokenizer::new()), Ok(vec![expr!("a"), expr!("b"), expr!(c), expr!()]));
+    }
+
+    #[test]
+    fn json_parser_errors() {
+        let tokenizer = Tokenizer::new();
+        assert_eq!(parse_json(r#"{"symbol": 1}"#, &tokenizer), Err(r#"Invalid JSON encoding of atom: {"symbol":1}"#.into()));
+        assert_eq!(parse_json(r#"{"symbol": "a", "variable": "b"}"#, &tokenizer),
+            Err(r#"Invalid JSON encoding of atom: {"symbol":"a","variable":"b"}"#.into()));
+        assert!(parse_json(r#"{"symbol": "a""#, &tokenizer).unwrap_err().starts_with("Invalid JSON: "));
+    }
+
+    #[test]
+    fn json_program_and_results() {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        let program = json!([
+            {"expression": [{"symbol": "="}, {"expression": [{"symbol": "double"}, {"variable": "x"}]},
+                {"expression": [{"token": "*"}, {"grounded": 2}, {"variable": "x"}]}]},
+            {"symbol": "!"},
+            {"expression": [{"symbol": "double"}, {"grounded": 21}]},
+        ]).to_string();
+        let results = metta.run(JsonParser::new(program.as_bytes())).unwrap();
+        assert_eq!(results_to_json(&results), json!([[{"grounded": 42}]]));
+    }
+}
@@ -1,10 +1,14 @@
 //! Contains MeTTa specific types, constants and functions.
 
 pub mod text;
+pub mod format;
+pub mod json;
 pub mod interpreter;
 #[cfg(feature = "minimal")]
 pub mod interpreter_minimal;
 pub mod types;
+pub mod adt;
+pub mod type_check;
 pub mod runner;
 
 use crate::*;
@@ -22,11 +26,17 @@ pub const HAS_TYPE_SYMBOL : Atom = sym!(":");
 pub const SUB_TYPE_SYMBOL : Atom = sym!(":<");
 pub const EQUAL_SYMBOL : Atom = sym!("=");
 pub const ARROW_SYMBOL : Atom = sym!("->");
+pub const DATA_SYMBOL : Atom = sym!("data");
+pub const DATA_CONSTRUCTORS_SYMBOL : Atom = sym!("data-constructors");
+pub const DISPATCH_SYMBOL : Atom = sym!("dispatch");
+pub const MOST_SPECIFIC_SYMBOL : Atom = sym!("most-specific");
+pub const OVERLOAD_SYMBOL : Atom = sym!("overload");
 pub const ERROR_SYMBOL : Atom = sym!("Error");
 pub const BAD_TYPE_SYMBOL : Atom = sym!("BadType");
 pub const INCORRECT_NUMBER_OF_ARGUMENTS_SYMBOL : Atom = sym!("IncorrectNumberOfArguments");
 pub const NOT_REDUCIBLE_SYMBOL : Atom = sym!("NotReducible");
 pub const NO_VALID_ALTERNATIVES : Atom = sym!("NoValidAlternatives");
+pub const AMBIGUOUS_CALL_SYMBOL : Atom = sym!("AmbiguousCall");
 
 pub const EMPTY_SYMBOL : Atom = sym!("Empty");
 
@@ -42,6 +52,9 @@ pub const SUPERPOSE_BIND_SYMBOL : Atom = sym!("superpose-bind");
 
 pub const INTERPRET_SYMBOL : Atom = sym!("interpret");
 
+pub const UNQUOTE_SYMBOL : Atom = sym!("unquote");
+pub const UNQUOTE_SPLICING_SYMBOL : Atom = sym!("unquote-splicing");
+
 //TODO: convert these from functions to static strcutures, when Atoms are Send+Sync
 #[allow(non_snake_case)]
 pub fn UNIT_ATOM() -> Atom {
@@ -66,6 +79,20 @@ pub fn error_atom(err_atom: Option<Atom>, err_code: Option<Atom>, message: Strin
     }
 }
 
+/// Initializes an error expression atom `(Error <err_atom> <err_code> <explanation>)`.
+/// Unlike [error_atom] the explanation is a string atom, thus the error can be
+/// written in MeTTa code.
+pub fn explained_error_atom(err_atom: Atom, err_code: Atom, explanation: String) -> Atom {
+    let explanation = Atom::gnd(runner::string::Str::from_string(explanation));
+    Atom::expr([ERROR_SYMBOL, err_atom, err_code, explanation])
+}
+
+/// Initializes a type error expression atom `(Error <err_atom> BadType <explanation>)`,
+/// see [explained_error_atom]
+pub fn bad_type_error_atom(err_atom: Atom, explanation: String) -> Atom {
+    explained_error_atom(err_atom, BAD_TYPE_SYMBOL, explanation)
+}
+
 /// Tests whether or not an atom is an error expression
 pub fn atom_is_error(atom: &Atom) -> bool {
     match atom {
@@ -83,13 +110,16 @@ pub fn atom_error_message(atom: &Atom) -> &str {
     const PANIC_STR: &str = "Atom is not error expression";
     match atom {
         Atom::Expression(expr) => {
-            let sym_atom = match expr.children().len() {
+            let msg_atom = match expr.children().len() {
                 3 => expr.children().get(2).unwrap(),
                 4 => expr.children().get(3).unwrap(),
                 _ => panic!("{}", PANIC_STR)
             };
-            let sym_atom = <&SymbolAtom>::try_from(sym_atom).expect(PANIC_STR);
-            sym_atom.name()
+            match msg_atom {
+                Atom::Grounded(gnd) => gnd.as_any_ref().downcast_ref::<runner::string::Str>()
+                    .expect(PANIC_STR).as_str(),
+                _ => <&SymbolAtom>::try_from(msg_atom).expect(PANIC_STR).name(),
+            }
         },
         _ => panic!("{}", PANIC_STR)
     }
@@ -342,7 +342,7 @@ impl serial::Serializer for NumberSerializer {
     }
 }
 
-struct AsPrimitive<'a> {
+pub(crate) struct AsPrimitive<'a> {
     atom: &'a super::Atom
 }
 
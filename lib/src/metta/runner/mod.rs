@@ -58,11 +58,13 @@
 
 use crate::*;
 use crate::common::shared::Shared;
+use crate::space::forward::fire_pending_rules;
 
 use super::*;
 use super::space::*;
 use super::text::{Tokenizer, Parser, SExprParser};
-use super::types::validate_atom;
+use super::types::explain_bad_type;
+use super::type_check::{check_module_text_with_imports, load_module_declarations, ImportedModule, TypeCheckError};
 
 pub mod modules;
 use modules::{MettaMod, ModId, ModuleInitState, ModNameNode, ModuleLoader, ResourceKey, TOP_MOD_NAME, ModNameNodeDisplayWrapper, normalize_relative_module_name, decompose_name_path, compose_name_path};
@@ -79,12 +81,12 @@ pub use environment::{Environment, EnvBuilder};
 
 pub mod stdlib;
 #[cfg(not(feature = "minimal"))]
-use super::interpreter::{interpret, interpret_init, interpret_step, InterpreterState};
+use super::interpreter::{interpret, interpret_init, interpret_step, interpret_with_provenance, InterpreterState};
 
 #[cfg(feature = "minimal")]
 pub mod stdlib_minimal;
 #[cfg(feature = "minimal")]
-use super::interpreter_minimal::{interpret, interpret_init, interpret_step, InterpreterState};
+use super::interpreter_minimal::{interpret, interpret_init, interpret_step, interpret_with_provenance, InterpreterState};
 #[cfg(feature = "minimal")]
 use stdlib_minimal::*;
 
@@ -93,7 +95,7 @@ use stdlib::CoreLibLoader;
 pub mod arithmetics;
 pub mod string;
 
-const EXEC_SYMBOL : Atom = sym!("!");
+pub const EXEC_SYMBOL : Atom = sym!("!");
 
 // *-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*-=-*
 // Metta & related objects
@@ -125,6 +127,8 @@ pub struct MettaContents {
     top_mod_tokenizer: Shared<Tokenizer>,
     /// The ModId of the extended stdlib to import into some modules loaded into the runner
     stdlib_mod: OnceLock<ModId>,
+    /// The ModId of the corelib which implements the minimal MeTTa interpreter
+    corelib_mod: OnceLock<ModId>,
     /// The runner's pragmas, affecting runner-wide behavior
     settings: Shared<HashMap<String, Atom>>,
     /// The runner's Environment
@@ -158,6 +162,7 @@ impl Metta {
 
         //Load the "corelib" module into the runner
         let corelib_mod_id = metta.load_module_direct(Box::new(CoreLibLoader), "corelib").expect("Failed to load corelib");
+        metta.0.corelib_mod.set(corelib_mod_id).unwrap();
 
         //Load the stdlib if we have one, and otherwise make an alias to corelib
         let stdlib_mod_id = match loader {
@@ -213,6 +218,7 @@ impl Metta {
             top_mod_space: space.clone(),
             top_mod_tokenizer: top_mod_tokenizer.clone(),
             stdlib_mod: OnceLock::new(),
+            corelib_mod: OnceLock::new(),
             settings,
             environment,
             context: std::sync::Arc::new(std::sync::Mutex::new(vec![])),
@@ -385,6 +391,12 @@ impl Metta {
         modules.get(mod_id.0).unwrap().space().clone()
     }
 
+    /// Returns `true` if `space` is the Space of any loaded module
+    pub(crate) fn is_module_space(&self, space: &DynSpace) -> bool {
+        let modules = self.0.modules.lock().unwrap();
+        modules.iter().any(|module| module.space() == space)
+    }
+
     /// Returns a buffer containing the specified resource, if it is available from a loaded module
     pub fn get_module_resource(&self, mod_id: ModId, res_key: ResourceKey) -> Result<Vec<u8>, String> {
         let modules = self.0.modules.lock().unwrap();
@@ -405,11 +417,11 @@ impl Metta {
     }
 
     pub fn get_setting(&self, key: &str) -> Option<Atom> {
-        self.0.settings.borrow().get(key.into()).cloned()
+        self.0.settings.borrow().get(key).cloned()
     }
 
     pub fn get_setting_string(&self, key: &str) -> Option<String> {
-        self.0.settings.borrow().get(key.into()).map(|a| a.to_string())
+        self.0.settings.borrow().get(key).map(|a| a.to_string())
     }
 
     pub fn run(&self, parser: impl Parser) -> Result<Vec<Vec<Atom>>, String> {
@@ -432,11 +444,92 @@ impl Metta {
         } else {
             wrap_atom_by_metta_interpreter(self.0.top_mod_space.clone(), atom)
         };
-        if self.type_check_is_enabled() && !validate_atom(self.0.top_mod_space.borrow().as_space(), &atom) {
-            Ok(vec![Atom::expr([ERROR_SYMBOL, atom, BAD_TYPE_SYMBOL])])
+        let explanation = match self.type_check_is_enabled() {
+            true => explain_bad_type(self.0.top_mod_space.borrow().as_space(), &atom),
+            false => None,
+        };
+        match explanation {
+            Some(explanation) => Ok(vec![bad_type_error_atom(atom, explanation)]),
+            None => interpret(self.space(), &atom),
+        }
+    }
+
+    /// Evaluates the atom like [Metta::evaluate_atom] does and returns each
+    /// result together with the atoms of the space which were used to derive
+    /// it. See [crate::metta::interpreter::interpret_with_provenance]
+    /// for details. The definitions of the corelib which implement the
+    /// minimal MeTTa interpreter are not returned.
+    pub fn evaluate_atom_with_provenance(&self, atom: Atom) -> Result<Vec<(Atom, Vec<Atom>)>, String> {
+        #[cfg(feature = "minimal")]
+        let atom = if is_bare_minimal_interpreter(self) {
+            atom
         } else {
-            interpret(self.space(), &atom)
+            wrap_atom_by_metta_interpreter(self.0.top_mod_space.clone(), atom)
+        };
+        let explanation = match self.type_check_is_enabled() {
+            true => explain_bad_type(self.0.top_mod_space.borrow().as_space(), &atom),
+            false => None,
+        };
+        match explanation {
+            Some(explanation) => Ok(vec![(bad_type_error_atom(atom, explanation), Vec::new())]),
+            #[cfg(not(feature = "minimal"))]
+            None => interpret_with_provenance(self.space(), &atom),
+            #[cfg(feature = "minimal")]
+            None => interpret_with_provenance(self.space(), &atom)
+                .map(|results| self.remove_interpreter_atoms(results)),
+        }
+    }
+
+    #[cfg(feature = "minimal")]
+    fn remove_interpreter_atoms(&self, mut results: Vec<(Atom, Vec<Atom>)>) -> Vec<(Atom, Vec<Atom>)> {
+        if let Some(corelib) = self.0.corelib_mod.get() {
+            let interpreter: Vec<Atom> = self.module_space(*corelib).borrow().atom_iter()
+                .map(|atoms| atoms.cloned().collect())
+                .unwrap_or_default();
+            results.iter_mut().for_each(|(_, atoms)| atoms.retain(|atom| !interpreter.contains(atom)));
+        }
+        results
+    }
+
+    /// Checks types of all `=` definitions and `!` expressions of the `text` in the context of the
+    /// runner's top module without running the code.  Modules imported by the `text` which are
+    /// loaded into the runner are used as is, declarations of other modules are read from their
+    /// MeTTa source without running it, see [load_module_declarations].  Modules which have no
+    /// MeTTa source are reported as bad imports.  See [check_module_text_with_imports] for details
+    pub fn check(&self, text: &str) -> Vec<TypeCheckError> {
+        let tokenizer = self.tokenizer().borrow().clone();
+        check_module_text_with_imports(text, &tokenizer, Some(self.space()),
+            &mut |mod_name| self.check_import(mod_name, &mut Vec::new()))
+    }
+
+    /// Returns the module imported by the text passed to [Metta::check], `importing` contains
+    /// the names of the modules which declarations are being read
+    fn check_import(&self, mod_name: &str, importing: &mut Vec<String>) -> Result<ImportedModule, String> {
+        let mut state = RunnerState::new_with_module(self, ModId::TOP);
+        if let Ok(mod_id) = state.run_in_context(|context| context.get_module_by_name(mod_name)) {
+            let module = self.get_mod_ptr(mod_id);
+            let tokenizer = module.tokenizer().borrow().clone();
+            return Ok(ImportedModule{ space: module.space().clone(), tokenizer });
         }
+        if importing.iter().any(|name| name == mod_name) {
+            return Err(format!("Module {} is imported recursively", mod_name));
+        }
+        // Parent modules are loaded to resolve the sub-module
+        let components = decompose_name_path(mod_name)?;
+        if components.len() > 1 {
+            let parent_name = compose_name_path(&components[..components.len() - 1])?;
+            if state.run_in_context(|context| context.get_module_by_name(&parent_name)).is_err() {
+                return Err(format!("Module {} cannot be checked before its parent module {} is loaded", mod_name, parent_name));
+            }
+        }
+        let text = state.run_in_context(|context| context.load_resource_from_module(mod_name, ResourceKey::MainMettaSrc))
+            .map_err(|err| format!("Module {} cannot be checked without running it: {}", mod_name, err))?;
+        let text = String::from_utf8(text).map_err(|err| format!("Module {} source is not valid UTF-8: {}", mod_name, err))?;
+        importing.push(mod_name.to_string());
+        let tokenizer = self.tokenizer().borrow().clone();
+        let module = load_module_declarations(&text, &tokenizer, &mut |name| self.check_import(name, importing));
+        importing.pop();
+        Ok(module)
     }
 
     fn type_check_is_enabled(&self) -> bool {
@@ -993,7 +1086,12 @@ impl<'input> RunContext<'_, '_, 'input> {
             if interpreter_state.has_next() {
 
                 //Take a step with the interpreter, and put it back for next time
-                self.i_wrapper.interpreter_state = Some(interpret_step(interpreter_state))
+                let interpreter_state = interpret_step(interpreter_state);
+                //Fire the forward chaining rules on the atoms added by the step
+                self.i_wrapper.interpreter_state = Some(match fire_pending_rules() {
+                    Ok(()) => interpreter_state,
+                    Err(err) => InterpreterState::new_finished(self.module().space().clone(), vec![error_atom(None, None, err)]),
+                });
             } else {
 
                 //This interpreter is finished, process the results
@@ -1034,7 +1132,9 @@ impl<'input> RunContext<'_, '_, 'input> {
                     }
                     match self.i_wrapper.mode {
                         MettaRunnerMode::ADD => {
-                            if let Err(atom) = self.module().add_atom(atom, self.metta.type_check_is_enabled()) {
+                            let result = self.module().add_atom(atom.clone(), self.metta.type_check_is_enabled())
+                                .and_then(|()| fire_pending_rules().map_err(|err| error_atom(Some(atom), None, err)));
+                            if let Err(atom) = result {
                                 self.i_wrapper.results.push(vec![atom]);
                                 self.i_wrapper.mode = MettaRunnerMode::TERMINATE;
                                 return Ok(());
@@ -1042,8 +1142,12 @@ impl<'input> RunContext<'_, '_, 'input> {
                         },
                         MettaRunnerMode::INTERPRET => {
 
-                            if self.metta.type_check_is_enabled() && !validate_atom(self.module().space().borrow().as_space(), &atom) {
-                                let type_err_exp = Atom::expr([ERROR_SYMBOL, atom, BAD_TYPE_SYMBOL]);
+                            let explanation = match self.metta.type_check_is_enabled() {
+                                true => explain_bad_type(self.module().space().borrow().as_space(), &atom),
+                                false => None,
+                            };
+                            if let Some(explanation) = explanation {
+                                let type_err_exp = bad_type_error_atom(atom, explanation);
                                 self.i_wrapper.interpreter_state = Some(InterpreterState::new_finished(self.module().space().clone(), vec![type_err_exp]));
                             } else {
                                 #[cfg(feature = "minimal")]
@@ -1210,7 +1314,8 @@ mod tests {
         let metta = Metta::new_core(None, Some(EnvBuilder::test_env()));
         metta.set_setting("type-check".into(), sym!("auto"));
         let result = metta.run(SExprParser::new(program));
-        assert_eq!(result, Ok(vec![vec![expr!("Error" ("foo" "b") "BadType")]]));
+        assert_eq!(result, Ok(vec![vec![expr!("Error" ("foo" "b")
+            "BadType" {string::Str::from_str("argument 1 of (foo b) should have type A, but b has type B")})]]));
     }
 
     #[test]
@@ -1224,7 +1329,123 @@ mod tests {
         let metta = Metta::new_core(None, Some(EnvBuilder::test_env()));
         metta.set_setting("type-check".into(), sym!("auto"));
         let result = metta.run(SExprParser::new(program));
-        assert_eq!(result, Ok(vec![vec![expr!("Error" ("foo" "b") "BadType")]]));
+        assert_eq!(result, Ok(vec![vec![expr!("Error" ("foo" "b")
+            "BadType" {string::Str::from_str("argument 1 of (foo b) should have type A, but b has type B")})]]));
+    }
+
+    #[test]
+    fn metta_check_uses_module_types() {
+        let program = "
+            (: foo (-> Number Number))
+            (= (bar) (foo \"a\"))
+            !(foo 1)
+        ";
+
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        let errors = metta.check(program);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].atom, Some(expr!("foo" {string::Str::from_str("a")})));
+        assert_eq!(metta.space().borrow().atom_count(), Some(1));
+    }
+
+    #[derive(Debug)]
+    struct TypesLoader;
+
+    impl ModuleLoader for TypesLoader {
+        fn load(&self, context: &mut RunContext) -> Result<(), String> {
+            let space = DynSpace::new(GroundingSpace::new());
+            context.init_self_module(space, None);
+            context.push_parser(Box::new(SExprParser::new("(: foo (-> Number Number))")));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn metta_check_uses_imported_module_types() {
+        use crate::metta::type_check::TypeCheckErrorKind;
+        let program = "
+            !(import! &self types)
+            !(foo \"a\")
+            !(import! &self unknown)
+        ";
+
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        metta.load_module_direct(Box::new(TypesLoader), "types").unwrap();
+        let errors = metta.check(program);
+        let kinds: Vec<TypeCheckErrorKind> = errors.iter().map(|err| err.kind).collect();
+        assert_eq!(kinds, vec![TypeCheckErrorKind::BadImport, TypeCheckErrorKind::BadType]);
+        assert_eq!(errors[1].atom, Some(expr!("foo" {string::Str::from_str("a")})));
+        assert_eq!(metta.space().borrow().atom_count(), Some(1));
+    }
+
+    #[test]
+    fn metta_check_does_not_run_imported_modules() {
+        let dir = std::env::temp_dir().join(format!("hyperon-test-check-imports-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("side.metta"), "
+            !(import! &self other)
+            (: foo (-> Number Number))
+            !(add-atom &self (side-loaded))
+        ").unwrap();
+        std::fs::write(dir.join("other.metta"), "(: bar (-> Number Number))").unwrap();
+        let program = "
+            !(import! &self side)
+            !(foo \"a\")
+            !(bar \"b\")
+        ";
+
+        let metta = Metta::new(Some(EnvBuilder::test_env().push_include_path(&dir)));
+        let errors = metta.check(program);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let atoms: Vec<Option<Atom>> = errors.into_iter().map(|err| err.atom).collect();
+        assert_eq!(atoms, vec![Some(expr!("foo" {string::Str::from_str("a")})),
+            Some(expr!("bar" {string::Str::from_str("b")}))]);
+        let mut state = RunnerState::new_with_module(&metta, ModId::TOP);
+        assert!(state.run_in_context(|context| context.get_module_by_name("side")).is_err());
+        assert!(state.run_in_context(|context| context.get_module_by_name("other")).is_err());
+    }
+
+    #[test]
+    fn metta_evaluate_atom_with_provenance() {
+        let program = "
+            (= (color) red)
+            (= (color) green)
+            (= (paint $x) (painted $x))
+        ";
+
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        metta.run(SExprParser::new(program)).unwrap();
+        let results = metta.evaluate_atom_with_provenance(expr!("paint" ("color")));
+
+        let source = results.unwrap().into_iter()
+            .find(|(atom, _)| *atom == expr!("painted" "green"))
+            .map(|(_, provenance)| provenance[0].clone());
+        assert_eq!(source, Some(expr!("=" ("color") "green")));
+        assert!(metta.space().borrow_mut().remove(&source.unwrap()));
+        assert_eq!(metta.evaluate_atom(expr!("paint" ("color"))), Ok(vec![expr!("painted" "red")]));
+    }
+
+    #[test]
+    fn metta_evaluate_atom_with_provenance_tracks_matched_atoms() {
+        let program = "
+            (parent Tom Bob)
+            (parent Eve Ann)
+            (= (child $p) (match &self (parent $p $c) $c))
+        ";
+
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        metta.run(SExprParser::new(program)).unwrap();
+        let results = metta.evaluate_atom_with_provenance(expr!("child" "Tom")).unwrap();
+
+        assert_eq!(results.len(), 1);
+        let (result, provenance) = &results[0];
+        assert_eq!(*result, expr!("Bob"));
+        assert_eq!(provenance.len(), 2);
+        assert_eq!(provenance[1], expr!("parent" "Tom" "Bob"));
+        assert!(metta.space().borrow_mut().remove(&provenance[0]));
+        assert!(metta.space().borrow_mut().remove(&provenance[1]));
+        assert_eq!(metta.evaluate_atom(expr!("child" "Tom")), Ok(vec![expr!("child" "Tom")]));
     }
 
     #[derive(Clone, PartialEq, Debug)]
@@ -1279,7 +1500,8 @@ mod tests {
         let metta = Metta::new_core(None, Some(EnvBuilder::test_env()));
         metta.set_setting("type-check".into(), sym!("auto"));
         let result = metta.run(SExprParser::new(program));
-        assert_eq!(result, Ok(vec![vec![expr!("Error" ("foo" "b") "BadType")]]));
+        assert_eq!(result, Ok(vec![vec![expr!("Error" ("foo" "b")
+            "BadType" {string::Str::from_str("argument 1 of (foo b) should have type A, but b has type B")})]]));
     }
 
     #[derive(Clone, PartialEq, Debug)]
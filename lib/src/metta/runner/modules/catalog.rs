@@ -74,7 +74,7 @@ use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
 use std::ffi::{OsStr, OsString};
 
-use crate::metta::text::OwnedSExprParser;
+use crate::metta::text::{OwnedSExprParser, ReaderSExprParser};
 use crate::metta::runner::*;
 use crate::metta::runner::modules::*;
 
@@ -259,10 +259,9 @@ impl ModuleLoader for SingleFileModule {
         let resource_dir = self.path.parent().unwrap();
         context.init_self_module(space, Some(resource_dir.into()));
 
-        let program_text = String::from_utf8(self.read_contents()?)
-            .map_err(|e| e.to_string())?;
-
-        let parser = OwnedSExprParser::new(program_text);
+        let file = std::fs::File::open(&self.path)
+            .map_err(|err| format!("Could not read file, path: {}, error: {}", self.path.display(), err))?;
+        let parser = ReaderSExprParser::from_read(file);
         context.push_parser(Box::new(parser));
 
         Ok(())
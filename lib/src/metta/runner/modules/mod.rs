@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::cell::RefCell;
 
 use crate::metta::*;
+use crate::metta::adt::{DataDeclaration, check_declaration_kinds};
 use crate::metta::runner::*;
 
 use regex::Regex;
@@ -333,8 +334,23 @@ impl MettaMod {
 
     /// A convenience to add an an atom to a module's Space, if it passes type-checking
     pub(crate) fn add_atom(&self, atom: Atom, type_check: bool) -> Result<(), Atom> {
-        if type_check && !validate_atom(self.space.borrow().as_space(), &atom) {
-            return Err(Atom::expr([ERROR_SYMBOL, atom, BAD_TYPE_SYMBOL]));
+        if type_check {
+            if let Some(explanation) = explain_bad_type(self.space.borrow().as_space(), &atom) {
+                return Err(bad_type_error_atom(atom, explanation));
+            }
+            if let Err(explanation) = check_declaration_kinds(self.space.borrow().as_space(), &atom) {
+                return Err(bad_type_error_atom(atom, explanation));
+            }
+        }
+        if let Some(decl) = DataDeclaration::parse(&atom) {
+            let decl = decl.and_then(|decl| {
+                decl.check_kinds(self.space.borrow().as_space()).map(|()| decl)
+            });
+            match decl {
+                Ok(decl) => decl.declarations().into_iter()
+                    .for_each(|decl| self.space.borrow_mut().add(decl)),
+                Err(explanation) => return Err(bad_type_error_atom(atom, explanation)),
+            }
         }
         self.space.borrow_mut().add(atom);
         Ok(())
@@ -1,15 +1,22 @@
 use crate::*;
 use crate::matcher::MatchResultIter;
 use crate::space::*;
+use crate::space::persistent::PersistentSpace;
+use crate::space::sync::SyncAtom;
+use crate::space::view::SpaceView;
+use crate::space::forward::{ForwardChainingSpace, RuleAction, add_rule};
+use crate::space::datalog::saturate;
+use crate::space::backward::{Prover, ProofTree};
 use crate::metta::*;
 use crate::metta::text::Tokenizer;
 use crate::metta::text::SExprParser;
-use crate::metta::runner::{Metta, RunContext, ModuleLoader, ResourceKey};
-use crate::metta::types::{get_atom_types, get_meta_type};
+use crate::metta::runner::{Metta, EnvBuilder, RunContext, ModuleLoader, ResourceKey};
+use crate::metta::types::{get_atom_types, get_meta_type, get_inferred_types};
 use crate::common::shared::Shared;
 use crate::common::CachingMapper;
 
 use std::rc::Rc;
+use std::sync::{Arc, Mutex, mpsc};
 use std::cell::RefCell;
 use std::fmt::Display;
 use std::collections::HashMap;
@@ -390,7 +397,11 @@ impl Grounded for BindOp {
         let atom = args.get(1).ok_or_else(arg_error)?.clone();
 
         let token_regex = Regex::new(token).map_err(|err| format!("Could convert token {} into regex: {}", token, err))?;
-        self.tokenizer.borrow_mut().register_token(token_regex, move |_| { atom.clone() });
+        let mut tokenizer = self.tokenizer.borrow_mut();
+        if let Some(shadowed) = tokenizer.find_token_regex(token) {
+            log::warn!("bind!: token {} shadows the token registered by regex {}", token, shadowed);
+        }
+        tokenizer.register_token(token_regex, move |_| { atom.clone() });
         unit_result()
     }
 
@@ -427,6 +438,200 @@ impl Grounded for NewSpaceOp {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct NewPersistentSpaceOp {
+    tokenizer: Shared<Tokenizer>,
+}
+
+impl NewPersistentSpaceOp {
+    pub fn new(tokenizer: Shared<Tokenizer>) -> Self {
+        Self{ tokenizer }
+    }
+}
+
+impl PartialEq for NewPersistentSpaceOp {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Display for NewPersistentSpaceOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "new-persistent-space")
+    }
+}
+
+impl Grounded for NewPersistentSpaceOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, ATOM_TYPE_STRING, rust_type_atom::<DynSpace>()])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("new-persistent-space expects a path to the space directory as an argument");
+        let path = match args {
+            [Atom::Grounded(gnd)] => gnd.as_any_ref().downcast_ref::<Str>().ok_or_else(arg_error)?,
+            _ => return Err(arg_error()),
+        };
+        let space = PersistentSpace::open(path.as_str(), &self.tokenizer.borrow())?;
+        Ok(vec![Atom::gnd(DynSpace::new(space))])
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct NewViewOp {}
+
+impl Display for NewViewOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "new-view")
+    }
+}
+
+impl Grounded for NewViewOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, rust_type_atom::<DynSpace>(), ATOM_TYPE_ATOM, ATOM_TYPE_ATOM, rust_type_atom::<DynSpace>()])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("new-view expects three arguments: space, pattern and template");
+        let space = args.first().ok_or_else(arg_error)?;
+        let pattern = args.get(1).ok_or_else(arg_error)?;
+        let template = args.get(2).ok_or_else(arg_error)?;
+        let space = Atom::as_gnd::<DynSpace>(space).ok_or("new-view expects a space as the first argument")?;
+        let view = SpaceView::new(space, pattern.clone(), template.clone());
+        Ok(vec![Atom::gnd(DynSpace::new(view))])
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct NewForwardChainingSpaceOp {}
+
+impl Display for NewForwardChainingSpaceOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "new-forward-chaining-space")
+    }
+}
+
+impl Grounded for NewForwardChainingSpaceOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, rust_type_atom::<DynSpace>()])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        if args.is_empty() {
+            Ok(vec![Atom::gnd(DynSpace::new(ForwardChainingSpace::new()))])
+        } else {
+            Err("new-forward-chaining-space doesn't expect arguments".into())
+        }
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct AddRuleOp {}
+
+impl Display for AddRuleOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "add-rule")
+    }
+}
+
+impl Grounded for AddRuleOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, rust_type_atom::<DynSpace>(), ATOM_TYPE_ATOM, ATOM_TYPE_ATOM, UNIT_TYPE()])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("add-rule expects three arguments: space, pattern and action");
+        let space = args.first().ok_or_else(arg_error)?;
+        let pattern = args.get(1).ok_or_else(arg_error)?;
+        let action = args.get(2).ok_or_else(arg_error)?;
+        let space = Atom::as_gnd::<DynSpace>(space).ok_or("add-rule expects a space as the first argument")?;
+        let action = match action {
+            Atom::Expression(expr) => match expr.children().as_slice() {
+                [Atom::Symbol(sym), template] if sym.name() == "add" => RuleAction::Add(template.clone()),
+                [Atom::Symbol(sym), template] if sym.name() == "remove" => RuleAction::Remove(template.clone()),
+                _ => return Err("add-rule expects (add <template>) or (remove <template>) as an action".into()),
+            },
+            _ => return Err("add-rule expects (add <template>) or (remove <template>) as an action".into()),
+        };
+        add_rule(space, pattern.clone(), action);
+        unit_result()
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SaturateOp {}
+
+impl Display for SaturateOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "saturate")
+    }
+}
+
+impl Grounded for SaturateOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, rust_type_atom::<DynSpace>(), ATOM_TYPE_NUMBER])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("saturate expects one argument: space");
+        let space = args.first().ok_or_else(arg_error)?;
+        let space = Atom::as_gnd::<DynSpace>(space).ok_or("saturate expects a space as the first argument")?;
+        let derived = saturate(&mut *space.borrow_mut())?;
+        Ok(vec![Atom::gnd(Number::Integer(derived as i64))])
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProveOp {}
+
+impl Display for ProveOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "prove")
+    }
+}
+
+impl Grounded for ProveOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, rust_type_atom::<DynSpace>(), ATOM_TYPE_ATOM, ATOM_TYPE_ATOM])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("prove expects two arguments: space and goal");
+        let space = args.first().ok_or_else(arg_error)?;
+        let goal = args.get(1).ok_or_else(arg_error)?;
+        let space = Atom::as_gnd::<DynSpace>(space).ok_or("prove expects a space as the first argument")?;
+        let prover = Prover::from_space(space.borrow().as_space());
+        Ok(prover.prove(goal).into_iter()
+            .map(|solution| Atom::expr([matcher::apply_bindings_to_atom(goal, &solution.bindings),
+                Atom::expr(solution.proofs.iter().map(ProofTree::to_atom).collect::<Vec<Atom>>())]))
+            .collect())
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct AddAtomOp {}
 
@@ -576,7 +781,7 @@ impl Grounded for GetTypeOp {
         let arg_error = || ExecError::from("get-type expects single atom as an argument");
         let atom = args.get(0).ok_or_else(arg_error)?;
 
-        Ok(get_atom_types(self.space.borrow().as_space(), atom))
+        Ok(get_inferred_atom_types(self.space.borrow().as_space(), atom))
     }
 
     fn match_(&self, other: &Atom) -> MatchResultIter {
@@ -584,6 +789,21 @@ impl Grounded for GetTypeOp {
     }
 }
 
+/// Returns types of the `atom`. Types of the variables of the expression are
+/// inferred from its content, conflicting usages of the variables are
+/// reported as an error with the `BadType` code and the explanation.
+pub(crate) fn get_inferred_atom_types(space: &dyn Space, atom: &Atom) -> Vec<Atom> {
+    let has_variables = matches!(atom, Atom::Expression(_))
+        && atom.iter().any(|atom| matches!(atom, Atom::Variable(_)));
+    if !has_variables {
+        return get_atom_types(space, atom);
+    }
+    match get_inferred_types(space, atom) {
+        Ok(types) => types,
+        Err(message) => vec![bad_type_error_atom(atom.clone(), message)],
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct GetTypeSpaceOp {}
 
@@ -701,10 +921,15 @@ impl Grounded for FormatArgsOp {
 fn atom_to_string(atom: &Atom) -> String {
     match atom {
         Atom::Grounded(gnd) if gnd.type_() == ATOM_TYPE_STRING => {
-            let mut s = gnd.to_string();
-            s.remove(0);
-            s.pop();
-            s
+            match gnd.as_any_ref().downcast_ref::<Str>() {
+                Some(s) => s.as_str().to_string(),
+                None => {
+                    let mut s = gnd.to_string();
+                    s.remove(0);
+                    s.pop();
+                    s
+                },
+            }
         },
         _ => atom.to_string(),
     }
@@ -793,6 +1018,64 @@ impl Grounded for NopOp {
     }
 }
 
+/// Builds an atom from the template of the quasi-quotation. The parser expands `` `(foo ,$x ,@$y) ``
+/// into `(quasiquote (foo (unquote $x) (unquote-splicing $y)))`, unquoted atoms which are not
+/// variables are evaluated by `let` before the template is passed to `quasiquote`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct QuasiQuoteOp {}
+
+impl Display for QuasiQuoteOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "quasiquote")
+    }
+}
+
+impl Grounded for QuasiQuoteOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, ATOM_TYPE_ATOM, ATOM_TYPE_ATOM])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("quasiquote expects one argument: template");
+        let template = args.first().ok_or_else(arg_error)?;
+        Ok(vec![Self::substitute(template)?])
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+impl QuasiQuoteOp {
+    /// Replaces `(unquote $x)` by `$x` and splices the children of `$y` in place of
+    /// `(unquote-splicing $y)`, nested quasi-quotations are kept as is
+    fn substitute(atom: &Atom) -> Result<Atom, ExecError> {
+        let children = match atom {
+            Atom::Expression(expr) => expr.children(),
+            _ => return Ok(atom.clone()),
+        };
+        match &children[..] {
+            [op, value] if *op == UNQUOTE_SYMBOL => return Ok(value.clone()),
+            [op, _value] if *op == UNQUOTE_SPLICING_SYMBOL => return Err("unquote-splicing is allowed only inside of expression".into()),
+            [op, _template] if *op == Atom::gnd(QuasiQuoteOp{}) || *op == sym!("quasiquote") => return Ok(atom.clone()),
+            _ => {},
+        }
+        let mut result = Vec::with_capacity(children.len());
+        for child in children {
+            match child {
+                Atom::Expression(expr) if expr.children().len() == 2 && expr.children()[0] == UNQUOTE_SPLICING_SYMBOL => {
+                    match &expr.children()[1] {
+                        Atom::Expression(spliced) => result.extend(spliced.children().iter().cloned()),
+                        value => return Err(format!("unquote-splicing expects expression, found: {}", value).into()),
+                    }
+                },
+                _ => result.push(Self::substitute(child)?),
+            }
+        }
+        Ok(Atom::expr(result))
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct StateAtom {
     state: Rc<RefCell<Atom>>
@@ -1011,6 +1294,309 @@ impl Grounded for MatchOp {
     }
 }
 
+#[derive(Clone, PartialEq, Debug)]
+pub struct MatchLimitOp {}
+
+impl Display for MatchLimitOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "match-limit")
+    }
+}
+
+impl Grounded for MatchLimitOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, rust_type_atom::<DynSpace>(), ATOM_TYPE_ATOM, ATOM_TYPE_ATOM, ATOM_TYPE_NUMBER, ATOM_TYPE_UNDEFINED])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("match-limit expects four arguments: space, pattern, template and limit");
+        let space = args.first().ok_or_else(arg_error)?;
+        let pattern = args.get(1).ok_or_else(arg_error)?;
+        let template = args.get(2).ok_or_else(arg_error)?;
+        let limit = args.get(3).ok_or_else(arg_error)?;
+        log::debug!("MatchLimitOp::execute: space: {:?}, pattern: {:?}, template: {:?}, limit: {:?}", space, pattern, template, limit);
+        let space = Atom::as_gnd::<DynSpace>(space).ok_or("match-limit expects a space as the first argument")?;
+        let limit = match AsPrimitive::from_atom(limit).as_number() {
+            Some(Number::Integer(n)) if n >= 0 => n as usize,
+            _ => return Err("match-limit expects a non-negative integer as the fourth argument".into()),
+        };
+        Ok(space.borrow().query_page(pattern, 0, limit).into_iter()
+            .map(|bindings| matcher::apply_bindings_to_atom(template, &bindings))
+            .collect())
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+/// Evaluates the alternatives of the expression in parallel. Alternatives are
+/// evaluated by the pool of worker threads, each thread has its own runner
+/// with the copy of the space, thus modifications of the space made by
+/// alternatives are not visible to the caller. Results are returned in the
+/// order of alternatives, as `superpose` does. An alternative which cannot be
+/// interpreted is replaced by an `Error` atom.
+///
+/// Worker threads and their runners are created on the first call and are
+/// reused by the next calls. The space is copied into the worker runner when
+/// either the space of the caller or the space of the worker runner has been
+/// modified since the previous copy.
+///
+/// Atoms are copied between runners as [SyncAtom]s. Grounded atoms which
+/// cannot be serialized or restored by the tokenizer of the other runner,
+/// like spaces bound by `bind!` or states, cannot be copied, and an error is
+/// returned when an alternative, an atom of the space or a result contains
+/// such atom. The space of the caller is replaced by the space of the worker
+/// runner. The spaces of the modules imported by the caller are not copied,
+/// the worker runners have their own standard library.
+#[derive(Clone)]
+pub struct ParSuperposeOp {
+    space: DynSpace,
+    tokenizer: Shared<Tokenizer>,
+    metta: Metta,
+    snapshot: SpaceObserverRef<ParSuperposeSnapshot>,
+    pool: Rc<std::cell::OnceCell<ParSuperposePool>>,
+}
+
+impl PartialEq for ParSuperposeOp {
+    fn eq(&self, other: &Self) -> bool {
+        self.space == other.space
+    }
+}
+
+impl std::fmt::Debug for ParSuperposeOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParSuperposeOp").field("space", &self.space).finish_non_exhaustive()
+    }
+}
+
+impl ParSuperposeOp {
+    pub fn new(space: DynSpace, tokenizer: Shared<Tokenizer>, metta: Metta) -> Self {
+        let snapshot = space.common().register_observer(ParSuperposeSnapshot(None));
+        Self{ space, tokenizer, metta, snapshot, pool: Rc::new(std::cell::OnceCell::new()) }
+    }
+
+    /// Returns the copy of the space, the copy is kept until the space is
+    /// modified
+    fn snapshot(&self) -> Result<Arc<Vec<SyncAtom>>, ExecError> {
+        if let Some(snapshot) = &self.snapshot.borrow().0 {
+            return Ok(snapshot.clone());
+        }
+        let space = self.space.borrow();
+        let atoms = space.as_space().atom_iter()
+            .ok_or("par-superpose cannot traverse atoms of the space")?;
+        let snapshot: Arc<Vec<SyncAtom>> = Arc::new(atoms
+            .filter(|atom| !Atom::as_gnd::<DynSpace>(atom).is_some_and(|space| self.metta.is_module_space(space)))
+            .map(|atom| self.branch(atom))
+            .collect());
+        self.snapshot.borrow_mut().0 = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// Converts the atom replacing the space of the caller by the `&self`
+    /// token which is resolved to the space of the worker runner
+    fn branch(&self, atom: &Atom) -> SyncAtom {
+        match atom {
+            Atom::Expression(expr) => SyncAtom::Expression(expr.children().iter()
+                .map(|child| self.branch(child)).collect()),
+            Atom::Grounded(_) if Atom::as_gnd::<DynSpace>(atom) == Some(&self.space) =>
+                SyncAtom::Text("&self".into()),
+            _ => SyncAtom::from(atom),
+        }
+    }
+}
+
+impl Display for ParSuperposeOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "par-superpose")
+    }
+}
+
+impl Grounded for ParSuperposeOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, ATOM_TYPE_EXPRESSION, ATOM_TYPE_UNDEFINED])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("par-superpose expects single expression as an argument");
+        let expr = match args.first() {
+            Some(Atom::Expression(expr)) => expr,
+            _ => return Err(arg_error()),
+        };
+        if expr.children().is_empty() {
+            return Ok(vec![]);
+        }
+        let snapshot = self.snapshot()?;
+        let pool = self.pool.get_or_init(ParSuperposePool::new);
+        log::debug!("ParSuperposeOp::execute: branches: {}, workers: {}", expr.children().len(), pool.size);
+
+        let (sender, receiver) = mpsc::channel();
+        for (index, branch) in expr.children().iter().enumerate() {
+            pool.send(ParSuperposeJob{ index, branch: self.branch(branch), snapshot: snapshot.clone(), results: sender.clone() });
+        }
+        drop(sender);
+        let mut results: Vec<Option<ParSuperposeResult>> = vec![None; expr.children().len()];
+        for (index, result) in receiver {
+            results[index] = Some(result?);
+        }
+
+        let tokenizer = self.tokenizer.borrow();
+        let mut atoms = Vec::new();
+        for (branch, result) in expr.children().iter().zip(results) {
+            match result.ok_or("par-superpose worker panicked")? {
+                ParSuperposeResult::Atoms(results) => for atom in results {
+                    atoms.push(atom.to_atom(&tokenizer).map_err(|gnd| par_superpose_copy_error(&atom, &gnd))?);
+                },
+                ParSuperposeResult::Error(err) => atoms.push(Atom::expr([ERROR_SYMBOL, branch.clone(), Atom::sym(err)])),
+            }
+        }
+        Ok(atoms)
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+fn par_superpose_copy_error(atom: &SyncAtom, gnd: &str) -> String {
+    format!("par-superpose cannot copy {} between runners: grounded atom {} cannot be serialized", atom, gnd)
+}
+
+/// Keeps the copy of the space until the space is modified
+#[derive(Clone)]
+struct ParSuperposeSnapshot(Option<Arc<Vec<SyncAtom>>>);
+
+impl SpaceObserver for ParSuperposeSnapshot {
+    fn notify(&mut self, _event: &SpaceEvent) {
+        self.0 = None;
+    }
+}
+
+/// Alternative to be evaluated by the worker, `results` receives the result
+/// together with the index of the alternative
+struct ParSuperposeJob {
+    index: usize,
+    branch: SyncAtom,
+    snapshot: Arc<Vec<SyncAtom>>,
+    results: mpsc::Sender<(usize, Result<ParSuperposeResult, String>)>,
+}
+
+#[derive(Clone)]
+enum ParSuperposeResult {
+    Atoms(Vec<SyncAtom>),
+    Error(String),
+}
+
+/// Worker threads evaluating the alternatives, threads are finished when the
+/// pool is dropped
+struct ParSuperposePool {
+    jobs: mpsc::Sender<ParSuperposeJob>,
+    size: usize,
+}
+
+impl ParSuperposePool {
+    fn new() -> Self {
+        let size = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let (jobs, receiver) = mpsc::channel::<ParSuperposeJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..size {
+            let receiver = receiver.clone();
+            std::thread::spawn(move || {
+                let mut worker = ParSuperposeWorker::new();
+                loop {
+                    // Lock is released before the job is evaluated
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => worker.run(job),
+                        Err(_) => break,
+                    }
+                }
+            });
+        }
+        Self{ jobs, size }
+    }
+
+    fn send(&self, job: ParSuperposeJob) {
+        // Workers are finished only when the pool is dropped
+        self.jobs.send(job).expect("par-superpose workers are finished");
+    }
+}
+
+/// Flags the modification of the space of the worker runner
+struct SpaceModified(bool);
+
+impl SpaceObserver for SpaceModified {
+    fn notify(&mut self, _event: &SpaceEvent) {
+        self.0 = true;
+    }
+}
+
+/// Runner of the worker thread with the copy of the space
+struct ParSuperposeWorker {
+    metta: Metta,
+    /// Atoms of the runner space which are not copied, like the standard
+    /// library module
+    own: Vec<Atom>,
+    loaded: Option<Arc<Vec<SyncAtom>>>,
+    modified: SpaceObserverRef<SpaceModified>,
+}
+
+impl ParSuperposeWorker {
+    fn new() -> Self {
+        let metta = Metta::new(Some(EnvBuilder::new().set_working_dir(None).set_no_config_dir()));
+        let own = metta.space().borrow().as_space().atom_iter()
+            .map_or(vec![], |atoms| atoms.cloned().collect());
+        let modified = metta.space().common().register_observer(SpaceModified(false));
+        Self{ metta, own, loaded: None, modified }
+    }
+
+    fn run(&mut self, job: ParSuperposeJob) {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.evaluate(&job.snapshot, &job.branch)))
+            .unwrap_or_else(|_| {
+                self.loaded = None;
+                Err("par-superpose worker panicked".into())
+            });
+        // Receiver is dropped when the caller has returned an error already
+        let _ = job.results.send((job.index, result));
+    }
+
+    fn evaluate(&mut self, snapshot: &Arc<Vec<SyncAtom>>, branch: &SyncAtom) -> Result<ParSuperposeResult, String> {
+        let is_loaded = self.loaded.as_ref().is_some_and(|loaded| Arc::ptr_eq(loaded, snapshot));
+        if !is_loaded || self.modified.borrow().0 {
+            self.load(snapshot)?;
+        }
+        let branch = branch.to_atom(&self.metta.tokenizer().borrow())
+            .map_err(|gnd| par_superpose_copy_error(branch, &gnd))?;
+        let result = interpret(self.metta.space().clone(), &branch);
+        log::debug!("ParSuperposeWorker::evaluate: interpretation expr: {}, result {:?}", branch, result);
+        Ok(match result {
+            Ok(atoms) => ParSuperposeResult::Atoms(atoms.iter().map(SyncAtom::from).collect()),
+            Err(err) => ParSuperposeResult::Error(err),
+        })
+    }
+
+    /// Replaces the atoms of the runner space by the copy of the space
+    fn load(&mut self, snapshot: &Arc<Vec<SyncAtom>>) -> Result<(), String> {
+        self.loaded = None;
+        let mut space = self.metta.space().borrow_mut();
+        let atoms: Vec<Atom> = space.as_space().atom_iter()
+            .ok_or("par-superpose cannot traverse atoms of the worker space")?
+            .cloned().collect();
+        for atom in atoms {
+            space.remove(&atom);
+        }
+        for atom in &self.own {
+            space.add(atom.clone());
+        }
+        let tokenizer = self.metta.tokenizer().borrow();
+        for atom in snapshot.iter() {
+            space.add(atom.to_atom(&tokenizer).map_err(|gnd| par_superpose_copy_error(atom, &gnd))?);
+        }
+        self.modified.borrow_mut().0 = false;
+        self.loaded = Some(snapshot.clone());
+        Ok(())
+    }
+}
 
 /// The internal `non_minimal_only_stdlib` module contains code that is never used by the minimal stdlib
 #[cfg(not(feature = "minimal"))]
@@ -1546,10 +2132,24 @@ mod non_minimal_only_stdlib {
 
         let match_op = Atom::gnd(MatchOp{});
         tref.register_token(regex(r"match"), move |_| { match_op.clone() });
-        let bind_op = Atom::gnd(BindOp::new(tokenizer));
+        let match_limit_op = Atom::gnd(MatchLimitOp{});
+        tref.register_token(regex(r"match-limit"), move |_| { match_limit_op.clone() });
+        let bind_op = Atom::gnd(BindOp::new(tokenizer.clone()));
         tref.register_token(regex(r"bind!"), move |_| { bind_op.clone() });
         let new_space_op = Atom::gnd(NewSpaceOp{});
         tref.register_token(regex(r"new-space"), move |_| { new_space_op.clone() });
+        let new_persistent_space_op = Atom::gnd(NewPersistentSpaceOp::new(tokenizer.clone()));
+        tref.register_token(regex(r"new-persistent-space"), move |_| { new_persistent_space_op.clone() });
+        let new_view_op = Atom::gnd(NewViewOp{});
+        tref.register_token(regex(r"new-view"), move |_| { new_view_op.clone() });
+        let new_forward_chaining_space_op = Atom::gnd(NewForwardChainingSpaceOp{});
+        tref.register_token(regex(r"new-forward-chaining-space"), move |_| { new_forward_chaining_space_op.clone() });
+        let add_rule_op = Atom::gnd(AddRuleOp{});
+        tref.register_token(regex(r"add-rule"), move |_| { add_rule_op.clone() });
+        let saturate_op = Atom::gnd(SaturateOp{});
+        tref.register_token(regex(r"saturate"), move |_| { saturate_op.clone() });
+        let prove_op = Atom::gnd(ProveOp{});
+        tref.register_token(regex(r"prove"), move |_| { prove_op.clone() });
         let add_atom_op = Atom::gnd(AddAtomOp{});
         tref.register_token(regex(r"add-atom"), move |_| { add_atom_op.clone() });
         let remove_atom_op = Atom::gnd(RemoveAtomOp{});
@@ -1570,6 +2170,8 @@ mod non_minimal_only_stdlib {
         tref.register_token(regex(r"trace!"), move |_| { trace_op.clone() });
         let nop_op = Atom::gnd(NopOp{});
         tref.register_token(regex(r"nop"), move |_| { nop_op.clone() });
+        let quasi_quote_op = Atom::gnd(QuasiQuoteOp{});
+        tref.register_token(regex(r"quasiquote"), move |_| { quasi_quote_op.clone() });
         let let_op = Atom::gnd(LetOp{});
         tref.register_token(regex(r"let"), move |_| { let_op.clone() });
         let let_var_op = Atom::gnd(LetVarOp{});
@@ -1595,7 +2197,7 @@ mod non_minimal_only_stdlib {
     //TODO: The metta argument is a temporary hack on account of the way the operation atoms store references
     // to the runner & module state.  https://github.com/trueagi-io/hyperon-experimental/issues/410
     #[cfg(not(feature = "minimal"))]
-    pub fn register_runner_tokens(tref: &mut Tokenizer, _tokenizer: Shared<Tokenizer>, space: &DynSpace, metta: &Metta) {
+    pub fn register_runner_tokens(tref: &mut Tokenizer, tokenizer: Shared<Tokenizer>, space: &DynSpace, metta: &Metta) {
 
         let capture_op = Atom::gnd(CaptureOp::new(space.clone()));
         tref.register_token(regex(r"capture"), move |_| { capture_op.clone() });
@@ -1609,6 +2211,8 @@ mod non_minimal_only_stdlib {
         tref.register_token(regex(r"collapse"), move |_| { collapse_op.clone() });
         let superpose_op = Atom::gnd(SuperposeOp::new(space.clone()));
         tref.register_token(regex(r"superpose"), move |_| { superpose_op.clone() });
+        let par_superpose_op = Atom::gnd(ParSuperposeOp::new(space.clone(), tokenizer.clone(), metta.clone()));
+        tref.register_token(regex(r"par-superpose"), move |_| { par_superpose_op.clone() });
         let get_type_op = Atom::gnd(GetTypeOp::new(space.clone()));
         tref.register_token(regex(r"get-type"), move |_| { get_type_op.clone() });
         let get_type_space_op = Atom::gnd(GetTypeSpaceOp{});
@@ -1644,7 +2248,7 @@ mod non_minimal_only_stdlib {
             |token| { Ok(Atom::gnd(Number::from_float_str(token)?)) });
         tref.register_token(regex(r"True|False"),
             |token| { Atom::gnd(Bool::from_str(token)) });
-        tref.register_token(regex(r#""[^"]+""#),
+        tref.register_token(regex(r#"(?s)".*""#),
             |token| { let mut s = String::from(token); s.remove(0); s.pop(); Atom::gnd(Str::from_string(s)) });
         let sum_op = Atom::gnd(SumOp{});
         tref.register_token(regex(r"\+"), move |_| { sum_op.clone() });
@@ -1689,6 +2293,8 @@ pub use non_minimal_only_stdlib::*;
 
 #[cfg(feature = "minimal")]
 use super::stdlib_minimal::*;
+#[cfg(not(feature = "minimal"))]
+use crate::metta::interpreter::interpret;
 
 #[cfg(feature = "minimal")]
 use crate::metta::runner::METTA_CODE;
@@ -1758,6 +2364,205 @@ mod tests {
             Ok(vec![expr!("B" "A")]));
     }
 
+    #[test]
+    fn match_limit_op() {
+        let space = DynSpace::new(metta_space("(A B) (A C) (A D)"));
+        let match_limit_op = MatchLimitOp{};
+        let result = match_limit_op.execute(&mut vec![expr!({space.clone()}), expr!("A" x), expr!("B" x), expr!({Number::Integer(2)})]).unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().all(|atom| [expr!("B" "B"), expr!("B" "C"), expr!("B" "D")].contains(atom)),
+            "unexpected result: {:?}", result);
+        assert_eq!(match_limit_op.execute(&mut vec![expr!({space.clone()}), expr!("A" x), expr!("B" x), expr!({Number::Integer(0)})]),
+            Ok(vec![]));
+        assert_eq!(match_limit_op.execute(&mut vec![expr!({space}), expr!("A" x), expr!("B" x), expr!({Number::Integer(-1)})]),
+            Err(ExecError::from("match-limit expects a non-negative integer as the fourth argument")));
+    }
+
+    #[test]
+    fn metta_match_limit() {
+        let program = "
+            (A B) (A C) (A D)
+            !(match-limit &self (A $x) $x 1)
+            !(match-limit &self (A $x) $x 5)
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq!(result[0].len(), 1);
+        assert_eq_no_order!(result[1], vec![expr!("B"), expr!("C"), expr!("D")]);
+    }
+
+    #[test]
+    fn metta_new_view() {
+        let program = "
+            (likes Sam pizza)
+            !(bind! &lovers (new-view &self (likes $x pizza) (pizza-lover $x)))
+            !(add-atom &self (likes Ann pizza))
+            !(remove-atom &self (likes Sam pizza))
+            !(match &lovers (pizza-lover $x) $x)
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq!(result[3], vec![expr!("Ann")]);
+    }
+
+    #[test]
+    fn metta_forward_chaining_space() {
+        let program = "
+            !(bind! &kb (new-forward-chaining-space))
+            !(add-rule &kb (parent $x $y) (add (ancestor $x $y)))
+            !(add-rule &kb (, (parent $x $y) (ancestor $y $z)) (add (ancestor $x $z)))
+            !(add-rule &kb (adopted $x) (remove (orphan $x)))
+            !(add-atom &kb (orphan Ann))
+            !(add-atom &kb (parent Tom Bob))
+            !(add-atom &kb (parent Bob Ann))
+            !(add-atom &kb (adopted Ann))
+            !(match &kb (ancestor Tom $x) $x)
+            !(match &kb (orphan $x) $x)
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq_no_order!(result[8], vec![expr!("Bob"), expr!("Ann")]);
+        assert_eq!(result[9], vec![]);
+    }
+
+    #[test]
+    fn metta_add_rule_to_any_space() {
+        let program = "
+            !(add-rule &self (human $x) (add (mortal $x)))
+            (human Socrates)
+            !(add-atom &self (human Plato))
+            !(match &self (mortal $x) $x)
+            !(bind! &kb (new-space))
+            !(add-rule &kb (a $x) (add (b $x)))
+            !(add-rule &kb (a $x) (remove (b $x)))
+            !(add-rule &kb (b $x) (remove (a $x)))
+            !(add-rule &kb (b $x) (add (a $x)))
+            !(add-atom &kb (a 1))
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq_no_order!(result[2], vec![expr!("Socrates"), expr!("Plato")]);
+        assert_eq!(result[8].len(), 1);
+        assert_eq!(atom_error_message(&result[8][0]), "Forward chaining is stopped after 10000 rule firings");
+    }
+
+    #[test]
+    fn metta_add_rule_fires_inside_expression() {
+        let program = "
+            !(add-rule &self (human $x) (add (mortal $x)))
+            !(let $_ (add-atom &self (human Plato)) (match &self (mortal $x) $x))
+        ";
+        assert_eq!(run_program(program), Ok(vec![vec![UNIT_ATOM()], vec![expr!("Plato")]]));
+    }
+
+    #[test]
+    fn metta_saturate() {
+        let program = "
+            !(bind! &kb (new-space))
+            !(add-atom &kb (edge a b))
+            !(add-atom &kb (edge b c))
+            !(add-atom &kb (edge c a))
+            !(add-atom &kb (implies (edge $x $y) (path $x $y)))
+            !(add-atom &kb (implies (, (path $x $y) (edge $y $z)) (path $x $z)))
+            !(saturate &kb)
+            !(match &kb (path a $x) $x)
+            !(saturate &kb)
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq!(result[6], vec![expr!({Number::Integer(9)})]);
+        assert_eq_no_order!(result[7], vec![expr!("a"), expr!("b"), expr!("c")]);
+        assert_eq!(result[8], vec![expr!({Number::Integer(0)})]);
+    }
+
+    #[test]
+    fn metta_prove() {
+        let program = "
+            !(bind! &kb (new-space))
+            !(add-atom &kb (human Socrates))
+            !(add-atom &kb (implies (human $x) (mortal $x)))
+            !(prove &kb (mortal $who))
+            !(prove &kb (mortal Zeus))
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq!(result[3], vec![expr!(("mortal" "Socrates")
+            (("rule" ("mortal" "Socrates") ("implies" ("human" x) ("mortal" x)) (("fact" ("human" "Socrates"))))))]);
+        assert_eq!(result[4], vec![]);
+    }
+
+    #[test]
+    fn metta_par_superpose() {
+        let program = "
+            (= (f) A)
+            (= (f) B)
+            (= (g $x) (+ $x 1))
+            !(par-superpose ((f) (g 1) (g 2) (g 3)))
+            !(par-superpose ())
+        ";
+        let result = run_program(program).unwrap();
+        let (f, g) = result[0].split_at(2);
+        assert_eq_no_order!(f, vec![expr!("A"), expr!("B")]);
+        assert_eq!(g, vec![expr!({Number::Integer(2)}), expr!({Number::Integer(3)}), expr!({Number::Integer(4)})]);
+        assert_eq!(result[1], vec![]);
+    }
+
+    #[test]
+    fn metta_par_superpose_uses_self_space_of_worker() {
+        let program = "
+            (fact 1)
+            (= (q) (match &self (fact $x) $x))
+            !(par-superpose ((q) (q)))
+        ";
+        assert_eq!(run_program(program), Ok(vec![vec![expr!({Number::Integer(1)}), expr!({Number::Integer(1)})]]));
+    }
+
+    #[test]
+    fn metta_par_superpose_rejects_grounded_atoms_which_cannot_be_copied() {
+        let program = "
+            !(bind! &kb (new-space))
+            !(add-atom &kb (fact 1))
+            (= (q) (match &kb (fact $x) $x))
+            !(par-superpose ((q) (q)))
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq!(result[2].len(), 1);
+        let message = atom_error_message(&result[2][0]);
+        assert!(message.starts_with("par-superpose cannot copy (= (q) (match GroundingSpace-"), "unexpected error: {}", message);
+
+        let result = run_program("!(par-superpose ((new-space)))").unwrap();
+        assert_eq!(result[0].len(), 1);
+        let message = atom_error_message(&result[0][0]);
+        assert!(message.starts_with("par-superpose cannot copy GroundingSpace-"), "unexpected error: {}", message);
+
+        let result = run_program("!(bind! &kb (new-space)) !(add-atom &self &kb) !(par-superpose ((A)))").unwrap();
+        assert_eq!(result[2].len(), 1);
+        let message = atom_error_message(&result[2][0]);
+        assert!(message.starts_with("par-superpose cannot copy GroundingSpace-"), "unexpected error: {}", message);
+    }
+
+    #[test]
+    fn metta_par_superpose_does_not_change_space() {
+        let program = "
+            (= (g $x) (+ $x 1))
+            !(par-superpose ((add-atom &self (B)) (g 1)))
+            !(match &self (B) found)
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq!(result[0].len(), 2);
+        assert_eq!(result[1], vec![]);
+    }
+
+    #[test]
+    fn metta_par_superpose_copies_modified_space() {
+        let program = "
+            (= (f) A)
+            !(par-superpose ((f) (f)))
+            !(add-atom &self (= (f) B))
+            !(par-superpose ((f)))
+            !(par-superpose ((add-atom &self (C)) (add-atom &self (C))))
+            !(par-superpose ((match &self (C) found) (match &self (C) found)))
+        ";
+        let result = run_program(program).unwrap();
+        assert_eq!(result[0], vec![expr!("A"), expr!("A")]);
+        assert_eq_no_order!(result[2], vec![expr!("A"), expr!("B")]);
+        assert_eq!(result[4], vec![]);
+    }
+
     #[test]
     fn match_op_issue_530() {
         let space = DynSpace::new(metta_space("(A $a $a)"));
@@ -1839,6 +2644,35 @@ mod tests {
         assert_eq!(res, vec![expr!(("A" "F") ("B" "C") "D")]);
     }
 
+    #[test]
+    fn metta_new_persistent_space() {
+        let path = std::env::temp_dir().join(format!("hyperon-test-new-persistent-space-{}", std::process::id()));
+        let copy = std::env::temp_dir().join(format!("hyperon-test-new-persistent-space-copy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let _ = std::fs::remove_dir_all(&copy);
+        let open = |path: &std::path::Path| format!("!(bind! &kb (new-persistent-space \"{}\"))", path.display());
+
+        let program = format!("{}\n!(add-atom &kb (likes Sam (pizza 2)))", open(&path));
+        assert_eq!(run_program(&program), Ok(vec![vec![UNIT_ATOM()], vec![UNIT_ATOM()]]));
+        let program = format!("!(new-persistent-space \"{}\")", path.display());
+        let result = run_program(&program).unwrap();
+        assert_eq!(result[0].len(), 1);
+        assert_eq!(atom_error_message(&result[0][0]), format!("Space {} is already opened", path.display()));
+
+        // Space bound by the first runner is kept open, thus the copy of its
+        // directory is reopened
+        std::fs::create_dir_all(&copy).unwrap();
+        for entry in std::fs::read_dir(&path).unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), copy.join(entry.file_name())).unwrap();
+        }
+        let program = format!("{}\n!(match &kb (likes Sam $x) $x)", open(&copy));
+        assert_eq!(run_program(&program), Ok(vec![vec![UNIT_ATOM()], vec![expr!("pizza" {Number::Integer(2)})]]));
+
+        std::fs::remove_dir_all(&path).unwrap();
+        std::fs::remove_dir_all(&copy).unwrap();
+    }
+
     #[test]
     fn bind_new_space_op() {
         let tokenizer = Shared::new(Tokenizer::new());
@@ -2008,7 +2842,8 @@ mod tests {
 
         assert_eq!(metta.run(parser), Ok(vec![vec![
                 expr!("Error" ("f" ({SuperposeOp{space:metta.space().clone()}} ())) "NoValidAlternatives"),
-                expr!("a"), expr!("Error" "b" "BadType")]]));
+                expr!("a"), expr!("Error" "b"
+                    "BadType" {Str::from_str("argument 1 of (f b) should have type A, but b has type B")})]]));
     }
 
     #[test]
@@ -2040,6 +2875,68 @@ mod tests {
             Vec::<Atom>::new());
     }
 
+    #[test]
+    fn get_type_op_infers_variable_types() {
+        let space = DynSpace::new(metta_space("
+            (: inc (-> Number Number))
+            (: neg (-> Bool Bool))
+        "));
+
+        let get_type_op = GetTypeOp::new(space);
+        assert_eq!(get_type_op.execute(&mut vec![expr!("=" ("f" x) ("inc" x))]).unwrap(),
+            vec![expr!("->" "Number" "Number")]);
+        assert_eq!(get_type_op.execute(&mut vec![expr!("inc" ("neg" x))]).unwrap(),
+            vec![expr!("Error" ("inc" ("neg" x))
+                "BadType" {Str::from_str("(neg $x) has type Bool but argument 1 of (inc (neg $x)) should have type Number")})]);
+    }
+
+    #[test]
+    fn metta_dispatch_most_specific_overload() {
+        let program = "
+            (dispatch add most-specific)
+            (: V (-> Number Number Vec))
+            (: add (-> Number Number Number))
+            (: add (-> Vec Vec Vec))
+            (= (add $a $b) (+ $a $b))
+            (overload (= (add $a $b) (+ $a $b)) (-> Number Number Number))
+            (= (add (V $a $b) (V $c $d)) (V (+ $a $c) (+ $b $d)))
+            !(add 1 2)
+            !(add (V 1 2) (V 3 4))
+        ";
+
+        assert_eq!(run_program(program), Ok(vec![
+            vec![expr!({Number::Integer(3)})],
+            vec![expr!("V" {Number::Integer(4)} {Number::Integer(6)})],
+        ]));
+    }
+
+    #[test]
+    fn metta_dispatch_overload_bound_by_annotation() {
+        let program = "
+            (dispatch show most-specific)
+            (: show (-> $t String))
+            (: show (-> Number String))
+            (= (show $x) \"any\")
+            (= (show $x) \"number\")
+            (overload (= (show $x) \"any\") (-> $t String))
+            (overload (= (show $x) \"number\") (-> Number String))
+            !(show 1)
+            !(show \"s\")
+        ";
+
+        assert_eq!(run_program(program), Ok(vec![
+            vec![expr!({Str::from_str("number")})],
+            vec![expr!({Str::from_str("any")})],
+        ]));
+    }
+
+    #[test]
+    fn format_args_op_does_not_escape_strings() {
+        let result = FormatArgsOp{}.execute(&mut vec![expr!({Str::from_str("x{}y{}")}),
+            expr!({Str::from_str("p\nq")} {Str::from_str("\"r\"")})]);
+        assert_eq!(result, Ok(vec![expr!({Str::from_str("xp\nqy\"r\"")})]));
+    }
+
     #[test]
     fn println_op() {
         assert_eq!(PrintlnOp{}.execute(&mut vec![sym!("A")]), unit_result());
@@ -2161,6 +3058,36 @@ mod tests {
             ]));
     }
 
+    #[test]
+    fn test_quasi_quote() {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        let parser = SExprParser::new("
+            (= (double $x) (* 2 $x))
+            !`(foo ,(double 3) ,@(cdr-atom (a b c)) d)
+            !(let $x 5 `(bar ,$x (baz ,(+ $x 1))))
+            !`(x ,@(superpose ((1 2) (3))))
+        ");
+
+        assert_eq_metta_results!(metta.run(parser),
+            Ok(vec![
+                vec![expr!("foo" {Number::Integer(6)} "b" "c" "d")],
+                vec![expr!("bar" {Number::Integer(5)} ("baz" {Number::Integer(6)}))],
+                vec![expr!("x" {Number::Integer(1)} {Number::Integer(2)}), expr!("x" {Number::Integer(3)})],
+            ]));
+    }
+
+    #[test]
+    fn quasi_quote_op() {
+        let template = expr!("a" ("unquote" "b") ("unquote-splicing" ("c" "d")) ("e" ("unquote" "f")));
+        assert_eq!(QuasiQuoteOp{}.execute(&mut vec![template]), Ok(vec![expr!("a" "b" "c" "d" ("e" "f"))]));
+        let nested = expr!("a" ({QuasiQuoteOp{}} ("unquote" "b")));
+        assert_eq!(QuasiQuoteOp{}.execute(&mut vec![nested.clone()]), Ok(vec![nested]));
+        assert_eq!(QuasiQuoteOp{}.execute(&mut vec![expr!("a" ("unquote-splicing" "b"))]),
+            Err(ExecError::from("unquote-splicing expects expression, found: b")));
+        assert_eq!(QuasiQuoteOp{}.execute(&mut vec![expr!("unquote-splicing" ("b"))]),
+            Err(ExecError::from("unquote-splicing is allowed only inside of expression")));
+    }
+
     #[test]
     fn test_unify() {
         let metta = Metta::new(Some(EnvBuilder::test_env()));
@@ -2292,6 +3219,12 @@ mod tests {
         ]));
     }
 
+    #[test]
+    fn stdlib_tokens_have_no_conflicts() {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        assert_eq!(metta.tokenizer().borrow().conflicts(), vec![]);
+    }
+
     #[test]
     fn test_get_doc_gnd_func() {
         let metta = Metta::new(Some(EnvBuilder::test_env()));
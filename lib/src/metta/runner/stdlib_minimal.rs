@@ -4,7 +4,7 @@ use crate::space::*;
 use crate::metta::*;
 use crate::metta::text::Tokenizer;
 use crate::metta::runner::Metta;
-use crate::metta::types::get_atom_types;
+use crate::metta::types::{get_atom_types, explain_type_mismatch, explain_arg_type_mismatch};
 use crate::common::assert::vec_eq_no_order;
 use crate::common::shared::Shared;
 use crate::metta::runner::stdlib;
@@ -91,12 +91,14 @@ impl Grounded for GetTypeOp {
     fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
         let arg_error = || ExecError::from("get-type expects single atom as an argument");
         let atom = args.get(0).ok_or_else(arg_error)?;
-        let space = match args.get(1) {
-            Some(space) => Atom::as_gnd::<DynSpace>(space)
-                .ok_or("match expects a space as the first argument"),
-            None => Ok(&self.space),
-        }?;
-        let types = get_atom_types(space, atom);
+        let types = match args.get(1) {
+            Some(space) => {
+                let space = Atom::as_gnd::<DynSpace>(space)
+                    .ok_or("match expects a space as the first argument")?;
+                get_atom_types(space, atom)
+            },
+            None => stdlib::get_inferred_atom_types(self.space.borrow().as_space(), atom),
+        };
         if types.is_empty() {
             Ok(vec![EMPTY_SYMBOL])
         } else {
@@ -109,6 +111,83 @@ impl Grounded for GetTypeOp {
     }
 }
 
+#[derive(Clone, PartialEq, Debug)]
+pub struct BadTypeErrorOp {}
+
+impl Display for BadTypeErrorOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bad-type-error")
+    }
+}
+
+impl Grounded for BadTypeErrorOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, ATOM_TYPE_ATOM, ATOM_TYPE_ATOM, rust_type_atom::<DynSpace>(), ATOM_TYPE_ATOM])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("bad-type-error expects atom, expected type and space as arguments");
+        let atom = args.get(0).ok_or_else(arg_error)?;
+        let typ = args.get(1).ok_or_else(arg_error)?;
+        let space = args.get(2).and_then(Atom::as_gnd::<DynSpace>).ok_or_else(arg_error)?;
+        let explanation = explain_type_mismatch(space.borrow().as_space(), atom, typ);
+        Ok(vec![bad_type_error_atom(atom.clone(), explanation)])
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
+/// Adds the position of the argument into the explanation of the type error
+/// returned by the interpretation of the argument. Takes the expression, the
+/// arguments which are not interpreted yet starting from the argument itself,
+/// the expected type of the argument and the error.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BadArgTypeErrorOp {}
+
+impl Display for BadArgTypeErrorOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bad-arg-type-error")
+    }
+}
+
+impl Grounded for BadArgTypeErrorOp {
+    fn type_(&self) -> Atom {
+        Atom::expr([ARROW_SYMBOL, ATOM_TYPE_ATOM, ATOM_TYPE_ATOM, ATOM_TYPE_ATOM, ATOM_TYPE_ATOM, rust_type_atom::<DynSpace>(), ATOM_TYPE_ATOM])
+    }
+
+    fn execute(&self, args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+        let arg_error = || ExecError::from("bad-arg-type-error expects expression, arguments left, expected type, error and space as arguments");
+        let expr = args.get(0).and_then(|atom| <&ExpressionAtom>::try_from(atom).ok()).ok_or_else(arg_error)?;
+        let tail = args.get(1).and_then(|atom| <&ExpressionAtom>::try_from(atom).ok()).ok_or_else(arg_error)?;
+        let typ = args.get(2).ok_or_else(arg_error)?;
+        let error = args.get(3).ok_or_else(arg_error)?;
+        let space = args.get(4).and_then(Atom::as_gnd::<DynSpace>).ok_or_else(arg_error)?;
+        let arg = tail.children().first().ok_or_else(arg_error)?;
+        let is_arg_type_error = match error {
+            Atom::Expression(error) => matches!(error.children().as_slice(),
+                [err, atom, code, ..] if *err == ERROR_SYMBOL && atom == arg && *code == BAD_TYPE_SYMBOL),
+            _ => false,
+        };
+        if is_arg_type_error {
+            let space = space.borrow();
+            let actual = get_atom_types(space.as_space(), arg);
+            // Badly typed argument is explained by the error itself
+            if !actual.is_empty() {
+                let index = expr.children().len() - tail.children().len();
+                let explanation = explain_arg_type_mismatch(space.as_space(), &Atom::Expression(expr.clone()), index, &actual, typ);
+                return Ok(vec![bad_type_error_atom(arg.clone(), explanation)]);
+            }
+        }
+        Ok(vec![error.clone()])
+    }
+
+    fn match_(&self, other: &Atom) -> MatchResultIter {
+        match_by_equality(self, other)
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct IfEqualOp { }
 
@@ -153,7 +232,7 @@ fn interpret_no_error(space: DynSpace, expr: &Atom) -> Result<Vec<Atom>, String>
     }
 }
 
-fn interpret(space: DynSpace, expr: &Atom) -> Result<Vec<Atom>, String> {
+pub(crate) fn interpret(space: DynSpace, expr: &Atom) -> Result<Vec<Atom>, String> {
     let expr = Atom::expr([EVAL_SYMBOL, Atom::expr([INTERPRET_SYMBOL, expr.clone(), ATOM_TYPE_UNDEFINED, Atom::gnd(space.clone())])]);
     crate::metta::interpreter_minimal::interpret(space, &expr)
 }
@@ -431,7 +510,7 @@ fn regex(regex: &str) -> Regex {
 
 //TODO: The additional arguments are a temporary hack on account of the way the operation atoms store references
 // to the runner & module state.  https://github.com/trueagi-io/hyperon-experimental/issues/410
-pub fn register_common_tokens(tref: &mut Tokenizer, _tokenizer: Shared<Tokenizer>, space: &DynSpace, metta: &Metta) {
+pub fn register_common_tokens(tref: &mut Tokenizer, tokenizer: Shared<Tokenizer>, space: &DynSpace, metta: &Metta) {
 
     let get_type_op = Atom::gnd(GetTypeOp::new(space.clone()));
     tref.register_token(regex(r"get-type"), move |_| { get_type_op.clone() });
@@ -439,10 +518,26 @@ pub fn register_common_tokens(tref: &mut Tokenizer, _tokenizer: Shared<Tokenizer
     tref.register_token(regex(r"get-type-space"), move |_| { get_type_space_op.clone() });
     let get_meta_type_op = Atom::gnd(stdlib::GetMetaTypeOp{});
     tref.register_token(regex(r"get-metatype"), move |_| { get_meta_type_op.clone() });
+    let bad_type_error_op = Atom::gnd(BadTypeErrorOp{});
+    tref.register_token(regex(r"bad-type-error"), move |_| { bad_type_error_op.clone() });
+    let bad_arg_type_error_op = Atom::gnd(BadArgTypeErrorOp{});
+    tref.register_token(regex(r"bad-arg-type-error"), move |_| { bad_arg_type_error_op.clone() });
     let is_equivalent = Atom::gnd(IfEqualOp{});
     tref.register_token(regex(r"if-equal"), move |_| { is_equivalent.clone() });
     let new_space_op = Atom::gnd(stdlib::NewSpaceOp{});
     tref.register_token(regex(r"new-space"), move |_| { new_space_op.clone() });
+    let new_persistent_space_op = Atom::gnd(stdlib::NewPersistentSpaceOp::new(tokenizer.clone()));
+    tref.register_token(regex(r"new-persistent-space"), move |_| { new_persistent_space_op.clone() });
+    let new_view_op = Atom::gnd(stdlib::NewViewOp{});
+    tref.register_token(regex(r"new-view"), move |_| { new_view_op.clone() });
+    let new_forward_chaining_space_op = Atom::gnd(stdlib::NewForwardChainingSpaceOp{});
+    tref.register_token(regex(r"new-forward-chaining-space"), move |_| { new_forward_chaining_space_op.clone() });
+    let add_rule_op = Atom::gnd(stdlib::AddRuleOp{});
+    tref.register_token(regex(r"add-rule"), move |_| { add_rule_op.clone() });
+    let saturate_op = Atom::gnd(stdlib::SaturateOp{});
+    tref.register_token(regex(r"saturate"), move |_| { saturate_op.clone() });
+    let prove_op = Atom::gnd(stdlib::ProveOp{});
+    tref.register_token(regex(r"prove"), move |_| { prove_op.clone() });
     let add_atom_op = Atom::gnd(stdlib::AddAtomOp{});
     tref.register_token(regex(r"add-atom"), move |_| { add_atom_op.clone() });
     let remove_atom_op = Atom::gnd(stdlib::RemoveAtomOp{});
@@ -457,8 +552,12 @@ pub fn register_common_tokens(tref: &mut Tokenizer, _tokenizer: Shared<Tokenizer
     tref.register_token(regex(r"get-state"), move |_| { get_state_op.clone() });
     let nop_op = Atom::gnd(stdlib::NopOp{});
     tref.register_token(regex(r"nop"), move |_| { nop_op.clone() });
+    let quasi_quote_op = Atom::gnd(stdlib::QuasiQuoteOp{});
+    tref.register_token(regex(r"quasiquote"), move |_| { quasi_quote_op.clone() });
     let match_op = Atom::gnd(stdlib::MatchOp{});
     tref.register_token(regex(r"match"), move |_| { match_op.clone() });
+    let match_limit_op = Atom::gnd(stdlib::MatchLimitOp{});
+    tref.register_token(regex(r"match-limit"), move |_| { match_limit_op.clone() });
     let register_module_op = Atom::gnd(stdlib::RegisterModuleOp::new(metta.clone()));
     tref.register_token(regex(r"register-module!"), move |_| { register_module_op.clone() });
     let mod_space_op = Atom::gnd(stdlib::ModSpaceOp::new(metta.clone()));
@@ -477,6 +576,8 @@ pub fn register_runner_tokens(tref: &mut Tokenizer, tokenizer: Shared<Tokenizer>
     tref.register_token(regex(r"assertEqualToResult"), move |_| { assert_equal_to_result_op.clone() });
     let superpose_op = Atom::gnd(SuperposeOp::new(space.clone()));
     tref.register_token(regex(r"superpose"), move |_| { superpose_op.clone() });
+    let par_superpose_op = Atom::gnd(stdlib::ParSuperposeOp::new(space.clone(), tokenizer.clone(), metta.clone()));
+    tref.register_token(regex(r"par-superpose"), move |_| { par_superpose_op.clone() });
     let collapse_op = Atom::gnd(CollapseOp::new(space.clone()));
     tref.register_token(regex(r"collapse"), move |_| { collapse_op.clone() });
     let case_op = Atom::gnd(CaseOp::new(space.clone()));
@@ -524,7 +625,7 @@ pub fn register_rust_stdlib_tokens(target: &mut Tokenizer) {
         |token| { Ok(Atom::gnd(Number::from_float_str(token)?)) });
     tref.register_token(regex(r"True|False"),
         |token| { Atom::gnd(Bool::from_str(token)) });
-    tref.register_token(regex(r#""[^"]+""#),
+    tref.register_token(regex(r#"(?s)".*""#),
         |token| { let mut s = String::from(token); s.remove(0); s.pop(); Atom::gnd(Str::from_string(s)) });
     let sum_op = Atom::gnd(SumOp{});
     tref.register_token(regex(r"\+"), move |_| { sum_op.clone() });
@@ -612,6 +713,19 @@ mod tests {
         assert_eq!(result, Ok(vec![vec![expr!("Error" ("car-atom" "A") {Str::from_str("car-atom expects a non-empty expression as an argument")})]]));
     }
 
+    #[test]
+    fn metta_quasi_quote() {
+        let result = run_program("
+            (= (double $x) (* 2 $x))
+            !`(foo ,(double 3) ,@(cdr-atom (a b c)) d)
+            !(let $x 5 `(bar ,$x (baz ,(+ $x 1))))
+        ");
+        assert_eq!(result, Ok(vec![
+            vec![expr!("foo" {Number::Integer(6)} "b" "c" "d")],
+            vec![expr!("bar" {Number::Integer(5)} ("baz" {Number::Integer(6)}))],
+        ]));
+    }
+
     #[test]
     fn metta_cdr_atom() {
         assert_eq!(run_program(&format!("!(cdr-atom (a b c))")), Ok(vec![vec![expr!("b" "c")]]));
@@ -656,7 +770,7 @@ mod tests {
     #[test]
     fn metta_type_cast() {
         assert_eq!(run_program("(: a A) !(eval (type-cast a A &self))"), Ok(vec![vec![expr!("a")]]));
-        assert_eq!(run_program("(: a A) !(eval (type-cast a B &self))"), Ok(vec![vec![expr!("Error" "a" "BadType")]]));
+        assert_eq!(run_program("(: a A) !(eval (type-cast a B &self))"), Ok(vec![vec![expr!("Error" "a" "BadType" {Str::from_str("a should have type B, but a has type A")})]]));
         assert_eq!(run_program("(: a A) !(eval (type-cast a %Undefined% &self))"), Ok(vec![vec![expr!("a")]]));
         assert_eq!(run_program("!(eval (type-cast a B &self))"), Ok(vec![vec![expr!("a")]]));
         assert_eq!(run_program("!(eval (type-cast 42 Number &self))"), Ok(vec![vec![expr!({Number::Integer(42)})]]));
@@ -707,7 +821,7 @@ mod tests {
     #[test]
     fn metta_interpret_symbol_or_grounded_value_as_type() {
         assert_eq!(run_program("(: a A) !(eval (interpret a A &self))"), Ok(vec![vec![expr!("a")]]));
-        assert_eq!(run_program("(: a A) !(eval (interpret a B &self))"), Ok(vec![vec![expr!("Error" "a" "BadType")]]));
+        assert_eq!(run_program("(: a A) !(eval (interpret a B &self))"), Ok(vec![vec![expr!("Error" "a" "BadType" {Str::from_str("a should have type B, but a has type A")})]]));
         assert_eq!(run_program("!(eval (interpret 42 Number &self))"), Ok(vec![vec![expr!({Number::Integer(42)})]]));
     }
 
@@ -748,7 +862,7 @@ mod tests {
             (= (foo $x) $x)
             !(eval (interpret (foo b) %Undefined% &self))
         ");
-        assert_eq!(result, Ok(vec![vec![expr!("Error" "b" "BadType")]]));
+        assert_eq!(result, Ok(vec![vec![expr!("Error" "b" "BadType" {Str::from_str("argument 1 of (foo b) should have type T, but b has type B")})]]));
         let result = run_program("
             (: Nil (List $t))
             (: Z Nat)
@@ -756,12 +870,12 @@ mod tests {
             (: Cons (-> $t (List $t) (List $t)))
             !(eval (interpret (Cons S (Cons Z Nil)) %Undefined% &self))
         ");
-        assert_eq!(result, Ok(vec![vec![expr!("Error" ("Cons" "Z" "Nil") "BadType")]]));
+        assert_eq!(result, Ok(vec![vec![expr!("Error" ("Cons" "Z" "Nil") "BadType" {Str::from_str("argument 2 of (Cons S (Cons Z Nil)) should have type (List (-> Nat Nat)), but (Cons Z Nil) has type (List Nat)")})]]));
         let result = run_program("
             (: foo (-> Atom Atom Atom))
             !(eval (interpret (foo A) %Undefined% &self))
         ");
-        assert_eq!(result, Ok(vec![vec![expr!("Error" ("foo" "A") "BadType")]]));
+        assert_eq!(result, Ok(vec![vec![expr!("Error" ("foo" "A") "BadType" {Str::from_str("(foo A) has 1 arguments but type (-> Atom Atom Atom) of foo expects 2")})]]));
     }
 
     #[test]
@@ -846,6 +960,18 @@ mod tests {
             Ok(vec![vec![expr!("FOO"), expr!("BAR"), expr!("BAZ")]]));
     }
 
+    #[test]
+    fn metta_par_superpose() {
+        let program = "
+            (= (foo) FOO)
+            (= (bar) BAR)
+            (= (inc $x) (+ $x 1))
+            !(par-superpose ((foo) (bar) (inc 1) BAZ))
+        ";
+        assert_eq_metta_results!(run_program(program),
+            Ok(vec![vec![expr!("FOO"), expr!("BAR"), expr!({Number::Integer(2)}), expr!("BAZ")]]));
+    }
+
     #[test]
     fn metta_collapse() {
         let program = "
@@ -1032,14 +1158,14 @@ mod tests {
             |_| Atom::gnd(ID_NUM));
 
         assert_eq!(metta.run(SExprParser::new(program1)),
-            Ok(vec![vec![expr!("Error" "myAtom" "BadType")]]));
+            Ok(vec![vec![expr!("Error" "myAtom" "BadType" {Str::from_str("argument 1 of (id_a myAtom) should have type A, but myAtom has type myType")})]]));
 
         let program2 = "
             !(eval (interpret (id_num myAtom) %Undefined% &self))
         ";
 
         assert_eq!(metta.run(SExprParser::new(program2)),
-            Ok(vec![vec![expr!("Error" "myAtom" "BadType")]]));
+            Ok(vec![vec![expr!("Error" "myAtom" "BadType" {Str::from_str("argument 1 of (id_num myAtom) should have type Number, but myAtom has type myType")})]]));
     }
 
     #[test]
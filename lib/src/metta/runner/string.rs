@@ -14,6 +14,9 @@ impl Str {
     pub fn from_string(s: String) -> Self {
         Str(ImmutableString::Allocated(s))
     }
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
 }
 
 impl Grounded for Str {
@@ -28,10 +31,62 @@ impl Grounded for Str {
     fn match_(&self, other: &Atom) -> MatchResultIter {
         match_by_equality(self, other)
     }
+
+    fn serialize(&self, serializer: &mut dyn serial::Serializer) -> serial::Result {
+        serializer.serialize_str(self.as_str())
+    }
 }
 
 impl std::fmt::Display for Str {
+    /// Writes the string as a string literal which is parsed back into the
+    /// same string, special characters are escaped
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "\"{}\"", self.0)
+        write!(f, "\"")?;
+        for c in self.0.as_str().chars() {
+            match c {
+                '"' => write!(f, "\\\""),
+                '\\' => write!(f, "\\\\"),
+                '\n' => write!(f, "\\n"),
+                '\r' => write!(f, "\\r"),
+                '\t' => write!(f, "\\t"),
+                c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32),
+                c => write!(f, "{}", c),
+            }?;
+        }
+        write!(f, "\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metta::runner::{Metta, EnvBuilder};
+    use crate::metta::text::SExprParser;
+
+    fn parse(text: &str) -> Atom {
+        let metta = Metta::new(Some(EnvBuilder::test_env()));
+        let mut parser = SExprParser::new(text);
+        let atom = parser.parse(&*metta.tokenizer().borrow()).unwrap().expect("Single atom is expected");
+        atom
+    }
+
+    #[test]
+    fn str_display_escapes_special_chars() {
+        assert_eq!(Str::from_str("").to_string(), r#""""#);
+        assert_eq!(Str::from_str("say \"hi\"\n\ttab \\ \u{1b}[0m").to_string(),
+            r#""say \"hi\"\n\ttab \\ \u{1b}[0m""#);
+    }
+
+    #[test]
+    fn str_display_parse_round_trip() {
+        for s in ["", "plain", "\"quoted\"", "back\\slash", "lines\r\nand\ttabs", "caf\u{e9} \u{1F600}", "\u{0}\u{7f}"] {
+            let atom = Atom::gnd(Str::from_str(s));
+            assert_eq!(parse(&atom.to_string()), atom);
+        }
+    }
+
+    #[test]
+    fn raw_string_literal_is_parsed_as_str() {
+        assert_eq!(parse("\"\"\"{\"key\": \"a\\nb\"}\n\"\"\""), Atom::gnd(Str::from_str("{\"key\": \"a\\nb\"}\n")));
     }
 }
@@ -1,13 +1,22 @@
 //! MeTTa parser implementation.
 
 use crate::*;
+use crate::metta::{UNQUOTE_SYMBOL, UNQUOTE_SPLICING_SYMBOL};
 
 use core::ops::Range;
 use std::str::CharIndices;
 use std::iter::Peekable;
+use std::io::{BufRead, BufReader, Read};
 use regex::Regex;
 use std::rc::Rc;
 
+/// Default priority of the tokens registered without an explicit priority
+pub const DEFAULT_TOKEN_PRIORITY: i32 = 0;
+
+/// Converts the text of the tokens into atoms. A token is converted by the constructor of the
+/// registered regex which matches the whole token text. When a token is matched by more than
+/// one regex the one with the highest priority is used, and among the regexes with the same
+/// priority the most recently registered one wins.
 #[derive(Clone, Debug)]
 pub struct Tokenizer {
     tokens: Vec<TokenDescr>,
@@ -16,17 +25,43 @@ pub struct Tokenizer {
 #[derive(Clone)]
 struct TokenDescr {
     regex: Regex,
+    /// `regex` anchored at both ends to match the whole token
+    full_match: Regex,
+    priority: i32,
     constr: Rc<AtomConstr>,
 }
 
+impl TokenDescr {
+    fn new(regex: Regex, priority: i32, constr: Rc<AtomConstr>) -> Self {
+        let full_match = Regex::new(&format!("^(?:{})$", regex.as_str()))
+            .expect("Anchored regex is expected to be valid");
+        Self{ regex, full_match, priority, constr }
+    }
+}
+
 impl std::fmt::Debug for TokenDescr {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "TokenDescr{{ regex: {:?}, constr: {:?} }}", self.regex, Rc::as_ptr(&self.constr))
+        write!(f, "TokenDescr{{ regex: {:?}, priority: {}, constr: {:?} }}", self.regex, self.priority, Rc::as_ptr(&self.constr))
     }
 }
 
 type AtomConstr = dyn Fn(&str) -> Result<Atom, String>;
 
+/// Two token registrations which match the same token text, the constructor of `used` regex
+/// is called for the `token` while `shadowed` regex is never used for it
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenConflict {
+    pub token: String,
+    pub used: String,
+    pub shadowed: String,
+}
+
+impl std::fmt::Display for TokenConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "token {} is matched by regex {} which shadows regex {}", self.token, self.used, self.shadowed)
+    }
+}
+
 impl Tokenizer {
 
     pub fn new() -> Self {
@@ -34,7 +69,13 @@ impl Tokenizer {
     }
 
     pub fn register_token<C: 'static + Fn(&str) -> Atom>(&mut self, regex: Regex, constr: C) {
-        self.register_token_with_func_ptr(regex, Rc::new(move |the_str| Ok(constr(the_str))))
+        self.register_token_with_priority(regex, DEFAULT_TOKEN_PRIORITY, constr)
+    }
+
+    /// Registers the token with the explicit `priority`. Tokens with higher priority are
+    /// preferred when more than one regex matches the token text.
+    pub fn register_token_with_priority<C: 'static + Fn(&str) -> Atom>(&mut self, regex: Regex, priority: i32, constr: C) {
+        self.tokens.push(TokenDescr::new(regex, priority, Rc::new(move |the_str| Ok(constr(the_str)))))
     }
 
     pub fn register_fallible_token<C: 'static + Fn(&str) -> Result<Atom, String>>(&mut self, regex: Regex, constr: C) {
@@ -48,8 +89,8 @@ impl Tokenizer {
 
     /// Moves all tokenizer entries from `from` into `self`, leaving `from` empty
     ///
-    /// NOTE: Tokens are tried in reverse order, so `move_front` actually adds entries that will be tried
-    /// **last** in the priority order
+    /// NOTE: Tokens with the same priority are tried in reverse order, so `move_front` actually
+    /// adds entries that will be tried **last** in the priority order
     pub fn move_front(&mut self, from: &mut Tokenizer) {
         from.move_back(self);
         self.move_back(from);
@@ -57,24 +98,74 @@ impl Tokenizer {
 
     /// Moves all tokenizer entries from `from` into `self`, leaving `from` empty
     ///
-    /// NOTE: Tokens are tried in reverse order, so `move_back` actually adds entries that will be tried
-    /// **first** in the priority order
+    /// NOTE: Tokens with the same priority are tried in reverse order, so `move_back` actually
+    /// adds entries that will be tried **first** in the priority order
     pub fn move_back(&mut self, from: &mut Tokenizer) {
         self.tokens.append(&mut from.tokens);
     }
 
     pub fn find_token(&self, token: &str) -> Option<&AtomConstr> {
-        self.tokens.iter().rfind(|descr| {
-            match descr.regex.find_at(token, 0) {
-                Some(m) => m.start() == 0 && m.end() == token.len(),
-                None => false,
+        self.find_descr(token).map(|descr| &*(descr.constr))
+    }
+
+    /// Returns the registration which is used for the `token` text
+    fn find_descr(&self, token: &str) -> Option<&TokenDescr> {
+        self.tokens.iter().rev()
+            .filter(|descr| descr.full_match.is_match(token))
+            .min_by_key(|descr| std::cmp::Reverse(descr.priority))
+    }
+
+    /// Returns the regex which is used to convert the `token` text into an atom
+    pub fn find_token_regex(&self, token: &str) -> Option<&str> {
+        self.find_descr(token).map(|descr| descr.regex.as_str())
+    }
+
+    /// Lists the registrations of different regexes which shadow each other. Only the literal
+    /// regexes (see [Tokenizer::literal_tokens]) are checked against other regexes, because the
+    /// texts matched by two arbitrary regexes cannot be enumerated. Registering the same regex
+    /// again is a redefinition of the token and it is not reported.
+    pub fn conflicts(&self) -> Vec<TokenConflict> {
+        let mut conflicts: Vec<TokenConflict> = Vec::new();
+        for descr in self.tokens.iter() {
+            let Some(token) = regex_literal(descr.regex.as_str()) else { continue };
+            let used = self.find_descr(&token).expect("Literal regex matches its own text");
+            for other in self.tokens.iter() {
+                // the same regex registered again is an explicit redefinition of the token
+                let same_regex = other.regex.as_str() == used.regex.as_str();
+                if same_regex || !other.full_match.is_match(&token) {
+                    continue;
+                }
+                let conflict = TokenConflict{
+                    token: token.clone(),
+                    used: used.regex.as_str().into(),
+                    shadowed: other.regex.as_str().into(),
+                };
+                if !conflicts.contains(&conflict) {
+                    conflicts.push(conflict);
+                }
             }
-        }).map(|descr| &*(descr.constr))
+        }
+        conflicts
+    }
+
+    /// Returns the tokens which are recognized by regexes without special characters, for
+    /// instance names of the grounded operations. Each token is returned once, in the
+    /// order of registration
+    pub fn literal_tokens(&self) -> Vec<String> {
+        let mut tokens: Vec<String> = Vec::new();
+        for descr in self.tokens.iter() {
+            if let Some(token) = regex_literal(descr.regex.as_str()) {
+                if !tokens.contains(&token) {
+                    tokens.push(token);
+                }
+            }
+        }
+        tokens
     }
 
     /// Registers the regex-function pair, for a function that's already wrapped in an RC pointer
     pub(crate) fn register_token_with_func_ptr(&mut self, regex: Regex, constr: Rc<AtomConstr>) {
-        self.tokens.push(TokenDescr{ regex, constr: constr })
+        self.tokens.push(TokenDescr::new(regex, DEFAULT_TOKEN_PRIORITY, constr))
     }
 
     /// Returns the constructor function associated with an exact regex string, or None if the Tokenizer
@@ -87,15 +178,38 @@ impl Tokenizer {
 
 }
 
+/// Returns the text matched by `regex` if it is a literal regex, i.e. all its special characters
+/// are escaped
+fn regex_literal(regex: &str) -> Option<String> {
+    let mut literal = String::new();
+    let mut chars = regex.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) if !c.is_alphanumeric() => literal.push(c),
+                _ => return None,
+            },
+            '.' | '[' | ']' | '(' | ')' | '{' | '}' | '*' | '+' | '?' | '|' | '^' | '$' => return None,
+            c => literal.push(c),
+        }
+    }
+    (!literal.is_empty()).then_some(literal)
+}
+
 /// The meaning of a parsed syntactic element, generated from a substring in the input text
 #[derive(Clone, Copy, Debug)]
 pub enum SyntaxNodeType {
     /// Comment line.  All text between a non-escaped ';' and a newline
     Comment,
+    /// Block comment.  All text between `#|` and a matching `|#`, block comments can be nested
+    BlockComment,
     /// Variable.  A symbol immediately preceded by a '$' sigil
     VariableToken,
     /// String Literal.  All text between non-escaped '"' (double quote) characters
     StringToken,
+    /// Raw String Literal.  All text between `"""` delimiters, it can span multiple lines
+    ///   and escape sequences are not processed inside it
+    RawStringToken,
     /// Word Token.  Any other whitespace-delimited token that isn't a [Variable](SyntaxNodeType::VariableToken),
     ///   or [StringToken](SyntaxNodeType::StringToken)
     WordToken,
@@ -103,6 +217,9 @@ pub enum SyntaxNodeType {
     OpenParen,
     /// Close Parenthesis.  A non-escaped ')' character indicating the end of an expression
     CloseParen,
+    /// Quasi-quotation Prefix.  A '`' (backquote), ',' (unquote) or ',@' (unquote-splicing)
+    ///   char sequence immediately preceding an atom
+    QuotePrefix,
     /// Whitespace. One or more whitespace chars
     Whitespace,
     /// Text that remains unparsed after a parse error has occurred
@@ -110,6 +227,11 @@ pub enum SyntaxNodeType {
     /// A Group of [SyntaxNode]s between an [OpenParen](SyntaxNodeType::OpenParen) and a matching
     ///   [CloseParen](SyntaxNodeType::CloseParen)
     ExpressionGroup,
+    /// A '`' [QuotePrefix](SyntaxNodeType::QuotePrefix) and the quasi-quoted atom which follows it
+    QuasiQuoteGroup,
+    /// A ',' or ',@' [QuotePrefix](SyntaxNodeType::QuotePrefix) and the atom inside of a quasi-quoted
+    ///   atom which is evaluated
+    UnquoteGroup,
     /// Syntax Nodes that cannot be combined into a coherent atom due to a parse error, even if some
     /// of the individual nodes could represent valid atoms
     ErrorGroup,
@@ -121,6 +243,8 @@ impl SyntaxNodeType {
     pub fn is_leaf(&self) -> bool {
         match self {
             Self::ExpressionGroup |
+            Self::QuasiQuoteGroup |
+            Self::UnquoteGroup |
             Self::ErrorGroup => false,
             _ => true
         }
@@ -171,6 +295,15 @@ impl SyntaxNode {
         node
     }
 
+    /// Returns the innermost incomplete node which is the cause of the parse error, or `None`
+    /// if the node is complete
+    pub fn error_node(&self) -> Option<&SyntaxNode> {
+        if self.is_complete {
+            return None;
+        }
+        Some(self.sub_nodes.iter().find_map(|node| node.error_node()).unwrap_or(self))
+    }
+
     /// Transforms a root SyntaxNode into an [Atom]
     pub fn as_atom(&self, tokenizer: &Tokenizer) -> Result<Option<Atom>, String> {
 
@@ -181,15 +314,18 @@ impl SyntaxNode {
 
         match self.node_type {
             SyntaxNodeType::Comment |
+            SyntaxNodeType::BlockComment |
             SyntaxNodeType::Whitespace => Ok(None),
             SyntaxNodeType::OpenParen |
-            SyntaxNodeType::CloseParen => Ok(None),
+            SyntaxNodeType::CloseParen |
+            SyntaxNodeType::QuotePrefix => Ok(None),
             SyntaxNodeType::VariableToken => {
                 let token_text = self.parsed_text.as_ref().unwrap();
                 let new_var_atom = Atom::var(token_text);
                 Ok(Some(new_var_atom))
             },
             SyntaxNodeType::StringToken |
+            SyntaxNodeType::RawStringToken |
             SyntaxNodeType::WordToken => {
                 let token_text = self.parsed_text.as_ref().unwrap();
                 let constr = tokenizer.find_token(token_text);
@@ -221,11 +357,56 @@ impl SyntaxNode {
                     Err(err) => Err(err)
                 }
             },
+            SyntaxNodeType::QuasiQuoteGroup => {
+                let mut values = Vec::new();
+                let template = self.sub_nodes[1].quasi_quote_template(tokenizer, &mut values)?
+                    .expect("Quasi-quoted atom is expected");
+                let mut code = Atom::expr([token_atom(tokenizer, "quasiquote"), template]);
+                for (var, value) in values.into_iter().rev() {
+                    code = Atom::expr([token_atom(tokenizer, "let"), var, value, code]);
+                }
+                Ok(Some(code))
+            },
+            SyntaxNodeType::UnquoteGroup => Err("Unquote outside of quasi-quotation".to_string()),
             SyntaxNodeType::LeftoverText |
             SyntaxNodeType::ErrorGroup => {unreachable!()}
         }
     }
 
+    /// Returns the template of the quasi-quoted atom: each unquoted atom is replaced by
+    /// `(unquote $v)` or `(unquote-splicing $v)`, where `$v` is a fresh variable, and the pair
+    /// of `$v` and the unquoted atom is added to `values`. Unquoted variables are kept as is.
+    fn quasi_quote_template(&self, tokenizer: &Tokenizer, values: &mut Vec<(Atom, Atom)>) -> Result<Option<Atom>, String> {
+        match self.node_type {
+            SyntaxNodeType::UnquoteGroup => {
+                let unquote = match self.sub_nodes[0].parsed_text.as_deref() {
+                    Some(",@") => UNQUOTE_SPLICING_SYMBOL,
+                    _ => UNQUOTE_SYMBOL,
+                };
+                let atom = self.sub_nodes[1].as_atom(tokenizer)?.expect("Unquoted atom is expected");
+                let value = match atom {
+                    Atom::Variable(_) => atom,
+                    _ => {
+                        let var = Atom::Variable(VariableAtom::new("qq").make_unique());
+                        values.push((var.clone(), atom));
+                        var
+                    },
+                };
+                Ok(Some(Atom::expr([unquote, value])))
+            },
+            SyntaxNodeType::ExpressionGroup => {
+                let mut children = Vec::new();
+                for node in self.sub_nodes.iter() {
+                    if let Some(atom) = node.quasi_quote_template(tokenizer, values)? {
+                        children.push(atom);
+                    }
+                }
+                Ok(Some(Atom::expr(children)))
+            },
+            _ => self.as_atom(tokenizer),
+        }
+    }
+
     /// Visits all the nodes in a parsed syntax tree in a depth-first order
     pub fn visit_depth_first<C>(&self, mut callback: C)
         where C: FnMut(&SyntaxNode)
@@ -243,6 +424,66 @@ impl SyntaxNode {
     }
 }
 
+/// Returns the atom constructed by `tokenizer` for the `token` text, or a symbol if the token
+/// is not registered
+fn token_atom(tokenizer: &Tokenizer, token: &str) -> Atom {
+    tokenizer.find_token(token)
+        .and_then(|constr| constr(token).ok())
+        .unwrap_or_else(|| Atom::sym(token))
+}
+
+/// A human-readable location within source text.  Both `line` and `column` start from 1, and
+/// `column` is counted in chars rather than bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextPosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl TextPosition {
+    /// Returns the position of the char at the byte `offset` within `text`.  An offset past the end
+    /// of `text` is clamped to the position just after the last char
+    pub fn from_offset(text: &str, offset: usize) -> Self {
+        let mut line = 1;
+        let mut column = 1;
+        for (idx, c) in text.char_indices() {
+            if idx >= offset {
+                break;
+            }
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        Self{ line, column }
+    }
+}
+
+impl std::fmt::Display for TextPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Syntax error found by [SExprParser::syntax_errors]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxError {
+    /// Byte range of the erroneous text
+    pub src_range: Range<usize>,
+    /// Position of the start of the erroneous text
+    pub position: TextPosition,
+    /// Human-readable description of the error
+    pub message: String,
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
 /// Implemented on a type that yields atoms to be interpreted as MeTTa code.  Typically
 /// by parsing source text
 pub trait Parser {
@@ -261,19 +502,48 @@ impl Parser for &mut (dyn Parser + '_) {
     }
 }
 
+/// Iterator over the chars of the text and their offsets which can be started
+/// from any char boundary of the text
+#[derive(Clone)]
+struct TextIter<'a> {
+    offset: usize,
+    it: CharIndices<'a>,
+}
+
+impl<'a> TextIter<'a> {
+    fn new(text: &'a str, offset: usize) -> Self {
+        Self{ offset, it: text[offset..].char_indices() }
+    }
+}
+
+impl Iterator for TextIter<'_> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.it.next().map(|(idx, c)| (self.offset + idx, c))
+    }
+}
+
 /// Provides a parser for MeTTa code written in S-Expression Syntax
 ///
+/// The backquote char is reserved for the quasi-quotation `` `<atom> ``, it cannot be a part
+/// of a word or a variable name, thus `` !`(foo) `` is parsed as `!` followed by the
+/// quasi-quotation. Inside of a quasi-quoted atom the `,<atom>` and `,@<atom>` tokens unquote
+/// `<atom>`, in other places `,` is an ordinary char, for example `,x` is a symbol.
+///
 /// NOTE: The SExprParser type is short-lived, and can be created cheaply to evaluate a specific block
 /// of MeTTa source code.
 #[derive(Clone)]
 pub struct SExprParser<'a> {
     text: &'a str,
-    it: Peekable<CharIndices<'a>>,
+    it: Peekable<TextIter<'a>>,
+    /// Number of quasi-quotations around the atom being parsed minus number of unquotes
+    quasi_quote_depth: usize,
 }
 
 impl<'a> SExprParser<'a> {
     pub fn new(text: &'a str) -> Self {
-        Self{ text, it: text.char_indices().peekable() }
+        Self{ text, it: TextIter::new(text, 0).peekable(), quasi_quote_depth: 0 }
     }
 
     pub fn parse(&mut self, tokenizer: &Tokenizer) -> Result<Option<Atom>, String> {
@@ -298,6 +568,10 @@ impl<'a> SExprParser<'a> {
                     let comment_node = self.parse_comment().unwrap();
                     return Some(comment_node);
                 },
+                '#' if self.next_is("#|") => {
+                    let comment_node = self.parse_block_comment();
+                    return Some(comment_node);
+                },
                 _ if c.is_whitespace() => {
                     let whispace_node = SyntaxNode::new(SyntaxNodeType::Whitespace, idx..idx+1, vec![]);
                     self.it.next();
@@ -305,22 +579,31 @@ impl<'a> SExprParser<'a> {
                 },
                 '$' => {
                     let var_node = self.parse_variable();
-                    return Some(var_node);
+                    return Some(self.recover(var_node));
                 },
                 '(' => {
                     let expr_node = self.parse_expr();
                     return Some(expr_node);
                 },
                 ')' => {
-                    let close_paren_node = SyntaxNode::new(SyntaxNodeType::CloseParen, idx..idx+1, vec![]);
+                    let close_paren_node = SyntaxNode::incomplete_with_message(SyntaxNodeType::CloseParen, idx..idx+1, vec![], "Unexpected right bracket".to_string());
                     self.it.next();
-                    let leftover_text_node = self.parse_leftovers("Unexpected right bracket".to_string());
-                    let error_group_node = SyntaxNode::new_error_group(idx..self.cur_idx(), vec![close_paren_node, leftover_text_node]);
-                    return Some(error_group_node);
+                    return Some(self.recover(close_paren_node));
+                },
+                '`' => {
+                    let quasi_quote_node = self.parse_quasi_quote("`");
+                    return Some(quasi_quote_node);
+                },
+                // `,` is an unquote only inside of a quasi-quotation and when it is followed
+                // by an atom, otherwise it is a part of the word, for example a conjunction
+                ',' if self.quasi_quote_depth > 0 && self.is_atom_at(idx + if self.text[idx..].starts_with(",@") { 2 } else { 1 }) => {
+                    let prefix = if self.next_is(",@") { ",@" } else { "," };
+                    let unquote_node = self.parse_quasi_quote(prefix);
+                    return Some(unquote_node);
                 },
                 _ => {
                     let token_node = self.parse_token();
-                    return token_node;
+                    return token_node.map(|node| self.recover(node));
                 },
             }
         }
@@ -336,6 +619,19 @@ impl<'a> SExprParser<'a> {
         }
     }
 
+    /// Returns `true` if the remaining text starts with `prefix`
+    fn next_is(&mut self, prefix: &str) -> bool {
+        let idx = self.cur_idx();
+        self.text[idx..].starts_with(prefix)
+    }
+
+    /// Skips `count` chars of the input
+    fn skip_chars(&mut self, count: usize) {
+        for _ in 0..count {
+            self.it.next();
+        }
+    }
+
     /// Parse to the next `\n` newline
     fn parse_comment(&mut self) -> Option<SyntaxNode> {
         if let Some((start_idx, _c)) = self.it.peek().cloned() {
@@ -352,11 +648,92 @@ impl<'a> SExprParser<'a> {
         }
     }
 
-    fn parse_leftovers(&mut self, message: String) -> SyntaxNode {
+    /// Parse a `#| ... |#` block comment, including nested block comments
+    fn parse_block_comment(&mut self) -> SyntaxNode {
+        let start_idx = self.cur_idx();
+        self.skip_chars(2);
+        let mut depth = 1;
+        while self.it.peek().is_some() {
+            if self.next_is("|#") {
+                self.skip_chars(2);
+                depth -= 1;
+                if depth == 0 {
+                    return SyntaxNode::new(SyntaxNodeType::BlockComment, start_idx..self.cur_idx(), vec![]);
+                }
+            } else if self.next_is("#|") {
+                self.skip_chars(2);
+                depth += 1;
+            } else {
+                self.it.next();
+            }
+        }
+        SyntaxNode::incomplete_with_message(SyntaxNodeType::BlockComment, start_idx..self.cur_idx(), vec![], "Unclosed block comment".to_string())
+    }
+
+    /// Returns the offset of the first line at or after `from` which starts with `(` or `!`,
+    /// i.e. the probable beginning of the next top level atom, or the end of the text.
+    /// Parsing is resumed from this offset after a syntax error.
+    fn next_sync_offset(&self, from: usize) -> usize {
+        let is_sync = |idx: usize| self.text[idx..].starts_with(['(', '!']);
+        if (from == 0 || self.text[..from].ends_with('\n')) && is_sync(from) {
+            return from;
+        }
+        self.text[from..].match_indices('\n')
+            .map(|(idx, _)| from + idx + 1)
+            .find(|idx| is_sync(*idx))
+            .unwrap_or(self.text.len())
+    }
+
+    fn skip_to(&mut self, offset: usize) {
+        while self.cur_idx() < offset {
+            self.it.next();
+        }
+    }
+
+    /// Skips the text after the incomplete `node` up to the next synchronization point, see
+    /// [Self::next_sync_offset]. Returns the error group which contains `node` and the skipped
+    /// text, complete nodes are returned as is.
+    fn recover(&mut self, node: SyntaxNode) -> SyntaxNode {
+        if node.is_complete {
+            return node;
+        }
         let start_idx = self.cur_idx();
-        while let Some(_) = self.it.next() {}
-        let range = start_idx..self.cur_idx();
-        SyntaxNode::incomplete_with_message(SyntaxNodeType::LeftoverText, range, vec![], message)
+        let end_idx = self.next_sync_offset(start_idx);
+        if end_idx == start_idx {
+            return node;
+        }
+        self.skip_to(end_idx);
+        let message = node.message.clone().unwrap();
+        let skipped_node = SyntaxNode::new(SyntaxNodeType::LeftoverText, start_idx..end_idx, vec![]);
+        SyntaxNode::incomplete_with_message(SyntaxNodeType::ErrorGroup, node.src_range.start..end_idx, vec![node, skipped_node], message)
+    }
+
+    /// Parses the rest of the text and returns all syntax errors found. Parsing is resumed
+    /// from the next line which starts with `(` or `!` after each error. Errors returned by
+    /// `tokenizer` while constructing atoms are reported as well.
+    pub fn syntax_errors(&mut self, tokenizer: &Tokenizer) -> Vec<SyntaxError> {
+        let mut errors = Vec::new();
+        while let Some(node) = self.parse_to_syntax_tree() {
+            let error = match node.error_node() {
+                Some(error_node) => Some((error_node.src_range.clone(), error_node.message.clone().unwrap())),
+                None => {
+                    let mut error = None;
+                    node.visit_depth_first(|leaf| {
+                        if error.is_none() && leaf.node_type.is_leaf() {
+                            if let Err(message) = leaf.as_atom(tokenizer) {
+                                error = Some((leaf.src_range.clone(), message));
+                            }
+                        }
+                    });
+                    error
+                },
+            };
+            if let Some((src_range, message)) = error {
+                let position = TextPosition::from_offset(self.text, src_range.start);
+                errors.push(SyntaxError{ src_range, position, message });
+            }
+        }
+        errors
     }
 
     fn parse_expr(&mut self) -> SyntaxNode {
@@ -403,12 +780,64 @@ impl<'a> SExprParser<'a> {
                 },
             }
         }
-        let leftover_node = SyntaxNode::incomplete_with_message(SyntaxNodeType::ErrorGroup, start_idx..self.cur_idx(), child_nodes, "Unexpected end of expression".to_string());
-        leftover_node
+        // The expression is not closed, the error is reported at the open bracket and
+        // parsing is resumed from the next top level atom after it
+        let message = "Unexpected end of expression".to_string();
+        child_nodes[0] = SyntaxNode::incomplete_with_message(SyntaxNodeType::OpenParen, start_idx..start_idx+1, vec![], message.clone());
+        let sync_idx = self.next_sync_offset(start_idx + 1);
+        if sync_idx < self.text.len() {
+            child_nodes.retain(|node| node.src_range.end <= sync_idx);
+            let last_idx = child_nodes.last().map_or(start_idx + 1, |node| node.src_range.end);
+            if last_idx < sync_idx {
+                child_nodes.push(SyntaxNode::new(SyntaxNodeType::LeftoverText, last_idx..sync_idx, vec![]));
+            }
+            self.it = TextIter::new(self.text, sync_idx).peekable();
+        }
+        SyntaxNode::incomplete_with_message(SyntaxNodeType::ErrorGroup, start_idx..self.cur_idx(), child_nodes, message)
+    }
+
+    /// Returns `true` if an atom starts at `offset`
+    fn is_atom_at(&self, offset: usize) -> bool {
+        let rest = &self.text[offset..];
+        match rest.chars().next() {
+            Some(c) => !c.is_whitespace() && !matches!(c, ')' | ';') && !rest.starts_with("#|"),
+            None => false,
+        }
+    }
+
+    /// Parses the quasi-quotation `prefix` and the atom which follows it
+    fn parse_quasi_quote(&mut self, prefix: &str) -> SyntaxNode {
+        let start_idx = self.cur_idx();
+        self.skip_chars(prefix.chars().count());
+        let prefix_range = start_idx..self.cur_idx();
+        let is_unquote = prefix != "`";
+        let group_type = if is_unquote { SyntaxNodeType::UnquoteGroup } else { SyntaxNodeType::QuasiQuoteGroup };
+        let atom_idx = self.cur_idx();
+        if !self.is_atom_at(atom_idx) {
+            let message = format!("Atom is expected after {prefix}");
+            let prefix_node = SyntaxNode::incomplete_with_message(SyntaxNodeType::QuotePrefix, prefix_range, vec![], message);
+            return self.recover(prefix_node);
+        }
+        let prefix_node = SyntaxNode::new_token_node(SyntaxNodeType::QuotePrefix, prefix_range, prefix.to_string());
+
+        let depth = self.quasi_quote_depth;
+        self.quasi_quote_depth = if is_unquote { depth - 1 } else { depth + 1 };
+        let quoted_node = self.parse_to_syntax_tree().expect("Atom is expected");
+        self.quasi_quote_depth = depth;
+
+        if quoted_node.is_complete {
+            SyntaxNode::new(group_type, start_idx..self.cur_idx(), vec![prefix_node, quoted_node])
+        } else {
+            SyntaxNode::new_error_group(start_idx..self.cur_idx(), vec![prefix_node, quoted_node])
+        }
     }
 
     fn parse_token(&mut self) -> Option<SyntaxNode> {
         match self.it.peek().cloned() {
+            Some((_idx, '"')) if self.next_is("\"\"\"") => {
+                let string_node = self.parse_raw_string();
+                Some(string_node)
+            },
             Some((_idx, '"')) => {
                 let string_node = self.parse_string();
                 Some(string_node)
@@ -453,6 +882,12 @@ impl<'a> SExprParser<'a> {
                                     None => {return escape_err(self.cur_idx()); }
                                 }
                             },
+                            'u' => { // unicode code point
+                                match self.parse_unicode_value() {
+                                    Some(code_val) => code_val,
+                                    None => {return escape_err(self.cur_idx()); }
+                                }
+                            },
                             _ => {
                                 return escape_err(self.cur_idx());
                             }
@@ -472,6 +907,23 @@ impl<'a> SExprParser<'a> {
         unclosed_string_node
     }
 
+    /// Parses a `"""` delimited raw string, the text between delimiters is taken as is
+    fn parse_raw_string(&mut self) -> SyntaxNode {
+        let start_idx = self.cur_idx();
+        self.skip_chars(3);
+        let mut token = String::from('"');
+        while let Some((_idx, c)) = self.it.peek().cloned() {
+            if self.next_is("\"\"\"") {
+                self.skip_chars(3);
+                token.push('"');
+                return SyntaxNode::new_token_node(SyntaxNodeType::RawStringToken, start_idx..self.cur_idx(), token);
+            }
+            token.push(c);
+            self.it.next();
+        }
+        SyntaxNode::incomplete_with_message(SyntaxNodeType::RawStringToken, start_idx..self.cur_idx(), vec![], "Unclosed Raw String Literal".to_string())
+    }
+
     /// Parses a 2-digit value from the parser at the current location
     fn parse_2_digit_radix_value(&mut self, radix: u32) -> Option<u8> {
         self.it.next()
@@ -486,12 +938,29 @@ impl<'a> SExprParser<'a> {
         }).and_then(|code_val| (code_val <= 0x7F).then(|| code_val))
     }
 
+    /// Parses a `{XXXXXX}` unicode code point value of 1 to 6 hex digits
+    /// from the parser at the current location
+    fn parse_unicode_value(&mut self) -> Option<char> {
+        if self.it.next()?.1 != '{' {
+            return None;
+        }
+        let mut digits = String::new();
+        loop {
+            match self.it.next()?.1 {
+                '}' => break,
+                c if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+                _ => return None,
+            }
+        }
+        u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+    }
+
     fn parse_word(&mut self) -> SyntaxNode {
         let mut token = String::new();
         let start_idx = self.cur_idx();
 
         while let Some((_idx, c)) = self.it.peek() {
-            if c.is_whitespace() || *c == '(' || *c == ')' {
+            if c.is_whitespace() || matches!(c, '(' | ')' | '`') {
                 break;
             }
             token.push(*c);
@@ -509,17 +978,16 @@ impl<'a> SExprParser<'a> {
 
         let mut token = String::new();
         while let Some((_idx, c)) = tmp_it.peek() {
-            if c.is_whitespace() || *c == '(' || *c == ')' {
+            if c.is_whitespace() || matches!(c, '(' | ')' | '`') {
                 break;
             }
-            if *c == '#' {
-                let leftover_node = self.parse_leftovers("'#' char is reserved for internal usage".to_string());
-                return leftover_node;
-            }
             token.push(*c);
             tmp_it.next();
         }
         self.it = tmp_it;
+        if token.contains('#') {
+            return SyntaxNode::incomplete_with_message(SyntaxNodeType::VariableToken, start_idx..self.cur_idx(), vec![], "'#' char is reserved for internal usage".to_string());
+        }
         let var_token_node = SyntaxNode::new_token_node(SyntaxNodeType::VariableToken, start_idx..self.cur_idx(), token);
         var_token_node
     }
@@ -552,6 +1020,172 @@ impl Parser for OwnedSExprParser {
     }
 }
 
+/// A version of [SExprParser] which reads its input incrementally from a [BufRead] source, so
+/// a text of any size can be parsed keeping in memory only the top level atom being parsed.
+///
+/// The input is read line by line until the buffered text contains a complete top level atom
+/// or the input ends. The text of the atoms which are already parsed is dropped from the buffer.
+/// NOTE: an unclosed expression, string literal or block comment can be detected only at the
+/// end of the input, thus in this case the whole rest of the input is read before the error is
+/// returned.
+pub struct ReaderSExprParser<R: BufRead> {
+    reader: R,
+    buffer: String,
+    eof: bool,
+    /// State of the lexer at the end of the buffer, the buffer is parsed again only when all
+    /// parentheses, literals and comments are closed. `None` if the state is not known.
+    state: Option<LexerState>,
+}
+
+impl<R: BufRead> ReaderSExprParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self{ reader, buffer: String::new(), eof: false, state: None }
+    }
+
+    /// Appends the next line of the input to the buffer
+    fn read_line(&mut self) -> Result<(), String> {
+        let start = self.buffer.len();
+        let read = self.reader.read_line(&mut self.buffer)
+            .map_err(|err| format!("Could not read input: {err}"))?;
+        self.eof = read == 0;
+        self.state = self.state.and_then(|state| state.scan(&self.buffer[start..]));
+        Ok(())
+    }
+
+    /// Returns `true` if the incomplete `node` can be completed by the text which is not read yet
+    fn is_unfinished(&self, node: &SyntaxNode) -> bool {
+        match node.error_node() {
+            Some(error) => !self.eof && (error.src_range.end == self.buffer.len() ||
+                matches!(error.node_type, SyntaxNodeType::OpenParen)),
+            None => false,
+        }
+    }
+}
+
+impl<R: Read> ReaderSExprParser<BufReader<R>> {
+    /// Creates a parser which reads the input from `read` using a [BufReader]
+    pub fn from_read(read: R) -> Self {
+        Self::new(BufReader::new(read))
+    }
+}
+
+/// Token which is opened in the text scanned by [LexerState::scan] and is not closed yet
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OpenToken {
+    String,
+    RawString,
+    /// Block comment with the given nesting depth
+    BlockComment(usize),
+}
+
+/// State of the lexer which is kept between the lines read by [ReaderSExprParser]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct LexerState {
+    /// Number of the unclosed parentheses
+    unclosed: usize,
+    /// Literal or comment which is not closed
+    open: Option<OpenToken>,
+}
+
+impl LexerState {
+    /// Returns `true` if the text scanned contains no unclosed parentheses, literals or comments
+    fn is_closed(&self) -> bool {
+        self.unclosed == 0 && self.open.is_none()
+    }
+
+    /// Continues scanning from the current state through the `text` and returns the state at
+    /// the end of it. Returns `None` if the text has an extra closing parenthesis.
+    fn scan(mut self, text: &str) -> Option<Self> {
+        let mut it = text.chars();
+        loop {
+            match self.open {
+                None => match it.next() {
+                    None => return Some(self),
+                    Some('(') => self.unclosed += 1,
+                    Some(')') => self.unclosed = self.unclosed.checked_sub(1)?,
+                    Some(';') => it = it.as_str().find('\n').map_or("", |end| &it.as_str()[end..]).chars(),
+                    Some('#') if it.as_str().starts_with('|') => {
+                        it.next();
+                        self.open = Some(OpenToken::BlockComment(1));
+                    },
+                    Some('"') if it.as_str().starts_with("\"\"") => {
+                        it.nth(1);
+                        self.open = Some(OpenToken::RawString);
+                    },
+                    Some('"') => self.open = Some(OpenToken::String),
+                    Some(c) if c.is_whitespace() || c == '`' => {},
+                    Some(_) => while it.as_str().starts_with(|c: char| !c.is_whitespace() && !matches!(c, '(' | ')' | '`')) {
+                        it.next();
+                    },
+                },
+                Some(OpenToken::String) => match it.next() {
+                    None => return Some(self),
+                    Some('\\') => { it.next(); },
+                    Some('"') => self.open = None,
+                    Some(_) => {},
+                },
+                Some(OpenToken::RawString) => match it.as_str().find("\"\"\"") {
+                    None => return Some(self),
+                    Some(end) => {
+                        it = it.as_str()[end + 3..].chars();
+                        self.open = None;
+                    },
+                },
+                Some(OpenToken::BlockComment(depth)) => {
+                    let rest = it.as_str();
+                    if rest.starts_with("|#") {
+                        it.nth(1);
+                        self.open = if depth == 1 { None } else { Some(OpenToken::BlockComment(depth - 1)) };
+                    } else if rest.starts_with("#|") {
+                        it.nth(1);
+                        self.open = Some(OpenToken::BlockComment(depth + 1));
+                    } else if it.next().is_none() {
+                        return Some(self);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Parser for ReaderSExprParser<R> {
+    fn next_atom(&mut self, tokenizer: &Tokenizer) -> Result<Option<Atom>, String> {
+        loop {
+            if self.state.is_some_and(|state| !state.is_closed()) && !self.eof {
+                self.read_line()?;
+                continue;
+            }
+            let mut parser = SExprParser::new(&self.buffer);
+            let mut parsed_idx = 0;
+            let mut result = None;
+            while let Some(node) = parser.parse_to_syntax_tree() {
+                if self.is_unfinished(&node) {
+                    break;
+                }
+                parsed_idx = parser.cur_idx();
+                match node.as_atom(tokenizer) {
+                    Ok(None) => {},
+                    atom => {
+                        result = Some(atom);
+                        break;
+                    },
+                }
+            }
+            self.buffer.drain(..parsed_idx);
+            self.state = None;
+            match result {
+                Some(atom) => return atom,
+                None if self.eof => return Ok(None),
+                None => {
+                    // the buffer contains the beginning of the next atom only
+                    self.state = LexerState::default().scan(&self.buffer);
+                    self.read_line()?
+                },
+            }
+        }
+    }
+}
+
 impl Parser for &[Atom] {
     fn next_atom(&mut self, _tokenizer: &Tokenizer) -> Result<Option<Atom>, String> {
         if let Some((atom, rest)) = self.split_first() {
@@ -596,6 +1230,14 @@ mod tests {
         assert_eq!(vec![expr!("\"test\x7Fmax\"")], parse_atoms(r#""test\x7fmax""#));
         // Parse failure, code out of range
         assert!(parse_atoms(r#""test\xFF""#).len() == 0);
+        // Unicode code point
+        assert_eq!(vec![expr!("\"caf\u{e9} \u{1F600}\"")], parse_atoms(r#""caf\u{e9} \u{1F600}""#));
+        // Parse failure, not a code point
+        assert!(parse_atoms(r#""\u{D800}""#).len() == 0);
+        assert!(parse_atoms(r#""\u{1234567}""#).len() == 0);
+        assert!(parse_atoms(r#""\u1234""#).len() == 0);
+        // Empty string
+        assert_eq!(vec![expr!("\"\"")], parse_atoms(r#""""#));
     }
 
     #[test]
@@ -714,6 +1356,69 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn test_block_comment() {
+        let program = "(a #| 4
+                  (b 5) |# 6) #|(c)|#";
+        assert_eq!(parse_atoms(program), vec![expr!("a" "6")]);
+    }
+
+    #[test]
+    fn test_nested_block_comment() {
+        let program = "#| outer #| inner |# (a) |# (b)";
+        assert_eq!(parse_atoms(program), vec![expr!(("b"))]);
+        let mut parser = SExprParser::new("#| outer #| inner |# (a)");
+        assert_eq!(Err(String::from("Unclosed block comment")), parser.parse(&Tokenizer::new()));
+    }
+
+    #[test]
+    fn test_block_comment_syntax_nodes() {
+        let text = "#|a|# #b";
+        let mut parser = SExprParser::new(text);
+        let node = parser.parse_to_syntax_tree().unwrap();
+        assert!(matches!(node.node_type, SyntaxNodeType::BlockComment));
+        assert_eq!(&text[node.src_range], "#|a|#");
+        parser.parse_to_syntax_tree();
+        let node = parser.parse_to_syntax_tree().unwrap();
+        assert!(matches!(node.node_type, SyntaxNodeType::WordToken));
+        assert_eq!(node.parsed_text.unwrap(), "#b");
+    }
+
+    #[test]
+    fn test_raw_string() {
+        let program = r#"(prompt """Say "hi"
+to \n {"json": 1}""" "")"#;
+        assert_eq!(parse_atoms(program), vec![expr!("prompt" "\"Say \"hi\"\nto \\n {\"json\": 1}\"" "\"\"")]);
+        assert_eq!(parse_atoms(r#""""""""#), vec![expr!("\"\"")]);
+
+        let mut parser = SExprParser::new(r#"""" a"#);
+        let node = parser.parse_to_syntax_tree().unwrap();
+        assert!(matches!(node.node_type, SyntaxNodeType::RawStringToken));
+        assert_eq!(Some("Unclosed Raw String Literal".to_string()), node.message);
+    }
+
+    #[test]
+    fn test_quasi_quote() {
+        let atoms = parse_atoms("!`(foo ,$x ,(bar $x) ,@$y) `a");
+        assert_eq!(atoms.len(), 3);
+        assert_eq!(atoms[0], sym!("!"));
+        assert!(crate::atom::matcher::atoms_are_equivalent(&atoms[1],
+            &expr!("let" qq ("bar" x) ("quasiquote" ("foo" ("unquote" x) ("unquote" qq) ("unquote-splicing" y))))));
+        assert_eq!(atoms[2], expr!("quasiquote" "a"));
+        assert_eq!(parse_atoms("a,b"), vec![expr!("a,b")]);
+        assert_eq!(parse_atoms("(, a b) `(, ,@ c)"), vec![expr!("," "a" "b"), expr!("quasiquote" ("," ",@" "c"))]);
+        assert_eq!(parse_atoms("(a ,b) `(c,d)"), vec![expr!("a" ",b"), expr!("quasiquote" ("c,d"))]);
+    }
+
+    #[test]
+    fn test_quasi_quote_errors() {
+        let tokenizer = Tokenizer::new();
+        assert_eq!(SExprParser::new("(a `)").parse(&tokenizer), Err("Atom is expected after `".into()));
+        let errors: Vec<String> = SExprParser::new("(a `)\n(b ,c)\n`(d ` e)").syntax_errors(&tokenizer)
+            .into_iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, vec!["1:4: Atom is expected after `", "3:5: Atom is expected after `"]);
+    }
+
     fn parse_atoms(program: &str) -> Vec<Atom> {
         let tokenizer = Tokenizer::new();
         let mut parser = SExprParser::new(program);
@@ -724,6 +1429,69 @@ mod tests {
         result
     }
 
+    const TEXT_WITH_ERRORS: &str = "(a b))
+(c \"escape \\q\")
+  (d $x#y)
+(e f)
+(g (h i)
+  (j k)
+!(l m)";
+
+    #[test]
+    fn test_parser_recovers_after_errors() {
+        let tokenizer = Tokenizer::new();
+        let mut parser = SExprParser::new(TEXT_WITH_ERRORS);
+        let mut atoms = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match parser.parse(&tokenizer) {
+                Ok(Some(atom)) => atoms.push(atom),
+                Ok(None) => break,
+                Err(err) => errors.push(err),
+            }
+        }
+        assert_eq!(atoms, vec![expr!("a" "b"), expr!("e" "f"), sym!("!"), expr!("l" "m")]);
+        assert_eq!(errors, vec!["Unexpected right bracket", "Invalid escape sequence", "Unexpected end of expression"]);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let errors: Vec<String> = SExprParser::new(TEXT_WITH_ERRORS).syntax_errors(&Tokenizer::new())
+            .iter().map(|err| err.to_string()).collect();
+        assert_eq!(errors, vec![
+            "1:6: Unexpected right bracket",
+            "2:12: Invalid escape sequence",
+            "5:1: Unexpected end of expression",
+        ]);
+
+        let errors: Vec<String> = SExprParser::new("(a $x#y)\n(b)\n(c))\n").syntax_errors(&Tokenizer::new())
+            .iter().map(|err| err.to_string()).collect();
+        assert_eq!(errors, vec!["1:4: '#' char is reserved for internal usage", "3:4: Unexpected right bracket"]);
+    }
+
+    #[test]
+    fn test_parser_resumes_after_unclosed_expressions() {
+        let mut parser = SExprParser::new("(é\n(a b)\n(ё (c\n!(d)\n");
+        let mut results = Vec::new();
+        while let Some(node) = parser.parse_to_syntax_tree() {
+            if !matches!(node.node_type, SyntaxNodeType::Whitespace) {
+                results.push((node.src_range.clone(), node.is_complete));
+            }
+        }
+        assert_eq!(results, vec![(0..17, false), (17..18, true), (18..21, true)]);
+    }
+
+    #[test]
+    fn test_syntax_errors_from_tokenizer() {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.register_fallible_token(Regex::new(r"\d+").unwrap(),
+            |token| token.parse::<u8>().map(|n| Atom::value(n)).map_err(|err| err.to_string()));
+        let errors = SExprParser::new("(a 1)\n(b 1000)").syntax_errors(&tokenizer);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].position, TextPosition{ line: 2, column: 4 });
+        assert_eq!(errors[0].src_range, 9..13);
+    }
+
     #[test]
     fn test_lattice_in_var_name() {
         let mut parser = SExprParser::new("$a#");
@@ -739,6 +1507,65 @@ mod tests {
         assert_eq!(tokenizer.find_token("A").unwrap()("A").unwrap(), Atom::sym("B"));
     }
 
+    #[test]
+    fn tokenizer_literal_tokens() {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.register_token(Regex::new(r"get-type").unwrap(), |_| Atom::sym("A"));
+        tokenizer.register_token(Regex::new(r"\d+").unwrap(), |_| Atom::sym("B"));
+        tokenizer.register_token(Regex::new(r"let\*").unwrap(), |_| Atom::sym("C"));
+        tokenizer.register_token(Regex::new(r"a|b").unwrap(), |_| Atom::sym("D"));
+        tokenizer.register_token(Regex::new(r"get-type").unwrap(), |_| Atom::sym("E"));
+        assert_eq!(tokenizer.literal_tokens(), vec!["get-type", "let*"]);
+    }
+
+    #[test]
+    fn tokenizer_full_match() {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.register_token(Regex::new(r"a|ab").unwrap(), |_| Atom::sym("A"));
+        tokenizer.register_token(Regex::new(r"get-type").unwrap(), |_| Atom::sym("B"));
+        assert_eq!(tokenizer.find_token_regex("ab"), Some("a|ab"));
+        assert_eq!(tokenizer.find_token_regex("abc"), None);
+        assert_eq!(tokenizer.find_token_regex("get-type-space"), None);
+        assert_eq!(tokenizer.find_token_regex("my-get-type"), None);
+    }
+
+    #[test]
+    fn tokenizer_priority() {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.register_token_with_priority(Regex::new(r"\+").unwrap(), 10, |_| Atom::sym("plus"));
+        tokenizer.register_token(Regex::new(r"[+*]").unwrap(), |_| Atom::sym("op"));
+        tokenizer.register_token(Regex::new(r"\*").unwrap(), |_| Atom::sym("mul"));
+        assert_eq!(tokenizer.find_token("+").unwrap()("+"), Ok(Atom::sym("plus")));
+        assert_eq!(tokenizer.find_token("*").unwrap()("*"), Ok(Atom::sym("mul")));
+    }
+
+    #[test]
+    fn tokenizer_conflicts() {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.register_token(Regex::new(r"get-type").unwrap(), |_| Atom::sym("A"));
+        tokenizer.register_token(Regex::new(r"get-type-space").unwrap(), |_| Atom::sym("B"));
+        tokenizer.register_token(Regex::new(r"\d+").unwrap(), |_| Atom::sym("C"));
+        tokenizer.register_token(Regex::new(r"\+").unwrap(), |_| Atom::sym("D"));
+        tokenizer.register_token(Regex::new(r"42").unwrap(), |_| Atom::sym("E"));
+        tokenizer.register_token(Regex::new(r"[+\-]").unwrap(), |_| Atom::sym("F"));
+        tokenizer.register_token(Regex::new(r"get-type").unwrap(), |_| Atom::sym("G"));
+        assert_eq!(tokenizer.conflicts(), vec![
+            TokenConflict{ token: "+".into(), used: r"[+\-]".into(), shadowed: r"\+".into() },
+            TokenConflict{ token: "42".into(), used: "42".into(), shadowed: r"\d+".into() },
+        ]);
+        assert_eq!(tokenizer.conflicts()[0].to_string(), r"token + is matched by regex [+\-] which shadows regex \+");
+    }
+
+    #[test]
+    fn test_text_position_from_offset() {
+        let text = "(a b)\n  (c\td)";
+        assert_eq!(TextPosition::from_offset(text, 0), TextPosition{ line: 1, column: 1 });
+        assert_eq!(TextPosition::from_offset(text, 3), TextPosition{ line: 1, column: 4 });
+        assert_eq!(TextPosition::from_offset(text, 8), TextPosition{ line: 2, column: 3 });
+        assert_eq!(TextPosition::from_offset(text, 100), TextPosition{ line: 2, column: 8 });
+        assert_eq!(TextPosition::from_offset(text, 8).to_string(), "2:3");
+    }
+
     #[test]
     fn test_owned_sexprparser() {
         let tokenizer = Tokenizer::new();
@@ -751,4 +1578,77 @@ mod tests {
         assert_eq!(results, expected);
     }
 
+    #[test]
+    fn test_reader_sexprparser() {
+        let tokenizer = Tokenizer::new();
+        let text = "One (two\n  3) ; comment\n\"four\nfive\" #| block\n|# (six\n(seven))\nlast";
+        let mut parser = ReaderSExprParser::from_read(text.as_bytes());
+        let mut results: Vec<Atom> = vec![];
+        while let Some(atom) = parser.next_atom(&tokenizer).unwrap() {
+            results.push(atom);
+        }
+        let expected = vec![sym!("One"), expr!("two" "3"), sym!("\"four\nfive\""),
+            expr!("six" ("seven")), sym!("last")];
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_reader_sexprparser_errors() {
+        let tokenizer = Tokenizer::new();
+        let mut parser = ReaderSExprParser::from_read("(a) b)\n(c)\n(d".as_bytes());
+        assert_eq!(parser.next_atom(&tokenizer), Ok(Some(expr!(("a")))));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(Some(expr!("b"))));
+        assert_eq!(parser.next_atom(&tokenizer), Err("Unexpected right bracket".to_string()));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(Some(expr!(("c")))));
+        assert_eq!(parser.next_atom(&tokenizer), Err("Unexpected end of expression".to_string()));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(None));
+    }
+
+    #[test]
+    fn test_reader_sexprparser_tracks_lexer_state() {
+        let code = |unclosed| Some(LexerState{ unclosed, open: None });
+        let open = |unclosed, token| Some(LexerState{ unclosed, open: Some(token) });
+        let scan = |text, unclosed| LexerState{ unclosed, open: None }.scan(text);
+        assert_eq!(scan("(a (b \"(\" c) ; (\n", 0), code(1));
+        assert_eq!(scan("d$x( e))", 2), code(1));
+        assert_eq!(scan("x\"(y ;(", 0), code(1));
+        assert_eq!(scan("))", 1), None);
+        assert_eq!(scan("(\"open\n", 0), open(1, OpenToken::String));
+        assert_eq!(scan("(\"\"\"raw \"\" \"\n", 0), open(1, OpenToken::RawString));
+        assert_eq!(scan("( #| block #| nested |# (\n", 0), open(1, OpenToken::BlockComment(1)));
+        assert_eq!(open(1, OpenToken::String).unwrap().scan("\\\" (\"(\n"), code(2));
+        assert_eq!(open(1, OpenToken::RawString).unwrap().scan("\" (\"\"\")\n"), code(0));
+        assert_eq!(open(1, OpenToken::BlockComment(2)).unwrap().scan("|# ( |# )\n"), code(0));
+
+        let tokenizer = Tokenizer::new();
+        let text = format!("(a\n{}\"s\n)\")\n(b)", " (c)\n".repeat(100));
+        let mut parser = ReaderSExprParser::from_read(text.as_bytes());
+        let mut expected = vec![sym!("a")];
+        expected.extend(std::iter::repeat(expr!(("c"))).take(100));
+        expected.push(sym!("\"s\n)\""));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(Some(Atom::expr(expected))));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(Some(expr!(("b")))));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(None));
+
+        let text = format!("#| (\n{}|# \"\"\"raw\n{}\"\"\" (d)", "#| |# \n".repeat(100), "\" \n".repeat(100));
+        let mut parser = ReaderSExprParser::from_read(text.as_bytes());
+        let raw = format!("\"raw\n{}\"", "\" \n".repeat(100));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(Some(Atom::sym(raw))));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(Some(expr!(("d")))));
+        assert_eq!(parser.next_atom(&tokenizer), Ok(None));
+    }
+
+    #[test]
+    fn test_reader_sexprparser_keeps_only_current_atom() {
+        let tokenizer = Tokenizer::new();
+        let text = "(fact 1)\n".repeat(1000);
+        let mut parser = ReaderSExprParser::from_read(text.as_bytes());
+        let mut count = 0;
+        while let Some(_atom) = parser.next_atom(&tokenizer).unwrap() {
+            assert!(parser.buffer.len() <= "(fact 1)\n".len());
+            count += 1;
+        }
+        assert_eq!(count, 1000);
+    }
+
 }
@@ -0,0 +1,514 @@
+//! Static type checking of a whole module before it is executed.
+//!
+//! The interpreter checks types lazily, one expression at a time, while reducing it. The
+//! [check_module_text] function instead walks every `=` definition and every `!` expression of a
+//! source text and checks them against the `:` declarations of the text and of the context space.
+//! Variables of each `=` definition should have the types which are consistent across all their
+//! usages, see [infer_var_types]. Data types declared by `data` expressions are checked for
+//! the correct application of type constructors, and `case`/`switch` expressions over the values
+//! of a data type should cover all its constructors, see [crate::metta::adt].
+//! Nothing is evaluated and nothing is added into the context space, the only exception are
+//! `!(import! <destination> <module>)` items which are passed to the resolver of
+//! [check_module_text_with_imports] to make the types and tokens of the imported modules
+//! visible to the checker. All errors found are returned together, each one with its position
+//! in the source text.
+//!
+//! # Examples
+//!
+//! ```
+//! use hyperon::metta::text::Tokenizer;
+//! use hyperon::metta::type_check::check_module_text;
+//!
+//! let text = "
+//!     (: foo (-> A B))
+//!     (: a A)
+//!     (: b B)
+//!     (= (bar) (foo a))
+//!     !(foo b)
+//! ";
+//! let errors = check_module_text(text, &Tokenizer::new(), None);
+//!
+//! assert_eq!(errors.len(), 1);
+//! assert_eq!(errors[0].to_string(), "6:6: (foo b) is badly typed");
+//! ```
+
+use crate::*;
+use crate::space::*;
+use crate::space::grounding::GroundingSpace;
+use crate::atom::matcher::Bindings;
+use crate::metta::*;
+use crate::metta::text::{Tokenizer, SExprParser, SyntaxNode, SyntaxNodeType, TextPosition};
+use crate::metta::types::{get_atom_types, check_type, validate_atom, infer_var_types, display_type, has_untyped_op};
+use crate::metta::adt::{DataDeclaration, check_declaration_kinds, missing_constructors};
+use crate::metta::runner::EXEC_SYMBOL;
+
+use std::ops::Range;
+use std::fmt::{Display, Formatter};
+
+/// Kind of the problem reported by the type checker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypeCheckErrorKind {
+    /// Source text cannot be parsed into atoms
+    Syntax,
+    /// Expression has no type which is compatible with the declared signatures
+    BadType,
+    /// Body of the `=` definition has no type returned by the head of the definition
+    BadReturnType,
+    /// Type constructor is applied to the wrong number of type arguments or
+    /// data declaration is malformed
+    BadKind,
+    /// `case` or `switch` expression doesn't cover all constructors of the data type
+    NonExhaustive,
+    /// Module imported by `import!` cannot be loaded
+    BadImport,
+}
+
+/// Type error found by [check_module_text]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeCheckError {
+    /// Kind of the error
+    pub kind: TypeCheckErrorKind,
+    /// Byte range of the erroneous atom in the source text
+    pub src_range: Range<usize>,
+    /// Position of the start of the erroneous atom in the source text
+    pub position: TextPosition,
+    /// Erroneous atom, `None` for syntax errors
+    pub atom: Option<Atom>,
+    /// Human-readable description of the error
+    pub message: String,
+}
+
+impl Display for TypeCheckError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+/// Module loaded by the resolver passed to [check_module_text_with_imports]
+pub struct ImportedModule {
+    /// Space of the module
+    pub space: DynSpace,
+    /// Tokenizer of the module
+    pub tokenizer: Tokenizer,
+}
+
+/// Function which loads the module by its name
+pub type ImportResolver<'a> = dyn FnMut(&str) -> Result<ImportedModule, String> + 'a;
+
+/// Top level atom of the module together with its syntax node
+struct ModuleItem {
+    node: SyntaxNode,
+    atom: Atom,
+    is_exec: bool,
+}
+
+/// Checks types of all `=` definitions and `!` expressions in the MeTTa source `text`.
+///
+/// `tokenizer` is used to convert tokens into atoms. Type declarations are taken from the text
+/// itself and from the `context` space when it is passed. Usually `context` is the space of the
+/// module the text is going to be loaded into, in this case types of the imported modules are
+/// also visible to the checker. Returns the list of errors found, an empty list means the text is
+/// well typed. All syntax errors of the text are reported, atoms which cannot be parsed are
+/// skipped by the checker. `import!` items of the text are ignored, use
+/// [check_module_text_with_imports] to take them into account.
+pub fn check_module_text(text: &str, tokenizer: &Tokenizer, context: Option<&DynSpace>) -> Vec<TypeCheckError> {
+    check_module_text_internal(text, tokenizer, context, None)
+}
+
+/// Checks types of the MeTTa source `text` as [check_module_text] does and also takes
+/// `!(import! <destination> <module>)` items of the text into account. The `import` function
+/// is called with the name of each imported module and returns the loaded module. When the
+/// destination is a space, for example `&self`, then the atoms and the tokens of the imported
+/// module become visible to the checker. Otherwise the destination is a name and only the token
+/// which refers to the space of the module is added, as `import!` does.
+pub fn check_module_text_with_imports(text: &str, tokenizer: &Tokenizer, context: Option<&DynSpace>,
+    import: &mut ImportResolver) -> Vec<TypeCheckError>
+{
+    check_module_text_internal(text, tokenizer, context, Some(import))
+}
+
+fn check_module_text_internal(text: &str, tokenizer: &Tokenizer, context: Option<&DynSpace>,
+    import: Option<&mut ImportResolver>) -> Vec<TypeCheckError>
+{
+    let mut errors = Vec::new();
+    let (space, _tokenizer, items) = load_module_items(text, tokenizer, context, import, &mut errors);
+
+    for item in &items {
+        if item.is_exec {
+            check_well_typed(&space, text, &item.node, &item.atom, &mut errors);
+            check_exhaustive(&space, text, &item.node, &item.atom, None, &mut errors);
+        } else if let Some(decl) = DataDeclaration::parse(&item.atom) {
+            if let Err(message) = decl.and_then(|decl| decl.check_kinds(&space)) {
+                errors.push(new_error(TypeCheckErrorKind::BadKind, text, &item.node, Some(item.atom.clone()), message));
+            }
+        } else if let Err(message) = check_declaration_kinds(&space, &item.atom) {
+            errors.push(new_error(TypeCheckErrorKind::BadKind, text, &item.node, Some(item.atom.clone()), message));
+        } else if let Some((head, body)) = split_equality(&item.atom) {
+            let (head_node, body_node) = match atom_nodes(&item.node)[..] {
+                [_, head_node, body_node] => (head_node, body_node),
+                _ => (&item.node, &item.node),
+            };
+            let head_ok = check_well_typed(&space, text, head_node, head, &mut errors);
+            let body_ok = check_well_typed(&space, text, body_node, body, &mut errors);
+            if head_ok && body_ok && check_return_type(&space, text, body_node, head, body, &mut errors) {
+                let var_types = check_variable_types(&space, text, &item.node, &item.atom, &mut errors);
+                check_exhaustive(&space, text, body_node, body, var_types.as_ref(), &mut errors);
+            }
+        }
+    }
+    log::debug!("check_module_text: {} errors found", errors.len());
+    errors
+}
+
+/// Returns the module which contains the declarations of the MeTTa source `text` without
+/// running it. `!` expressions of the text are not evaluated, `!(import! <destination> <module>)`
+/// expressions are resolved by the `import` function as [check_module_text_with_imports] does.
+/// Errors found in the text are ignored. The returned module can be passed to the checker of the
+/// text which imports it.
+pub fn load_module_declarations(text: &str, tokenizer: &Tokenizer, import: &mut ImportResolver) -> ImportedModule {
+    let (space, tokenizer, _items) = load_module_items(text, tokenizer, None, Some(import), &mut Vec::new());
+    ImportedModule{ space: DynSpace::new(space), tokenizer }
+}
+
+/// Parses the items of the module, resolves its imports and returns the space which contains the
+/// declarations of the module together with the tokenizer extended by the imported modules
+fn load_module_items(text: &str, tokenizer: &Tokenizer, context: Option<&DynSpace>,
+    import: Option<&mut ImportResolver>, errors: &mut Vec<TypeCheckError>) -> (GroundingSpace, Tokenizer, Vec<ModuleItem>)
+{
+    let mut items = parse_module_items(text, tokenizer, errors);
+    let mut tokenizer = tokenizer.clone();
+
+    let mut space = GroundingSpace::new();
+    if let Some(context) = context {
+        space.add(Atom::gnd(context.clone()));
+    }
+    if let Some(import) = import {
+        let mut imported = false;
+        for item in items.iter().filter(|item| item.is_exec) {
+            if let Some((dest, mod_name)) = split_import(text, item) {
+                match import(&mod_name) {
+                    Ok(module) => {
+                        add_imported_module(&mut space, &mut tokenizer, dest, module);
+                        imported = true;
+                    },
+                    Err(message) => errors.push(new_error(TypeCheckErrorKind::BadImport,
+                        text, &item.node, Some(item.atom.clone()), message)),
+                }
+            }
+        }
+        // Tokens of the imported modules can change the atoms of the text
+        if imported {
+            items = parse_module_items(text, &tokenizer, &mut Vec::new());
+        }
+    }
+    items.iter().filter(|item| !item.is_exec)
+        .for_each(|item| {
+            if let Some(Ok(decl)) = DataDeclaration::parse(&item.atom) {
+                decl.declarations().into_iter().for_each(|decl| space.add(decl));
+            }
+            space.add(item.atom.clone())
+        });
+    (space, tokenizer, items)
+}
+
+/// Returns the destination and the module name of the `import!` expression. The operation is
+/// recognized by its token because the tokenizer can replace it by a grounded atom.
+fn split_import<'a>(text: &str, item: &'a ModuleItem) -> Option<(&'a Atom, String)> {
+    let op_node = atom_nodes(&item.node).into_iter().next()?;
+    if &text[op_node.src_range.clone()] != "import!" {
+        return None;
+    }
+    match &item.atom {
+        Atom::Expression(expr) => match expr.children().as_slice() {
+            [_op, dest, Atom::Symbol(mod_name)] => Some((dest, mod_name.name().to_string())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn add_imported_module(space: &mut GroundingSpace, tokenizer: &mut Tokenizer, dest: &Atom, module: ImportedModule) {
+    match dest {
+        Atom::Symbol(name) if name.name() != "&self" => {
+            let name = name.name();
+            let token = if name.starts_with('&') { name.to_string() } else { format!("&{}", name) };
+            let space_atom = Atom::gnd(module.space);
+            tokenizer.register_token_with_regex_str(&regex::escape(&token), move |_| space_atom.clone());
+        },
+        _ => {
+            let mut module_tokenizer = module.tokenizer;
+            space.add(Atom::gnd(module.space));
+            tokenizer.move_front(&mut module_tokenizer);
+        },
+    }
+}
+
+fn parse_module_items(text: &str, tokenizer: &Tokenizer, errors: &mut Vec<TypeCheckError>) -> Vec<ModuleItem> {
+    let mut items = Vec::new();
+    let mut parser = SExprParser::new(text);
+    let mut is_exec = false;
+    while let Some(node) = parser.parse_to_syntax_tree() {
+        if let Some(error_node) = node.error_node() {
+            let message = error_node.message.clone().unwrap_or_else(|| "Syntax error".into());
+            errors.push(new_error(TypeCheckErrorKind::Syntax, text, error_node, None, message));
+            is_exec = false;
+            continue;
+        }
+        match node.as_atom(tokenizer) {
+            Ok(Some(atom)) if atom == EXEC_SYMBOL => is_exec = true,
+            Ok(Some(atom)) => {
+                items.push(ModuleItem{ node, atom, is_exec });
+                is_exec = false;
+            },
+            Ok(None) => {},
+            Err(message) => errors.push(new_error(TypeCheckErrorKind::Syntax, text, &node, None, message)),
+        }
+    }
+    items
+}
+
+fn new_error(kind: TypeCheckErrorKind, text: &str, node: &SyntaxNode, atom: Option<Atom>, message: String) -> TypeCheckError {
+    let src_range = node.src_range.clone();
+    let position = TextPosition::from_offset(text, src_range.start);
+    TypeCheckError{ kind, src_range, position, atom, message }
+}
+
+fn split_equality(atom: &Atom) -> Option<(&Atom, &Atom)> {
+    match atom {
+        Atom::Expression(expr) => match expr.children().as_slice() {
+            [op, head, body] if *op == EQUAL_SYMBOL => Some((head, body)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns sub-nodes of the expression node which are converted into the children atoms
+fn atom_nodes(node: &SyntaxNode) -> Vec<&SyntaxNode> {
+    node.sub_nodes.iter().filter(|node| matches!(node.node_type,
+        SyntaxNodeType::VariableToken |
+        SyntaxNodeType::StringToken |
+        SyntaxNodeType::WordToken |
+        SyntaxNodeType::ExpressionGroup)).collect()
+}
+
+/// Reports the innermost badly typed sub-expression of the `atom`. Returns `true` when `atom`
+/// is typed correctly.
+fn check_well_typed(space: &dyn Space, text: &str, node: &SyntaxNode, atom: &Atom, errors: &mut Vec<TypeCheckError>) -> bool {
+    match find_badly_typed(space, node, atom) {
+        None => true,
+        Some((node, atom)) => {
+            let message = format!("{} is badly typed", atom);
+            errors.push(new_error(TypeCheckErrorKind::BadType, text, node, Some(atom.clone()), message));
+            false
+        },
+    }
+}
+
+fn find_badly_typed<'a>(space: &dyn Space, node: &'a SyntaxNode, atom: &'a Atom) -> Option<(&'a SyntaxNode, &'a Atom)> {
+    if validate_atom(space, atom) {
+        return None;
+    }
+    if let Atom::Expression(expr) = atom {
+        let child_nodes = atom_nodes(node);
+        if child_nodes.len() == expr.children().len() {
+            let found = child_nodes.into_iter().zip(expr.children().iter())
+                .find_map(|(node, child)| find_badly_typed(space, node, child));
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+    Some((node, atom))
+}
+
+fn check_return_type(space: &dyn Space, text: &str, body_node: &SyntaxNode, head: &Atom, body: &Atom, errors: &mut Vec<TypeCheckError>) -> bool {
+    let head_types = get_atom_types(space, head);
+    // Result of the function returning Atom or of the operation without type
+    // is evaluated further, thus its type is not known until run time
+    let is_compatible = get_atom_types(space, body).contains(&ATOM_TYPE_ATOM) || has_untyped_op(space, body) || head_types.iter()
+        .any(|typ| *typ == ATOM_TYPE_UNDEFINED || check_type(space, body, typ));
+    if !is_compatible {
+        let head_types: Vec<String> = head_types.iter().map(|typ| typ.to_string()).collect();
+        let message = format!("{} is expected to have type {} returned by {}",
+            body, head_types.join(" or "), head);
+        errors.push(new_error(TypeCheckErrorKind::BadReturnType, text, body_node, Some(body.clone()), message));
+    }
+    is_compatible
+}
+
+/// Reports the definition which uses its variables with conflicting types,
+/// returns the inferred types of the variables otherwise
+fn check_variable_types(space: &dyn Space, text: &str, node: &SyntaxNode, rule: &Atom, errors: &mut Vec<TypeCheckError>) -> Option<Bindings> {
+    match infer_var_types(space, rule) {
+        Ok(var_types) => var_types.into_iter().next(),
+        Err(explanation) => {
+            let message = format!("{} is badly typed: {}", rule, explanation);
+            errors.push(new_error(TypeCheckErrorKind::BadType, text, node, Some(rule.clone()), message));
+            None
+        },
+    }
+}
+
+/// Splits `(case <atom> ((<pattern> <template>) ...))` or the same `switch`
+/// expression on the atom and the list of patterns
+fn split_case(atom: &Atom) -> Option<(&Atom, Vec<Atom>)> {
+    let children = match atom {
+        Atom::Expression(expr) => expr.children(),
+        _ => return None,
+    };
+    match children.as_slice() {
+        [op, atom, Atom::Expression(cases)] if matches!(op.to_string().as_str(), "case" | "switch") => {
+            let patterns = cases.children().iter().map(|case| match case {
+                Atom::Expression(case) if case.children().len() == 2 => Some(case.children()[0].clone()),
+                _ => None,
+            }).collect::<Option<Vec<Atom>>>()?;
+            Some((atom, patterns))
+        },
+        _ => None,
+    }
+}
+
+/// Reports `case` and `switch` expressions inside of the `atom` which don't
+/// cover all constructors of the data type of the atom they are matching.
+/// `var_types` contains the types of the variables inferred from the definition.
+fn check_exhaustive(space: &dyn Space, text: &str, node: &SyntaxNode, atom: &Atom, var_types: Option<&Bindings>, errors: &mut Vec<TypeCheckError>) {
+    if let Some((matched, patterns)) = split_case(atom) {
+        let types = match (matched, var_types) {
+            (Atom::Variable(var), Some(var_types)) => var_types.resolve(var).into_iter().collect(),
+            _ => get_atom_types(space, matched),
+        };
+        let missing = types.iter().map(|typ| (typ, missing_constructors(space, typ, &patterns)))
+            .find(|(_typ, missing)| !missing.is_empty());
+        if let Some((typ, missing)) = missing {
+            let missing: Vec<String> = missing.iter().map(|ctor| ctor.to_string()).collect();
+            let message = format!("{} doesn't cover {} of type {}", atom, missing.join(", "), display_type(typ));
+            errors.push(new_error(TypeCheckErrorKind::NonExhaustive, text, node, Some(atom.clone()), message));
+        }
+    }
+    if let Atom::Expression(expr) = atom {
+        let child_nodes = atom_nodes(node);
+        if child_nodes.len() == expr.children().len() {
+            child_nodes.into_iter().zip(expr.children().iter())
+                .for_each(|(node, child)| check_exhaustive(space, text, node, child, var_types, errors));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &str) -> Vec<String> {
+        check_module_text(text, &Tokenizer::new(), None).iter()
+            .map(|err| err.to_string()).collect()
+    }
+
+    #[test]
+    fn check_module_text_well_typed() {
+        let text = "
+            (: foo (-> A B))
+            (: a A)
+            (= (bar $x) (foo $x))
+            (= (baz) (foo a))
+            !(baz)
+        ";
+        assert_eq!(check(text), Vec::<String>::new());
+    }
+
+    #[test]
+    fn check_module_text_reports_all_errors() {
+        let text = "(: foo (-> A B))\n(: a A)\n(: b B)\n(= (bar) (foo b))\n!(foo a b)\n!(foo (foo a))";
+        assert_eq!(check(text), vec![
+            "4:10: (foo b) is badly typed",
+            "5:2: (foo a b) is badly typed",
+            "6:2: (foo (foo a)) is badly typed",
+        ]);
+    }
+
+    #[test]
+    fn check_module_text_return_type() {
+        let text = "(: foo (-> A B))\n(: bar (-> A))\n(: a A)\n(= (bar) (foo a))";
+        let errors = check_module_text(text, &Tokenizer::new(), None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, TypeCheckErrorKind::BadReturnType);
+        assert_eq!(errors[0].atom, Some(expr!("foo" "a")));
+        assert_eq!(errors[0].to_string(), "4:10: (foo a) is expected to have type A returned by (bar)");
+    }
+
+    #[test]
+    fn check_module_text_variable_types() {
+        let text = "(: foo (-> A B))\n(: bar (-> B B))\n(: qux (-> B B))\n(= (baz $x) (bar (foo $x)))\n(= (qux $x) (bar (foo $x)))";
+        let errors = check_module_text(text, &Tokenizer::new(), None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, TypeCheckErrorKind::BadType);
+        assert_eq!(errors[0].to_string(), "5:1: (= (qux $x) (bar (foo $x))) is badly typed: \
+            $x has type B but argument 1 of (foo $x) should have type A");
+    }
+
+    #[test]
+    fn check_module_text_data_types() {
+        let text = "(data (Maybe $t) (Nothing) (Just $t))\n(: a A)\n(: get (-> (Maybe A) A))\n\
+            (= (get $m) (case $m (((Just $x) $x))))\n!(switch (Just a) ((Nothing a)))\n\
+            (= (has $m) (case $m ((Nothing F) ($_ T))))\n(: bad (-> Maybe A))\n(data Pair (MkPair Maybe))";
+        let errors = check_module_text(text, &Tokenizer::new(), None);
+        let kinds: Vec<TypeCheckErrorKind> = errors.iter().map(|err| err.kind).collect();
+        assert_eq!(kinds, vec![TypeCheckErrorKind::NonExhaustive, TypeCheckErrorKind::NonExhaustive,
+            TypeCheckErrorKind::BadKind, TypeCheckErrorKind::BadKind]);
+        let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(errors, vec![
+            "4:13: (case $m (((Just $x) $x))) doesn't cover Nothing of type (Maybe A)",
+            "5:2: (switch (Just a) ((Nothing a))) doesn't cover (Just $t) of type (Maybe A)",
+            "7:1: type constructor Maybe expects 1 type arguments but 0 given in Maybe",
+            "8:1: type constructor Maybe expects 1 type arguments but 0 given in Maybe",
+        ]);
+    }
+
+    #[test]
+    fn check_module_text_does_not_run_code() {
+        let text = "(: a A)\n!(foo a)";
+        let mut context = GroundingSpace::new();
+        context.add(expr!(":" "foo" ("->" "B" "B")));
+        let context = DynSpace::new(context);
+        let errors = check_module_text(text, &Tokenizer::new(), Some(&context));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].src_range, 9..16);
+        assert_eq!(context.borrow().atom_count(), Some(1));
+    }
+
+    #[test]
+    fn check_module_text_syntax_error() {
+        let errors = check_module_text("!(foo)\n(bar", &Tokenizer::new(), None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, TypeCheckErrorKind::Syntax);
+        assert_eq!(errors[0].position, TextPosition{ line: 2, column: 1 });
+    }
+
+    #[test]
+    fn check_module_text_all_syntax_errors() {
+        let text = "(: foo (-> A B))\n(: a A)\n(bar))\n!(foo \"\\q\")\n!(foo a b\n(: b B)\n!(foo b)";
+        let errors: Vec<String> = check_module_text(text, &Tokenizer::new(), None)
+            .iter().map(|err| err.to_string()).collect();
+        assert_eq!(errors, vec![
+            "3:6: Unexpected right bracket",
+            "4:8: Invalid escape sequence",
+            "5:2: Unexpected end of expression",
+            "7:2: (foo b) is badly typed",
+        ]);
+    }
+
+    #[test]
+    fn check_module_text_with_imports() {
+        let text = "!(import! &self types)\n!(import! lib types)\n(: a A)\n!(foo a)\n!(get-type-space &lib a)";
+        let mut imported = Vec::new();
+        let errors = super::check_module_text_with_imports(text, &Tokenizer::new(), None, &mut |mod_name| {
+            imported.push(mod_name.to_string());
+            let mut space = GroundingSpace::new();
+            space.add(expr!(":" "foo" ("->" "B" "B")));
+            Ok(ImportedModule{ space: DynSpace::new(space), tokenizer: Tokenizer::new() })
+        });
+        assert_eq!(imported, vec!["types", "types"]);
+        let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(errors, vec!["4:2: (foo a) is badly typed"]);
+    }
+}
@@ -19,8 +19,9 @@
 //! of `%Undefined%` type can be matched with any type required.
 
 use super::*;
-use crate::atom::matcher::{Bindings, BindingsSet, apply_bindings_to_atom};
+use crate::atom::matcher::{Bindings, BindingsSet, apply_bindings_to_atom, match_atoms, atoms_are_equivalent};
 use crate::space::Space;
+use crate::space::grounding::record_matched_atoms;
 
 fn typeof_query(atom: &Atom, typ: &Atom) -> Atom {
     Atom::expr(vec![HAS_TYPE_SYMBOL, atom.clone(), typ.clone()])
@@ -285,6 +286,11 @@ fn get_application_types(space: &dyn Space, atom: &Atom, expr: &ExpressionAtom)
         for fn_type in fn_types {
             has_function_types = true;
             let (expected_arg_types, ret_typ) = get_arg_types(&fn_type);
+            // check_arg_types doesn't check the rest of the arguments after
+            // the argument of Atom type, thus arity is checked separately
+            if expected_arg_types.len() != args.len() {
+                continue;
+            }
             for bindings in check_arg_types(actual_arg_types.as_slice(), meta_arg_types.as_slice(), expected_arg_types, Bindings::new()) {
                 types.push(apply_bindings_to_atom(&ret_typ, &bindings));
             }
@@ -475,6 +481,758 @@ pub fn validate_atom(space: &dyn Space, atom: &Atom) -> bool {
     !get_atom_types(space, atom).is_empty()
 }
 
+/// Returns true if `typ` is declared as a super type of some other type
+fn has_sub_types(space: &dyn Space, typ: &Atom) -> bool {
+    let var_x = VariableAtom::new("X").make_unique();
+    !space.query(&isa_query(&Atom::Variable(var_x), typ)).is_empty()
+}
+
+fn super_type_chains(space: &dyn Space, typ: &Atom) -> Vec<Vec<Atom>> {
+    fn walk(space: &dyn Space, chain: Vec<Atom>, chains: &mut Vec<Vec<Atom>>) {
+        let super_types: Vec<Atom> = query_super_types(space, chain.last().unwrap())
+            .into_iter().filter(|typ| !chain.contains(typ)).collect();
+        if super_types.is_empty() {
+            if chain.len() > 1 {
+                chains.push(chain);
+            }
+        } else {
+            for typ in super_types {
+                let mut next = chain.clone();
+                next.push(typ);
+                walk(space, next, chains);
+            }
+        }
+    }
+    let mut chains = Vec::new();
+    walk(space, vec![typ.clone()], &mut chains);
+    chains
+}
+
+/// Removes unique suffixes from the names of type variables to make
+/// explanations of errors reproducible
+pub(crate) fn display_type(typ: &Atom) -> Atom {
+    let mut typ = typ.clone();
+    typ.iter_mut().for_each(|atom| if let Atom::Variable(var) = atom {
+        let name = var.name();
+        let name = name.split('#').next().unwrap_or_default();
+        *atom = Atom::Variable(VariableAtom::new(name));
+    });
+    typ
+}
+
+fn join_atoms<'a>(atoms: impl Iterator<Item=&'a Atom>, separator: &str) -> String {
+    atoms.map(|atom| display_type(atom).to_string()).collect::<Vec<String>>().join(separator)
+}
+
+/// Explains why the `index`-th argument (starting from 1) of the `expr`
+/// cannot be used as an argument of the `expected` type. `actual` contains
+/// the types found for the argument, the supertypes of them which are
+/// declared via `:<` are listed in the explanation as well.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::types::{get_atom_types, explain_arg_type_mismatch};
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("(: foo (-> A B)) (: b B) (:< B C)")).unwrap();
+/// let space = metta.space();
+/// let actual = get_atom_types(&space, &expr!("b"));
+///
+/// assert_eq!(explain_arg_type_mismatch(&space, &expr!("foo" "b"), 1, &actual, &expr!("A")),
+///     "argument 1 of (foo b) should have type A, but b has type B or C, super types tried: B :< C");
+/// ```
+pub fn explain_arg_type_mismatch(space: &dyn Space, expr: &Atom, index: usize, actual: &[Atom], expected: &Atom) -> String {
+    let arg = match expr {
+        Atom::Expression(expr) => expr.children().get(index).cloned(),
+        _ => None,
+    }.unwrap_or(EMPTY_SYMBOL);
+    format!("argument {} of {} should have type {}, but {}", index, expr,
+        display_type(expected), explain_actual_types(space, &arg, actual, expected))
+}
+
+/// Explains why the `atom` cannot have the `expected` type in context of the
+/// `space`. When the `atom` itself is badly typed its innermost badly typed
+/// sub-expression is explained as [explain_bad_type] does. Otherwise the
+/// types of the `atom` and the supertypes tried are listed.
+pub fn explain_type_mismatch(space: &dyn Space, atom: &Atom, expected: &Atom) -> String {
+    if let Some(explanation) = explain_bad_type(space, atom).or_else(|| explain_arity_mismatch(space, atom)) {
+        return explanation;
+    }
+    format!("{} should have type {}, but {}", atom, display_type(expected),
+        explain_actual_types(space, atom, &get_atom_types(space, atom), expected))
+}
+
+/// Explains why the number of arguments of the `atom` doesn't match any
+/// function type of its operation
+fn explain_arity_mismatch(space: &dyn Space, atom: &Atom) -> Option<String> {
+    let expr = match atom {
+        Atom::Expression(expr) if !expr.children().is_empty() => expr,
+        _ => return None,
+    };
+    let args = get_args(expr);
+    let fn_types: Vec<Atom> = get_atom_types(space, get_op(expr)).into_iter().filter(is_func).collect();
+    let arity_matches = |fn_type: &Atom| get_arg_types(fn_type).0.len() == args.len();
+    if fn_types.is_empty() || fn_types.iter().any(arity_matches) {
+        return None;
+    }
+    Some(fn_types.iter().map(|fn_type| format!("{} has {} arguments but type {} of {} expects {}",
+        atom, args.len(), display_type(fn_type), get_op(expr), get_arg_types(fn_type).0.len()))
+        .collect::<Vec<String>>().join("; "))
+}
+
+fn explain_actual_types(space: &dyn Space, atom: &Atom, actual: &[Atom], expected: &Atom) -> String {
+    if actual.is_empty() {
+        return format!("{} is badly typed", atom);
+    }
+    let mut message = format!("{} has type {}", atom, join_atoms(actual.iter(), " or "));
+    let super_types: Vec<Atom> = actual.iter().flat_map(|typ| query_super_types(space, typ)).collect();
+    let chains: Vec<String> = actual.iter()
+        .filter(|typ| !super_types.contains(typ))
+        .flat_map(|typ| super_type_chains(space, typ))
+        .map(|chain| join_atoms(chain.iter(), " :< "))
+        .collect();
+    if chains.is_empty() {
+        // Hint is given only when some type can be cast to the expected one
+        if has_sub_types(space, expected) {
+            message.push_str(", no super types are declared using :<");
+        }
+    } else {
+        message.push_str(&format!(", super types tried: {}", chains.join(", ")));
+    }
+    message
+}
+
+/// Explains why the `atom` is badly typed in context of the `space`. The
+/// innermost badly typed sub-expression is explained: which argument has a
+/// type incompatible with the signature of the operation, which types were
+/// found for it and which supertypes were tried. Returns `None` when `atom`
+/// is typed correctly.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::types::explain_bad_type;
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("(: foo (-> A B)) (: a A) (: b B)")).unwrap();
+/// let space = metta.space();
+///
+/// assert_eq!(explain_bad_type(&space, &expr!("foo" "a")), None);
+/// assert_eq!(explain_bad_type(&space, &expr!("foo" ("foo" "a"))),
+///     Some("argument 1 of (foo (foo a)) should have type A, but (foo a) has type B".into()));
+/// ```
+pub fn explain_bad_type(space: &dyn Space, atom: &Atom) -> Option<String> {
+    if validate_atom(space, atom) {
+        return None;
+    }
+    let expr = match atom {
+        Atom::Expression(expr) if !expr.children().is_empty() => expr,
+        _ => return Some(format!("{} is badly typed", atom)),
+    };
+    if let Some(explanation) = expr.children().iter().find_map(|child| explain_bad_type(space, child)) {
+        return Some(explanation);
+    }
+    let args = get_args(expr);
+    let actual: Vec<Vec<Atom>> = args.iter().map(|arg| get_atom_types(space, arg)).collect();
+    let meta: Vec<Vec<Atom>> = args.iter().map(|arg| vec![get_meta_type(arg), ATOM_TYPE_ATOM]).collect();
+    let explanations: Vec<String> = get_atom_types(space, get_op(expr)).into_iter()
+        .filter(is_func)
+        .map(|fn_type| {
+            let (expected, _ret) = get_arg_types(&fn_type);
+            if expected.len() != args.len() {
+                return format!("{} has {} arguments but type {} of {} expects {}",
+                    atom, args.len(), display_type(&fn_type), get_op(expr), expected.len());
+            }
+            let mut bindings = Bindings::new();
+            for i in 0..args.len() {
+                let matched = check_arg_types(&actual[..=i], &meta[..=i], &expected[..=i], Bindings::new());
+                match matched.into_iter().next() {
+                    Some(next) => bindings = next,
+                    None => {
+                        let expected = apply_bindings_to_atom(&expected[i], &bindings);
+                        return explain_arg_type_mismatch(space, atom, i + 1, &actual[i], &expected);
+                    },
+                }
+            }
+            format!("{} is badly typed", atom)
+        }).collect();
+    if explanations.is_empty() {
+        Some(format!("{} is badly typed", atom))
+    } else {
+        Some(explanations.join("; "))
+    }
+}
+
+/// Replaces type variables and `%Undefined%` types by unique symbols, thus
+/// they are matched only by the type variables of the other type
+fn freeze_type(typ: &Atom) -> Atom {
+    let mut typ = typ.clone();
+    typ.iter_mut().for_each(|atom| {
+        let frozen = match atom {
+            Atom::Variable(var) => Some(format!("%frozen {}%", var.name())),
+            _ if *atom == ATOM_TYPE_UNDEFINED => Some(format!("%frozen {}%", atom)),
+            _ => None,
+        };
+        if let Some(frozen) = frozen {
+            *atom = Atom::sym(frozen);
+        }
+    });
+    typ
+}
+
+/// Returns `true` when each argument type of the function type `specific` is
+/// the same type, an instance or a subtype of the corresponding argument type
+/// of the function type `general`, thus any arguments accepted by `specific`
+/// are accepted by `general` as well. Return types are not compared.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::types::is_more_specific_type;
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("(:< Int Number)")).unwrap();
+/// let space = metta.space();
+///
+/// assert!(is_more_specific_type(&space, &expr!("->" "Int" "Int"), &expr!("->" "Number" "Number")));
+/// assert!(is_more_specific_type(&space, &expr!("->" "Number" "Number"), &expr!("->" t t)));
+/// assert!(!is_more_specific_type(&space, &expr!("->" t t), &expr!("->" "Number" "Number")));
+/// ```
+pub fn is_more_specific_type(space: &dyn Space, specific: &Atom, general: &Atom) -> bool {
+    let (specific_args, _) = get_arg_types(specific);
+    let (general_args, _) = get_arg_types(general);
+    if specific_args.len() != general_args.len() {
+        return false;
+    }
+    let actual: Vec<Vec<Atom>> = specific_args.iter().map(|typ| {
+        let mut types = vec![typ.clone()];
+        if !matches!(typ, Atom::Variable(_)) && *typ != ATOM_TYPE_UNDEFINED {
+            add_super_types(space, &mut types, 0);
+        }
+        types.iter().map(freeze_type).collect()
+    }).collect();
+    let meta = vec![vec![ATOM_TYPE_ATOM]; actual.len()];
+    !check_arg_types(&actual, &meta, general_args, Bindings::new()).is_empty()
+}
+
+/// Selects the function type of the operation which is used to call the
+/// `atom`. Selection is made only when the operation is declared using
+/// `(dispatch <op> most-specific)` expression, `None` is returned otherwise.
+/// Among the function types which accept the arguments of the `atom` the
+/// most specific one is returned, see [is_more_specific_type]. Only the `=`
+/// definitions which implement the selected function type are used to
+/// evaluate the call, see [get_overload_definitions]. If there is no single
+/// most specific type then the call is ambiguous and the error is returned.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::types::select_overload;
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("
+///     (dispatch show most-specific)
+///     (: show (-> $t String))
+///     (: show (-> Number String))
+///     (: show (-> A $t String))
+///     (: show (-> $t B String))
+///     (: a A) (: b B) (: n Number)
+/// ")).unwrap();
+/// let space = metta.space();
+///
+/// assert_eq!(select_overload(&space, &expr!("show" "n")), Ok(Some(expr!("->" "Number" "String"))));
+/// assert_eq!(select_overload(&space, &expr!("show" "a" "b")),
+///     Err("(show a b) is ambiguous: types (-> $t B String) and (-> A $t String) of show are equally specific".into()));
+/// ```
+pub fn select_overload(space: &dyn Space, atom: &Atom) -> Result<Option<Atom>, String> {
+    let expr = match atom {
+        Atom::Expression(expr) if !expr.children().is_empty() => expr,
+        _ => return Ok(None),
+    };
+    let op = get_op(expr);
+    if !is_dispatched(space, op) {
+        return Ok(None);
+    }
+    let most_specific = get_most_specific_types(space, op, get_args(expr));
+    match most_specific.len() {
+        0 => Ok(None),
+        1 => Ok(most_specific.into_iter().next()),
+        _ => Err(format!("{} is ambiguous: {}", atom, explain_equally_specific(op, &most_specific))),
+    }
+}
+
+fn is_dispatched(space: &dyn Space, op: &Atom) -> bool {
+    let dispatch = Atom::expr([DISPATCH_SYMBOL, op.clone(), MOST_SPECIFIC_SYMBOL]);
+    !space.query(&dispatch).is_empty()
+}
+
+/// Returns the most specific function types of the `op` among the ones
+/// which accept the `args`, see [is_more_specific_type]
+fn get_most_specific_types(space: &dyn Space, op: &Atom, args: &[Atom]) -> Vec<Atom> {
+    let actual: Vec<Vec<Atom>> = args.iter().map(|arg| get_atom_types(space, arg)).collect();
+    let meta: Vec<Vec<Atom>> = args.iter().map(|arg| vec![get_meta_type(arg), ATOM_TYPE_ATOM]).collect();
+    let applicable: Vec<Atom> = get_atom_types(space, op).into_iter()
+        .filter(is_func)
+        .filter(|fn_type| {
+            let (expected, _ret) = get_arg_types(fn_type);
+            expected.len() == args.len()
+                && !check_arg_types(&actual, &meta, expected, Bindings::new()).is_empty()
+        }).collect();
+    let mut most_specific: Vec<Atom> = Vec::new();
+    for typ in &applicable {
+        let is_less_specific = applicable.iter().any(|other| {
+            is_more_specific_type(space, other, typ) && !is_more_specific_type(space, typ, other)
+        });
+        // the most specific type which is equivalent to the already selected one
+        let is_duplicate = most_specific.iter().any(|other| is_more_specific_type(space, other, typ));
+        if !is_less_specific && !is_duplicate {
+            most_specific.push(typ.clone());
+        }
+    }
+    most_specific
+}
+
+fn explain_equally_specific(op: &Atom, types: &[Atom]) -> String {
+    let mut types: Vec<String> = types.iter().map(|typ| display_type(typ).to_string()).collect();
+    types.sort();
+    format!("types {} of {} are equally specific", types.join(" and "), op)
+}
+
+/// Returns the `=` definitions of the operation of the `atom` which have the
+/// same number of arguments, each definition is returned as it is kept in the
+/// space together with its head and body with renamed variables
+fn query_definitions(space: &dyn Space, atom: &Atom) -> Vec<(Atom, Atom, Atom)> {
+    let expr = match atom {
+        Atom::Expression(expr) if !expr.children().is_empty() => expr,
+        _ => return vec![],
+    };
+    let mut head = vec![get_op(expr).clone()];
+    head.extend(get_args(expr).iter().map(|_| Atom::Variable(VariableAtom::new("arg").make_unique())));
+    let head = Atom::expr(head);
+    let body = Atom::Variable(VariableAtom::new("body").make_unique());
+    let (results, stored) = record_matched_atoms(||
+        space.query(&Atom::expr([EQUAL_SYMBOL, head.clone(), body.clone()])));
+    results.into_iter()
+        .map(|bindings| {
+            let head = apply_bindings_to_atom(&head, &bindings);
+            let body = apply_bindings_to_atom(&body, &bindings);
+            let definition = Atom::expr([EQUAL_SYMBOL, head.clone(), body.clone()]);
+            // spaces which are not based on GroundingSpace don't record
+            // atoms, the renamed definition is returned in this case
+            let definition = stored.iter().find(|stored| atoms_are_equivalent(stored, &definition))
+                .cloned().unwrap_or(definition);
+            (definition, head, body)
+        })
+        .collect()
+}
+
+/// Returns the `=` definitions of the operation of the `atom` which have the
+/// same number of arguments. Definitions are returned as they are kept in
+/// the `space` when it is based on [GroundingSpace](crate::space::grounding::GroundingSpace).
+pub fn get_definitions(space: &dyn Space, atom: &Atom) -> Vec<Atom> {
+    query_definitions(space, atom).into_iter()
+        .map(|(definition, _head, _body)| definition)
+        .collect()
+}
+
+/// Returns the `=` definitions of the operation of the `atom` which can be
+/// applied to the `atom` and implement the function type `fn_type` of the
+/// operation, see [get_definition_type]. Returns an error when one of the
+/// definitions is not bound to a function type. Definitions are returned as
+/// [get_definitions] returns them.
+pub fn get_overload_definitions(space: &dyn Space, atom: &Atom, fn_type: &Atom) -> Result<Vec<Atom>, String> {
+    let mut definitions = Vec::new();
+    for (definition, head, body) in query_definitions(space, atom) {
+        if match_atoms(&head, atom).next().is_none() {
+            continue;
+        }
+        if atoms_are_equivalent(&get_definition_type(space, &Atom::expr([EQUAL_SYMBOL, head, body]))?, fn_type) {
+            definitions.push(definition);
+        }
+    }
+    Ok(definitions)
+}
+
+/// Returns the function type of the operation which is implemented by the
+/// `=` `definition` of the operation declared using
+/// `(dispatch <op> most-specific)`. The definition is bound to the function
+/// type either explicitly using `(overload <definition> <type>)` expression
+/// or by the types of the arguments of its head. In the latter case all
+/// arguments should be typed and the most specific function type which
+/// accepts them is returned. For example `(= (add (V $a $b) (V $c $d)) ...)`
+/// implements `(-> Vec Vec Vec)` when `(: V (-> Number Number Vec))`, while
+/// `(= (add $a $b) (+ $a $b))` should be bound using
+/// `(overload (= (add $a $b) (+ $a $b)) (-> Number Number Number))`.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::types::get_definition_type;
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("
+///     (dispatch voice most-specific)
+///     (:< Dog Animal) (: rex Dog)
+///     (: voice (-> Animal Sound))
+///     (: voice (-> Dog Sound))
+///     (overload (= (voice $x) generic) (-> Animal Sound))
+/// ")).unwrap();
+/// let space = metta.space();
+///
+/// assert_eq!(get_definition_type(&space, &expr!("=" ("voice" x) "generic")), Ok(expr!("->" "Animal" "Sound")));
+/// assert_eq!(get_definition_type(&space, &expr!("=" ("voice" "rex") "woof")), Ok(expr!("->" "Dog" "Sound")));
+/// assert_eq!(get_definition_type(&space, &expr!("=" ("voice" x) "dog")),
+///     Err("(= (voice $x) dog) is not bound to a type of voice, it should be typed by (overload <definition> <type>)".into()));
+/// ```
+pub fn get_definition_type(space: &dyn Space, definition: &Atom) -> Result<Atom, String> {
+    let expr = match split_rule(definition) {
+        Some((Atom::Expression(expr), _body)) if !expr.children().is_empty() => expr,
+        _ => return Err(format!("{} is not a definition of an operation", definition)),
+    };
+    let op = get_op(expr);
+    let typ = Atom::Variable(VariableAtom::new("type").make_unique());
+    let mut annotated: Vec<Atom> = Vec::new();
+    for bindings in space.query(&Atom::expr([OVERLOAD_SYMBOL, definition.clone(), typ.clone()])) {
+        // annotation of the more specific definition is not applicable
+        if !atoms_are_equivalent(&apply_bindings_to_atom(definition, &bindings), definition) {
+            continue;
+        }
+        let typ = apply_bindings_to_atom(&typ, &bindings);
+        if !annotated.iter().any(|other| atoms_are_equivalent(other, &typ)) {
+            annotated.push(typ);
+        }
+    }
+    if annotated.len() > 1 {
+        return Err(format!("{} is bound to several types: {}", definition, explain_equally_specific(op, &annotated)));
+    }
+    if let Some(typ) = annotated.pop() {
+        return Ok(typ);
+    }
+    let args = get_args(expr);
+    let is_typed = |arg: &Atom| !get_atom_types(space, arg).contains(&ATOM_TYPE_UNDEFINED);
+    if !args.iter().all(is_typed) {
+        return Err(format!("{} is not bound to a type of {}, it should be typed by (overload <definition> <type>)", definition, op));
+    }
+    let most_specific = get_most_specific_types(space, op, args);
+    match most_specific.len() {
+        0 => Err(format!("{} is not bound to a type of {}, no type accepts its arguments", definition, op)),
+        1 => Ok(most_specific.into_iter().next().unwrap()),
+        _ => Err(format!("{} is ambiguous: {}", definition, explain_equally_specific(op, &most_specific))),
+    }
+}
+
+/// Type inference for the variables of an atom. Variables of the atom are
+/// used as type variables: each variable gets the type which is consistent
+/// with all function applications it is passed into. Arguments of the meta
+/// types (for example `Atom`) are not evaluated, thus they don't constrain
+/// the type of the variable they contain, but type constraints found inside
+/// such arguments are still collected.
+struct TypeInference<'a> {
+    space: &'a dyn Space,
+    conflict: Option<String>,
+}
+
+impl<'a> TypeInference<'a> {
+    fn new(space: &'a dyn Space) -> Self {
+        Self{ space, conflict: None }
+    }
+
+    fn report(&mut self, conflict: impl FnOnce() -> String) {
+        if self.conflict.is_none() {
+            self.conflict = Some(conflict());
+        }
+    }
+
+    fn infer(&mut self, atom: &Atom, bindings: Bindings) -> Vec<(Atom, Bindings)> {
+        match atom {
+            Atom::Variable(_) => vec![(atom.clone(), bindings)],
+            Atom::Expression(expr) if !expr.children().is_empty() => {
+                let fn_types: Vec<Atom> = get_atom_types(self.space, get_op(expr))
+                    .into_iter().filter(is_func).map(make_variables_unique).collect();
+                if fn_types.is_empty() {
+                    self.infer_tuple(atom, expr, bindings)
+                } else {
+                    fn_types.iter().flat_map(|fn_type| self.infer_application(atom, fn_type, bindings.clone())).collect()
+                }
+            },
+            _ => get_atom_types(self.space, atom).into_iter()
+                .map(|typ| (make_variables_unique(typ), bindings.clone())).collect(),
+        }
+    }
+
+    fn infer_tuple(&mut self, atom: &Atom, expr: &ExpressionAtom, bindings: Bindings) -> Vec<(Atom, Bindings)> {
+        let mut states = vec![bindings];
+        for child in expr.children() {
+            states = states.into_iter()
+                .flat_map(|bindings| self.infer(child, bindings))
+                .map(|(_typ, bindings)| bindings).collect();
+        }
+        let types = get_atom_types(self.space, atom);
+        states.into_iter().flat_map(|bindings| {
+            types.iter().map(move |typ| (typ.clone(), bindings.clone()))
+        }).collect()
+    }
+
+    fn infer_application(&mut self, atom: &Atom, fn_type: &Atom, bindings: Bindings) -> Vec<(Atom, Bindings)> {
+        let args = match atom {
+            Atom::Expression(expr) => get_args(expr),
+            _ => unreachable!("Function application is expected"),
+        };
+        let (arg_types, ret_type) = get_arg_types(fn_type);
+        if arg_types.len() != args.len() {
+            self.report(|| format!("{} has {} arguments but type {} of {} expects {}",
+                atom, args.len(), display_type(fn_type), atom_op(atom), arg_types.len()));
+            return vec![];
+        }
+        let mut states = vec![bindings];
+        for (i, (arg, expected)) in args.iter().zip(arg_types.iter()).enumerate() {
+            states = states.into_iter().flat_map(|bindings| {
+                if is_meta_type(expected) {
+                    self.infer_unevaluated(arg, expected, bindings)
+                } else {
+                    self.infer(arg, bindings).into_iter().flat_map(|(actual, bindings)| {
+                        let unified = unify_types(&actual, expected, &bindings);
+                        if unified.is_empty() {
+                            self.report(|| format!("{} has type {} but argument {} of {} should have type {}",
+                                arg, display_type(&resolve_type(&actual, &bindings)), i + 1, atom,
+                                display_type(&resolve_type(expected, &bindings))));
+                        }
+                        unified
+                    }).collect()
+                }
+            }).collect();
+        }
+        states.into_iter().map(|bindings| (ret_type.clone(), bindings)).collect()
+    }
+
+    fn infer_unevaluated(&mut self, arg: &Atom, expected: &Atom, bindings: Bindings) -> Vec<Bindings> {
+        if *expected != ATOM_TYPE_ATOM && *expected != get_meta_type(arg) && !matches!(arg, Atom::Variable(_)) {
+            self.report(|| format!("{} has meta type {} but {} is expected", arg, get_meta_type(arg), expected));
+            return vec![];
+        }
+        let conflict = self.conflict.clone();
+        let inferred: Vec<Bindings> = self.infer(arg, bindings.clone()).into_iter()
+            .map(|(_typ, bindings)| bindings).collect();
+        if inferred.is_empty() {
+            // argument is not evaluated thus it can be typed incorrectly
+            self.conflict = conflict;
+            vec![bindings]
+        } else {
+            inferred
+        }
+    }
+
+    fn infer_rule(&mut self, head: &Atom, body: &Atom) -> Vec<(Atom, Bindings)> {
+        self.infer(head, Bindings::new()).into_iter().flat_map(|(head_type, bindings)| {
+            self.infer(body, bindings).into_iter().flat_map(|(body_type, bindings)| {
+                // Body returning Atom is evaluated further at run time
+                if body_type == ATOM_TYPE_ATOM || has_untyped_op(self.space, body) {
+                    return vec![(head_type.clone(), bindings)];
+                }
+                let unified = unify_types(&body_type, &head_type, &bindings);
+                if unified.is_empty() {
+                    self.report(|| format!("{} has type {} but {} should have type {}",
+                        body, display_type(&resolve_type(&body_type, &bindings)), head,
+                        display_type(&resolve_type(&head_type, &bindings))));
+                }
+                unified.into_iter().map(|bindings| (body_type.clone(), bindings)).collect::<Vec<_>>()
+            }).collect::<Vec<_>>()
+        }).collect()
+    }
+
+    fn infer_atom(&mut self, atom: &Atom) -> Vec<(Atom, Bindings)> {
+        match split_rule(atom) {
+            Some((head, body)) => self.infer_rule(head, body),
+            None => self.infer(atom, Bindings::new()),
+        }
+    }
+
+    fn into_result<T>(self, atom: &Atom, result: Vec<T>) -> Result<Vec<T>, String> {
+        if result.is_empty() {
+            Err(self.conflict.unwrap_or_else(|| format!("No consistent types found for {}", atom)))
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+/// Returns true when `atom` is an expression which operation has no type,
+/// the type of such expression is not known until it is evaluated
+pub(crate) fn has_untyped_op(space: &dyn Space, atom: &Atom) -> bool {
+    match atom {
+        Atom::Expression(expr) => expr.children().first()
+            .is_some_and(|op| get_atom_types(space, op) == vec![ATOM_TYPE_UNDEFINED]),
+        _ => false,
+    }
+}
+
+fn atom_op(atom: &Atom) -> &Atom {
+    match atom {
+        Atom::Expression(expr) => get_op(expr),
+        _ => atom,
+    }
+}
+
+fn is_meta_type(typ: &Atom) -> bool {
+    *typ == ATOM_TYPE_ATOM || *typ == ATOM_TYPE_SYMBOL || *typ == ATOM_TYPE_VARIABLE
+        || *typ == ATOM_TYPE_GROUNDED || *typ == ATOM_TYPE_EXPRESSION
+}
+
+fn split_rule(atom: &Atom) -> Option<(&Atom, &Atom)> {
+    match atom {
+        Atom::Expression(expr) => match expr.children().as_slice() {
+            [op, head, body] if *op == EQUAL_SYMBOL => Some((head, body)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn unify_types(actual: &Atom, expected: &Atom, bindings: &Bindings) -> Vec<Bindings> {
+    let actual = apply_bindings_to_atom(actual, bindings);
+    let expected = apply_bindings_to_atom(expected, bindings);
+    match_reducted_types_v2(&actual, &expected)
+        .flat_map(|unified| unified.merge_v2(bindings))
+        .collect()
+}
+
+fn resolve_type(typ: &Atom, bindings: &Bindings) -> Atom {
+    let mut typ = apply_bindings_to_atom(typ, bindings);
+    let undefined = Atom::gnd(UndefinedTypeMatch{});
+    typ.iter_mut().filter(|atom| **atom == undefined)
+        .for_each(|atom| *atom = ATOM_TYPE_UNDEFINED);
+    typ
+}
+
+/// Infers types of the variables of the `atom` in context of the `space`.
+/// Each variable is typed using the signatures of the functions it is passed
+/// into. When `atom` is an `=` rule the type of the body should also be
+/// consistent with the type of the head. Returns a list of alternative
+/// typings, each one binds all variables of the `atom` to their types.
+/// Variable which is not constrained has `%Undefined%` type, variables which
+/// should have the same yet unknown type are bound to the same type variable.
+/// Returns an explanation of the first conflict found when no consistent
+/// typing exists.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::{expr, bind};
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::types::infer_var_types;
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("(: inc (-> N N)) (: neg (-> B B))")).unwrap();
+/// let space = metta.space();
+///
+/// assert_eq!(infer_var_types(&space, &expr!("=" ("f" x) ("inc" x))),
+///     Ok(vec![bind!{ x: expr!("N") }]));
+/// assert_eq!(infer_var_types(&space, &expr!("=" ("f" x) ("inc" ("neg" x)))),
+///     Err("(neg $x) has type B but argument 1 of (inc (neg $x)) should have type N".into()));
+/// ```
+pub fn infer_var_types(space: &dyn Space, atom: &Atom) -> Result<Vec<Bindings>, String> {
+    let mut inference = TypeInference::new(space);
+    let mut vars: Vec<&VariableAtom> = Vec::new();
+    atom.iter().filter_type::<&VariableAtom>()
+        .for_each(|var| if !vars.contains(&var) { vars.push(var) });
+    let mut result: Vec<Bindings> = Vec::new();
+    for (_typ, bindings) in inference.infer_atom(atom) {
+        let mut typing = Bindings::new();
+        for var in &vars {
+            let typ = match bindings.resolve(var) {
+                Some(typ) => resolve_type(&typ, &bindings),
+                None => ATOM_TYPE_UNDEFINED,
+            };
+            typing = typing.add_var_binding_v2((*var).clone(), typ)
+                .expect("Each variable is bound once");
+        }
+        if !result.contains(&typing) {
+            result.push(typing);
+        }
+    }
+    inference.into_result(atom, result)
+}
+
+/// Returns types of the `atom` in context of the `space` using types of its
+/// variables inferred by [infer_var_types]. When `atom` is an `=` rule with
+/// the head `(f a1 ... an)` the function type `(-> A1 ... An R)` of the rule
+/// is returned, here `Ai` is a type of the `ai` argument and `R` is a type of
+/// the body. Types which cannot be inferred are replaced by `%Undefined%`, thus
+/// returned types don't contain type variables. Returns an explanation of the
+/// conflict when variables cannot be typed consistently.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::metta::runner::*;
+/// use hyperon::metta::text::SExprParser;
+/// use hyperon::metta::types::get_inferred_types;
+///
+/// let metta = Metta::new(None);
+/// metta.run(SExprParser::new("(: inc (-> N N)) (: pair (-> $a $b (Pair $a $b)))")).unwrap();
+/// let space = metta.space();
+///
+/// assert_eq!(get_inferred_types(&space, &expr!("=" ("f" x) ("inc" x))),
+///     Ok(vec![expr!("->" "N" "N")]));
+/// assert_eq!(get_inferred_types(&space, &expr!("pair" x ("inc" x))),
+///     Ok(vec![expr!("Pair" "N" "N")]));
+/// assert_eq!(get_inferred_types(&space, &expr!("=" ("g" x) ("pair" x x))),
+///     Ok(vec![expr!("->" "%Undefined%" ("Pair" "%Undefined%" "%Undefined%"))]));
+/// ```
+pub fn get_inferred_types(space: &dyn Space, atom: &Atom) -> Result<Vec<Atom>, String> {
+    let mut inference = TypeInference::new(space);
+    let mut result: Vec<Atom> = Vec::new();
+    let mut push_type = |mut typ: Atom| {
+        typ.iter_mut().filter(|atom| matches!(atom, Atom::Variable(_)))
+            .for_each(|atom| *atom = ATOM_TYPE_UNDEFINED);
+        if !result.contains(&typ) {
+            result.push(typ)
+        }
+    };
+    match split_rule(atom) {
+        Some((Atom::Expression(head), body)) if !head.children().is_empty() => {
+            let head_atom = Atom::Expression(head.clone());
+            for (ret_type, bindings) in inference.infer_rule(&head_atom, body) {
+                let mut states = vec![(vec![ARROW_SYMBOL], bindings)];
+                for arg in get_args(head) {
+                    states = states.into_iter().flat_map(|(prefix, bindings)| {
+                        inference.infer(arg, bindings).into_iter().map(move |(typ, bindings)| {
+                            let mut prefix = prefix.clone();
+                            prefix.push(typ);
+                            (prefix, bindings)
+                        })
+                    }).collect();
+                }
+                for (mut fn_type, bindings) in states {
+                    fn_type.push(ret_type.clone());
+                    push_type(resolve_type(&Atom::expr(fn_type), &bindings));
+                }
+            }
+        },
+        _ => inference.infer_atom(atom).into_iter()
+            .for_each(|(typ, bindings)| push_type(resolve_type(&typ, &bindings))),
+    }
+    inference.into_result(atom, result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -915,6 +1673,17 @@ mod tests {
         assert_eq!(get_atom_types(&space, &atom("(a b)")), vec![]);
     }
 
+    #[test]
+    fn get_atom_types_function_call_checks_number_of_arguments() {
+        let space = metta_space("
+            (: f (-> Atom B))
+            (: f (-> Atom Atom C))
+        ");
+        assert_eq!(get_atom_types(&space, &atom("(f a)")), vec![atom("B")]);
+        assert_eq!(get_atom_types(&space, &atom("(f a b)")), vec![atom("C")]);
+        assert_eq!(get_atom_types(&space, &atom("(f a b c)")), vec![]);
+    }
+
     #[test]
     fn get_atom_types_function_call_parameterized_types() {
         let space = metta_space("
@@ -1130,4 +1899,54 @@ mod tests {
         assert!(validate_atom(&space, &atom("(varF (varR a))")));
         assert!(!validate_atom(&space, &atom("(varF (atomR a))")));
     }
+
+    #[test]
+    fn infer_var_types_from_usage() {
+        let space = metta_space("
+            (: inc (-> Number Number))
+            (: == (-> $t $t Bool))
+            (: if (-> Bool Atom Atom $t))
+        ");
+
+        assert_eq!(infer_var_types(&space, &atom("(= (f $x) (inc $x))")),
+            Ok(vec![bind!{ x: sym!("Number") }]));
+        let typings = infer_var_types(&space, &atom("(= (g $x $y) (== $x $y))")).unwrap();
+        assert_eq!(typings.len(), 1);
+        assert_eq!(typings[0].resolve(&VariableAtom::new("x")), typings[0].resolve(&VariableAtom::new("y")));
+        assert_eq!(infer_var_types(&space, &atom("(= (h $x $y) (if (== $x 0) (inc $y) $y))")),
+            Ok(vec![bind!{ x: sym!("Number"), y: sym!("Number") }]));
+        assert_eq!(infer_var_types(&space, &atom("(= (k $x) $x)")),
+            Ok(vec![bind!{ x: ATOM_TYPE_UNDEFINED }]));
+    }
+
+    #[test]
+    fn infer_var_types_conflict() {
+        let space = metta_space("
+            (: inc (-> Number Number))
+            (: not (-> Bool Bool))
+            (: f (-> Bool Number))
+        ");
+
+        assert_eq!(infer_var_types(&space, &atom("(= (g $x) (inc (not $x)))")),
+            Err("(not $x) has type Bool but argument 1 of (inc (not $x)) should have type Number".into()));
+        assert_eq!(infer_var_types(&space, &atom("(= (g $x) (inc $x $x))")),
+            Err("(inc $x $x) has 2 arguments but type (-> Number Number) of inc expects 1".into()));
+        assert_eq!(infer_var_types(&space, &atom("(= (f $x) (not $x))")),
+            Err("(not $x) has type Bool but (f $x) should have type Number".into()));
+    }
+
+    #[test]
+    fn get_inferred_types_of_rule() {
+        let space = metta_space("
+            (: inc (-> Number Number))
+            (: pair (-> $a $b (Pair $a $b)))
+        ");
+
+        assert_eq!(get_inferred_types(&space, &atom("(= (f $x) (inc $x))")),
+            Ok(vec![expr!("->" "Number" "Number")]));
+        assert_eq!(get_inferred_types(&space, &atom("(= (f $x $y) (pair $y (inc $x)))")),
+            Ok(vec![expr!("->" "Number" "%Undefined%" ("Pair" "%Undefined%" "Number"))]));
+        assert_eq!(get_inferred_types(&space, &atom("(pair $x (inc $x))")),
+            Ok(vec![expr!("Pair" "Number" "Number")]));
+    }
 }
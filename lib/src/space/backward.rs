@@ -0,0 +1,526 @@
+//! Goal-directed prover over the facts and rules of a space.
+//!
+//! The [Prover] uses the same representation of rules as [super::datalog]:
+//! a rule is an atom `(implies <body> <head>)` where the body is a single atom
+//! or several atoms glued by [COMMA_SYMBOL], all other atoms are facts. A goal
+//! is proven by matching it with a fact, or by matching it with the head of a
+//! rule and proving the body of the rule. Unlike saturation the prover derives
+//! only facts which are needed to prove the goal, and each solution is
+//! returned together with the [ProofTree] which explains it.
+//!
+//! The search is depth first with tabling. Answers of each subgoal are kept in
+//! a table shared by all subgoals which are equal up to the variable names, an
+//! answer is added to the table only once. A subgoal which is a variant of one
+//! of its ancestors doesn't start the new search: it consumes the answers the
+//! ancestor has found so far, and the ancestor repeats its evaluation until no
+//! new answers are found. Thus left recursive rules like
+//! `(implies (, (path $x $y) (edge $y $z)) (path $x $z))` terminate and return
+//! each answer once. Rules which construct new atoms (for instance
+//! `(implies (nat $x) (nat (S $x)))`) can have infinite number of answers, so
+//! the depth of the proof tree is limited as well.
+
+use super::*;
+use super::grounding::COMMA_SYMBOL;
+use super::datalog::IMPLIES_SYMBOL;
+use crate::atom::matcher::{match_atoms, atoms_are_equivalent};
+use crate::common::CachingMapper;
+use crate::sym;
+
+use std::collections::HashSet;
+
+/// Maximal depth of the proof tree by default
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// Explanation of the proven atom
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProofTree {
+    /// Atom is a fact of the space
+    Fact(Atom),
+    /// Atom is a head of the rule which body is proven by the premises
+    Rule {
+        conclusion: Atom,
+        rule: Atom,
+        premises: Vec<ProofTree>,
+    },
+}
+
+impl ProofTree {
+    /// Returns the atom proven by the tree
+    pub fn conclusion(&self) -> &Atom {
+        match self {
+            ProofTree::Fact(fact) => fact,
+            ProofTree::Rule{ conclusion, .. } => conclusion,
+        }
+    }
+
+    /// Represents the tree as an atom: a fact is represented as `(fact <atom>)`
+    /// and the rule application as `(rule <conclusion> <rule> (<premises>))`
+    pub fn to_atom(&self) -> Atom {
+        match self {
+            ProofTree::Fact(fact) => Atom::expr([sym!("fact"), fact.clone()]),
+            ProofTree::Rule{ conclusion, rule, premises } => Atom::expr([sym!("rule"),
+                conclusion.clone(), rule.clone(),
+                Atom::expr(premises.iter().map(ProofTree::to_atom).collect::<Vec<Atom>>())]),
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match self {
+            ProofTree::Fact(_) => 0,
+            ProofTree::Rule{ premises, .. } =>
+                1 + premises.iter().map(ProofTree::depth).max().unwrap_or(0),
+        }
+    }
+
+    fn map_conclusions<F: FnMut(&Atom) -> Atom>(self, f: &mut F) -> Self {
+        match self {
+            ProofTree::Fact(fact) => ProofTree::Fact(f(&fact)),
+            ProofTree::Rule{ conclusion, rule, premises } => ProofTree::Rule {
+                conclusion: f(&conclusion),
+                rule,
+                premises: premises.into_iter().map(|premise| premise.map_conclusions(f)).collect(),
+            },
+        }
+    }
+
+    fn instantiate(self, bindings: &Bindings) -> Self {
+        self.map_conclusions(&mut |atom| apply_bindings_to_atom(atom, bindings))
+    }
+
+    /// Replaces variables of the conclusions by the fresh ones
+    fn make_variables_unique(self) -> Self {
+        let mut mapper = CachingMapper::new(VariableAtom::make_unique);
+        self.map_conclusions(&mut |atom| {
+            let mut atom = atom.clone();
+            atom.iter_mut().filter_type::<&mut VariableAtom>().for_each(|var| *var = mapper.replace(var.clone()));
+            atom
+        })
+    }
+
+    fn fmt_indent(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        match self {
+            ProofTree::Fact(fact) => writeln!(f, "{:indent$}{} is a fact", "", fact),
+            ProofTree::Rule{ conclusion, rule, premises } => {
+                writeln!(f, "{:indent$}{} by {}", "", conclusion, rule)?;
+                premises.iter().try_for_each(|premise| premise.fmt_indent(f, indent + 2))
+            },
+        }
+    }
+}
+
+impl Display for ProofTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indent(f, 0)
+    }
+}
+
+/// Solution found by the [Prover]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    /// Values of the goal variables
+    pub bindings: Bindings,
+    /// Proof trees of the goal atoms
+    pub proofs: Vec<ProofTree>,
+}
+
+struct Rule {
+    atom: Atom,
+    body: Vec<Atom>,
+    head: Atom,
+}
+
+impl Rule {
+    /// Returns the body and the head of the rule with the fresh variables
+    fn rename(&self) -> (Vec<Atom>, Atom) {
+        match make_variables_unique(Atom::expr([Atom::expr(self.body.clone()), self.head.clone()])) {
+            Atom::Expression(expr) => {
+                let mut children = expr.into_children().into_iter();
+                let body = match children.next() {
+                    Some(Atom::Expression(body)) => body.into_children(),
+                    _ => unreachable!(),
+                };
+                (body, children.next().unwrap())
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Splits the conjunction glued by [COMMA_SYMBOL] into separate atoms
+fn conjuncts(atom: Atom) -> Vec<Atom> {
+    match atom {
+        Atom::Expression(expr) if expr.children().first() == Some(&COMMA_SYMBOL) =>
+            expr.into_children().into_iter().skip(1).collect(),
+        atom => vec![atom],
+    }
+}
+
+/// Backward chaining prover, see [module documentation](self) for details.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::{expr, sym, bind};
+/// use hyperon::space::*;
+/// use hyperon::space::grounding::GroundingSpace;
+/// use hyperon::space::backward::{Prover, ProofTree};
+///
+/// let mut space = GroundingSpace::new();
+/// space.add(expr!("human" "Socrates"));
+/// space.add(expr!("implies" ("human" x) ("mortal" x)));
+///
+/// let solutions = Prover::from_space(&space).prove(&expr!("mortal" y));
+///
+/// assert_eq!(solutions.len(), 1);
+/// assert_eq!(solutions[0].bindings, bind!{ y: sym!("Socrates") });
+/// assert_eq!(solutions[0].proofs, vec![ProofTree::Rule {
+///     conclusion: expr!("mortal" "Socrates"),
+///     rule: expr!("implies" ("human" x) ("mortal" x)),
+///     premises: vec![ProofTree::Fact(expr!("human" "Socrates"))],
+/// }]);
+/// ```
+pub struct Prover {
+    facts: Vec<Atom>,
+    rules: Vec<Rule>,
+    max_depth: usize,
+}
+
+impl Prover {
+    /// Constructs the prover from the facts and rules of the `space`
+    pub fn from_space(space: &dyn Space) -> Self {
+        let mut prover = Self{ facts: Vec::new(), rules: Vec::new(), max_depth: DEFAULT_MAX_DEPTH };
+        match space.atom_iter() {
+            Some(atoms) => atoms.for_each(|atom| prover.add(atom.clone())),
+            None => {
+                let var = VariableAtom::new("atom");
+                for bindings in space.query(&Atom::Variable(var.clone())) {
+                    if let Some(atom) = bindings.resolve(&var) {
+                        prover.add(atom);
+                    }
+                }
+            },
+        }
+        prover
+    }
+
+    fn add(&mut self, atom: Atom) {
+        match atom {
+            Atom::Expression(ref expr) if expr.children().len() == 3
+                && expr.children()[0] == IMPLIES_SYMBOL => {
+                let body = conjuncts(expr.children()[1].clone());
+                let head = expr.children()[2].clone();
+                self.rules.push(Rule{ atom, body, head });
+            },
+            atom => self.facts.push(atom),
+        }
+    }
+
+    /// Sets the maximal depth of the proof tree, deeper subgoals are not proven
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Returns all solutions of the `goal` which is a single atom or several
+    /// atoms glued by [COMMA_SYMBOL]. Solutions with equal bindings are
+    /// returned once. The solutions are returned when the search is finished,
+    /// because answers of the recursive subgoal are not known to be complete
+    /// before its evaluation reaches the fixed point.
+    pub fn prove(&self, goal: &Atom) -> Vec<Solution> {
+        let goals = conjuncts(goal.clone());
+        let vars: HashSet<&VariableAtom> = goal.iter().filter_type::<&VariableAtom>().collect();
+        let mut solutions: Vec<Solution> = Vec::new();
+        for (bindings, proofs) in Search::new(self).prove_all(&goals, Bindings::new(), 0) {
+            let solution = Solution {
+                proofs: proofs.into_iter().map(|proof| proof.instantiate(&bindings)).collect(),
+                bindings: bindings.narrow_vars(&vars),
+            };
+            if !solutions.iter().any(|prev| prev.bindings == solution.bindings) {
+                solutions.push(solution);
+            }
+        }
+        solutions
+    }
+}
+
+/// Answers found for the goal
+struct Table {
+    goal: Atom,
+    answers: Vec<ProofTree>,
+    complete: bool,
+}
+
+/// Goal which is being evaluated
+struct Frame {
+    table: usize,
+    /// Lowest position of the stack frame which answers were consumed while
+    /// evaluating this frame, the frame is complete only when it is the
+    /// lowest one itself
+    leader: usize,
+    /// Subgoal is not evaluated because of the depth limit, thus answers may
+    /// be incomplete
+    cut: bool,
+    /// Number of the evaluated tables before the frame is started
+    evaluated: usize,
+}
+
+/// State of the single [Prover::prove] call
+struct Search<'a> {
+    prover: &'a Prover,
+    tables: Vec<Table>,
+    stack: Vec<Frame>,
+    evaluated: Vec<usize>,
+    added: usize,
+}
+
+impl<'a> Search<'a> {
+    fn new(prover: &'a Prover) -> Self {
+        Self{ prover, tables: Vec::new(), stack: Vec::new(), evaluated: Vec::new(), added: 0 }
+    }
+
+    fn prove_all(&mut self, goals: &[Atom], bindings: Bindings, depth: usize) -> Vec<(Bindings, Vec<ProofTree>)> {
+        let (goal, rest) = match goals.split_first() {
+            Some(split) => split,
+            None => return vec![(bindings, Vec::new())],
+        };
+        let mut results = Vec::new();
+        for (bindings, proof) in self.prove_one(goal, bindings, depth) {
+            for (bindings, mut proofs) in self.prove_all(rest, bindings, depth) {
+                proofs.insert(0, proof.clone());
+                results.push((bindings, proofs));
+            }
+        }
+        results
+    }
+
+    fn prove_one(&mut self, goal: &Atom, bindings: Bindings, depth: usize) -> Vec<(Bindings, ProofTree)> {
+        let goal = apply_bindings_to_atom(goal, &bindings);
+        let answers = match self.tables.iter().position(|table| atoms_are_equivalent(&table.goal, &goal)) {
+            Some(table) if self.tables[table].complete => self.tables[table].answers.clone(),
+            Some(table) if self.stack.iter().any(|frame| frame.table == table) => {
+                log::debug!("Search::prove_one: loop is detected: {}", goal);
+                let position = self.stack.iter().position(|frame| frame.table == table).unwrap();
+                self.stack[position..].iter_mut().for_each(|frame| frame.leader = frame.leader.min(position));
+                self.tables[table].answers.clone()
+            },
+            _ if depth >= self.prover.max_depth => {
+                log::debug!("Search::prove_one: max depth is reached: {}", goal);
+                self.stack.iter_mut().for_each(|frame| frame.cut = true);
+                self.facts(&goal)
+            },
+            Some(table) => self.evaluate(table),
+            None => {
+                self.tables.push(Table{ goal: goal.clone(), answers: Vec::new(), complete: false });
+                self.evaluate(self.tables.len() - 1)
+            },
+        };
+        let mut results = Vec::new();
+        for answer in answers {
+            let answer = answer.make_variables_unique();
+            for answer_bindings in match_atoms(&goal, answer.conclusion()) {
+                results.extend(merge(&bindings, &answer_bindings).into_iter()
+                    .map(|bindings| (bindings, answer.clone())));
+            }
+        }
+        results
+    }
+
+    /// Evaluates the goal of the `table` until no new answers are found
+    fn evaluate(&mut self, table: usize) -> Vec<ProofTree> {
+        let position = self.stack.len();
+        self.stack.push(Frame{ table, leader: position, cut: false, evaluated: self.evaluated.len() });
+        self.evaluated.push(table);
+        let prover = self.prover;
+        let goal = self.tables[table].goal.clone();
+        loop {
+            let added = self.added;
+            for answer in self.facts(&goal) {
+                self.add_answer(table, answer);
+            }
+            for rule in &prover.rules {
+                let (body, head) = rule.rename();
+                for bindings in match_atoms(&goal, &head).filter(|bindings| !bindings.has_loops()) {
+                    for (bindings, premises) in self.prove_all(&body, bindings, position + 1) {
+                        let answer = ProofTree::Rule{ conclusion: goal.clone(),
+                            rule: rule.atom.clone(), premises }.instantiate(&bindings);
+                        self.add_answer(table, answer);
+                    }
+                }
+            }
+            if self.added == added {
+                break;
+            }
+        }
+        let frame = self.stack.pop().unwrap();
+        if frame.leader == position {
+            // tables which were cut by the depth limit are evaluated again
+            // when they are met next time
+            for table in self.evaluated.drain(frame.evaluated..) {
+                self.tables[table].complete = !frame.cut;
+            }
+        } else if let Some(parent) = self.stack.last_mut() {
+            parent.leader = parent.leader.min(frame.leader);
+        }
+        self.tables[table].answers.clone()
+    }
+
+    fn facts(&self, goal: &Atom) -> Vec<ProofTree> {
+        let mut answers = Vec::new();
+        for fact in &self.prover.facts {
+            let fact = make_variables_unique(fact.clone());
+            for bindings in match_atoms(goal, &fact).filter(|bindings| !bindings.has_loops()) {
+                answers.push(ProofTree::Fact(goal.clone()).instantiate(&bindings));
+            }
+        }
+        answers
+    }
+
+    fn add_answer(&mut self, table: usize, answer: ProofTree) {
+        if answer.depth() > self.prover.max_depth {
+            return;
+        }
+        let answers = &mut self.tables[table].answers;
+        if !answers.iter().any(|prev| atoms_are_equivalent(prev.conclusion(), answer.conclusion())) {
+            answers.push(answer);
+            self.added += 1;
+        }
+    }
+}
+
+fn merge(prev: &Bindings, next: &Bindings) -> Vec<Bindings> {
+    prev.clone().merge_v2(next).into_iter().filter(|bindings| !bindings.has_loops()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use crate::metta::text::{SExprParser, Tokenizer};
+    use crate::space::grounding::GroundingSpace;
+
+    fn prover(text: &str) -> Prover {
+        let mut space = GroundingSpace::new();
+        let mut parser = SExprParser::new(text);
+        while let Some(atom) = parser.parse(&Tokenizer::new()).unwrap() {
+            space.add(atom);
+        }
+        Prover::from_space(&space)
+    }
+
+    fn conclusions(solutions: Vec<Solution>) -> Vec<Atom> {
+        solutions.into_iter().flat_map(|solution| solution.proofs)
+            .map(|proof| proof.conclusion().clone()).collect()
+    }
+
+    #[test]
+    fn prover_builds_proof_tree() {
+        let prover = prover("
+            (parent Tom Bob) (parent Bob Ann)
+            (implies (parent $x $y) (ancestor $x $y))
+            (implies (, (parent $x $y) (ancestor $y $z)) (ancestor $x $z))
+        ");
+
+        let solutions = prover.prove(&expr!("ancestor" "Tom" "Ann"));
+
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].proofs, vec![ProofTree::Rule {
+            conclusion: expr!("ancestor" "Tom" "Ann"),
+            rule: expr!("implies" ("," ("parent" x y) ("ancestor" y z)) ("ancestor" x z)),
+            premises: vec![
+                ProofTree::Fact(expr!("parent" "Tom" "Bob")),
+                ProofTree::Rule {
+                    conclusion: expr!("ancestor" "Bob" "Ann"),
+                    rule: expr!("implies" ("parent" x y) ("ancestor" x y)),
+                    premises: vec![ProofTree::Fact(expr!("parent" "Bob" "Ann"))],
+                },
+            ],
+        }]);
+        assert_eq!(solutions[0].proofs[0].to_string(), concat!(
+            "(ancestor Tom Ann) by (implies (, (parent $x $y) (ancestor $y $z)) (ancestor $x $z))\n",
+            "  (parent Tom Bob) is a fact\n",
+            "  (ancestor Bob Ann) by (implies (parent $x $y) (ancestor $x $y))\n",
+            "    (parent Bob Ann) is a fact\n"));
+    }
+
+    #[test]
+    fn prover_returns_goal_bindings() {
+        let prover = prover("
+            (parent Tom Bob) (parent Bob Ann)
+            (implies (parent $x $y) (ancestor $x $y))
+            (implies (, (parent $x $y) (ancestor $y $z)) (ancestor $x $z))
+        ");
+
+        let actual: Vec<Bindings> = prover.prove(&expr!("ancestor" "Tom" w)).into_iter()
+            .map(|solution| solution.bindings).collect();
+
+        assert_eq_no_order!(actual, vec![bind!{w: sym!("Bob")}, bind!{w: sym!("Ann")}]);
+    }
+
+    #[test]
+    fn prover_proves_conjunction() {
+        let prover = prover("
+            (human Socrates) (human Plato) (teacher Socrates Plato)
+            (implies (human $x) (mortal $x))
+        ");
+
+        let solutions = prover.prove(&expr!("," ("teacher" t s) ("mortal" s)));
+
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].bindings, bind!{t: sym!("Socrates"), s: sym!("Plato")});
+        assert_eq!(conclusions(solutions), vec![expr!("teacher" "Socrates" "Plato"), expr!("mortal" "Plato")]);
+    }
+
+    #[test]
+    fn prover_skips_loops() {
+        let graph = prover("
+            (edge a b) (edge b a)
+            (implies (, (path $x $y) (edge $y $z)) (path $x $z))
+            (implies (edge $x $y) (path $x $y))
+        ");
+
+        let actual = conclusions(graph.prove(&expr!("path" "a" w)));
+        assert_eq_no_order!(actual, vec![expr!("path" "a" "b"), expr!("path" "a" "a")]);
+        assert!(graph.prove(&expr!("path" "a" "c")).is_empty());
+
+        let symmetric = prover("
+            (sibling Ann Bob)
+            (implies (sibling $x $y) (sibling $y $x))
+        ");
+        let solutions = symmetric.prove(&expr!("sibling" "Bob" "Ann"));
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].proofs, vec![ProofTree::Rule {
+            conclusion: expr!("sibling" "Bob" "Ann"),
+            rule: expr!("implies" ("sibling" x y) ("sibling" y x)),
+            premises: vec![ProofTree::Fact(expr!("sibling" "Ann" "Bob"))],
+        }]);
+    }
+
+    #[test]
+    fn prover_returns_each_answer_once() {
+        let graph = prover("
+            (edge a b) (edge b c) (edge c d) (edge d a) (edge b d) (edge c a) (edge d b)
+            (implies (, (path $x $y) (edge $y $z)) (path $x $z))
+            (implies (, (edge $x $y) (path $y $z)) (path $x $z))
+            (implies (edge $x $y) (path $x $y))
+        ");
+
+        let actual: Vec<Bindings> = graph.prove(&expr!("path" "a" w)).into_iter()
+            .map(|solution| solution.bindings).collect();
+        assert_eq_no_order!(actual, vec![bind!{w: sym!("a")}, bind!{w: sym!("b")},
+            bind!{w: sym!("c")}, bind!{w: sym!("d")}]);
+        assert_eq!(graph.prove(&expr!("path" x y)).len(), 16);
+        assert_eq!(graph.prove(&expr!("," ("path" "a" x) ("path" x "a"))).len(), 4);
+    }
+
+    #[test]
+    fn prover_max_depth() {
+        let mut prover = prover("
+            (nat Z)
+            (implies (nat $x) (nat (S $x)))
+        ");
+        prover.set_max_depth(2);
+
+        assert_eq!(conclusions(prover.prove(&expr!("nat" n))),
+            vec![expr!("nat" "Z"), expr!("nat" ("S" "Z")), expr!("nat" ("S" ("S" "Z")))]);
+        assert!(prover.prove(&expr!("nat" ("S" ("S" ("S" "Z"))))).is_empty());
+    }
+}
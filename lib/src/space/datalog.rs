@@ -0,0 +1,313 @@
+//! Bottom-up evaluation of relational rules kept in a space.
+//!
+//! Rules are atoms `(implies <body> <head>)` where the body is a single atom
+//! or several atoms glued by [COMMA_SYMBOL]. All other atoms of the space
+//! except `=` definitions are facts. [saturate] derives all facts which follow
+//! from the rules and adds them into the space, thus the space contains the
+//! least fixpoint of the rules after it returns. Atoms with `=` are not
+//! interpreted, bodies of the rules are matched only against facts, not
+//! against rules or `=` definitions.
+//!
+//! The fixpoint is computed by semi-naive evaluation: after the first
+//! iteration each rule is applied only to the combinations of facts which
+//! include at least one fact derived by the previous iteration. Rules should
+//! be range restricted, i.e. each variable of the head should appear in the
+//! body, and facts should not contain variables, otherwise a derived fact is
+//! not ground and the evaluation returns an error.
+
+use super::*;
+use super::grounding::{GroundingSpace, COMMA_SYMBOL};
+use super::forward::contains;
+use crate::sym;
+use crate::metta::EQUAL_SYMBOL;
+
+/// Symbol which is used to represent the rule
+pub const IMPLIES_SYMBOL: Atom = sym!("implies");
+
+/// Maximal number of facts derived by [saturate] by default
+pub const DEFAULT_MAX_DERIVED: usize = 1000000;
+
+struct Rule {
+    body: Vec<Atom>,
+    head: Atom,
+}
+
+impl Rule {
+    fn from_atoms(body: Atom, head: Atom) -> Result<Self, String> {
+        let body = match body {
+            Atom::Expression(expr) if expr.children().first() == Some(&COMMA_SYMBOL) =>
+                expr.into_children().into_iter().skip(1).collect(),
+            body => vec![body],
+        };
+        let body_vars: Vec<&VariableAtom> = body.iter()
+            .flat_map(|atom| atom.iter().filter_type::<&VariableAtom>()).collect();
+        let unbound = head.iter().filter_type::<&VariableAtom>()
+            .find(|var| !body_vars.contains(var)).cloned();
+        match unbound {
+            Some(var) => Err(format!("Variable {} of the rule head {} is not found in the body", var, head)),
+            None => Ok(Self{ body, head }),
+        }
+    }
+
+    fn body_query(atoms: Vec<Atom>) -> Atom {
+        let mut query = vec![COMMA_SYMBOL];
+        query.extend(atoms);
+        Atom::expr(query)
+    }
+
+    /// Returns true when `atoms` instantiated by the `bindings` are facts
+    fn matches_facts(atoms: &[Atom], bindings: &Bindings) -> bool {
+        atoms.iter().all(|atom| is_fact(&apply_bindings_to_atom(atom, bindings)))
+    }
+
+    /// Returns heads of the rule for all matches of the body in the `space`
+    fn apply(&self, space: &dyn Space) -> Vec<Atom> {
+        space.query(&Self::body_query(self.body.clone())).into_iter()
+            .filter(|bindings| Self::matches_facts(&self.body, bindings))
+            .map(|bindings| apply_bindings_to_atom(&self.head, &bindings))
+            .collect()
+    }
+
+    /// Returns heads of the rule for all matches of the body in the `space`
+    /// which match at least one body atom with the atom of the `delta`
+    fn apply_delta(&self, space: &dyn Space, delta: &GroundingSpace) -> Vec<Atom> {
+        let mut heads = Vec::new();
+        for (i, atom) in self.body.iter().enumerate() {
+            for bindings in delta.query(atom) {
+                if !Self::matches_facts(std::slice::from_ref(atom), &bindings) {
+                    continue;
+                }
+                let rest: Vec<Atom> = self.body.iter().enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, atom)| apply_bindings_to_atom(atom, &bindings))
+                    .collect();
+                let head = apply_bindings_to_atom(&self.head, &bindings);
+                if rest.is_empty() {
+                    heads.push(head);
+                } else {
+                    for rest_bindings in space.query(&Self::body_query(rest.clone())) {
+                        if Self::matches_facts(&rest, &rest_bindings) {
+                            heads.push(apply_bindings_to_atom(&head, &rest_bindings));
+                        }
+                    }
+                }
+            }
+        }
+        heads
+    }
+}
+
+/// Returns false for the rules and `=` definitions
+fn is_fact(atom: &Atom) -> bool {
+    match atom {
+        Atom::Expression(expr) => match expr.children().as_slice() {
+            [op, _, _] => *op != IMPLIES_SYMBOL && *op != EQUAL_SYMBOL,
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+/// Computes the least fixpoint of the rules kept in the space, see [module
+/// documentation](self) for details.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::{expr, sym};
+/// use hyperon::metta::text::{SExprParser, Tokenizer};
+/// use hyperon::space::*;
+/// use hyperon::space::grounding::GroundingSpace;
+/// use hyperon::space::datalog::Saturation;
+///
+/// let mut space = GroundingSpace::new();
+/// let mut parser = SExprParser::new("
+///     (edge a b) (edge b c) (edge c d)
+///     (implies (edge $x $y) (path $x $y))
+///     (implies (, (edge $x $y) (path $y $z)) (path $x $z))
+/// ");
+/// while let Some(atom) = parser.parse(&Tokenizer::new()).unwrap() {
+///     space.add(atom);
+/// }
+///
+/// let derived = Saturation::from_space(&space).unwrap().run(&mut space).unwrap();
+///
+/// assert_eq!(derived, 6);
+/// assert_eq!(space.query(&expr!("path" "a" x)).len(), 3);
+/// ```
+pub struct Saturation {
+    rules: Vec<Rule>,
+    max_derived: usize,
+}
+
+impl Saturation {
+    /// Collects the rules of the `space`
+    pub fn from_space(space: &dyn Space) -> Result<Self, String> {
+        let body = VariableAtom::new("body");
+        let head = VariableAtom::new("head");
+        let query = Atom::expr([IMPLIES_SYMBOL, Atom::Variable(body.clone()), Atom::Variable(head.clone())]);
+        let rules = space.query(&query).into_iter()
+            .map(|bindings| match (bindings.resolve(&body), bindings.resolve(&head)) {
+                (Some(body), Some(head)) => Rule::from_atoms(body, head),
+                _ => Err(format!("Incorrect rule is found: {}", apply_bindings_to_atom(&query, &bindings))),
+            })
+            .collect::<Result<Vec<Rule>, String>>()?;
+        Ok(Self{ rules, max_derived: DEFAULT_MAX_DERIVED })
+    }
+
+    /// Sets the maximal number of derived facts, evaluation returns an error
+    /// when it is exceeded
+    pub fn set_max_derived(&mut self, max_derived: usize) {
+        self.max_derived = max_derived;
+    }
+
+    /// Derives facts from the facts of the `space` and adds them into the
+    /// `space`. Returns the number of added facts.
+    pub fn run(&self, space: &mut dyn SpaceMut) -> Result<usize, String> {
+        let mut derived = 0;
+        let heads: Vec<Atom> = self.rules.iter()
+            .flat_map(|rule| rule.apply(space.as_space()))
+            .collect();
+        let mut delta = self.new_facts(space.as_space(), heads)?;
+        while !delta.is_empty() {
+            derived += delta.len();
+            if derived > self.max_derived {
+                return Err(format!("Saturation is stopped after deriving {} facts", self.max_derived));
+            }
+            log::debug!("Saturation::run: new facts: {}", delta.len());
+            for atom in &delta {
+                space.add(atom.clone());
+            }
+            let delta_space = GroundingSpace::from_vec(delta);
+            let heads: Vec<Atom> = self.rules.iter()
+                .flat_map(|rule| rule.apply_delta(space.as_space(), &delta_space))
+                .collect();
+            delta = self.new_facts(space.as_space(), heads)?;
+        }
+        Ok(derived)
+    }
+
+    /// Returns the facts which are not in the `space` without duplicates
+    fn new_facts(&self, space: &dyn Space, heads: Vec<Atom>) -> Result<Vec<Atom>, String> {
+        let mut index = GroundingSpace::new();
+        let mut facts = Vec::new();
+        for head in heads {
+            if head.iter().filter_type::<&VariableAtom>().next().is_some() {
+                return Err(format!("Derived fact {} is not ground", head));
+            }
+            if !contains(space, &head) && !index.contains(&head) {
+                index.add(head.clone());
+                facts.push(head);
+            }
+        }
+        Ok(facts)
+    }
+}
+
+/// Computes the least fixpoint of the rules kept in the `space` and adds the
+/// derived facts into the `space`. Returns the number of added facts.
+pub fn saturate(space: &mut dyn SpaceMut) -> Result<usize, String> {
+    Saturation::from_space(space.as_space())?.run(space)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use crate::metta::text::{SExprParser, Tokenizer};
+
+    fn space(text: &str) -> GroundingSpace {
+        let mut space = GroundingSpace::new();
+        let mut parser = SExprParser::new(text);
+        while let Some(atom) = parser.parse(&Tokenizer::new()).unwrap() {
+            space.add(atom);
+        }
+        space
+    }
+
+    #[test]
+    fn saturate_transitive_closure_with_cycle() {
+        let mut space = space("
+            (edge a b) (edge b c) (edge c a) (edge c d)
+            (implies (edge $x $y) (path $x $y))
+            (implies (, (path $x $y) (path $y $z)) (path $x $z))
+        ");
+
+        assert_eq!(saturate(&mut space), Ok(12));
+
+        let actual = space.query(&expr!("path" "a" x));
+        assert_eq_no_order!(actual, vec![bind!{x: sym!("a")}, bind!{x: sym!("b")},
+            bind!{x: sym!("c")}, bind!{x: sym!("d")}]);
+        assert_eq!(space.query(&expr!("path" "d" x)), BindingsSet::empty());
+        assert_eq!(saturate(&mut space), Ok(0));
+    }
+
+    #[test]
+    fn saturate_does_not_add_existing_facts() {
+        let mut space = space("
+            (human Socrates) (mortal Socrates) (human Plato)
+            (implies (human $x) (mortal $x))
+        ");
+
+        assert_eq!(saturate(&mut space), Ok(1));
+        assert_eq!(space.query(&expr!("mortal" "Socrates")).len(), 1);
+        assert_eq!(space.atom_count(), Some(5));
+    }
+
+    #[test]
+    fn saturate_matches_rule_bodies_only_against_facts() {
+        let mut space = space("
+            (edge a b)
+            (= (edge c d) (edge a b))
+            (implies ($rel $x $y) (related $x $y))
+        ");
+
+        assert_eq!(saturate(&mut space), Ok(1));
+        assert_eq!(space.query(&expr!("related" x y)), bind_set![bind!{x: sym!("a"), y: sym!("b")}]);
+    }
+
+    #[test]
+    fn saturate_adds_fact_which_matches_existing_non_ground_atom() {
+        let mut space = space("
+            (path a $z)
+            (edge a b)
+            (implies (edge $x $y) (path $x $y))
+        ");
+
+        assert_eq!(saturate(&mut space), Ok(1));
+        assert_eq!(space.atom_count(), Some(4));
+    }
+
+    #[test]
+    fn saturate_non_range_restricted_rule() {
+        let mut space = space("
+            (human Socrates)
+            (implies (human $x) (likes $x $y))
+        ");
+
+        assert!(saturate(&mut space).unwrap_err().contains("is not found in the body"));
+    }
+
+    #[test]
+    fn saturate_non_ground_fact() {
+        let mut space = space("
+            (human $x)
+            (implies (human $x) (mortal $x))
+        ");
+
+        assert!(saturate(&mut space).unwrap_err().contains("is not ground"));
+    }
+
+    #[test]
+    fn saturate_stops_after_max_derived() {
+        let mut space = space("
+            (nat Z)
+            (implies (nat $x) (nat (S $x)))
+        ");
+        let mut saturation = Saturation::from_space(&space).unwrap();
+        saturation.set_max_derived(10);
+
+        assert_eq!(saturation.run(&mut space), Err("Saturation is stopped after deriving 10 facts".into()));
+        assert_eq!(space.atom_count(), Some(12));
+    }
+}
@@ -0,0 +1,509 @@
+//! Forward chaining rules which are applied to the atoms added into a space.
+//!
+//! Each rule contains a pattern and an action: when an atom which matches the
+//! pattern is added into the space the template of the action instantiated by
+//! the match is added into the space or removed from it. A pattern may consist
+//! of sub-queries glued by [COMMA_SYMBOL], such rule is fired when the added
+//! atom matches one of the sub-queries and the rest of them are found in the
+//! space.
+//!
+//! Rules can be registered on any space by [add_rule]. The rules receive the
+//! added atoms through the [SpaceObserver] mechanism, but observers cannot
+//! modify the space while it is modified, thus the rules are fired by
+//! [fire_rules] or [fire_pending_rules] after the modification. The MeTTa
+//! runner fires pending rules after each step of the interpretation and after
+//! each atom added by a program. [ForwardChainingSpace] fires its rules after
+//! each modification.
+//!
+//! Atoms added by rules fire rules in turn. Rules are applied until no new
+//! atoms are added: an atom which is already in the space is not added again,
+//! so recursive rules reach the fixpoint. The number of rule firings after
+//! a single modification is limited to stop the rules which never reach the
+//! fixpoint, exceeding the limit is reported as an error.
+
+use super::*;
+use super::grounding::{GroundingSpace, COMMA_SYMBOL};
+use crate::atom::matcher::match_atoms;
+
+use std::fmt::Debug;
+use std::rc::{Rc, Weak};
+
+/// Maximal number of rule firings caused by a single modification of the space by default
+pub const DEFAULT_MAX_FIRINGS: usize = 10000;
+
+/// Action executed when the rule is fired
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleAction {
+    /// Add the instantiated template into the space
+    Add(Atom),
+    /// Remove the instantiated template from the space
+    Remove(Atom),
+}
+
+impl RuleAction {
+    fn template(&self) -> &Atom {
+        match self {
+            RuleAction::Add(template) | RuleAction::Remove(template) => template,
+        }
+    }
+
+    fn instantiate(&self, bindings: &Bindings) -> Self {
+        let template = apply_bindings_to_atom(self.template(), bindings);
+        match self {
+            RuleAction::Add(_) => RuleAction::Add(template),
+            RuleAction::Remove(_) => RuleAction::Remove(template),
+        }
+    }
+}
+
+struct Rule {
+    conjuncts: Vec<Atom>,
+    action: RuleAction,
+}
+
+impl Rule {
+    fn new(pattern: Atom, action: RuleAction) -> Self {
+        // Variables are renamed to not intersect with variables of the added atoms
+        let (pattern, action) = match make_variables_unique(Atom::expr([pattern, action.template().clone()])) {
+            Atom::Expression(expr) => {
+                let mut children = expr.into_children().into_iter();
+                let pattern = children.next().unwrap();
+                let template = children.next().unwrap();
+                let action = match action {
+                    RuleAction::Add(_) => RuleAction::Add(template),
+                    RuleAction::Remove(_) => RuleAction::Remove(template),
+                };
+                (pattern, action)
+            },
+            _ => unreachable!(),
+        };
+        let conjuncts = match pattern {
+            Atom::Expression(expr) if expr.children().first() == Some(&COMMA_SYMBOL) =>
+                expr.into_children().into_iter().skip(1).collect(),
+            pattern => vec![pattern],
+        };
+        Self{ conjuncts, action }
+    }
+
+    /// Returns actions fired by adding `atom` into the `space`
+    fn fire(&self, atom: &Atom, space: &dyn Space) -> Vec<RuleAction> {
+        let mut actions = Vec::new();
+        for (i, conjunct) in self.conjuncts.iter().enumerate() {
+            for bindings in match_atoms(atom, conjunct) {
+                let rest: Vec<Atom> = self.conjuncts.iter().enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, conjunct)| apply_bindings_to_atom(conjunct, &bindings))
+                    .collect();
+                let action = self.action.instantiate(&bindings);
+                if rest.is_empty() {
+                    actions.push(action);
+                } else {
+                    let mut query = vec![COMMA_SYMBOL];
+                    query.extend(rest);
+                    for rest_bindings in space.query(&Atom::expr(query)) {
+                        actions.push(action.instantiate(&rest_bindings));
+                    }
+                }
+            }
+        }
+        actions
+    }
+}
+
+/// Collects atoms added into the space
+#[derive(Default)]
+struct AddedAtoms(Vec<Atom>);
+
+impl SpaceObserver for AddedAtoms {
+    fn notify(&mut self, event: &SpaceEvent) {
+        match event {
+            SpaceEvent::Add(atom) | SpaceEvent::Replace(_, atom) => self.0.push(atom.clone()),
+            SpaceEvent::Remove(_) => {},
+        }
+    }
+}
+
+/// Returns true when the `space` contains the `atom` itself, not an atom
+/// which matches it
+pub(super) fn contains(space: &dyn Space, atom: &Atom) -> bool {
+    if let Some(space) = space.as_any().and_then(|space| space.downcast_ref::<GroundingSpace>()) {
+        return space.contains(atom);
+    }
+    match space.atom_iter() {
+        Some(mut atoms) => atoms.any(|other| other == atom),
+        None => space.query(atom).iter().any(|bindings| bindings.is_empty()),
+    }
+}
+
+/// Forward chaining rules registered on a space, see [module
+/// documentation](self) for details.
+pub struct ForwardRules {
+    rules: Vec<Rule>,
+    added: SpaceObserverRef<AddedAtoms>,
+    max_firings: usize,
+    /// Error of the firing which could not be returned to the caller
+    error: Option<String>,
+}
+
+impl ForwardRules {
+    fn new(common: &SpaceCommon) -> Self {
+        let added = common.register_observer(AddedAtoms::default());
+        Self{ rules: Vec::new(), added, max_firings: DEFAULT_MAX_FIRINGS, error: None }
+    }
+
+    /// Returns the rules registered on the `space`, registers the rules of
+    /// the [ForwardChainingSpace] or the empty set of rules when there are no
+    /// rules yet
+    fn of(space: &DynSpace) -> Rc<RefCell<Self>> {
+        if let Some(rules) = Self::registered(space) {
+            return rules;
+        }
+        let rules = Self::of_forward_chaining_space(space)
+            .unwrap_or_else(|| Rc::new(RefCell::new(Self::new(&space.common()))));
+        RULES.with(|registry| registry.borrow_mut().push((Rc::downgrade(&space.0), rules.clone())));
+        rules
+    }
+
+    /// Returns the rules registered on the `space` or the rules of the
+    /// [ForwardChainingSpace]
+    fn registered(space: &DynSpace) -> Option<Rc<RefCell<Self>>> {
+        let rules = RULES.with(|registry| registry.borrow().iter()
+            .find(|(other, _)| std::ptr::addr_eq(other.as_ptr(), Rc::as_ptr(&space.0)))
+            .map(|(_, rules)| rules.clone()));
+        rules.or_else(|| Self::of_forward_chaining_space(space))
+    }
+
+    fn of_forward_chaining_space(space: &DynSpace) -> Option<Rc<RefCell<Self>>> {
+        space.borrow().as_any()
+            .and_then(|space| space.downcast_ref::<ForwardChainingSpace>())
+            .map(|space| space.rules.clone())
+    }
+
+    /// Adds the rule which executes `action` when an atom matching `pattern`
+    /// is added. The rule is fired by the atoms added after it.
+    pub fn add_rule(&mut self, pattern: Atom, action: RuleAction) {
+        self.rules.push(Rule::new(pattern, action));
+    }
+
+    /// Sets the maximal number of rule firings caused by a single modification
+    /// of the space
+    pub fn set_max_firings(&mut self, max_firings: usize) {
+        self.max_firings = max_firings;
+    }
+
+    fn is_pending(&self) -> bool {
+        self.error.is_some() || !self.added.borrow().0.is_empty()
+    }
+
+    /// Fires rules on the atoms added into the `space` since the last call
+    /// until the fixpoint is reached, returns an error when the limit of
+    /// firings is exceeded
+    pub fn fire(&mut self, space: &mut dyn SpaceMut) -> Result<(), String> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        let mut firings = 0;
+        loop {
+            let added = std::mem::take(&mut self.added.borrow_mut().0);
+            if added.is_empty() {
+                return Ok(());
+            }
+            for atom in added {
+                let actions: Vec<RuleAction> = self.rules.iter()
+                    .flat_map(|rule| rule.fire(&atom, space.as_space()))
+                    .collect();
+                for action in actions {
+                    firings += 1;
+                    if firings > self.max_firings {
+                        self.added.borrow_mut().0.clear();
+                        return Err(format!("Forward chaining is stopped after {} rule firings", self.max_firings));
+                    }
+                    log::debug!("ForwardRules::fire: atom: {}, action: {:?}", atom, action);
+                    match action {
+                        RuleAction::Add(atom) => if !contains(space.as_space(), &atom) {
+                            space.add(atom);
+                        },
+                        RuleAction::Remove(atom) => { space.remove(&atom); },
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Registers the rule which executes `action` when an atom matching `pattern`
+/// is added into the `space`. The rule is fired by the atoms added after it.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::space::*;
+/// use hyperon::space::grounding::GroundingSpace;
+/// use hyperon::space::forward::{add_rule, fire_rules, RuleAction};
+///
+/// let space = DynSpace::new(GroundingSpace::new());
+/// add_rule(&space, expr!("human" x), RuleAction::Add(expr!("mortal" x)));
+///
+/// space.borrow_mut().add(expr!("human" "Socrates"));
+/// assert_eq!(fire_rules(&space), Ok(()));
+///
+/// assert_eq!(space.borrow().query(&expr!("mortal" x)).len(), 1);
+/// ```
+pub fn add_rule(space: &DynSpace, pattern: Atom, action: RuleAction) {
+    ForwardRules::of(space).borrow_mut().add_rule(pattern, action);
+}
+
+/// Rules registered on the spaces by [add_rule], spaces are kept by weak
+/// references to not prolong their lifetime
+type RegisteredRules = Vec<(Weak<RefCell<dyn SpaceMut>>, Rc<RefCell<ForwardRules>>)>;
+
+thread_local! {
+    static RULES: RefCell<RegisteredRules> = RefCell::new(Vec::new());
+}
+
+/// Fires the rules registered on the `space` on the atoms added since the
+/// last firing, see [ForwardRules::fire]
+pub fn fire_rules(space: &DynSpace) -> Result<(), String> {
+    match ForwardRules::registered(space) {
+        Some(rules) => rules.borrow_mut().fire(&mut *space.borrow_mut()),
+        None => Ok(()),
+    }
+}
+
+/// Fires the rules registered by [add_rule] on all spaces which have the
+/// atoms added since the last firing, returns the first error
+pub fn fire_pending_rules() -> Result<(), String> {
+    let spaces: Vec<(DynSpace, Rc<RefCell<ForwardRules>>)> = RULES.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.retain(|(space, _)| space.strong_count() > 0);
+        registry.iter()
+            .filter(|(_, rules)| rules.borrow().is_pending())
+            .filter_map(|(space, rules)| space.upgrade().map(|space| (DynSpace(space), rules.clone())))
+            .collect()
+    });
+    let mut result = Ok(());
+    for (space, rules) in spaces {
+        let fired = rules.borrow_mut().fire(&mut *space.borrow_mut());
+        result = result.and(fired);
+    }
+    result
+}
+
+/// Space which fires forward chaining rules after each modification, see
+/// [module documentation](self) for details. [SpaceMut] methods cannot return
+/// an error, thus the error of the firing is logged and returned by the next
+/// [fire_rules] call.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::space::*;
+/// use hyperon::space::forward::{ForwardChainingSpace, RuleAction};
+///
+/// let mut space = ForwardChainingSpace::new();
+/// space.add_rule(expr!("parent" x y), RuleAction::Add(expr!("ancestor" x y)));
+/// space.add_rule(expr!("," ("parent" x y) ("ancestor" y z)), RuleAction::Add(expr!("ancestor" x z)));
+///
+/// space.add(expr!("parent" "Tom" "Bob"));
+/// space.add(expr!("parent" "Bob" "Ann"));
+///
+/// assert_eq!(space.query(&expr!("ancestor" "Tom" x)).len(), 2);
+/// ```
+pub struct ForwardChainingSpace {
+    space: GroundingSpace,
+    rules: Rc<RefCell<ForwardRules>>,
+}
+
+impl ForwardChainingSpace {
+    /// Constructs new empty space without rules
+    pub fn new() -> Self {
+        let space = GroundingSpace::new();
+        let rules = Rc::new(RefCell::new(ForwardRules::new(&space.common())));
+        Self{ space, rules }
+    }
+
+    /// Adds the rule which executes `action` when an atom matching `pattern`
+    /// is added. The rule is fired by the atoms added after it.
+    pub fn add_rule(&mut self, pattern: Atom, action: RuleAction) {
+        self.rules.borrow_mut().add_rule(pattern, action);
+    }
+
+    /// Sets the maximal number of rule firings caused by a single modification
+    /// of the space
+    pub fn set_max_firings(&mut self, max_firings: usize) {
+        self.rules.borrow_mut().set_max_firings(max_firings);
+    }
+
+    fn fire(&mut self) {
+        // Rules are borrowed when the atom is added by the rule, the atom is
+        // processed by the firing in progress
+        let Ok(mut rules) = self.rules.try_borrow_mut() else { return };
+        if let Err(err) = rules.fire(&mut self.space) {
+            log::error!("ForwardChainingSpace: {}", err);
+            rules.error = Some(err);
+        }
+    }
+}
+
+impl Default for ForwardChainingSpace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Space for ForwardChainingSpace {
+    fn common(&self) -> FlexRef<'_, SpaceCommon> {
+        self.space.common()
+    }
+    fn query(&self, query: &Atom) -> BindingsSet {
+        self.space.query(query)
+    }
+    fn query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        self.space.query_iter(query)
+    }
+    fn atom_count(&self) -> Option<usize> {
+        Space::atom_count(&self.space)
+    }
+    fn atom_iter(&self) -> Option<SpaceIter<'_>> {
+        Space::atom_iter(&self.space)
+    }
+    fn as_any(&self) -> Option<&dyn std::any::Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
+        Some(self)
+    }
+}
+
+impl SpaceMut for ForwardChainingSpace {
+    fn add(&mut self, atom: Atom) {
+        self.space.add(atom);
+        self.fire();
+    }
+    fn remove(&mut self, atom: &Atom) -> bool {
+        self.space.remove(atom)
+    }
+    fn replace(&mut self, from: &Atom, to: Atom) -> bool {
+        let is_replaced = self.space.replace(from, to);
+        self.fire();
+        is_replaced
+    }
+    fn as_space(&self) -> &dyn Space {
+        self
+    }
+}
+
+impl Debug for ForwardChainingSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ForwardChainingSpace-{self:p}")
+    }
+}
+
+impl Display for ForwardChainingSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ForwardChainingSpace-{self:p}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    struct EventCollector(Vec<SpaceEvent>);
+
+    impl SpaceObserver for EventCollector {
+        fn notify(&mut self, event: &SpaceEvent) {
+            self.0.push(event.clone());
+        }
+    }
+
+    fn ancestor_space() -> ForwardChainingSpace {
+        let mut space = ForwardChainingSpace::new();
+        space.add_rule(expr!("parent" x y), RuleAction::Add(expr!("ancestor" x y)));
+        space.add_rule(expr!("," ("parent" x y) ("ancestor" y z)), RuleAction::Add(expr!("ancestor" x z)));
+        space
+    }
+
+    #[test]
+    fn forward_chaining_derives_atoms() {
+        let mut space = ancestor_space();
+        space.add(expr!("parent" "Tom" "Bob"));
+        space.add(expr!("parent" "Bob" "Ann"));
+        space.add(expr!("parent" "Ann" "Eve"));
+
+        let actual = space.query(&expr!("ancestor" "Tom" x));
+        assert_eq_no_order!(actual, vec![bind!{x: sym!("Bob")}, bind!{x: sym!("Ann")}, bind!{x: sym!("Eve")}]);
+        assert_eq!(space.atom_count(), Some(9));
+    }
+
+    #[test]
+    fn forward_chaining_reaches_fixpoint_on_cycle() {
+        let mut space = ancestor_space();
+        space.add(expr!("parent" "A" "B"));
+        space.add(expr!("parent" "B" "A"));
+
+        let actual = space.query(&expr!("ancestor" x y));
+        assert_eq_no_order!(actual, vec![
+            bind!{x: sym!("A"), y: sym!("B")}, bind!{x: sym!("B"), y: sym!("A")},
+            bind!{x: sym!("A"), y: sym!("A")}, bind!{x: sym!("B"), y: sym!("B")},
+        ]);
+    }
+
+    #[test]
+    fn forward_chaining_removes_atoms() {
+        let mut space = ForwardChainingSpace::new();
+        space.add_rule(expr!("done" x), RuleAction::Remove(expr!("todo" x)));
+        space.add(expr!("todo" "A"));
+        space.add(expr!("todo" "B"));
+        space.add(expr!("done" "A"));
+
+        let actual = space.query(&expr!("todo" x));
+        assert_eq!(actual, bind_set![bind!{x: sym!("B")}]);
+    }
+
+    #[test]
+    fn forward_chaining_stops_runaway_rules() {
+        let mut space = ForwardChainingSpace::new();
+        space.set_max_firings(10);
+        space.add_rule(expr!("nat" x), RuleAction::Add(expr!("nat" ("S" x))));
+        let space = DynSpace::new(space);
+        space.borrow_mut().add(expr!("nat" "Z"));
+
+        assert_eq!(space.borrow().atom_count(), Some(11));
+        assert_eq!(fire_rules(&space), Err("Forward chaining is stopped after 10 rule firings".into()));
+        assert_eq!(fire_rules(&space), Ok(()));
+        space.borrow_mut().add(expr!("other"));
+        assert_eq!(space.borrow().atom_count(), Some(12));
+        assert_eq!(fire_rules(&space), Ok(()));
+    }
+
+    #[test]
+    fn forward_rules_of_grounding_space() {
+        let space = DynSpace::new(GroundingSpace::new());
+        add_rule(&space, expr!("parent" x y), RuleAction::Add(expr!("ancestor" x y)));
+        add_rule(&space, expr!("," ("parent" x y) ("ancestor" y z)), RuleAction::Add(expr!("ancestor" x z)));
+
+        space.borrow_mut().add(expr!("parent" "Tom" "Bob"));
+        space.borrow_mut().add(expr!("parent" "Bob" "Ann"));
+        assert_eq!(space.borrow().query(&expr!("ancestor" "Tom" x)), BindingsSet::empty());
+        assert_eq!(fire_rules(&space), Ok(()));
+
+        let actual = space.borrow().query(&expr!("ancestor" "Tom" x));
+        assert_eq_no_order!(actual, vec![bind!{x: sym!("Bob")}, bind!{x: sym!("Ann")}]);
+    }
+
+    #[test]
+    fn forward_chaining_notifies_observers() {
+        let mut space = ForwardChainingSpace::new();
+        space.add_rule(expr!("A" x), RuleAction::Add(expr!("B" x)));
+        let events = space.common().register_observer(EventCollector(Vec::new()));
+
+        space.add(expr!("A" "1"));
+
+        assert_eq!(events.borrow().0, vec![SpaceEvent::Add(expr!("A" "1")),
+            SpaceEvent::Add(expr!("B" "1"))]);
+    }
+}
@@ -11,12 +11,142 @@ use crate::common::multitrie::{MultiTrie, TrieKey, TrieToken};
 use std::fmt::Debug;
 use std::collections::BTreeSet;
 use std::collections::HashSet;
+use std::rc::Rc;
+use std::cell::RefCell;
 
 // Grounding space
 
 /// Symbol to concatenate queries to space.
 pub const COMMA_SYMBOL : Atom = sym!(",");
 
+/// Executes `query` which may include sub-queries glued by [COMMA_SYMBOL]
+/// symbol. Each simple sub-query is executed by `single_query` after
+/// applying the bindings found by the previous sub-queries. Results are
+/// found lazily.
+pub fn complex_query_iter<'a, F>(query: &Atom, single_query: F) -> QueryIter<'a>
+    where F: Fn(&Atom) -> QueryIter<'a> + Clone + 'a
+{
+    match split_expr(query) {
+        // Cannot match with COMMA_SYMBOL here, because Rust allows
+        // it only when Atom has PartialEq and Eq derived.
+        Some((sym @ Atom::Symbol(_), args)) if *sym == COMMA_SYMBOL => {
+            args.fold(QueryIter::new(std::iter::once(Bindings::new())),
+                |acc, query| {
+                    let query = query.clone();
+                    let single_query = single_query.clone();
+                    QueryIter::new(acc.flat_map(move |prev| {
+                        let query = matcher::apply_bindings_to_atom(&query, &prev);
+                        complex_query_iter(&query, single_query.clone())
+                            .flat_map(move |next| next.merge_v2(&prev))
+                    }))
+                })
+        },
+        _ => single_query(query),
+    }
+}
+
+/// Atoms recorded by [record_matched_atoms], each atom is identified by the
+/// address of its space and its index inside of the space
+#[derive(Default)]
+struct MatchedAtoms {
+    keys: HashSet<(usize, usize)>,
+    atoms: Vec<((usize, usize), Atom)>,
+}
+
+impl MatchedAtoms {
+    fn push(&mut self, key: (usize, usize), atom: &Atom) {
+        if self.keys.insert(key) {
+            self.atoms.push((key, atom.clone()));
+        }
+    }
+}
+
+thread_local! {
+    static MATCHED_ATOMS: RefCell<Vec<MatchedAtoms>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Calls `f` and returns its result together with the atoms of the
+/// [GroundingSpace] instances which matched the queries executed by `f` in
+/// the current thread. Atoms are returned as they are kept in the space,
+/// variables are not renamed, thus they can be passed into
+/// [SpaceMut::remove]. Atoms which are spaces themselves are not returned,
+/// atoms of the nested space matched instead. Recordings can be nested, the
+/// atoms recorded by the inner call are recorded by the outer call as well.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::space::Space;
+/// use hyperon::space::grounding::{GroundingSpace, record_matched_atoms};
+///
+/// let space = GroundingSpace::from_vec(vec![expr!("A" x), expr!("B" "C")]);
+///
+/// let (result, atoms) = record_matched_atoms(|| space.query(&expr!("A" "D")));
+///
+/// assert_eq!(result.len(), 1);
+/// assert_eq!(atoms, vec![expr!("A" x)]);
+/// ```
+pub fn record_matched_atoms<R, F: FnOnce() -> R>(f: F) -> (R, Vec<Atom>) {
+    MATCHED_ATOMS.with(|recorded| recorded.borrow_mut().push(MatchedAtoms::default()));
+    let result = f();
+    let atoms = MATCHED_ATOMS.with(|recorded| {
+        let mut recorded = recorded.borrow_mut();
+        let matched = recorded.pop().unwrap_or_default();
+        if let Some(outer) = recorded.last_mut() {
+            matched.atoms.iter().for_each(|(key, atom)| outer.push(*key, atom));
+        }
+        matched.atoms.into_iter().map(|(_key, atom)| atom).collect()
+    });
+    (result, atoms)
+}
+
+fn record_matched_atom(space: &GroundingSpace, index: usize, atom: &Atom) {
+    MATCHED_ATOMS.with(|recorded| {
+        if let Some(matched) = recorded.borrow_mut().last_mut() {
+            if atom.as_gnd::<DynSpace>().is_none() {
+                matched.push((space as *const GroundingSpace as usize, index), atom);
+            }
+        }
+    })
+}
+
+/// Executes the `query` on the `space` and returns each result together
+/// with the atoms of the space which produce the same result when they are
+/// matched with the query. Atoms are recorded by [record_matched_atoms], thus
+/// they are found only in the [GroundingSpace] instances.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::{expr, bind, assert_eq_no_order};
+/// use hyperon::space::grounding::{GroundingSpace, query_with_matched_atoms};
+///
+/// let space = GroundingSpace::from_vec(vec![expr!("A" "B"), expr!("A" "C")]);
+///
+/// let results = query_with_matched_atoms(&space, &expr!("A" x));
+///
+/// assert_eq_no_order!(results, vec![
+///     (bind!{x: expr!("B")}, vec![expr!("A" "B")]),
+///     (bind!{x: expr!("C")}, vec![expr!("A" "C")]),
+/// ]);
+/// ```
+pub fn query_with_matched_atoms(space: &dyn Space, query: &Atom) -> Vec<(Bindings, Vec<Atom>)> {
+    let (results, matched) = record_matched_atoms(|| space.query(query));
+    results.into_iter()
+        .map(|bindings| {
+            let result = matcher::apply_bindings_to_atom(query, &bindings);
+            // atom is matched with the query instead of the result because
+            // grounded atoms of the result can match the atom differently
+            let atoms = matched.iter()
+                .filter(|atom| match_atoms(&make_variables_unique((*atom).clone()), query)
+                    .any(|bindings| matcher::atoms_are_equivalent(&matcher::apply_bindings_to_atom(query, &bindings), &result)))
+                .cloned().collect();
+            (bindings, atoms)
+        })
+        .collect()
+}
+
 struct GroundingSpaceIter<'a> {
     space: &'a GroundingSpace,
     i: usize,
@@ -189,6 +319,23 @@ impl GroundingSpace {
         is_removed
     }
 
+    /// Returns true if the space contains an atom which is equal to `atom`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperon::{sym, expr};
+    /// use hyperon::space::grounding::GroundingSpace;
+    ///
+    /// let space = GroundingSpace::from_vec(vec![expr!("A" x)]);
+    ///
+    /// assert!(space.contains(&expr!("A" x)));
+    /// assert!(!space.contains(&expr!("A" "B")));
+    /// ```
+    pub fn contains(&self, atom: &Atom) -> bool {
+        self.index.get(&atom_to_trie_key(atom)).any(|i| self.content[*i] == *atom)
+    }
+
     /// Replaces `from` atom to `to` atom inside space. Doesn't add `to` when
     /// `from` is not found. Returns true if atom was found and replaced, and
     /// false otherwise.
@@ -243,48 +390,55 @@ impl GroundingSpace {
     /// assert_eq!(result, bind_set![{x: sym!("B")}]);
     /// ```
     pub fn query(&self, query: &Atom) -> BindingsSet {
-        match split_expr(query) {
-            // Cannot match with COMMA_SYMBOL here, because Rust allows
-            // it only when Atom has PartialEq and Eq derived.
-            Some((sym @ Atom::Symbol(_), args)) if *sym == COMMA_SYMBOL => {
-                args.fold(BindingsSet::single(),
-                    |mut acc, query| {
-                        let result = if acc.is_empty() {
-                            acc
-                        } else {
-                            acc.drain(0..).flat_map(|prev| -> BindingsSet {
-                                let query = matcher::apply_bindings_to_atom(&query, &prev);
-                                let mut res = self.query(&query);
-                                res.drain(0..)
-                                    .flat_map(|next| next.merge_v2(&prev))
-                                    .collect()
-                            }).collect()
-                        };
-                        log::debug!("query: current result: {:?}", result);
-                        result
-                    })
-            },
-            _ => self.single_query(query),
-        }
+        let result: BindingsSet = self.query_iter(query).collect();
+        log::debug!("query: query: {}, result: {:?}", query, result);
+        result
+    }
+
+    /// Executes `query` on the space and returns an iterator over the variable
+    /// bindings found. Results are found lazily: each next result is searched
+    /// for only when it is requested from the iterator. Query may include
+    /// sub-queries glued by [COMMA_SYMBOL] symbol.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperon::{expr, bind, sym};
+    /// use hyperon::space::grounding::GroundingSpace;
+    ///
+    /// let space = GroundingSpace::from_vec(vec![expr!("A" "B"), expr!("A" "C"), expr!("B" "C")]);
+    /// let query = expr!("," ("A" x) (x "C"));
+    ///
+    /// let mut result = space.query_iter(&query);
+    ///
+    /// assert_eq!(result.next(), Some(bind!{x: sym!("B")}));
+    /// assert_eq!(result.next(), None);
+    /// ```
+    pub fn query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        complex_query_iter(query, move |query| self.single_query_iter(query))
     }
 
     /// Executes simple `query` without sub-queries on the space.
-    fn single_query(&self, query: &Atom) -> BindingsSet {
+    fn single_query_iter(&self, query: &Atom) -> QueryIter<'_> {
         log::debug!("single_query: query: {}", query);
-        let mut result = BindingsSet::empty();
-        let query_vars: HashSet<&VariableAtom> = query.iter().filter_type::<&VariableAtom>().collect();
-        for i in self.index.get(&atom_to_trie_key(query)) {
-            let next = self.content.get(*i).expect(format!("Index contains absent atom: key: {:?}, position: {}", query, i).as_str());
-            let next = make_variables_unique(next.clone());
+        let query = query.clone();
+        let query_vars: Rc<HashSet<VariableAtom>> = Rc::new(query.iter().filter_type::<&VariableAtom>().cloned().collect());
+        // Index is searched eagerly because the key is borrowed by the index
+        // iterator, atoms are matched lazily.
+        let key = atom_to_trie_key(&query);
+        let indexes: Vec<usize> = self.index.get(&key).copied().collect();
+        QueryIter::new(indexes.into_iter().flat_map(move |i| {
+            let atom = self.content.get(i).expect(format!("Index contains absent atom: key: {:?}, position: {}", query, i).as_str());
+            let next = make_variables_unique(atom.clone());
             log::trace!("single_query: match next: {}", next);
-            for bindings in match_atoms(&next, query) {
-                let bindings = bindings.narrow_vars(&query_vars);
+            let query_vars = query_vars.clone();
+            match_atoms(&next, &query).map(move |bindings| {
+                record_matched_atom(self, i, atom);
+                let bindings = bindings.narrow_vars(query_vars.as_ref());
                 log::trace!("single_query: push result: {}", bindings);
-                result.push(bindings);
-            }
-        }
-        log::debug!("single_query: result: {:?}", result);
-        result
+                bindings
+            })
+        }))
     }
 
     /// Returns the iterator over content of the space.
@@ -310,6 +464,9 @@ impl Space for GroundingSpace {
     fn query(&self, query: &Atom) -> BindingsSet {
         GroundingSpace::query(self, query)
     }
+    fn query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        GroundingSpace::query_iter(self, query)
+    }
     fn atom_count(&self) -> Option<usize> {
         Some(self.iter().count())
     }
@@ -676,6 +833,90 @@ mod test {
         assert_eq!(result, bind_set![{x: sym!("a")}]);
     }
 
+    #[derive(Clone, Debug)]
+    struct MatchCounter(std::rc::Rc<std::cell::Cell<usize>>);
+
+    impl PartialEq for MatchCounter {
+        fn eq(&self, _other: &Self) -> bool {
+            true
+        }
+    }
+
+    impl Display for MatchCounter {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "MatchCounter")
+        }
+    }
+
+    impl Grounded for MatchCounter {
+        fn type_(&self) -> Atom {
+            rust_type_atom::<MatchCounter>()
+        }
+        fn execute(&self, _args: &[Atom]) -> Result<Vec<Atom>, ExecError> {
+            execute_not_executable(self)
+        }
+        fn match_(&self, _other: &Atom) -> MatchResultIter {
+            self.0.set(self.0.get() + 1);
+            Box::new(std::iter::once(Bindings::new()))
+        }
+    }
+
+    #[test]
+    fn test_query_iter_is_lazy() {
+        let counter = MatchCounter(std::rc::Rc::new(std::cell::Cell::new(0)));
+        let space = GroundingSpace::from_vec((0..10).map(|_| Atom::gnd(counter.clone())).collect());
+
+        let mut result = space.query_iter(&expr!("A"));
+        assert_eq!(counter.0.get(), 0);
+        assert!(result.next().is_some());
+        assert_eq!(counter.0.get(), 1);
+        assert_eq!(result.count(), 9);
+        assert_eq!(counter.0.get(), 10);
+    }
+
+    #[test]
+    fn test_query_iter_returns_results_of_query() {
+        let space = GroundingSpace::from_vec(vec![
+            expr!("A" "B"), expr!("A" "C"), expr!("B" "D"), expr!("C" "D"),
+        ]);
+        let query = expr!("," ("A" x) (x y));
+        let result: BindingsSet = space.query_iter(&query).collect();
+        assert_eq!(result, space.query(&query));
+        assert_eq!(result, bind_set![bind!{x: sym!("B"), y: sym!("D")}, bind!{x: sym!("C"), y: sym!("D")}]);
+    }
+
+    #[test]
+    fn test_query_page() {
+        let space = GroundingSpace::from_vec(vec![
+            expr!("A" "B"), expr!("A" "C"), expr!("A" "D"),
+        ]);
+        let query = expr!("A" x);
+        let first = space.query_page(&query, 0, 2);
+        let second = space.query_page(&query, 2, 2);
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 1);
+        let all: Vec<Bindings> = first.into_iter().chain(second).collect();
+        assert_eq_no_order!(all,
+            vec![bind!{x: sym!("B")}, bind!{x: sym!("C")}, bind!{x: sym!("D")}]);
+        assert_eq!(space.query_page(&query, 3, 2), BindingsSet::empty());
+        assert_eq!(space.query_page(&query, 1, 0), BindingsSet::empty());
+    }
+
+    #[test]
+    fn record_matched_atoms_nested() {
+        let space = GroundingSpace::from_vec(vec![expr!("A" x), expr!("B" "C"), expr!("B" "D")]);
+        let nested = GroundingSpace::from_vec(vec![expr!("A" "E")]);
+
+        let ((_, inner), outer) = record_matched_atoms(|| {
+            space.query(&expr!("B" y));
+            record_matched_atoms(|| nested.query(&expr!("A" y)))
+        });
+
+        assert_eq!(inner, vec![expr!("A" "E")]);
+        assert_eq_no_order!(outer, vec![expr!("B" "C"), expr!("B" "D"), expr!("A" "E")]);
+        assert_eq!(record_matched_atoms(|| ()).1, vec![]);
+    }
+
     #[test]
     fn index_atom_to_key() {
         assert_eq!(atom_to_trie_key(&Atom::sym("A")), TrieKey::from([TrieToken::Exact(SymbolAtom::new("A".into()))]));
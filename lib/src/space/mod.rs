@@ -1,7 +1,16 @@
 //! Space is a storage for atoms with search queries execution capability.
 //! This module is intended to keep different space implementations.
 
+pub mod backward;
+pub mod datalog;
+pub mod forward;
 pub mod grounding;
+pub mod persistent;
+pub mod remote;
+pub mod sync;
+pub mod view;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 use std::fmt::Display;
 use std::rc::{Rc, Weak};
@@ -9,7 +18,7 @@ use std::cell::{RefCell, Ref, RefMut};
 
 use crate::common::FlexRef;
 use crate::atom::*;
-use crate::atom::matcher::{BindingsSet, apply_bindings_to_atom};
+use crate::atom::matcher::{Bindings, BindingsSet, apply_bindings_to_atom};
 
 /// Contains information about space modification event.
 #[derive(Clone, Debug, PartialEq)]
@@ -106,6 +115,25 @@ impl<'a> Iterator for SpaceIter<'a> {
     }
 }
 
+/// Iterator over the results of the space query, see [Space::query_iter]
+pub struct QueryIter<'a> {
+    iter: Box<dyn Iterator<Item=Bindings> + 'a>
+}
+
+impl<'a> QueryIter<'a> {
+    pub fn new<I: Iterator<Item=Bindings> + 'a>(iter: I) -> Self {
+        Self{ iter: Box::new(iter) }
+    }
+}
+
+impl<'a> Iterator for QueryIter<'a> {
+    type Item = Bindings;
+
+    fn next(&mut self) -> Option<Bindings> {
+        self.iter.next()
+    }
+}
+
 /// A common object that needs to be maintained by all objects implementing the Space trait
 #[derive(Default)]
 pub struct SpaceCommon {
@@ -174,6 +202,49 @@ pub trait Space: std::fmt::Debug + std::fmt::Display {
     /// ```
     fn query(&self, query: &Atom) -> BindingsSet;
 
+    /// Executes `query` on the space and returns an iterator over the variable bindings
+    /// found. Spaces which are able to find results one by one return them lazily, thus
+    /// the caller can stop the search after getting enough results. Default
+    /// implementation returns results of [Space::query].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperon::{expr, bind, sym};
+    /// use hyperon::space::Space;
+    /// use hyperon::space::grounding::GroundingSpace;
+    ///
+    /// let space = GroundingSpace::from_vec(vec![expr!("A" "B"), expr!("B" "C")]);
+    ///
+    /// let mut results = space.query_iter(&expr!("A" x));
+    ///
+    /// assert_eq!(results.next(), Some(bind!{x: sym!("B")}));
+    /// assert_eq!(results.next(), None);
+    /// ```
+    fn query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        QueryIter::new(self.query(query).into_iter())
+    }
+
+    /// Executes `query` on the space skipping first `offset` results and returns
+    /// at most `limit` next results. Results are returned in the order of
+    /// [Space::query_iter].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperon::expr;
+    /// use hyperon::space::Space;
+    /// use hyperon::space::grounding::GroundingSpace;
+    ///
+    /// let space = GroundingSpace::from_vec(vec![expr!("A" "B"), expr!("A" "C"), expr!("A" "D")]);
+    ///
+    /// assert_eq!(space.query_page(&expr!("A" x), 0, 2).len(), 2);
+    /// assert_eq!(space.query_page(&expr!("A" x), 2, 2).len(), 1);
+    /// ```
+    fn query_page(&self, query: &Atom, offset: usize, limit: usize) -> BindingsSet {
+        self.query_iter(query).skip(offset).take(limit).collect()
+    }
+
     /// Executes `pattern` query on the space and for each result substitutes
     /// variables in `template` by the values from `pattern`. Returns results
     /// of the substitution.
@@ -332,6 +403,13 @@ impl Space for DynSpace {
     fn query(&self, query: &Atom) -> BindingsSet {
         self.0.borrow().query(query)
     }
+    // [Space::query_iter] is not overridden: a lazy iterator would need to
+    // keep the [RefCell] borrowed after the call returns, so the default
+    // implementation collecting the results of [Space::query] is used.
+    // [Space::query_page] is forwarded to let the underlying space stop early.
+    fn query_page(&self, query: &Atom, offset: usize, limit: usize) -> BindingsSet {
+        self.0.borrow().query_page(query, offset, limit)
+    }
     fn subst(&self, pattern: &Atom, template: &Atom) -> Vec<Atom> {
         self.0.borrow().subst(pattern, template)
     }
@@ -376,6 +454,12 @@ impl<T: Space> Space for &T {
     fn query(&self, query: &Atom) -> BindingsSet {
         T::query(*self, query)
     }
+    fn query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        T::query_iter(*self, query)
+    }
+    fn query_page(&self, query: &Atom, offset: usize, limit: usize) -> BindingsSet {
+        T::query_page(*self, query, offset, limit)
+    }
     fn subst(&self, pattern: &Atom, template: &Atom) -> Vec<Atom> {
         T::subst(*self, pattern, template)
     }
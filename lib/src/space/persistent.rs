@@ -0,0 +1,496 @@
+//! Space which keeps its content in a directory on the local file system.
+//!
+//! [PersistentSpace] keeps all atoms in memory inside of a [GroundingSpace] to
+//! execute queries, and writes each modification into an append-only log of
+//! [SpaceEvent]s. The log is periodically compacted into a snapshot of the
+//! space content. The directory of the space contains the following files:
+//! - `snapshot`: the generation number of the snapshot followed by atoms of the
+//!   space;
+//! - `log.<generation>`: the events which happened after the snapshot of the
+//!   same generation was written.
+//!
+//! Both files contain one JSON value per line, atoms are encoded by
+//! [atom_to_json]. New snapshot is written into a temporary file which is
+//! atomically renamed after it is complete, thus the space is reopened to its
+//! last state after a crash at any moment. A partially written last record of
+//! the log is ignored.
+//!
+//! The directory is locked by an exclusive lock of the `lock` file while the
+//! space is open, thus an attempt to open the same space twice fails instead
+//! of interleaving the records of two logs.
+//!
+//! The content of the space in memory is changed only after the record is
+//! written into the log. After the first failure to write the log or to
+//! compact it the space refuses all further modifications and the failure is
+//! returned by [PersistentSpace::sync].
+
+use super::*;
+use super::grounding::GroundingSpace;
+use crate::metta::text::Tokenizer;
+use crate::metta::json::{atom_to_json, atom_from_json};
+
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+
+/// Number of log records after which the log is compacted into a snapshot by default
+pub const DEFAULT_COMPACTION_THRESHOLD: usize = 10000;
+
+const SNAPSHOT_FILE: &str = "snapshot";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.tmp";
+const LOCK_FILE: &str = "lock";
+
+/// Space which persists its content in a local directory, see [module
+/// documentation](self) for details.
+///
+/// Grounded atoms are saved using [Grounded::serialize]. Grounded atoms which
+/// cannot be serialized are saved as their textual representation and are
+/// restored by the [Tokenizer] passed to [PersistentSpace::open].
+pub struct PersistentSpace {
+    space: GroundingSpace,
+    path: PathBuf,
+    generation: u64,
+    log: File,
+    log_records: usize,
+    compaction_threshold: usize,
+    /// First failure to write the log, the space is not modified after it
+    error: Option<String>,
+    /// Lock is released when the file is closed
+    _lock: File,
+}
+
+impl PersistentSpace {
+    /// Opens the space stored in the directory `path`, the directory is created
+    /// when it doesn't exist. The `tokenizer` is used to restore grounded
+    /// atoms which were saved as text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperon::{sym, assert_eq_no_order};
+    /// use hyperon::metta::text::Tokenizer;
+    /// use hyperon::space::SpaceMut;
+    /// use hyperon::space::persistent::PersistentSpace;
+    ///
+    /// let path = std::env::temp_dir().join(format!("hyperon-doc-persistent-space-{}", std::process::id()));
+    /// let mut space = PersistentSpace::open(&path, &Tokenizer::new()).unwrap();
+    /// space.add(sym!("A"));
+    /// drop(space);
+    ///
+    /// let space = PersistentSpace::open(&path, &Tokenizer::new()).unwrap();
+    /// assert_eq_no_order!(space, vec![sym!("A")]);
+    /// # std::fs::remove_dir_all(&path).unwrap();
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P, tokenizer: &Tokenizer) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path)
+            .map_err(|err| format!("Could not create space directory {}: {}", path.display(), err))?;
+        let lock = lock_dir(&path)?;
+        let mut space = GroundingSpace::new();
+        let generation = read_snapshot(&path.join(SNAPSHOT_FILE), tokenizer, &mut space)?;
+        let log_path = log_file(&path, generation);
+        let log_records = replay_log(&log_path, tokenizer, &mut space)?;
+        // Leftovers of the compaction which was interrupted
+        let _ = fs::remove_file(path.join(SNAPSHOT_TMP_FILE));
+        if generation > 0 {
+            let _ = fs::remove_file(log_file(&path, generation - 1));
+        }
+        let log = OpenOptions::new().create(true).append(true).open(&log_path)
+            .map_err(|err| format!("Could not open space log {}: {}", log_path.display(), err))?;
+        log::debug!("PersistentSpace::open: path: {}, generation: {}, log records: {}", path.display(), generation, log_records);
+        Ok(Self{ space, path, generation, log, log_records, compaction_threshold: DEFAULT_COMPACTION_THRESHOLD, error: None, _lock: lock })
+    }
+
+    /// Returns the directory of the space
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sets the number of log records after which the log is compacted
+    pub fn set_compaction_threshold(&mut self, threshold: usize) {
+        self.compaction_threshold = threshold;
+    }
+
+    /// Writes the snapshot of the space content and starts a new empty log
+    pub fn compact(&mut self) -> Result<(), String> {
+        let generation = self.generation + 1;
+        let io_error = |err: std::io::Error| format!("Could not compact space {}: {}", self.path.display(), err);
+
+        let tmp_path = self.path.join(SNAPSHOT_TMP_FILE);
+        let mut snapshot = std::io::BufWriter::new(File::create(&tmp_path).map_err(io_error)?);
+        writeln!(snapshot, "{}", json!({"generation": generation})).map_err(io_error)?;
+        for atom in self.space.iter() {
+            writeln!(snapshot, "{}", atom_to_json(atom)).map_err(io_error)?;
+        }
+        let snapshot = snapshot.into_inner().map_err(|err| io_error(err.into_error()))?;
+        snapshot.sync_all().map_err(io_error)?;
+
+        let log_path = log_file(&self.path, generation);
+        let log = File::create(&log_path).map_err(io_error)?;
+        log.sync_all().map_err(io_error)?;
+        fs::rename(&tmp_path, self.path.join(SNAPSHOT_FILE)).map_err(io_error)?;
+        sync_dir(&self.path).map_err(io_error)?;
+        let _ = fs::remove_file(log_file(&self.path, self.generation));
+
+        self.log = OpenOptions::new().append(true).open(&log_path).map_err(io_error)?;
+        self.generation = generation;
+        self.log_records = 0;
+        log::debug!("PersistentSpace::compact: path: {}, generation: {}", self.path.display(), generation);
+        Ok(())
+    }
+
+    /// Flushes the log to the storage device. Records are written into the log
+    /// immediately, thus they survive a crash of the process without calling
+    /// this method, but can be lost if the whole system crashes. Returns the
+    /// error if some modification of the space could not be written.
+    pub fn sync(&self) -> Result<(), String> {
+        if let Some(err) = &self.error {
+            return Err(err.clone());
+        }
+        self.log.sync_data()
+            .map_err(|err| format!("Could not sync space log {}: {}", self.path.display(), err))
+    }
+
+    /// Returns the iterator over content of the space
+    pub fn iter(&self) -> SpaceIter<'_> {
+        self.space.iter()
+    }
+
+    /// Returns the failure which stopped modifications of the space if any
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn is_writable(&self) -> bool {
+        match &self.error {
+            Some(err) => {
+                log::error!("Space {} is not modified after the failure: {}", self.path.display(), err);
+                false
+            },
+            None => true,
+        }
+    }
+
+    fn write_record(&mut self, record: Value) -> bool {
+        if let Err(err) = writeln!(self.log, "{}", record) {
+            let err = format!("Could not write space log {}: {}", self.path.display(), err);
+            log::error!("{}", err);
+            self.error = Some(err);
+            return false;
+        }
+        self.log_records += 1;
+        true
+    }
+
+    /// Compacts the log after the written record is applied to the space
+    fn compact_if_needed(&mut self) {
+        if self.log_records >= self.compaction_threshold {
+            // The log may be left in an inconsistent state by the failed
+            // compaction thus it is not written anymore
+            if let Err(err) = self.compact() {
+                log::error!("{}", err);
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+fn log_file(path: &Path, generation: u64) -> PathBuf {
+    path.join(format!("log.{}", generation))
+}
+
+fn lock_dir(path: &Path) -> Result<File, String> {
+    let lock_path = path.join(LOCK_FILE);
+    let lock = OpenOptions::new().create(true).truncate(false).write(true).open(&lock_path)
+        .map_err(|err| format!("Could not open space lock {}: {}", lock_path.display(), err))?;
+    match lock.try_lock() {
+        Ok(()) => Ok(lock),
+        Err(fs::TryLockError::WouldBlock) => Err(format!("Space {} is already opened", path.display())),
+        Err(fs::TryLockError::Error(err)) => Err(format!("Could not lock space {}: {}", path.display(), err)),
+    }
+}
+
+/// Flushes the entries of the directory to make the renaming of the snapshot
+/// durable. Directories cannot be opened as files on Windows, where renaming
+/// is durable after it returns.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> std::io::Result<()> {
+    File::open(path)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+fn read_snapshot(path: &Path, tokenizer: &Tokenizer, space: &mut GroundingSpace) -> Result<u64, String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(format!("Could not open space snapshot {}: {}", path.display(), err)),
+    };
+    let mut lines = BufReader::new(file).lines();
+    let invalid = |err: String| format!("Invalid space snapshot {}: {}", path.display(), err);
+    let header = lines.next().unwrap_or(Ok(String::new())).map_err(|err| invalid(err.to_string()))?;
+    let generation = serde_json::from_str::<Value>(&header).ok()
+        .and_then(|header| header["generation"].as_u64())
+        .ok_or_else(|| invalid(format!("unexpected header: {}", header)))?;
+    for line in lines {
+        let line = line.map_err(|err| invalid(err.to_string()))?;
+        let value = serde_json::from_str(&line).map_err(|err| invalid(err.to_string()))?;
+        space.add(atom_from_json(&value, tokenizer).map_err(invalid)?);
+    }
+    Ok(generation)
+}
+
+fn replay_log(path: &Path, tokenizer: &Tokenizer, space: &mut GroundingSpace) -> Result<usize, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(format!("Could not read space log {}: {}", path.display(), err)),
+    };
+    let mut records = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if !line.ends_with('\n') {
+            // The last record was not written completely, it is cut off to
+            // continue writing the log from the end of the previous record
+            log::warn!("PersistentSpace: incomplete record is removed from the log {}: {}", path.display(), line);
+            let file = OpenOptions::new().write(true).open(path)
+                .map_err(|err| format!("Could not open space log {}: {}", path.display(), err))?;
+            file.set_len(offset as u64)
+                .map_err(|err| format!("Could not truncate space log {}: {}", path.display(), err))?;
+            break;
+        }
+        apply_record(line, tokenizer, space)
+            .map_err(|err| format!("Invalid record in space log {}: {}", path.display(), err))?;
+        offset += line.len();
+        records += 1;
+    }
+    Ok(records)
+}
+
+fn apply_record(line: &str, tokenizer: &Tokenizer, space: &mut GroundingSpace) -> Result<(), String> {
+    let record: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    match record.as_object().and_then(|record| record.iter().next()) {
+        Some((event, atom)) if event == "add" => {
+            space.add(atom_from_json(atom, tokenizer)?);
+        },
+        Some((event, atom)) if event == "remove" => {
+            space.remove(&atom_from_json(atom, tokenizer)?);
+        },
+        Some((event, Value::Array(atoms))) if event == "replace" && atoms.len() == 2 => {
+            let from = atom_from_json(&atoms[0], tokenizer)?;
+            space.replace(&from, atom_from_json(&atoms[1], tokenizer)?);
+        },
+        _ => return Err(format!("unexpected record: {}", record)),
+    }
+    Ok(())
+}
+
+impl Space for PersistentSpace {
+    fn common(&self) -> FlexRef<'_, SpaceCommon> {
+        self.space.common()
+    }
+    fn query(&self, query: &Atom) -> BindingsSet {
+        self.space.query(query)
+    }
+    fn query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        self.space.query_iter(query)
+    }
+    fn atom_count(&self) -> Option<usize> {
+        Space::atom_count(&self.space)
+    }
+    fn atom_iter(&self) -> Option<SpaceIter<'_>> {
+        Some(self.iter())
+    }
+    fn as_any(&self) -> Option<&dyn std::any::Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
+        Some(self)
+    }
+}
+
+impl SpaceMut for PersistentSpace {
+    fn add(&mut self, atom: Atom) {
+        if self.is_writable() && self.write_record(json!({"add": atom_to_json(&atom)})) {
+            self.space.add(atom);
+            self.compact_if_needed();
+        }
+    }
+    fn remove(&mut self, atom: &Atom) -> bool {
+        if !self.is_writable() || !self.space.remove(atom) {
+            return false;
+        }
+        if !self.write_record(json!({"remove": atom_to_json(atom)})) {
+            self.space.add(atom.clone());
+            return false;
+        }
+        self.compact_if_needed();
+        true
+    }
+    fn replace(&mut self, from: &Atom, to: Atom) -> bool {
+        let record = json!({"replace": [atom_to_json(from), atom_to_json(&to)]});
+        if !self.is_writable() || !self.space.replace(from, to.clone()) {
+            return false;
+        }
+        if !self.write_record(record) {
+            self.space.replace(&to, from.clone());
+            return false;
+        }
+        self.compact_if_needed();
+        true
+    }
+    fn as_space(&self) -> &dyn Space {
+        self
+    }
+}
+
+impl Debug for PersistentSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PersistentSpace-{} ({self:p})", self.path.display())
+    }
+}
+
+impl Display for PersistentSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PersistentSpace-{}", self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use crate::metta::runner::arithmetics::Number;
+    use crate::metta::runner::string::Str;
+
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("hyperon-test-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn open(dir: &TestDir) -> PersistentSpace {
+        PersistentSpace::open(&dir.0, &Tokenizer::new()).unwrap()
+    }
+
+    #[test]
+    fn persistent_space_reopens_to_last_state() {
+        let dir = TestDir::new("persistent-space-reopen");
+        let mut space = open(&dir);
+        space.add(expr!("A" "B"));
+        space.add(expr!("B" "C"));
+        space.add(expr!("C" "D"));
+        assert!(space.remove(&expr!("A" "B")));
+        assert!(!space.remove(&expr!("X")));
+        assert!(space.replace(&expr!("B" "C"), expr!("B" "E")));
+        drop(space);
+
+        let space = open(&dir);
+        assert_eq_no_order!(space, vec![expr!("B" "E"), expr!("C" "D")]);
+    }
+
+    #[test]
+    fn persistent_space_keeps_grounded_atoms() {
+        let dir = TestDir::new("persistent-space-grounded");
+        let mut space = open(&dir);
+        space.add(expr!("value" {Number::Integer(42)} {Str::from_str("a b")}));
+        drop(space);
+
+        let space = open(&dir);
+        assert_eq_no_order!(space, vec![expr!("value" {Number::Integer(42)} {Str::from_str("a b")})]);
+    }
+
+    #[test]
+    fn persistent_space_compaction() {
+        let dir = TestDir::new("persistent-space-compaction");
+        let mut space = open(&dir);
+        space.set_compaction_threshold(3);
+        space.add(expr!("A"));
+        space.add(expr!("B"));
+        space.add(expr!("C"));
+        assert!(dir.0.join(SNAPSHOT_FILE).exists());
+        assert!(!log_file(&dir.0, 0).exists());
+        assert_eq!(fs::read_to_string(log_file(&dir.0, 1)).unwrap(), "");
+        space.remove(&expr!("A"));
+        drop(space);
+
+        let space = open(&dir);
+        assert_eq_no_order!(space, vec![expr!("B"), expr!("C")]);
+    }
+
+    #[test]
+    fn persistent_space_ignores_incomplete_record() {
+        let dir = TestDir::new("persistent-space-incomplete");
+        let mut space = open(&dir);
+        space.add(expr!("A"));
+        drop(space);
+        let mut log = OpenOptions::new().append(true).open(log_file(&dir.0, 0)).unwrap();
+        write!(log, r#"{{"add": {{"symb"#).unwrap();
+        drop(log);
+
+        let mut space = open(&dir);
+        assert_eq_no_order!(space, vec![expr!("A")]);
+        space.add(expr!("B"));
+        drop(space);
+
+        let space = open(&dir);
+        assert_eq_no_order!(space, vec![expr!("A"), expr!("B")]);
+    }
+
+    #[test]
+    fn persistent_space_cannot_be_opened_twice() {
+        let dir = TestDir::new("persistent-space-lock");
+        let space = open(&dir);
+
+        let error = PersistentSpace::open(&dir.0, &Tokenizer::new()).err().unwrap();
+        assert_eq!(error, format!("Space {} is already opened", dir.0.display()));
+        drop(space);
+        assert!(PersistentSpace::open(&dir.0, &Tokenizer::new()).is_ok());
+    }
+
+    #[test]
+    fn persistent_space_refuses_modifications_after_write_failure() {
+        let dir = TestDir::new("persistent-space-write-failure");
+        let mut space = open(&dir);
+        space.add(expr!("A"));
+        space.add(expr!("B"));
+        space.log = File::open(log_file(&dir.0, 0)).unwrap();
+
+        space.add(expr!("C"));
+        assert!(!space.remove(&expr!("A")));
+        assert!(!space.replace(&expr!("B"), expr!("D")));
+        assert_eq_no_order!(space, vec![expr!("A"), expr!("B")]);
+        let error = space.sync().unwrap_err();
+        assert!(error.starts_with("Could not write space log"), "unexpected error: {}", error);
+        assert_eq!(space.error(), Some(error.as_str()));
+        drop(space);
+
+        let space = open(&dir);
+        assert_eq_no_order!(space, vec![expr!("A"), expr!("B")]);
+    }
+
+    #[test]
+    fn persistent_space_reports_invalid_record() {
+        let dir = TestDir::new("persistent-space-invalid");
+        fs::create_dir_all(&dir.0).unwrap();
+        fs::write(log_file(&dir.0, 0), "{\"insert\": {\"symbol\": \"A\"}}\n").unwrap();
+
+        let error = PersistentSpace::open(&dir.0, &Tokenizer::new()).unwrap_err();
+        assert!(error.starts_with("Invalid record in space log"), "unexpected error: {}", error);
+    }
+}
@@ -0,0 +1,598 @@
+//! Access to a space from other processes over TCP or Unix sockets.
+//!
+//! [SpaceServer] exposes a [DynSpace] to the clients and [RemoteSpace] is the
+//! client side [Space] implementation which proxies calls to the server. Thus
+//! several MeTTa runners can share one knowledge base.
+//!
+//! Client and server exchange JSON values, one value per line, atoms are
+//! encoded by [atom_to_json]. The client sends requests
+//! `{"id": <number>, "method": <name>, "args": [<atom>, ...]}` with the
+//! following methods:
+//! - `add`, `remove` and `replace` modify the space;
+//! - `query` returns the query atoms with the variables substituted by
+//!   the values found;
+//! - `atom_count` returns the number of atoms in the space;
+//! - `subscribe` asks the server to send the events of the space.
+//!
+//! The server replies by `{"id": <number>, "result": <value>}` or by
+//! `{"id": <number>, "error": <message>}`. The events of the space are sent to
+//! the subscribed clients as `{"event": <name>, "atoms": [<atom>, ...]}` where
+//! the name is one of `add`, `remove` or `replace`. Events caused by a request
+//! are sent before the reply.
+
+use super::*;
+use crate::atom::matcher::match_atoms;
+use crate::metta::text::Tokenizer;
+use crate::metta::json::{atom_to_json, atom_from_json};
+
+use std::fmt::Debug;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+use serde_json::{json, Value};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+
+/// Interval after which the server sends events of the space which are not
+/// caused by the clients' requests
+const EVENTS_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+/// Interval after which the server accepts connections again after an error,
+/// it prevents spinning when the error persists
+const ACCEPT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+/// Time the client waits for the reply of the server by default
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn event_to_json(event: &SpaceEvent) -> Value {
+    let (name, atoms) = match event {
+        SpaceEvent::Add(atom) => ("add", vec![atom_to_json(atom)]),
+        SpaceEvent::Remove(atom) => ("remove", vec![atom_to_json(atom)]),
+        SpaceEvent::Replace(from, to) => ("replace", vec![atom_to_json(from), atom_to_json(to)]),
+    };
+    json!({"event": name, "atoms": atoms})
+}
+
+fn event_from_json(name: &str, atoms: &[Atom]) -> Result<SpaceEvent, String> {
+    match (name, atoms) {
+        ("add", [atom]) => Ok(SpaceEvent::Add(atom.clone())),
+        ("remove", [atom]) => Ok(SpaceEvent::Remove(atom.clone())),
+        ("replace", [from, to]) => Ok(SpaceEvent::Replace(from.clone(), to.clone())),
+        _ => Err(format!("Unexpected event: {}", name)),
+    }
+}
+
+fn atoms_from_json(value: &Value, tokenizer: &Tokenizer) -> Result<Vec<Atom>, String> {
+    value.as_array().ok_or_else(|| format!("Array of atoms is expected: {}", value))?
+        .iter().map(|atom| atom_from_json(atom, tokenizer)).collect()
+}
+
+/// Reads JSON values from the `reader` line by line and sends them using
+/// `sender` wrapped by `wrap`. Returns when the stream is closed or the
+/// receiver is dropped.
+fn read_values<R: Read, T, F: Fn(Value) -> T>(reader: R, sender: &Sender<T>, wrap: F) {
+    for line in BufReader::new(reader).lines() {
+        let value = match line.map(|line| serde_json::from_str(&line)) {
+            Ok(Ok(value)) => value,
+            Ok(Err(err)) => {
+                log::error!("Invalid JSON is received: {}", err);
+                continue
+            },
+            Err(_) => break,
+        };
+        if sender.send(wrap(value)).is_err() {
+            break;
+        }
+    }
+}
+
+enum ServerMessage {
+    Connected(usize, Box<dyn Write + Send>),
+    Request(usize, Value),
+    Disconnected(usize),
+}
+
+struct Connection {
+    writer: Box<dyn Write + Send>,
+    subscribed: bool,
+}
+
+#[derive(Default)]
+struct EventQueue(Vec<SpaceEvent>);
+
+impl SpaceObserver for EventQueue {
+    fn notify(&mut self, event: &SpaceEvent) {
+        self.0.push(event.clone());
+    }
+}
+
+/// Server which gives the clients access to the [DynSpace]. The space is
+/// accessed from the thread which calls [SpaceServer::run], connections are
+/// accepted and read by the separate threads.
+pub struct SpaceServer {
+    space: DynSpace,
+    tokenizer: Tokenizer,
+    events: SpaceObserverRef<EventQueue>,
+    sender: Sender<ServerMessage>,
+    receiver: Receiver<ServerMessage>,
+    connections: HashMap<usize, Connection>,
+    next_connection: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl SpaceServer {
+    /// Creates new server for the `space`, the `tokenizer` is used to
+    /// restore grounded atoms received from the clients
+    pub fn new(space: DynSpace, tokenizer: Tokenizer) -> Self {
+        let events = space.register_observer(EventQueue::default());
+        let (sender, receiver) = channel();
+        Self{ space, tokenizer, events, sender, receiver, connections: HashMap::new(),
+            next_connection: Default::default() }
+    }
+
+    /// Starts accepting TCP connections on the `addr`, returns the local
+    /// address of the listener
+    pub fn listen_tcp<A: ToSocketAddrs>(&mut self, addr: A) -> Result<SocketAddr, String> {
+        let listener = TcpListener::bind(addr).map_err(|err| format!("Could not listen TCP address: {}", err))?;
+        let local_addr = listener.local_addr().map_err(|err| err.to_string())?;
+        log::debug!("SpaceServer::listen_tcp: {}", local_addr);
+        self.accept(move || listener.accept().map(|(stream, _)| stream),
+            |stream| stream.try_clone().map(|writer| Box::new(writer) as Box<dyn Write + Send>));
+        Ok(local_addr)
+    }
+
+    /// Starts accepting connections on the Unix socket `path`
+    #[cfg(unix)]
+    pub fn listen_unix<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let listener = UnixListener::bind(path.as_ref())
+            .map_err(|err| format!("Could not listen Unix socket {}: {}", path.as_ref().display(), err))?;
+        log::debug!("SpaceServer::listen_unix: {}", path.as_ref().display());
+        self.accept(move || listener.accept().map(|(stream, _)| stream),
+            |stream| stream.try_clone().map(|writer| Box::new(writer) as Box<dyn Write + Send>));
+        Ok(())
+    }
+
+    fn accept<A, S, F>(&self, mut accept: A, writer: F)
+        where
+            A: FnMut() -> std::io::Result<S> + Send + 'static,
+            S: Read + Send + 'static,
+            F: Fn(&S) -> std::io::Result<Box<dyn Write + Send>> + Send + 'static,
+    {
+        let sender = self.sender.clone();
+        let next_connection = self.next_connection.clone();
+        std::thread::spawn(move || {
+            loop {
+                let (stream, writer) = match accept().and_then(|stream| writer(&stream).map(|writer| (stream, writer))) {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        log::error!("SpaceServer: could not accept connection: {}", err);
+                        std::thread::sleep(ACCEPT_RETRY_INTERVAL);
+                        continue
+                    },
+                };
+                let id = next_connection.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                if sender.send(ServerMessage::Connected(id, writer)).is_err() {
+                    break;
+                }
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    read_values(stream, &sender, |value| ServerMessage::Request(id, value));
+                    let _ = sender.send(ServerMessage::Disconnected(id));
+                });
+            }
+        });
+    }
+
+    /// Processes requests of the clients, the method never returns
+    pub fn run(&mut self) {
+        loop {
+            match self.receiver.recv_timeout(EVENTS_FLUSH_INTERVAL) {
+                Ok(message) => self.handle(message),
+                Err(RecvTimeoutError::Timeout) => {},
+                // Never happens because the server keeps the sender
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            self.send_events();
+        }
+    }
+
+    /// Processes requests which were received so far and returns
+    pub fn process_requests(&mut self) {
+        while let Ok(message) = self.receiver.try_recv() {
+            self.handle(message);
+        }
+        self.send_events();
+    }
+
+    fn handle(&mut self, message: ServerMessage) {
+        match message {
+            ServerMessage::Connected(id, writer) => {
+                log::debug!("SpaceServer: connection {} is accepted", id);
+                self.connections.insert(id, Connection{ writer, subscribed: false });
+            },
+            ServerMessage::Disconnected(id) => {
+                log::debug!("SpaceServer: connection {} is closed", id);
+                self.connections.remove(&id);
+            },
+            ServerMessage::Request(id, request) => {
+                log::debug!("SpaceServer: connection {} request: {}", id, request);
+                let response = match self.execute(id, &request) {
+                    Ok(result) => json!({"id": request["id"], "result": result}),
+                    Err(error) => json!({"id": request["id"], "error": error}),
+                };
+                // Events caused by the request are sent before the response
+                self.send_events();
+                self.send(id, &response);
+            },
+        }
+    }
+
+    fn execute(&mut self, connection: usize, request: &Value) -> Result<Value, String> {
+        let method = request["method"].as_str().ok_or_else(|| format!("Invalid request: {}", request))?;
+        let args = match request.get("args") {
+            Some(args) => atoms_from_json(args, &self.tokenizer)?,
+            None => Vec::new(),
+        };
+        match (method, &args[..]) {
+            ("add", [atom]) => {
+                self.space.borrow_mut().add(atom.clone());
+                Ok(Value::Null)
+            },
+            ("remove", [atom]) => Ok(Value::from(self.space.borrow_mut().remove(atom))),
+            ("replace", [from, to]) => Ok(Value::from(self.space.borrow_mut().replace(from, to.clone()))),
+            ("query", [query]) => Ok(Value::Array(self.space.borrow().query(query).into_iter()
+                .map(|bindings| atom_to_json(&matcher::apply_bindings_to_atom(query, &bindings)))
+                .collect())),
+            ("atom_count", []) => Ok(self.space.borrow().atom_count().map_or(Value::Null, Value::from)),
+            ("subscribe", []) => {
+                if let Some(connection) = self.connections.get_mut(&connection) {
+                    connection.subscribed = true;
+                }
+                Ok(Value::Null)
+            },
+            _ => Err(format!("Unexpected method or arguments: {}", request)),
+        }
+    }
+
+    fn send_events(&mut self) {
+        let events = std::mem::take(&mut self.events.borrow_mut().0);
+        for event in events {
+            let event = event_to_json(&event);
+            let subscribed: Vec<usize> = self.connections.iter()
+                .filter(|(_, connection)| connection.subscribed)
+                .map(|(id, _)| *id).collect();
+            for id in subscribed {
+                self.send(id, &event);
+            }
+        }
+    }
+
+    fn send(&mut self, id: usize, value: &Value) {
+        if let Some(connection) = self.connections.get_mut(&id) {
+            let result = writeln!(connection.writer, "{}", value)
+                .and_then(|()| connection.writer.flush());
+            if let Err(err) = result {
+                log::error!("SpaceServer: could not write into connection {}: {}", id, err);
+                self.connections.remove(&id);
+            }
+        }
+    }
+}
+
+/// Client side [Space] implementation which executes all operations on the
+/// space of the [SpaceServer]. The client is subscribed to the events of the
+/// remote space, the events are passed to the observers of the client during
+/// the calls of the client methods or by [RemoteSpace::process_events].
+/// The connection is closed when the client is dropped.
+pub struct RemoteSpace {
+    writer: RefCell<Box<dyn Write + Send>>,
+    shutdown: Box<dyn Fn() -> std::io::Result<()> + Send>,
+    timeout: Duration,
+    receiver: Receiver<Value>,
+    tokenizer: Tokenizer,
+    common: SpaceCommon,
+    next_id: std::cell::Cell<u64>,
+    name: String,
+}
+
+impl RemoteSpace {
+    /// Connects to the server using TCP `addr`, the `tokenizer` is used to
+    /// restore grounded atoms received from the server
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A, tokenizer: &Tokenizer) -> Result<Self, String> {
+        let stream = TcpStream::connect(addr).map_err(|err| format!("Could not connect to space server: {}", err))?;
+        let name = stream.peer_addr().map_or_else(|_| "tcp".into(), |addr| addr.to_string());
+        let writer = stream.try_clone().map_err(|err| err.to_string())?;
+        let shutdown = stream.try_clone().map_err(|err| err.to_string())?;
+        Self::new(stream, Box::new(writer), Box::new(move || shutdown.shutdown(Shutdown::Both)), tokenizer, name)
+    }
+
+    /// Connects to the server using Unix socket `path`
+    #[cfg(unix)]
+    pub fn connect_unix<P: AsRef<Path>>(path: P, tokenizer: &Tokenizer) -> Result<Self, String> {
+        let path = path.as_ref();
+        let stream = UnixStream::connect(path)
+            .map_err(|err| format!("Could not connect to space server {}: {}", path.display(), err))?;
+        let writer = stream.try_clone().map_err(|err| err.to_string())?;
+        let shutdown = stream.try_clone().map_err(|err| err.to_string())?;
+        Self::new(stream, Box::new(writer), Box::new(move || shutdown.shutdown(Shutdown::Both)), tokenizer, path.display().to_string())
+    }
+
+    fn new<R: Read + Send + 'static>(reader: R, writer: Box<dyn Write + Send>,
+        shutdown: Box<dyn Fn() -> std::io::Result<()> + Send>, tokenizer: &Tokenizer, name: String) -> Result<Self, String>
+    {
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || read_values(reader, &sender, |value| value));
+        let space = Self{ writer: RefCell::new(writer), shutdown, timeout: DEFAULT_CALL_TIMEOUT,
+            receiver, tokenizer: tokenizer.clone(), common: SpaceCommon::default(),
+            next_id: Default::default(), name };
+        space.call("subscribe", vec![])?;
+        Ok(space)
+    }
+
+    /// Sets the time the client waits for the reply of the server, after
+    /// the timeout the call fails and the late reply is ignored
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Passes events of the remote space received so far to the observers
+    pub fn process_events(&self) {
+        while let Ok(message) = self.receiver.try_recv() {
+            self.process_message(message);
+        }
+    }
+
+    fn process_message(&self, message: Value) {
+        match message.get("event") {
+            Some(Value::String(name)) => {
+                match atoms_from_json(&message["atoms"], &self.tokenizer).and_then(|atoms| event_from_json(name, &atoms)) {
+                    Ok(event) => self.common.notify_all_observers(&event),
+                    Err(err) => log::error!("RemoteSpace: invalid event {}: {}", message, err),
+                }
+            },
+            _ => log::error!("RemoteSpace: unexpected message: {}", message),
+        }
+    }
+
+    fn send(&self, method: &str, args: Vec<Value>) -> Result<u64, String> {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let request = json!({"id": id, "method": method, "args": args});
+        log::debug!("RemoteSpace::send: {}, request: {}", self.name, request);
+        let mut writer = self.writer.borrow_mut();
+        writeln!(writer, "{}", request).and_then(|()| writer.flush())
+            .map_err(|err| format!("Could not send request to space server {}: {}", self.name, err))?;
+        Ok(id)
+    }
+
+    fn call(&self, method: &str, args: Vec<Value>) -> Result<Value, String> {
+        let id = self.send(method, args)?;
+        let deadline = std::time::Instant::now() + self.timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(std::time::Instant::now());
+            let message = self.receiver.recv_timeout(timeout).map_err(|err| match err {
+                RecvTimeoutError::Timeout => format!("Space server {} didn't reply in {:?}", self.name, self.timeout),
+                RecvTimeoutError::Disconnected => format!("Connection to space server {} is closed", self.name),
+            })?;
+            match message.get("id").and_then(Value::as_u64) {
+                Some(reply_id) if reply_id == id => {},
+                Some(_) => {
+                    log::debug!("RemoteSpace::call: {}, ignore reply: {}", self.name, message);
+                    continue;
+                },
+                None => {
+                    self.process_message(message);
+                    continue;
+                },
+            }
+            return match (message.get("result"), message.get("error")) {
+                (Some(result), _) => Ok(result.clone()),
+                (_, Some(error)) => Err(format!("Space server {} error: {}", self.name, error)),
+                _ => Err(format!("Invalid response of space server {}: {}", self.name, message)),
+            }
+        }
+    }
+
+    fn call_or_log<T, F: FnOnce(Value) -> Result<T, String>>(&self, method: &str, args: Vec<Value>, result: F) -> Option<T> {
+        match self.call(method, args).and_then(result) {
+            Ok(result) => Some(result),
+            Err(err) => {
+                log::error!("RemoteSpace: {} failed: {}", method, err);
+                None
+            },
+        }
+    }
+}
+
+impl Space for RemoteSpace {
+    fn common(&self) -> FlexRef<'_, SpaceCommon> {
+        FlexRef::from_simple(&self.common)
+    }
+    fn query(&self, query: &Atom) -> BindingsSet {
+        let query_vars: HashSet<&VariableAtom> = query.iter().filter_type::<&VariableAtom>().collect();
+        let results = self.call_or_log("query", vec![atom_to_json(query)],
+            |results| atoms_from_json(&results, &self.tokenizer)).unwrap_or_default();
+        results.iter()
+            .flat_map(|result| match_atoms(query, result))
+            .map(|bindings| bindings.narrow_vars(&query_vars))
+            .collect()
+    }
+    fn atom_count(&self) -> Option<usize> {
+        self.call_or_log("atom_count", vec![], |count| Ok(count.as_u64().map(|count| count as usize))).flatten()
+    }
+    fn as_any(&self) -> Option<&dyn std::any::Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
+        Some(self)
+    }
+}
+
+impl SpaceMut for RemoteSpace {
+    fn add(&mut self, atom: Atom) {
+        self.call_or_log("add", vec![atom_to_json(&atom)], |_| Ok(()));
+    }
+    fn remove(&mut self, atom: &Atom) -> bool {
+        self.call_or_log("remove", vec![atom_to_json(atom)], |removed| Ok(removed.as_bool() == Some(true)))
+            .unwrap_or(false)
+    }
+    fn replace(&mut self, from: &Atom, to: Atom) -> bool {
+        self.call_or_log("replace", vec![atom_to_json(from), atom_to_json(&to)], |replaced| Ok(replaced.as_bool() == Some(true)))
+            .unwrap_or(false)
+    }
+    fn as_space(&self) -> &dyn Space {
+        self
+    }
+}
+
+impl Drop for RemoteSpace {
+    fn drop(&mut self) {
+        // Stops the thread which reads the replies of the server
+        let _ = (self.shutdown)();
+    }
+}
+
+impl Debug for RemoteSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RemoteSpace-{} ({self:p})", self.name)
+    }
+}
+
+impl Display for RemoteSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RemoteSpace-{}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use crate::space::grounding::GroundingSpace;
+    use crate::metta::runner::arithmetics::Number;
+
+    struct EventCollector(Vec<SpaceEvent>);
+
+    impl SpaceObserver for EventCollector {
+        fn notify(&mut self, event: &SpaceEvent) {
+            self.0.push(event.clone());
+        }
+    }
+
+    fn start_server<F: FnOnce(&mut SpaceServer) -> T + Send + 'static, T: Send + 'static>(listen: F) -> T {
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            let space = DynSpace::new(GroundingSpace::new());
+            let mut server = SpaceServer::new(space, Tokenizer::new());
+            sender.send(listen(&mut server)).unwrap();
+            server.run();
+        });
+        receiver.recv().unwrap()
+    }
+
+    #[cfg(unix)]
+    fn unix_socket(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("hyperon-test-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server_path = path.clone();
+        start_server(move |server| server.listen_unix(server_path).unwrap());
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remote_space_over_unix_socket() {
+        let path = unix_socket("remote-space");
+        let mut first = RemoteSpace::connect_unix(&path, &Tokenizer::new()).unwrap();
+        let mut second = RemoteSpace::connect_unix(&path, &Tokenizer::new()).unwrap();
+
+        first.add(expr!("A" "B"));
+        first.add(expr!("A" ("C" x x)));
+        first.add(expr!("N" {Number::Integer(1)}));
+        assert_eq!(second.atom_count(), Some(3));
+        assert_eq!(second.query(&expr!("A" "B")), BindingsSet::single());
+        assert_eq!(second.query(&expr!("A" ("C" "D" z))), bind_set![{z: sym!("D")}]);
+        assert_eq!(second.query(&expr!("N" n)), bind_set![{n: expr!({Number::Integer(1)})}]);
+
+        assert!(second.remove(&expr!("A" "B")));
+        assert!(!second.remove(&expr!("A" "B")));
+        assert!(second.replace(&expr!("N" {Number::Integer(1)}), expr!("N" {Number::Integer(2)})));
+        assert_eq!(first.query(&expr!("A" "B")), BindingsSet::empty());
+        assert_eq!(first.query(&expr!("N" n)), bind_set![{n: expr!({Number::Integer(2)})}]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remote_space_notifies_observers() {
+        let path = unix_socket("remote-space-events");
+        let mut first = RemoteSpace::connect_unix(&path, &Tokenizer::new()).unwrap();
+        let second = RemoteSpace::connect_unix(&path, &Tokenizer::new()).unwrap();
+        let first_events = first.common().register_observer(EventCollector(Vec::new()));
+        let second_events = second.common().register_observer(EventCollector(Vec::new()));
+
+        first.add(expr!("A"));
+        first.replace(&expr!("A"), expr!("B"));
+        first.remove(&expr!("B"));
+        let expected = vec![
+            SpaceEvent::Add(expr!("A")),
+            SpaceEvent::Replace(expr!("A"), expr!("B")),
+            SpaceEvent::Remove(expr!("B")),
+        ];
+        assert_eq!(first_events.borrow().0, expected);
+        // Events are sent before the reply to the next request of the client
+        assert_eq!(second.atom_count(), Some(0));
+        assert_eq!(second_events.borrow().0, expected);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Returns a client connected to the server which replies only to the
+    /// subscription request and the server side of the connection
+    fn silent_server() -> (RemoteSpace, BufReader<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            assert_eq!(request, "{\"args\":[],\"id\":0,\"method\":\"subscribe\"}\n");
+            writeln!(stream, "{}", json!({"id": 0, "result": null})).unwrap();
+            reader
+        });
+        let space = RemoteSpace::connect_tcp(addr, &Tokenizer::new()).unwrap();
+        (space, server.join().unwrap())
+    }
+
+    #[test]
+    fn remote_space_call_timeout() {
+        let (mut space, _server) = silent_server();
+        space.set_timeout(Duration::from_millis(50));
+
+        assert_eq!(space.call("atom_count", vec![]),
+            Err(format!("Space server {} didn't reply in 50ms", space.name)));
+        assert_eq!(space.atom_count(), None);
+    }
+
+    #[test]
+    fn remote_space_closes_connection_on_drop() {
+        let (space, mut server) = silent_server();
+        drop(space);
+
+        let mut request = String::new();
+        assert_eq!(server.read_line(&mut request).unwrap(), 0);
+    }
+
+    #[test]
+    fn remote_space_over_tcp() {
+        let addr = start_server(|server| server.listen_tcp("127.0.0.1:0").unwrap());
+        let space = DynSpace::new(RemoteSpace::connect_tcp(addr, &Tokenizer::new()).unwrap());
+        let other = RemoteSpace::connect_tcp(addr, &Tokenizer::new()).unwrap();
+
+        space.borrow_mut().add(expr!("likes" "Sam" "pizza"));
+        assert_eq!(other.query(&expr!("likes" x "pizza")), bind_set![{x: sym!("Sam")}]);
+        assert_eq!(space.borrow().subst(&expr!("likes" x y), &expr!(y)), vec![sym!("pizza")]);
+    }
+}
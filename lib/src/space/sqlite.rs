@@ -0,0 +1,490 @@
+//! Space which stores atoms in an SQLite database.
+//!
+//! [SqliteSpace] doesn't keep atoms in memory. Each atom is stored as its JSON
+//! encoding (see [atom_to_json]) together with the tree of its sub-atoms. Each
+//! sub-atom is a row of the `nodes` table which contains the path of the
+//! sub-atom inside of the atom, the kind of the sub-atom and the name of the
+//! symbol or the arity of the expression. The table is indexed by the path,
+//! thus symbols and expressions of the query are translated into an SQL query
+//! which selects the candidate atoms using the index. Variables and grounded
+//! atoms can match anything, so they are not included into the SQL query.
+//! Candidates are matched with the query in the same way [GroundingSpace]
+//! does it.
+//!
+//! [GroundingSpace]: super::grounding::GroundingSpace
+
+use super::*;
+use super::grounding::complex_query_iter;
+use crate::atom::matcher::match_atoms;
+use crate::metta::text::Tokenizer;
+use crate::metta::json::{atom_to_json, atom_from_json};
+
+use std::fmt::Debug;
+use std::collections::HashSet;
+use std::path::Path;
+use rusqlite::{Connection, OptionalExtension, params};
+
+const SYMBOL: i64 = 0;
+const VARIABLE: i64 = 1;
+const GROUNDED: i64 = 2;
+const EXPRESSION: i64 = 3;
+
+/// Maximal number of sub-atoms of the query translated into SQL, the rest of
+/// the query is checked by matching candidates
+const MAX_QUERY_CONSTRAINTS: usize = 64;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS atoms (
+        id INTEGER PRIMARY KEY,
+        json TEXT NOT NULL);
+    CREATE INDEX IF NOT EXISTS atoms_json ON atoms (json);
+    CREATE TABLE IF NOT EXISTS nodes (
+        atom INTEGER NOT NULL,
+        path TEXT NOT NULL,
+        kind INTEGER NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (atom, path)) WITHOUT ROWID;
+    CREATE INDEX IF NOT EXISTS nodes_value ON nodes (path, kind, value);
+";
+
+/// Space which stores atoms in an SQLite database, see [module
+/// documentation](self) for details.
+///
+/// Grounded atoms are stored using [Grounded::serialize]. Grounded atoms which
+/// cannot be serialized are stored as their textual representation and are
+/// restored by the [Tokenizer] passed to the constructor.
+pub struct SqliteSpace {
+    conn: Connection,
+    tokenizer: Tokenizer,
+    common: SpaceCommon,
+    name: String,
+}
+
+impl SqliteSpace {
+    /// Opens the space stored in the SQLite database file `path`, the file is
+    /// created when it doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperon::{expr, bind_set, sym};
+    /// use hyperon::metta::text::Tokenizer;
+    /// use hyperon::space::{Space, SpaceMut};
+    /// use hyperon::space::sqlite::SqliteSpace;
+    ///
+    /// let path = std::env::temp_dir().join(format!("hyperon-doc-sqlite-space-{}.db", std::process::id()));
+    /// let mut space = SqliteSpace::open(&path, &Tokenizer::new()).unwrap();
+    /// space.add(expr!("A" "B"));
+    /// drop(space);
+    ///
+    /// let space = SqliteSpace::open(&path, &Tokenizer::new()).unwrap();
+    /// assert_eq!(space.query(&expr!("A" x)), bind_set![{x: sym!("B")}]);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P, tokenizer: &Tokenizer) -> Result<Self, String> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .map_err(|err| format!("Could not open SQLite space {}: {}", path.display(), err))?;
+        Self::from_connection(conn, tokenizer, path.display().to_string())
+    }
+
+    /// Creates new space which stores atoms in an in-memory SQLite database
+    pub fn open_in_memory(tokenizer: &Tokenizer) -> Result<Self, String> {
+        let conn = Connection::open_in_memory()
+            .map_err(|err| format!("Could not open SQLite space in memory: {}", err))?;
+        Self::from_connection(conn, tokenizer, ":memory:".into())
+    }
+
+    fn from_connection(conn: Connection, tokenizer: &Tokenizer, name: String) -> Result<Self, String> {
+        conn.execute_batch(SCHEMA)
+            .map_err(|err| format!("Could not initialize SQLite space {}: {}", name, err))?;
+        Ok(Self{ conn, tokenizer: tokenizer.clone(), common: SpaceCommon::default(), name })
+    }
+
+    /// Adds `atom` into the space
+    pub fn add(&mut self, atom: Atom) {
+        log::debug!("SqliteSpace::add: {}, atom: {}", self.name, atom);
+        match self.insert(&atom) {
+            Ok(()) => self.common.notify_all_observers(&SpaceEvent::Add(atom)),
+            Err(err) => log::error!("Could not add atom {} into SQLite space {}: {}", atom, self.name, err),
+        }
+    }
+
+    /// Removes `atom` from the space, returns true if atom was found and removed
+    pub fn remove(&mut self, atom: &Atom) -> bool {
+        log::debug!("SqliteSpace::remove: {}, atom: {}", self.name, atom);
+        match self.delete(atom) {
+            Ok(true) => {
+                self.common.notify_all_observers(&SpaceEvent::Remove(atom.clone()));
+                true
+            },
+            Ok(false) => false,
+            Err(err) => {
+                log::error!("Could not remove atom {} from SQLite space {}: {}", atom, self.name, err);
+                false
+            },
+        }
+    }
+
+    /// Replaces `from` atom by `to` atom, returns true if `from` was found and replaced
+    pub fn replace(&mut self, from: &Atom, to: Atom) -> bool {
+        log::debug!("SqliteSpace::replace: {}, from: {}, to: {}", self.name, from, to);
+        let result = self.transaction(|space| {
+            let is_found = space.delete(from)?;
+            if is_found {
+                space.insert(&to)?;
+            }
+            Ok(is_found)
+        });
+        match result {
+            Ok(true) => {
+                self.common.notify_all_observers(&SpaceEvent::Replace(from.clone(), to));
+                true
+            },
+            Ok(false) => false,
+            Err(err) => {
+                log::error!("Could not replace atom {} in SQLite space {}: {}", from, self.name, err);
+                false
+            },
+        }
+    }
+
+    /// Executes `query` on the space and returns an iterator over the
+    /// variable bindings found. Identifiers of the candidate atoms are
+    /// selected from the database first, candidates are loaded and matched
+    /// with the query lazily.
+    pub fn query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        complex_query_iter(query, move |query| self.single_query_iter(query))
+    }
+
+    fn single_query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        log::debug!("SqliteSpace::single_query: {}, query: {}", self.name, query);
+        let candidates = match self.select_candidates(query) {
+            Ok(candidates) => candidates,
+            Err(err) => {
+                log::error!("Could not execute query {} on SQLite space {}: {}", query, self.name, err);
+                Vec::new()
+            },
+        };
+        let query = query.clone();
+        let query_vars: HashSet<VariableAtom> = query.iter().filter_type::<&VariableAtom>().cloned().collect();
+        QueryIter::new(candidates.into_iter()
+            .filter_map(move |id| match self.load(id) {
+                Ok(atom) => atom,
+                Err(err) => {
+                    log::error!("Could not load atom {} from SQLite space {}: {}", id, self.name, err);
+                    None
+                },
+            })
+            .flat_map(move |next| {
+                let next = make_variables_unique(next);
+                log::trace!("SqliteSpace::single_query: match next: {}", next);
+                let query_vars = query_vars.clone();
+                match_atoms(&next, &query).map(move |bindings| bindings.narrow_vars(&query_vars))
+            }))
+    }
+
+    fn select_candidates(&self, query: &Atom) -> Result<Vec<i64>, String> {
+        let mut constraints = Vec::new();
+        query_constraints(query, &mut vec![String::new()], &mut constraints);
+        // The arity of the query root is matched by each stored atom of the
+        // same arity, thus it is used only when there are no other constraints
+        if constraints.len() > 1 {
+            constraints.retain(|constraint| constraint.prefixes.len() > 1);
+        }
+        constraints.truncate(MAX_QUERY_CONSTRAINTS);
+
+        let mut sql = String::new();
+        let mut args: Vec<rusqlite::types::Value> = Vec::new();
+        for (i, constraint) in constraints.into_iter().enumerate() {
+            if i > 0 {
+                sql.push_str(" INTERSECT ");
+            }
+            let wildcards = vec!["?"; constraint.prefixes.len()].join(", ");
+            sql.push_str(&format!("SELECT atom FROM (\
+                SELECT atom FROM nodes WHERE path = ? AND kind = ? AND value = ? \
+                UNION SELECT atom FROM nodes WHERE path IN ({}) AND kind IN ({}, {}))",
+                wildcards, VARIABLE, GROUNDED));
+            args.push(constraint.prefixes.last().unwrap().clone().into());
+            args.push(constraint.kind.into());
+            args.push(constraint.value.into());
+            args.extend(constraint.prefixes.into_iter().map(Into::into));
+        }
+        if sql.is_empty() {
+            sql.push_str("SELECT id FROM atoms");
+        }
+        sql.push_str(" ORDER BY 1");
+        log::trace!("SqliteSpace::select_candidates: sql: {}, args: {:?}", sql, args);
+
+        let mut stmt = self.conn.prepare_cached(&sql).map_err(|err| err.to_string())?;
+        let ids = stmt.query_map(rusqlite::params_from_iter(args), |row| row.get(0))
+            .map_err(|err| err.to_string())?;
+        ids.collect::<Result<Vec<i64>, _>>().map_err(|err| err.to_string())
+    }
+
+    fn load(&self, id: i64) -> Result<Option<Atom>, String> {
+        let mut stmt = self.conn.prepare_cached("SELECT json FROM atoms WHERE id = ?")
+            .map_err(|err| err.to_string())?;
+        let json: Option<String> = stmt.query_row(params![id], |row| row.get(0)).optional()
+            .map_err(|err| err.to_string())?;
+        json.map(|json| {
+            let value = serde_json::from_str(&json).map_err(|err| err.to_string())?;
+            atom_from_json(&value, &self.tokenizer)
+        }).transpose()
+    }
+
+    fn insert(&self, atom: &Atom) -> Result<(), String> {
+        self.transaction(|space| {
+            space.conn.execute("INSERT INTO atoms (json) VALUES (?)", params![atom_to_json(atom).to_string()])
+                .map_err(|err| err.to_string())?;
+            let id = space.conn.last_insert_rowid();
+            let mut stmt = space.conn.prepare_cached("INSERT INTO nodes (atom, path, kind, value) VALUES (?, ?, ?, ?)")
+                .map_err(|err| err.to_string())?;
+            let mut nodes = Vec::new();
+            atom_nodes(atom, String::new(), &mut nodes);
+            for (path, kind, value) in nodes {
+                stmt.execute(params![id, path, kind, value]).map_err(|err| err.to_string())?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Deletes all copies of the `atom`, returns true if any copy was found
+    fn delete(&self, atom: &Atom) -> Result<bool, String> {
+        self.transaction(|space| {
+            let json = atom_to_json(atom).to_string();
+            space.conn.execute("DELETE FROM nodes WHERE atom IN (SELECT id FROM atoms WHERE json = ?)", params![json])
+                .map_err(|err| err.to_string())?;
+            let deleted = space.conn.execute("DELETE FROM atoms WHERE json = ?", params![json])
+                .map_err(|err| err.to_string())?;
+            Ok(deleted > 0)
+        })
+    }
+
+    /// Executes `f` inside of a transaction, nested calls are executed
+    /// inside of the outer transaction
+    fn transaction<T, F: FnOnce(&Self) -> Result<T, String>>(&self, f: F) -> Result<T, String> {
+        if !self.conn.is_autocommit() {
+            return f(self);
+        }
+        self.conn.execute_batch("BEGIN").map_err(|err| err.to_string())?;
+        match f(self) {
+            Ok(result) => {
+                self.conn.execute_batch("COMMIT").map_err(|err| err.to_string())?;
+                Ok(result)
+            },
+            Err(err) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(err)
+            },
+        }
+    }
+}
+
+/// Returns path of the child sub-atom with the `index` inside of the parent
+/// sub-atom with the `path`
+fn child_path(path: &str, index: usize) -> String {
+    if path.is_empty() {
+        index.to_string()
+    } else {
+        format!("{}.{}", path, index)
+    }
+}
+
+fn atom_nodes(atom: &Atom, path: String, nodes: &mut Vec<(String, i64, String)>) {
+    match atom {
+        Atom::Symbol(sym) => nodes.push((path, SYMBOL, sym.name().into())),
+        Atom::Variable(var) => nodes.push((path, VARIABLE, var.name())),
+        Atom::Grounded(_) => nodes.push((path, GROUNDED, String::new())),
+        Atom::Expression(expr) => {
+            let children = expr.children();
+            for (i, child) in children.iter().enumerate() {
+                atom_nodes(child, child_path(&path, i), nodes);
+            }
+            nodes.push((path, EXPRESSION, children.len().to_string()));
+        },
+    }
+}
+
+/// Sub-atom of the query which should be present in the candidate atom: a
+/// node with the path which is the last of `prefixes` and with the `kind` and
+/// `value`, or a variable or grounded atom with one of the `prefixes` as a path
+struct Constraint {
+    prefixes: Vec<String>,
+    kind: i64,
+    value: String,
+}
+
+fn query_constraints(query: &Atom, prefixes: &mut Vec<String>, constraints: &mut Vec<Constraint>) {
+    let path = prefixes.last().unwrap().clone();
+    match query {
+        Atom::Symbol(sym) => constraints.push(Constraint{ prefixes: prefixes.clone(), kind: SYMBOL, value: sym.name().into() }),
+        Atom::Variable(_) | Atom::Grounded(_) => {},
+        Atom::Expression(expr) => {
+            let children = expr.children();
+            constraints.push(Constraint{ prefixes: prefixes.clone(), kind: EXPRESSION, value: children.len().to_string() });
+            for (i, child) in children.iter().enumerate() {
+                prefixes.push(child_path(&path, i));
+                query_constraints(child, prefixes, constraints);
+                prefixes.pop();
+            }
+        },
+    }
+}
+
+impl Space for SqliteSpace {
+    fn common(&self) -> FlexRef<'_, SpaceCommon> {
+        FlexRef::from_simple(&self.common)
+    }
+    fn query(&self, query: &Atom) -> BindingsSet {
+        let result: BindingsSet = self.query_iter(query).collect();
+        log::debug!("SqliteSpace::query: {}, query: {}, result: {:?}", self.name, query, result);
+        result
+    }
+    fn query_iter(&self, query: &Atom) -> QueryIter<'_> {
+        SqliteSpace::query_iter(self, query)
+    }
+    fn atom_count(&self) -> Option<usize> {
+        self.conn.query_row("SELECT COUNT(*) FROM atoms", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize).ok()
+    }
+    fn as_any(&self) -> Option<&dyn std::any::Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
+        Some(self)
+    }
+}
+
+impl SpaceMut for SqliteSpace {
+    fn add(&mut self, atom: Atom) {
+        SqliteSpace::add(self, atom)
+    }
+    fn remove(&mut self, atom: &Atom) -> bool {
+        SqliteSpace::remove(self, atom)
+    }
+    fn replace(&mut self, from: &Atom, to: Atom) -> bool {
+        SqliteSpace::replace(self, from, to)
+    }
+    fn as_space(&self) -> &dyn Space {
+        self
+    }
+}
+
+impl Debug for SqliteSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SqliteSpace-{} ({self:p})", self.name)
+    }
+}
+
+impl Display for SqliteSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SqliteSpace-{}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use crate::space::grounding::GroundingSpace;
+    use crate::metta::runner::arithmetics::Number;
+
+    fn sqlite_space(atoms: &[Atom]) -> SqliteSpace {
+        let mut space = SqliteSpace::open_in_memory(&Tokenizer::new()).unwrap();
+        atoms.iter().for_each(|atom| space.add(atom.clone()));
+        space
+    }
+
+    fn assert_same_results(atoms: Vec<Atom>, query: Atom) {
+        let space = sqlite_space(&atoms);
+        let expected = GroundingSpace::from_vec(atoms).query(&query);
+        let actual = space.query(&query);
+        assert_eq_no_order!(actual, expected);
+    }
+
+    #[test]
+    fn sqlite_space_query() {
+        let atoms = vec![
+            expr!("likes" "Sam" "pizza"),
+            expr!("likes" "Sam" ("green" "tea")),
+            expr!("likes" "Ann" ("green" "tea")),
+            expr!("hates" "Ann" "pizza"),
+            expr!("likes" "Ann"),
+        ];
+        assert_same_results(atoms.clone(), expr!("likes" "Sam" x));
+        assert_same_results(atoms.clone(), expr!("likes" x ("green" y)));
+        assert_same_results(atoms.clone(), expr!(x "Ann" "pizza"));
+        assert_same_results(atoms.clone(), expr!("likes" x));
+        assert_same_results(atoms.clone(), expr!(x));
+        assert_same_results(atoms.clone(), expr!("," ("likes" x "pizza") ("likes" x ("green" "tea"))));
+        assert_same_results(atoms, expr!("," ("hates" x y) ("likes" "Sam" y)));
+    }
+
+    #[test]
+    fn sqlite_space_selects_candidates_by_index() {
+        let space = sqlite_space(&[
+            expr!("A" "B"),
+            expr!("A" ("C" "D")),
+            expr!("B" "B"),
+            expr!("A" "B" "C"),
+        ]);
+        assert_eq!(space.select_candidates(&expr!("A" x)), Ok(vec![1, 2, 4]));
+        assert_eq!(space.select_candidates(&expr!(x "B")), Ok(vec![1, 3, 4]));
+        assert_eq!(space.select_candidates(&expr!("A" ("C" x))), Ok(vec![2]));
+        assert_eq!(space.select_candidates(&expr!(x y z)), Ok(vec![4]));
+        assert_eq!(space.select_candidates(&expr!(x)), Ok(vec![1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn sqlite_space_variables_and_grounded_atoms_are_wildcards() {
+        let atoms = vec![
+            expr!("A" x),
+            expr!("A" ("B" x)),
+            expr!("A" {Number::Integer(1)}),
+            expr!("C" x x),
+        ];
+        assert_same_results(atoms.clone(), expr!("A" "B"));
+        assert_same_results(atoms.clone(), expr!("A" ("B" "C")));
+        assert_same_results(atoms.clone(), expr!("A" {Number::Integer(1)}));
+        assert_same_results(atoms, expr!("C" "D" y));
+    }
+
+    #[test]
+    fn sqlite_space_modification() {
+        let mut space = sqlite_space(&[expr!("A"), expr!("B"), expr!("A")]);
+        assert_eq!(space.atom_count(), Some(3));
+        assert!(space.remove(&expr!("A")));
+        assert_eq!(space.query(&expr!("A")), BindingsSet::empty());
+        space.add(expr!("A"));
+        assert!(!space.remove(&expr!("C")));
+        assert!(space.replace(&expr!("B"), expr!("C" "D")));
+        assert!(!space.replace(&expr!("B"), expr!("E")));
+        assert_eq!(space.query(&expr!("B")), BindingsSet::empty());
+        assert_eq!(space.query(&expr!("C" x)), bind_set![{x: sym!("D")}]);
+        assert_eq!(space.atom_count(), Some(2));
+    }
+
+    #[test]
+    fn sqlite_space_notifies_observers() {
+        struct Collector(Vec<SpaceEvent>);
+        impl SpaceObserver for Collector {
+            fn notify(&mut self, event: &SpaceEvent) {
+                self.0.push(event.clone());
+            }
+        }
+
+        let mut space = sqlite_space(&[]);
+        let observer = space.common().register_observer(Collector(Vec::new()));
+        space.add(expr!("A"));
+        space.replace(&expr!("A"), expr!("B"));
+        space.remove(&expr!("B"));
+        space.remove(&expr!("B"));
+
+        assert_eq!(observer.borrow().0, vec![
+            SpaceEvent::Add(expr!("A")),
+            SpaceEvent::Replace(expr!("A"), expr!("B")),
+            SpaceEvent::Remove(expr!("B")),
+        ]);
+    }
+}
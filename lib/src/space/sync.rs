@@ -0,0 +1,608 @@
+//! Space which can be shared between threads.
+//!
+//! [Atom] cannot be sent to another thread because grounded atoms may contain
+//! data which is not thread-safe. [SyncSpace] keeps atoms as [SyncAtom]s
+//! which are [Send] and [Sync]. [SyncSpace] is cheap to clone, all clones
+//! refer to the same content protected by a read-write lock, thus several
+//! threads can query the space in parallel. Each thread accesses the space
+//! through the [SyncSpaceRef] which implements [Space] and [SpaceMut] and
+//! can be added into the [Metta](crate::metta::runner::Metta) runner of the
+//! thread.
+//!
+//! Only the space is thread-safe, [Send] and [Sync] runner is not provided.
+//! The [Metta](crate::metta::runner::Metta) runner keeps its tokenizer,
+//! modules and grounded operations in [Rc](std::rc::Rc)s, thus it cannot be
+//! shared between threads. Each thread runs its own runner, and the knowledge
+//! shared by the threads is kept in a [SyncSpace]. A grounded atom added by
+//! one thread can be read by another thread only when it can be serialized
+//! or when the tokenizer of the other thread can parse its text, otherwise
+//! the query returns an error.
+//!
+//! Every modification of the [SyncSpace] is sent to all [SyncSpaceRef]s, thus
+//! observers registered in one thread see modifications made by the other
+//! threads. Events are passed to the observers during the calls of the
+//! [SyncSpaceRef] methods or by [SyncSpaceRef::process_events].
+//!
+//! # Examples
+//!
+//! ```
+//! use hyperon::{expr, Atom};
+//! use hyperon::metta::text::SExprParser;
+//! use hyperon::metta::runner::{Metta, EnvBuilder};
+//! use hyperon::space::DynSpace;
+//! use hyperon::space::sync::SyncSpace;
+//! use regex::Regex;
+//!
+//! let kb = SyncSpace::new();
+//! kb.add(&expr!("likes" "Sam" "pizza"));
+//!
+//! let threads: Vec<_> = (0..2).map(|_| {
+//!     let kb = kb.clone();
+//!     std::thread::spawn(move || {
+//!         let metta = Metta::new(Some(EnvBuilder::test_env()));
+//!         let space = DynSpace::new(kb.local(metta.tokenizer().clone()));
+//!         metta.tokenizer().borrow_mut().register_token(Regex::new("&kb").unwrap(),
+//!             move |_| Atom::gnd(space.clone()));
+//!         let result = metta.run(SExprParser::new("!(match &kb (likes Sam $x) $x)")).unwrap();
+//!         result[0][0].to_string()
+//!     })
+//! }).collect();
+//!
+//! for thread in threads {
+//!     assert_eq!(thread.join().unwrap(), "pizza");
+//! }
+//! ```
+
+use super::*;
+use super::grounding::complex_query_iter;
+use crate::atom::matcher::match_atoms;
+use crate::common::shared::Shared;
+use crate::metta::text::Tokenizer;
+use crate::metta::json::{grounded_to_json, grounded_from_json};
+
+use std::fmt::Debug;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use serde_json::Value;
+
+/// Thread-safe representation of an [Atom]. Grounded atoms are kept as the
+/// values written by [Grounded::serialize]. Grounded atoms which cannot be
+/// serialized are kept as their textual representation, and are converted
+/// back into atoms by a [Tokenizer], see [SyncAtom::to_atom].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyncAtom {
+    Symbol(SymbolAtom),
+    Variable(VariableAtom),
+    Expression(Vec<SyncAtom>),
+    /// Serialized value of the grounded atom
+    Value(Value),
+    /// Textual representation of the grounded atom which cannot be serialized
+    Text(String),
+}
+
+impl From<&Atom> for SyncAtom {
+    fn from(atom: &Atom) -> Self {
+        match atom {
+            Atom::Symbol(sym) => SyncAtom::Symbol(sym.clone()),
+            Atom::Variable(var) => SyncAtom::Variable(var.clone()),
+            Atom::Expression(expr) => SyncAtom::Expression(expr.children().iter().map(SyncAtom::from).collect()),
+            Atom::Grounded(gnd) => match grounded_to_json(&**gnd) {
+                Some(value) => SyncAtom::Value(value),
+                None => SyncAtom::Text(atom.to_string()),
+            },
+        }
+    }
+}
+
+impl SyncAtom {
+    /// Converts the value back into the [Atom], the `tokenizer` is used to
+    /// restore grounded atoms kept as text. Returns the textual
+    /// representation of the grounded atom as an error when it cannot be
+    /// restored.
+    pub fn to_atom(&self, tokenizer: &Tokenizer) -> Result<Atom, String> {
+        match self {
+            SyncAtom::Symbol(sym) => Ok(Atom::Symbol(sym.clone())),
+            SyncAtom::Variable(var) => Ok(Atom::Variable(var.clone())),
+            SyncAtom::Expression(children) => Ok(Atom::expr(children.iter()
+                .map(|child| child.to_atom(tokenizer)).collect::<Result<Vec<Atom>, String>>()?)),
+            SyncAtom::Value(value) => grounded_from_json(value)
+                .ok_or_else(|| value.to_string()),
+            SyncAtom::Text(text) => tokenizer.find_token(text)
+                .and_then(|constr| constr(text).ok())
+                .ok_or_else(|| text.clone()),
+        }
+    }
+}
+
+impl Display for SyncAtom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncAtom::Symbol(sym) => Display::fmt(sym, f),
+            SyncAtom::Variable(var) => Display::fmt(var, f),
+            SyncAtom::Expression(children) => {
+                write!(f, "(")?;
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    Display::fmt(child, f)?;
+                }
+                write!(f, ")")
+            },
+            SyncAtom::Value(value) => Display::fmt(value, f),
+            SyncAtom::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Returns the symbol used to index the atom: the symbol itself or the head
+/// of the expression. Other atoms are not indexed.
+fn index_key(atom: &Atom) -> Option<&SymbolAtom> {
+    match atom {
+        Atom::Symbol(sym) => Some(sym),
+        Atom::Expression(expr) => match expr.children().first() {
+            Some(Atom::Symbol(head)) => Some(head),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Thread-safe representation of a [SpaceEvent]
+#[derive(Clone, Debug)]
+enum SyncSpaceEvent {
+    Add(SyncAtom),
+    Remove(SyncAtom),
+    Replace(SyncAtom, SyncAtom),
+}
+
+impl SyncSpaceEvent {
+    fn to_event(&self, tokenizer: &Tokenizer) -> Result<SpaceEvent, String> {
+        Ok(match self {
+            SyncSpaceEvent::Add(atom) => SpaceEvent::Add(atom.to_atom(tokenizer)?),
+            SyncSpaceEvent::Remove(atom) => SpaceEvent::Remove(atom.to_atom(tokenizer)?),
+            SyncSpaceEvent::Replace(from, to) => SpaceEvent::Replace(from.to_atom(tokenizer)?, to.to_atom(tokenizer)?),
+        })
+    }
+}
+
+// MultiTrie cannot be used as an index because it is not thread-safe
+#[derive(Default)]
+struct SyncSpaceContent {
+    index: HashMap<SymbolAtom, BTreeSet<usize>>,
+    not_indexed: BTreeSet<usize>,
+    content: Vec<SyncAtom>,
+    free: BTreeSet<usize>,
+    /// Event queues of the [SyncSpaceRef]s, queue is removed after its
+    /// [SyncSpaceRef] is dropped
+    subscribers: Vec<Sender<SyncSpaceEvent>>,
+}
+
+impl SyncSpaceContent {
+    fn add(&mut self, key: Option<&SymbolAtom>, atom: SyncAtom) {
+        let pos = match self.free.pop_first() {
+            Some(pos) => {
+                self.content[pos] = atom;
+                pos
+            },
+            None => {
+                self.content.push(atom);
+                self.content.len() - 1
+            },
+        };
+        self.positions(key).insert(pos);
+    }
+
+    fn remove(&mut self, key: Option<&SymbolAtom>, query: &Atom, atom: &SyncAtom) -> bool {
+        let removed: Vec<usize> = self.candidates(query).into_iter()
+            .filter(|pos| self.content[*pos] == *atom).collect();
+        for pos in &removed {
+            self.positions(key).remove(pos);
+            self.free.insert(*pos);
+        }
+        !removed.is_empty()
+    }
+
+    fn notify(&mut self, event: SyncSpaceEvent) {
+        self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn positions(&mut self, key: Option<&SymbolAtom>) -> &mut BTreeSet<usize> {
+        match key {
+            Some(key) => self.index.entry(key.clone()).or_default(),
+            None => &mut self.not_indexed,
+        }
+    }
+
+    fn candidates(&self, query: &Atom) -> Vec<usize> {
+        match index_key(query) {
+            Some(key) => self.index.get(key).into_iter().flatten()
+                .chain(self.not_indexed.iter()).copied().collect(),
+            None => (0..self.content.len()).filter(|pos| !self.free.contains(pos)).collect(),
+        }
+    }
+}
+
+/// Space which can be shared between threads, see [module documentation](self)
+#[derive(Clone, Default)]
+pub struct SyncSpace(Arc<RwLock<SyncSpaceContent>>);
+
+impl SyncSpace {
+    /// Constructs new empty space
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the [Space] implementation to access the space from the current
+    /// thread, the `tokenizer` is used to restore grounded atoms which are
+    /// kept as text
+    pub fn local(&self, tokenizer: Shared<Tokenizer>) -> SyncSpaceRef {
+        let (sender, events) = channel();
+        self.0.write().unwrap().subscribers.push(sender);
+        SyncSpaceRef{ space: self.clone(), tokenizer, common: SpaceCommon::default(), events }
+    }
+
+    /// Adds `atom` into the space
+    pub fn add(&self, atom: &Atom) {
+        let sync_atom = SyncAtom::from(atom);
+        let mut space = self.0.write().unwrap();
+        space.add(index_key(atom), sync_atom.clone());
+        space.notify(SyncSpaceEvent::Add(sync_atom));
+    }
+
+    /// Removes all copies of the `atom` from the space, returns true if the
+    /// atom was found
+    pub fn remove(&self, atom: &Atom) -> bool {
+        let sync_atom = SyncAtom::from(atom);
+        let mut space = self.0.write().unwrap();
+        let is_removed = space.remove(index_key(atom), atom, &sync_atom);
+        if is_removed {
+            space.notify(SyncSpaceEvent::Remove(sync_atom));
+        }
+        is_removed
+    }
+
+    /// Replaces `from` atom by `to` atom, doesn't add `to` when `from` is not
+    /// found. Returns true if `from` was found. Other threads never observe
+    /// the space without both atoms.
+    pub fn replace(&self, from: &Atom, to: &Atom) -> bool {
+        let sync_from = SyncAtom::from(from);
+        let sync_to = SyncAtom::from(to);
+        let mut space = self.0.write().unwrap();
+        let is_replaced = space.remove(index_key(from), from, &sync_from);
+        if is_replaced {
+            space.add(index_key(to), sync_to.clone());
+            space.notify(SyncSpaceEvent::Replace(sync_from, sync_to));
+        }
+        is_replaced
+    }
+
+    /// Executes `query` on the space, the `tokenizer` is used to restore
+    /// grounded atoms which are kept as text. Returns the textual
+    /// representation of the grounded atom as an error when the atom
+    /// matching the query contains a grounded atom which cannot be restored.
+    pub fn query(&self, query: &Atom, tokenizer: &Tokenizer) -> Result<BindingsSet, String> {
+        let error = RefCell::new(None);
+        let result = complex_query_iter(query, |query| self.single_query_iter(query, tokenizer, &error)).collect();
+        match error.into_inner() {
+            Some(text) => Err(text),
+            None => Ok(result),
+        }
+    }
+
+    /// Executes simple query, puts the text of the first grounded atom which
+    /// cannot be restored into `error` and returns no results in such case
+    fn single_query_iter<'a>(&'a self, query: &Atom, tokenizer: &'a Tokenizer, error: &RefCell<Option<String>>) -> QueryIter<'a> {
+        log::debug!("SyncSpace::single_query: query: {}", query);
+        // Candidates are copied to release the lock before matching
+        let candidates: Result<Vec<Atom>, String> = {
+            let space = self.0.read().unwrap();
+            space.candidates(query).into_iter()
+                .map(|pos| space.content[pos].to_atom(tokenizer))
+                .collect()
+        };
+        let candidates = match candidates {
+            Ok(candidates) => candidates,
+            Err(text) => {
+                error.borrow_mut().get_or_insert(text);
+                return QueryIter::new(std::iter::empty());
+            },
+        };
+        let query = query.clone();
+        let query_vars: HashSet<VariableAtom> = query.iter().filter_type::<&VariableAtom>().cloned().collect();
+        QueryIter::new(candidates.into_iter().flat_map(move |next| {
+            let next = make_variables_unique(next);
+            let query_vars = query_vars.clone();
+            match_atoms(&next, &query).map(move |bindings| bindings.narrow_vars(&query_vars))
+        }))
+    }
+
+    /// Returns the number of atoms in the space
+    pub fn atom_count(&self) -> usize {
+        let space = self.0.read().unwrap();
+        space.content.len() - space.free.len()
+    }
+}
+
+impl Debug for SyncSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SyncSpace-{:p}", Arc::as_ptr(&self.0))
+    }
+}
+
+/// Implementation of [Space] which gives access to the [SyncSpace] from the
+/// current thread. Observers of the [SyncSpaceRef] are notified about all
+/// modifications of the [SyncSpace], see [module documentation](self).
+/// Events are queued until they are processed, thus [SyncSpaceRef] which is
+/// not used should be dropped.
+pub struct SyncSpaceRef {
+    space: SyncSpace,
+    tokenizer: Shared<Tokenizer>,
+    common: SpaceCommon,
+    events: Receiver<SyncSpaceEvent>,
+}
+
+impl SyncSpaceRef {
+    /// Returns the shared space
+    pub fn space(&self) -> &SyncSpace {
+        &self.space
+    }
+
+    /// Passes modifications of the space made so far to the observers.
+    /// Events with grounded atoms which cannot be restored are skipped.
+    pub fn process_events(&self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event.to_event(&self.tokenizer.borrow()) {
+                Ok(event) => self.common.notify_all_observers(&event),
+                Err(text) => log::error!("SyncSpaceRef::process_events: grounded atom {} cannot be restored, event is skipped: {:?}", text, event),
+            }
+        }
+    }
+}
+
+impl Space for SyncSpaceRef {
+    fn common(&self) -> FlexRef<'_, SpaceCommon> {
+        FlexRef::from_simple(&self.common)
+    }
+    fn query(&self, query: &Atom) -> BindingsSet {
+        self.process_events();
+        match self.space.query(query, &self.tokenizer.borrow()) {
+            Ok(result) => result,
+            Err(text) => {
+                log::error!("SyncSpaceRef::query: grounded atom {} cannot be restored, query: {}", text, query);
+                BindingsSet::empty()
+            },
+        }
+    }
+    fn atom_count(&self) -> Option<usize> {
+        self.process_events();
+        Some(self.space.atom_count())
+    }
+    fn as_any(&self) -> Option<&dyn std::any::Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
+        Some(self)
+    }
+}
+
+impl SpaceMut for SyncSpaceRef {
+    fn add(&mut self, atom: Atom) {
+        self.space.add(&atom);
+        self.process_events();
+    }
+    fn remove(&mut self, atom: &Atom) -> bool {
+        let is_removed = self.space.remove(atom);
+        self.process_events();
+        is_removed
+    }
+    fn replace(&mut self, from: &Atom, to: Atom) -> bool {
+        let is_replaced = self.space.replace(from, &to);
+        self.process_events();
+        is_replaced
+    }
+    fn as_space(&self) -> &dyn Space {
+        self
+    }
+}
+
+impl Debug for SyncSpaceRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SyncSpaceRef-{:?} ({self:p})", self.space)
+    }
+}
+
+impl Display for SyncSpaceRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.space)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use crate::space::grounding::GroundingSpace;
+    use crate::space::view::SpaceView;
+    use crate::metta::runner::{Metta, EnvBuilder};
+    use crate::metta::runner::arithmetics::Number;
+    use crate::metta::text::SExprParser;
+    use regex::Regex;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    struct SpaceEventCollector {
+        events: Vec<SpaceEvent>,
+    }
+
+    impl SpaceEventCollector {
+        fn new() -> Self {
+            Self{ events: Vec::new() }
+        }
+    }
+
+    impl SpaceObserver for SpaceEventCollector {
+        fn notify(&mut self, event: &SpaceEvent) {
+            self.events.push(event.clone());
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Opaque;
+
+    #[test]
+    fn sync_space_is_send_and_sync() {
+        assert_send_sync::<SyncSpace>();
+        assert_send_sync::<SyncAtom>();
+    }
+
+    #[test]
+    fn sync_space_query_same_as_grounding_space() {
+        let atoms = vec![
+            expr!("likes" "Sam" "pizza"),
+            expr!("likes" "Sam" "pasta"),
+            expr!("likes" "Ann" "pizza"),
+            expr!("likes" ("friend" "Ann") x),
+            expr!("dislikes" "Ann" "pasta"),
+        ];
+        let space = SyncSpace::new();
+        let mut expected = GroundingSpace::new();
+        for atom in atoms {
+            space.add(&atom);
+            expected.add(atom);
+        }
+        let tokenizer = Tokenizer::new();
+
+        for query in [expr!("likes" "Sam" x), expr!("likes" x "pizza"),
+            expr!("likes" ("friend" x) y), expr!("," ("likes" x "pasta") ("dislikes" "Ann" x))] {
+            let actual = space.query(&query, &tokenizer).unwrap();
+            let expected = expected.query(&query);
+            assert_eq_no_order!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn sync_space_remove_and_replace() {
+        let space = SyncSpace::new();
+        space.add(&expr!("A"));
+        space.add(&expr!("A"));
+        space.add(&expr!("B"));
+
+        assert!(space.remove(&expr!("A")));
+        assert!(!space.remove(&expr!("A")));
+        assert!(space.replace(&expr!("B"), &expr!("C")));
+        assert!(!space.replace(&expr!("B"), &expr!("D")));
+        assert_eq!(space.atom_count(), 1);
+        assert_eq!(space.query(&expr!("C"), &Tokenizer::new()), Ok(BindingsSet::single()));
+    }
+
+    #[test]
+    fn sync_space_restores_grounded_atoms() {
+        let space = SyncSpace::new();
+        space.add(&expr!("value" {Number::Integer(42)}));
+        space.add(&expr!("opaque" {Opaque}));
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.register_token(Regex::new("Opaque").unwrap(), |_| Atom::value(Opaque));
+
+        assert_eq!(space.query(&expr!("value" x), &tokenizer),
+            Ok(bind_set![bind!{x: expr!({Number::Integer(42)})}]));
+        assert_eq!(space.query(&expr!("opaque" x), &tokenizer),
+            Ok(bind_set![bind!{x: Atom::value(Opaque)}]));
+        assert_eq!(space.query(&expr!("opaque" x), &Tokenizer::new()), Err("Opaque".into()));
+        assert_eq!(space.query(&expr!("," ("value" x) ("opaque" y)), &Tokenizer::new()), Err("Opaque".into()));
+        assert_eq!(SyncAtom::from(&expr!("opaque" {Opaque})).to_atom(&Tokenizer::new()), Err("Opaque".into()));
+        assert_eq!(SyncAtom::from(&expr!("opaque" {Opaque})).to_string(), "(opaque Opaque)");
+    }
+
+    #[test]
+    fn sync_space_parallel_access() {
+        let space = SyncSpace::new();
+        let threads: Vec<_> = (0..4).map(|i| {
+            let space = space.clone();
+            std::thread::spawn(move || {
+                let tokenizer = Tokenizer::new();
+                for j in 0..100 {
+                    space.add(&expr!("item" {Number::Integer(i)} {Number::Integer(j)}));
+                    space.query(&expr!("item" {Number::Integer(i)} x), &tokenizer).unwrap();
+                }
+                space.query(&expr!("item" {Number::Integer(i)} x), &tokenizer).unwrap().len()
+            })
+        }).collect();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 100);
+        }
+        assert_eq!(space.atom_count(), 400);
+    }
+
+    #[test]
+    fn sync_space_ref_notifies_observers() {
+        let space = SyncSpace::new();
+        let mut local = space.local(Shared::new(Tokenizer::new()));
+        let observer = local.common().register_observer(SpaceEventCollector::new());
+
+        local.add(expr!("A"));
+        space.add(&expr!("B"));
+        local.replace(&expr!("A"), expr!("C"));
+
+        assert_eq!(observer.borrow().events, vec![SpaceEvent::Add(expr!("A")),
+            SpaceEvent::Add(expr!("B")), SpaceEvent::Replace(expr!("A"), expr!("C"))]);
+        assert_eq!(local.atom_count(), Some(2));
+    }
+
+    #[test]
+    fn sync_space_ref_notifies_observers_about_other_threads() {
+        let space = SyncSpace::new();
+        let local = space.local(Shared::new(Tokenizer::new()));
+        let observer = local.common().register_observer(SpaceEventCollector::new());
+
+        let other = space.clone();
+        std::thread::spawn(move || {
+            let mut other = other.local(Shared::new(Tokenizer::new()));
+            other.add(expr!("A" {Number::Integer(1)}));
+            other.replace(&expr!("A" {Number::Integer(1)}), expr!("B"));
+        }).join().unwrap();
+        assert!(observer.borrow().events.is_empty());
+
+        local.process_events();
+        assert_eq!(observer.borrow().events, vec![SpaceEvent::Add(expr!("A" {Number::Integer(1)})),
+            SpaceEvent::Replace(expr!("A" {Number::Integer(1)}), expr!("B"))]);
+    }
+
+    #[test]
+    fn sync_space_view_sees_other_threads() {
+        let space = SyncSpace::new();
+        let local = DynSpace::new(space.local(Shared::new(Tokenizer::new())));
+        let view = SpaceView::new(&local, expr!("likes" x y), expr!("fan" y x));
+
+        let other = space.clone();
+        std::thread::spawn(move || other.add(&expr!("likes" "Sam" "pizza"))).join().unwrap();
+        assert_eq!(view.query(&expr!("fan" "pizza" x)), BindingsSet::empty());
+
+        local.borrow().as_any().unwrap().downcast_ref::<SyncSpaceRef>().unwrap().process_events();
+        assert_eq!(view.query(&expr!("fan" "pizza" x)), bind_set![{x: sym!("Sam")}]);
+    }
+
+    #[test]
+    fn sync_space_per_thread_metta() {
+        let space = SyncSpace::new();
+        space.add(&expr!("=" ("double" x) ("*" x "2")));
+        let threads: Vec<_> = (0..2).map(|i| {
+            let space = space.clone();
+            std::thread::spawn(move || {
+                let metta = Metta::new(Some(EnvBuilder::test_env()));
+                let kb = DynSpace::new(space.local(metta.tokenizer().clone()));
+                metta.tokenizer().borrow_mut().register_token(Regex::new("&kb").unwrap(),
+                    move |_| Atom::gnd(kb.clone()));
+                let program = format!("!(match &kb (= (double {}) $r) $r)", i);
+                let result = metta.run(SExprParser::new(&program)).unwrap();
+                result[0][0].to_string()
+            })
+        }).collect();
+
+        for (i, thread) in threads.into_iter().enumerate() {
+            assert_eq!(thread.join().unwrap(), format!("(* {} 2)", i));
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! Materialized view of a space.
+//!
+//! [SpaceView] keeps the results of the query over the source space: for each
+//! match of the pattern it contains the template with the bindings applied.
+//! The view observes the source space and updates its content on each
+//! [SpaceEvent] instead of executing the query again. Each result is kept in
+//! the view once even when it is produced by several matches. Observers of
+//! the view are notified when results are added or removed, thus views can be
+//! built on top of other views.
+//!
+//! Results of a pattern without sub-queries are updated by matching the added
+//! or removed atom with the pattern. Results of a pattern which consists of
+//! sub-queries glued by [COMMA_SYMBOL] are updated by the delta join: only
+//! the matches which match at least one sub-query with the changed atom are
+//! searched. The view keeps a copy of the atoms matching the sub-queries and
+//! counts the matches producing each result, thus a result is removed only
+//! when the last match producing it is removed.
+
+use super::*;
+use super::grounding::{GroundingSpace, COMMA_SYMBOL};
+use crate::atom::matcher::match_atoms;
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::fmt::Debug;
+
+/// Wrapper to use [Atom] as a key of the [HashMap]
+#[derive(Clone, PartialEq, Eq)]
+struct AtomKey(Atom);
+
+impl Hash for AtomKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        fn hash_atom<H: Hasher>(atom: &Atom, state: &mut H) {
+            match atom {
+                Atom::Symbol(sym) => { 0u8.hash(state); sym.hash(state); },
+                Atom::Variable(var) => { 1u8.hash(state); var.hash(state); },
+                Atom::Expression(expr) => {
+                    2u8.hash(state);
+                    expr.children().len().hash(state);
+                    expr.children().iter().for_each(|child| hash_atom(child, state));
+                },
+                // Grounded atoms are compared by Grounded::eq_gnd, there is
+                // no hash consistent with it
+                Atom::Grounded(_) => 3u8.hash(state),
+            }
+        }
+        hash_atom(&self.0, state)
+    }
+}
+
+enum ViewPattern {
+    /// Pattern without sub-queries and the number of copies of the source
+    /// atoms matching it
+    Single(Atom, HashMap<AtomKey, usize>),
+    /// Pattern with sub-queries, the number of copies of the source atoms
+    /// which match at least one of them and the space with these copies
+    Conjunction(Vec<Atom>, HashMap<AtomKey, usize>, Box<GroundingSpace>),
+}
+
+struct ViewContent {
+    pattern: ViewPattern,
+    template: Atom,
+    results: HashMap<AtomKey, usize>,
+    space: GroundingSpace,
+    common: Rc<SpaceCommon>,
+    events: Vec<SpaceEvent>,
+}
+
+impl ViewContent {
+    fn add(&mut self, atom: &Atom) {
+        match &mut self.pattern {
+            ViewPattern::Single(pattern, sources) => {
+                let results = match_template(atom, pattern, &self.template);
+                if !results.is_empty() {
+                    *sources.entry(AtomKey(atom.clone())).or_insert(0) += 1;
+                    results.into_iter().for_each(|result| self.add_result(result, 1));
+                }
+            },
+            ViewPattern::Conjunction(conjuncts, sources, relevant) => {
+                if matches_any(atom, conjuncts) {
+                    let delta = GroundingSpace::from_vec(vec![atom.clone()]);
+                    let results = join_delta(conjuncts, &self.template, relevant, &delta);
+                    *sources.entry(AtomKey(atom.clone())).or_insert(0) += 1;
+                    relevant.add(atom.clone());
+                    results.into_iter().for_each(|result| self.add_result(result, 1));
+                }
+            },
+        }
+    }
+
+    fn remove(&mut self, atom: &Atom) {
+        match &mut self.pattern {
+            ViewPattern::Single(pattern, sources) => {
+                // Source space removes all copies of the atom
+                if let Some(copies) = sources.remove(&AtomKey(atom.clone())) {
+                    match_template(atom, pattern, &self.template).into_iter()
+                        .for_each(|result| self.remove_result(result, copies));
+                }
+            },
+            ViewPattern::Conjunction(conjuncts, sources, relevant) => {
+                // Source space removes all copies of the atom
+                if let Some(copies) = sources.remove(&AtomKey(atom.clone())) {
+                    relevant.remove(atom);
+                    let delta = GroundingSpace::from_vec(vec![atom.clone(); copies]);
+                    join_delta(conjuncts, &self.template, relevant, &delta).into_iter()
+                        .for_each(|result| self.remove_result(result, 1));
+                }
+            },
+        }
+    }
+
+    fn add_result(&mut self, result: Atom, copies: usize) {
+        let count = self.results.entry(AtomKey(result.clone())).or_insert(0);
+        *count += copies;
+        if *count == copies {
+            self.space.add(result.clone());
+            self.events.push(SpaceEvent::Add(result));
+        }
+    }
+
+    fn remove_result(&mut self, result: Atom, copies: usize) {
+        let key = AtomKey(result);
+        if let Some(count) = self.results.get_mut(&key) {
+            *count = count.saturating_sub(copies);
+            if *count == 0 {
+                self.results.remove(&key);
+                self.space.remove(&key.0);
+                self.events.push(SpaceEvent::Remove(key.0));
+            }
+        }
+    }
+
+    fn notify_all_observers(&mut self) {
+        for event in std::mem::take(&mut self.events) {
+            self.common.notify_all_observers(&event);
+        }
+    }
+}
+
+impl SpaceObserver for ViewContent {
+    fn notify(&mut self, event: &SpaceEvent) {
+        match event {
+            SpaceEvent::Add(atom) => self.add(atom),
+            SpaceEvent::Remove(atom) => self.remove(atom),
+            SpaceEvent::Replace(from, to) => {
+                self.remove(from);
+                self.add(to);
+            },
+        }
+        self.notify_all_observers();
+    }
+}
+
+fn matches_any(atom: &Atom, patterns: &[Atom]) -> bool {
+    patterns.iter().any(|pattern| match_atoms(atom, pattern).next().is_some())
+}
+
+/// Returns the `template` instantiated by each match of the `conjuncts`
+/// which matches at least one conjunct with an atom of the `delta`. The
+/// conjunct `i` is matched with the `delta` while the conjuncts before it are
+/// matched with the `space` only and the conjuncts after it are matched with
+/// both, thus each match is returned once.
+fn join_delta(conjuncts: &[Atom], template: &Atom, space: &GroundingSpace, delta: &GroundingSpace) -> Vec<Atom> {
+    let mut results = Vec::new();
+    for (i, conjunct) in conjuncts.iter().enumerate() {
+        let mut matches: Vec<Bindings> = delta.query(conjunct).into_iter().collect();
+        for (j, next) in conjuncts.iter().enumerate().filter(|(j, _)| *j != i) {
+            matches = matches.into_iter().flat_map(|prev| {
+                let query = apply_bindings_to_atom(next, &prev);
+                let mut found = space.query(&query);
+                if j > i {
+                    found.extend(delta.query(&query));
+                }
+                found.into_iter().flat_map(move |next| next.merge_v2(&prev))
+            }).collect();
+        }
+        results.extend(matches.iter().map(|bindings| apply_bindings_to_atom(template, bindings)));
+    }
+    results
+}
+
+fn match_template(atom: &Atom, pattern: &Atom, template: &Atom) -> Vec<Atom> {
+    match_atoms(atom, pattern)
+        .map(|bindings| apply_bindings_to_atom(template, &bindings))
+        .collect()
+}
+
+/// Space which contains results of the query over the source space and is
+/// updated on modifications of the source space, see [module
+/// documentation](self) for details. The view cannot be modified directly.
+///
+/// # Examples
+///
+/// ```
+/// use hyperon::expr;
+/// use hyperon::space::*;
+/// use hyperon::space::grounding::GroundingSpace;
+/// use hyperon::space::view::SpaceView;
+///
+/// let source = DynSpace::new(GroundingSpace::from_vec(vec![expr!("likes" "Sam" "pizza")]));
+/// let view = SpaceView::new(&source, expr!("likes" x "pizza"), expr!("pizza-lover" x));
+///
+/// source.borrow_mut().add(expr!("likes" "Ann" "pizza"));
+/// source.borrow_mut().remove(&expr!("likes" "Sam" "pizza"));
+///
+/// assert_eq!(view.query(&expr!("pizza-lover" x)).len(), 1);
+/// assert_eq!(view.query(&expr!("pizza-lover" "Ann")).len(), 1);
+/// ```
+pub struct SpaceView {
+    content: SpaceObserverRef<ViewContent>,
+    common: Rc<SpaceCommon>,
+}
+
+impl SpaceView {
+    /// Constructs the view of the `source` space which contains the
+    /// `template` instantiated by each match of the `pattern`.
+    pub fn new(source: &DynSpace, pattern: Atom, template: Atom) -> Self {
+        // Variables are renamed to not intersect with variables of the source atoms
+        let (pattern, template) = match make_variables_unique(Atom::expr([pattern, template])) {
+            Atom::Expression(expr) => {
+                let mut children = expr.into_children().into_iter();
+                (children.next().unwrap(), children.next().unwrap())
+            },
+            _ => unreachable!(),
+        };
+        let pattern = match &pattern {
+            Atom::Expression(expr) if expr.children().first() == Some(&COMMA_SYMBOL) =>
+                ViewPattern::Conjunction(expr.children()[1..].to_vec(), HashMap::new(), Box::new(GroundingSpace::new())),
+            _ => ViewPattern::Single(pattern, HashMap::new()),
+        };
+        let common = Rc::new(SpaceCommon::default());
+        let mut content = ViewContent{ pattern, template, results: HashMap::new(),
+            space: GroundingSpace::new(), common: common.clone(), events: Vec::new() };
+        {
+            let source = source.borrow();
+            match source.as_space().atom_iter() {
+                Some(atoms) => atoms.for_each(|atom| content.add(atom)),
+                // Atoms of the space which cannot be iterated are received by
+                // the query, variables of such atoms are renamed
+                None => {
+                    let var = VariableAtom::new("atom");
+                    for bindings in source.query(&Atom::Variable(var.clone())) {
+                        if let Some(atom) = bindings.resolve(&var) {
+                            content.add(&atom);
+                        }
+                    }
+                },
+            }
+            content.events.clear();
+        }
+        let content = source.register_observer(content);
+        Self{ content, common }
+    }
+}
+
+impl Space for SpaceView {
+    fn common(&self) -> FlexRef<'_, SpaceCommon> {
+        FlexRef::from_simple(&self.common)
+    }
+    fn query(&self, query: &Atom) -> BindingsSet {
+        self.content.borrow().space.query(query)
+    }
+    fn atom_count(&self) -> Option<usize> {
+        Space::atom_count(&self.content.borrow().space)
+    }
+    fn as_any(&self) -> Option<&dyn std::any::Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
+        Some(self)
+    }
+}
+
+impl SpaceMut for SpaceView {
+    fn add(&mut self, atom: Atom) {
+        log::error!("SpaceView::add: view cannot be modified directly, atom: {}", atom);
+    }
+    fn remove(&mut self, atom: &Atom) -> bool {
+        log::error!("SpaceView::remove: view cannot be modified directly, atom: {}", atom);
+        false
+    }
+    fn replace(&mut self, from: &Atom, to: Atom) -> bool {
+        log::error!("SpaceView::replace: view cannot be modified directly, atoms: {} {}", from, to);
+        false
+    }
+    fn as_space(&self) -> &dyn Space {
+        self
+    }
+}
+
+impl Debug for SpaceView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SpaceView-{self:p}")
+    }
+}
+
+impl Display for SpaceView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SpaceView-{self:p}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    struct EventCollector(Vec<SpaceEvent>);
+
+    impl SpaceObserver for EventCollector {
+        fn notify(&mut self, event: &SpaceEvent) {
+            self.0.push(event.clone());
+        }
+    }
+
+    fn view_atoms(view: &SpaceView) -> Vec<Atom> {
+        view.content.borrow().space.iter().cloned().collect()
+    }
+
+    #[test]
+    fn space_view_keeps_results_up_to_date() {
+        let source = DynSpace::new(GroundingSpace::from_vec(vec![
+            expr!("likes" "Sam" "pizza"),
+            expr!("likes" "Ann" "pasta"),
+        ]));
+        let view = SpaceView::new(&source, expr!("likes" x "pizza"), expr!("pizza-lover" x));
+        assert_eq!(view_atoms(&view), vec![expr!("pizza-lover" "Sam")]);
+
+        source.borrow_mut().add(expr!("likes" "Ann" "pizza"));
+        source.borrow_mut().remove(&expr!("likes" "Sam" "pizza"));
+        source.borrow_mut().replace(&expr!("likes" "Ann" "pasta"), expr!("likes" "Bob" "pizza"));
+
+        let actual = view_atoms(&view);
+        assert_eq_no_order!(actual, vec![expr!("pizza-lover" "Ann"), expr!("pizza-lover" "Bob")]);
+        assert_eq!(view.atom_count(), Some(2));
+    }
+
+    #[test]
+    fn space_view_keeps_result_while_it_is_produced() {
+        let source = DynSpace::new(GroundingSpace::from_vec(vec![
+            expr!("likes" "Sam" "pizza"),
+            expr!("likes" "Sam" "pasta"),
+            expr!("likes" "Sam" "pasta"),
+        ]));
+        let view = SpaceView::new(&source, expr!("likes" x y), expr!("eater" x));
+        assert_eq!(view_atoms(&view), vec![expr!("eater" "Sam")]);
+
+        source.borrow_mut().remove(&expr!("likes" "Sam" "pasta"));
+        assert_eq!(view_atoms(&view), vec![expr!("eater" "Sam")]);
+        source.borrow_mut().remove(&expr!("likes" "Sam" "pizza"));
+        assert_eq!(view_atoms(&view), vec![]);
+    }
+
+    #[test]
+    fn space_view_with_sub_queries() {
+        let source = DynSpace::new(GroundingSpace::from_vec(vec![
+            expr!("parent" "Tom" "Bob"),
+            expr!("parent" "Bob" "Ann"),
+            expr!("age" "Ann" "10"),
+        ]));
+        let view = SpaceView::new(&source, expr!("," ("parent" x y) ("parent" y z)),
+            expr!("grandparent" x z));
+        assert_eq!(view_atoms(&view), vec![expr!("grandparent" "Tom" "Ann")]);
+
+        source.borrow_mut().add(expr!("parent" "Ann" "Eve"));
+        source.borrow_mut().remove(&expr!("parent" "Tom" "Bob"));
+
+        assert_eq!(view_atoms(&view), vec![expr!("grandparent" "Bob" "Eve")]);
+        let content = view.content.borrow();
+        match &content.pattern {
+            ViewPattern::Conjunction(_, _, relevant) => assert_eq!(relevant.atom_count(), Some(2)),
+            _ => panic!("Conjunction is expected"),
+        }
+    }
+
+    #[test]
+    fn space_view_with_sub_queries_counts_matches() {
+        let source = DynSpace::new(GroundingSpace::from_vec(vec![
+            expr!("parent" "Tom" "Bob"),
+            expr!("parent" "Bob" "Ann"),
+            expr!("parent" "Tom" "Liz"),
+            expr!("parent" "Liz" "Ann"),
+            expr!("parent" "Eve" "Eve"),
+        ]));
+        let view = DynSpace::new(SpaceView::new(&source, expr!("," ("parent" x y) ("parent" y z)),
+            expr!("grandparent" x z)));
+        let events = view.register_observer(EventCollector(Vec::new()));
+        assert_eq!(view.borrow().atom_count(), Some(2));
+        assert_eq!(view.borrow().query(&expr!("grandparent" "Tom" "Ann")).len(), 1);
+
+        source.borrow_mut().add(expr!("parent" "Liz" "Ann"));
+        source.borrow_mut().remove(&expr!("parent" "Bob" "Ann"));
+        source.borrow_mut().remove(&expr!("parent" "Eve" "Eve"));
+        assert_eq!(events.borrow().0, vec![SpaceEvent::Remove(expr!("grandparent" "Eve" "Eve"))]);
+        source.borrow_mut().remove(&expr!("parent" "Liz" "Ann"));
+        assert_eq!(events.borrow().0, vec![SpaceEvent::Remove(expr!("grandparent" "Eve" "Eve")),
+            SpaceEvent::Remove(expr!("grandparent" "Tom" "Ann"))]);
+        assert_eq!(view.borrow().atom_count(), Some(0));
+    }
+
+    #[test]
+    fn space_view_notifies_observers() {
+        let source = DynSpace::new(GroundingSpace::from_vec(vec![expr!("A" "0")]));
+        let view = DynSpace::new(SpaceView::new(&source, expr!("A" x), expr!("B" x)));
+        let upper = SpaceView::new(&view, expr!("B" x), expr!("C" x));
+        let events = view.register_observer(EventCollector(Vec::new()));
+        assert_eq!(view_atoms(&upper), vec![expr!("C" "0")]);
+
+        source.borrow_mut().add(expr!("A" "1"));
+        source.borrow_mut().add(expr!("D" "1"));
+        source.borrow_mut().remove(&expr!("A" "1"));
+
+        assert_eq!(events.borrow().0, vec![SpaceEvent::Add(expr!("B" "1")),
+            SpaceEvent::Remove(expr!("B" "1"))]);
+        assert_eq!(view_atoms(&upper), vec![expr!("C" "0")]);
+        source.borrow_mut().replace(&expr!("A" "0"), expr!("A" "2"));
+        assert_eq!(view_atoms(&upper), vec![expr!("C" "2")]);
+    }
+
+    #[test]
+    fn space_view_cannot_be_modified() {
+        let source = DynSpace::new(GroundingSpace::new());
+        let mut view = SpaceView::new(&source, expr!("A" x), expr!("B" x));
+        view.add(expr!("B" "1"));
+        assert!(!view.remove(&expr!("B" "1")));
+        assert_eq!(view.atom_count(), Some(0));
+    }
+}
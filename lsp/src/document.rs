@@ -0,0 +1,202 @@
+//! Text of the MeTTa document opened in the editor and the queries over its syntax tree.
+
+use std::cell::OnceCell;
+use std::ops::Range;
+
+use hyperon::*;
+use hyperon::space::grounding::GroundingSpace;
+use hyperon::metta::runner::EXEC_SYMBOL;
+use hyperon::metta::text::{SExprParser, SyntaxNode, SyntaxNodeType, Tokenizer};
+
+/// Position in the document as it is defined by LSP: both `line` and `character` start from 0,
+/// and `character` is counted in UTF-16 code units
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// Leaf token of the syntax tree
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub node_type: SyntaxNodeType,
+    pub src_range: Range<usize>,
+    pub text: String,
+}
+
+pub struct Document {
+    text: String,
+    line_starts: Vec<usize>,
+    /// Space built from the atoms of this version of the document
+    space: OnceCell<GroundingSpace>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self{ text, line_starts, space: OnceCell::new() }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the space of the document, it is built by `init` on the first call. A new
+    /// `Document` is created on each change of the text, thus the space is never outdated.
+    pub fn space<F: FnOnce() -> GroundingSpace>(&self, init: F) -> &GroundingSpace {
+        self.space.get_or_init(init)
+    }
+
+    /// Converts the byte `offset` into the LSP position
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let character = self.text[self.line_starts[line]..offset].encode_utf16().count();
+        Position{ line, character }
+    }
+
+    /// Converts the LSP position into the byte offset, positions outside of the text are clamped
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(line_start) = self.line_starts.get(position.line) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (idx, c) in self.text[*line_start..].char_indices() {
+            if units >= position.character || c == '\n' {
+                return line_start + idx;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    /// Returns the word or variable token which contains `offset` or ends at `offset`
+    pub fn token_at(&self, offset: usize) -> Option<Token> {
+        let mut found = None;
+        self.visit_leaves(|node| {
+            let is_token = matches!(node.node_type, SyntaxNodeType::WordToken | SyntaxNodeType::VariableToken);
+            if is_token && node.src_range.start <= offset && offset <= node.src_range.end {
+                found = Some(self.token(node));
+            }
+        });
+        found
+    }
+
+    /// Returns the ranges of `name` in the `(= (name ...) ...)`, `(= name ...)` and `(: name ...)`
+    /// top level definitions of the document
+    pub fn definitions(&self, name: &str) -> Vec<Range<usize>> {
+        let mut parser = SExprParser::new(&self.text);
+        let mut definitions = Vec::new();
+        while let Some(node) = parser.parse_to_syntax_tree() {
+            if !matches!(node.node_type, SyntaxNodeType::ExpressionGroup) {
+                continue;
+            }
+            let children = significant_children(&node);
+            let defined = match children[..] {
+                [op, defined, ..] if is_word(op, "=") => match defined.node_type {
+                    SyntaxNodeType::ExpressionGroup => significant_children(defined).first().copied(),
+                    _ => Some(defined),
+                },
+                [op, defined, ..] if is_word(op, ":") => Some(defined),
+                _ => None,
+            };
+            if let Some(defined) = defined.filter(|defined| is_word(defined, name)) {
+                definitions.push(defined.src_range.clone());
+            }
+        }
+        definitions
+    }
+
+    /// Returns atoms added into the space when the document is loaded, `!` expressions
+    /// are skipped. Parsing stops on the first syntax error.
+    pub fn atoms(&self, tokenizer: &Tokenizer) -> Vec<Atom> {
+        let mut parser = SExprParser::new(&self.text);
+        let mut atoms = Vec::new();
+        let mut is_exec = false;
+        while let Ok(Some(atom)) = parser.parse(tokenizer) {
+            if atom == EXEC_SYMBOL {
+                is_exec = true;
+            } else if is_exec {
+                is_exec = false;
+            } else {
+                atoms.push(atom);
+            }
+        }
+        atoms
+    }
+
+    fn token(&self, node: &SyntaxNode) -> Token {
+        Token{
+            node_type: node.node_type,
+            src_range: node.src_range.clone(),
+            text: self.text[node.src_range.clone()].to_string(),
+        }
+    }
+
+    fn visit_leaves<C: FnMut(&SyntaxNode)>(&self, mut callback: C) {
+        let mut parser = SExprParser::new(&self.text);
+        while let Some(node) = parser.parse_to_syntax_tree() {
+            node.visit_depth_first(|node| {
+                if node.node_type.is_leaf() {
+                    callback(node)
+                }
+            });
+        }
+    }
+}
+
+fn significant_children(node: &SyntaxNode) -> Vec<&SyntaxNode> {
+    node.sub_nodes.iter()
+        .filter(|node| !matches!(node.node_type, SyntaxNodeType::Whitespace | SyntaxNodeType::Comment |
+            SyntaxNodeType::BlockComment | SyntaxNodeType::OpenParen | SyntaxNodeType::CloseParen))
+        .collect()
+}
+
+fn is_word(node: &SyntaxNode, word: &str) -> bool {
+    matches!(node.node_type, SyntaxNodeType::WordToken) && node.parsed_text.as_deref() == Some(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_position_and_offset() {
+        let doc = Document::new("(a b)\n(é 😀 c)\n".to_string());
+        assert_eq!(doc.position(0), Position{ line: 0, character: 0 });
+        assert_eq!(doc.position(6), Position{ line: 1, character: 0 });
+        let c_offset = doc.text().find('c').unwrap();
+        assert_eq!(doc.position(c_offset), Position{ line: 1, character: 6 });
+        assert_eq!(doc.offset(Position{ line: 1, character: 6 }), c_offset);
+        assert_eq!(doc.offset(Position{ line: 0, character: 100 }), 5);
+        assert_eq!(doc.offset(Position{ line: 10, character: 0 }), doc.text().len());
+    }
+
+    #[test]
+    fn document_token_at() {
+        let doc = Document::new("(foo $x \"s\")".to_string());
+        assert_eq!(doc.token_at(2).map(|token| token.text), Some("foo".to_string()));
+        assert_eq!(doc.token_at(4).map(|token| token.text), Some("foo".to_string()));
+        assert_eq!(doc.token_at(6).map(|token| token.text), Some("$x".to_string()));
+        assert_eq!(doc.token_at(9).map(|token| token.text), None);
+    }
+
+    #[test]
+    fn document_definitions() {
+        let text = "(: foo (-> A B))\n(= (foo $x) (bar $x))\n(= foo-const (foo a))\n!(foo a)";
+        let doc = Document::new(text.to_string());
+        let definitions: Vec<&str> = doc.definitions("foo").into_iter().map(|range| &text[range]).collect();
+        assert_eq!(definitions, vec!["foo", "foo"]);
+        assert_eq!(doc.definitions("foo")[1].start, text.find("(foo $x)").unwrap() + 1);
+        assert_eq!(doc.definitions("foo-const").len(), 1);
+        assert!(doc.definitions("bar").is_empty());
+    }
+
+    #[test]
+    fn document_atoms() {
+        let doc = Document::new("(a) !(b) (c) (d".to_string());
+        assert_eq!(doc.atoms(&Tokenizer::new()), vec![expr!(("a")), expr!(("c"))]);
+    }
+}
@@ -0,0 +1,34 @@
+//! Language Server Protocol server for MeTTa. The server communicates with the editor using
+//! stdin and stdout, and provides diagnostics, go-to-definition, hover and completion for
+//! the opened `.metta` documents.
+
+use std::process::exit;
+
+use hyperon::metta::runner::{Metta, EnvBuilder};
+
+mod transport;
+mod document;
+mod server;
+
+use server::Server;
+
+fn main() -> std::io::Result<()> {
+    // stdout is used by the protocol, thus the log is written into stderr
+    env_logger::init();
+
+    let working_dir = std::env::current_dir().ok();
+    let mut server = Server::new(move || {
+        let env_builder = EnvBuilder::new()
+            .set_working_dir(working_dir.as_deref())
+            .set_no_config_dir();
+        Metta::new(Some(env_builder))
+    });
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let shutdown = server.run(&mut stdin.lock(), &mut stdout.lock())?;
+    if !shutdown {
+        exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,467 @@
+//! Handlers of the Language Server Protocol requests and notifications.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+use hyperon::*;
+use hyperon::space::grounding::GroundingSpace;
+use hyperon::metta::*;
+use hyperon::metta::types::get_atom_types;
+use hyperon::metta::runner::Metta;
+use hyperon::metta::runner::string::Str;
+use hyperon::metta::text::{SExprParser, SyntaxNodeType};
+
+use crate::document::{Document, Position};
+use crate::transport::{read_message, write_message};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INVALID_REQUEST: i64 = -32600;
+
+const SEVERITY_ERROR: i64 = 1;
+const COMPLETION_KIND_FUNCTION: i64 = 3;
+const COMPLETION_KIND_KEYWORD: i64 = 14;
+const COMPLETION_KIND_CONSTANT: i64 = 21;
+const TEXT_DOCUMENT_SYNC_FULL: i64 = 1;
+
+/// Language server which keeps the documents opened in the editor and answers the requests
+/// using the tokenizer and the space of the runner. Each document is checked by its own runner,
+/// thus checking one document cannot change the results of another one.
+pub struct Server {
+    new_runner: Box<dyn Fn() -> Metta>,
+    metta: Metta,
+    documents: HashMap<String, Document>,
+    runners: HashMap<String, Metta>,
+    shutdown_requested: bool,
+    exit_requested: bool,
+}
+
+impl Server {
+    /// Constructs the server, `new_runner` is called to create the runner for each opened document
+    pub fn new<F: Fn() -> Metta + 'static>(new_runner: F) -> Self {
+        let metta = new_runner();
+        Self{ new_runner: Box::new(new_runner), metta, documents: HashMap::new(), runners: HashMap::new(),
+            shutdown_requested: false, exit_requested: false }
+    }
+
+    /// Reads messages from `reader` and writes responses into `writer` until the `exit`
+    /// notification is received or the input is closed. Returns `true` if the server was
+    /// shut down properly.
+    pub fn run<R: BufRead, W: Write>(&mut self, reader: &mut R, writer: &mut W) -> io::Result<bool> {
+        while !self.exit_requested {
+            let Some(message) = read_message(reader)? else {
+                break;
+            };
+            for response in self.handle_message(&message) {
+                write_message(writer, &response)?;
+            }
+        }
+        Ok(self.shutdown_requested)
+    }
+
+    /// Handles the incoming message and returns the messages which should be sent back
+    pub fn handle_message(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            // Responses to the server requests are not expected
+            return vec![];
+        };
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => vec![self.handle_request(id, method, params)],
+            None => self.handle_notification(method, params),
+        }
+    }
+
+    fn handle_request(&mut self, id: &Value, method: &str, params: &Value) -> Value {
+        if self.shutdown_requested {
+            return error_response(id, INVALID_REQUEST, "Server is shut down".into());
+        }
+        let result = match method {
+            "initialize" => Ok(self.initialize()),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            },
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            _ => return error_response(id, METHOD_NOT_FOUND, format!("Method not found: {method}")),
+        };
+        match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(message) => error_response(id, INVALID_PARAMS, message),
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        match method {
+            "exit" => {
+                self.exit_requested = true;
+                vec![]
+            },
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                match (document["uri"].as_str(), document["text"].as_str()) {
+                    (Some(uri), Some(text)) => self.update_document(uri, text.to_string()),
+                    _ => vec![],
+                }
+            },
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str();
+                // Full text synchronization is requested, thus the last change contains the whole text
+                let text = params["contentChanges"].as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                match (uri, text) {
+                    (Some(uri), Some(text)) => self.update_document(uri, text.to_string()),
+                    _ => vec![],
+                }
+            },
+            "textDocument/didClose" => {
+                match params["textDocument"]["uri"].as_str() {
+                    Some(uri) => {
+                        self.documents.remove(uri);
+                        self.runners.remove(uri);
+                        vec![publish_diagnostics(uri, vec![])]
+                    },
+                    None => vec![],
+                }
+            },
+            _ => vec![],
+        }
+    }
+
+    fn initialize(&self) -> Value {
+        json!({
+            "capabilities": {
+                "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL,
+                "definitionProvider": true,
+                "hoverProvider": true,
+                "completionProvider": {},
+            },
+            "serverInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        })
+    }
+
+    fn update_document(&mut self, uri: &str, text: String) -> Vec<Value> {
+        let document = Document::new(text);
+        let runner = self.runners.entry(uri.to_string()).or_insert_with(|| (self.new_runner)());
+        let diagnostics = runner.check(document.text()).into_iter()
+            .map(|err| json!({
+                "range": range(&document, err.src_range),
+                "severity": SEVERITY_ERROR,
+                "source": "metta",
+                "message": err.message,
+            }))
+            .collect();
+        self.documents.insert(uri.to_string(), document);
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    fn definition(&self, params: &Value) -> Result<Value, String> {
+        let (uri, document, offset) = self.document_position(params)?;
+        let name = match document.token_at(offset) {
+            Some(token) if matches!(token.node_type, SyntaxNodeType::WordToken) => token.text,
+            _ => return Ok(Value::Null),
+        };
+        // Definitions from the current document go first
+        let mut documents: Vec<(&String, &Document)> = self.documents.iter().collect();
+        documents.sort_by_key(|(doc_uri, _)| (doc_uri.as_str() != uri, doc_uri.as_str()));
+        let locations: Vec<Value> = documents.into_iter()
+            .flat_map(|(doc_uri, doc)| doc.definitions(&name).into_iter()
+                .map(move |src_range| json!({"uri": doc_uri, "range": range(doc, src_range)})))
+            .collect();
+        Ok(Value::Array(locations))
+    }
+
+    fn hover(&self, params: &Value) -> Result<Value, String> {
+        let (_uri, document, offset) = self.document_position(params)?;
+        let token = match document.token_at(offset) {
+            Some(token) if matches!(token.node_type, SyntaxNodeType::WordToken) => token,
+            _ => return Ok(Value::Null),
+        };
+        let atom = match SExprParser::new(&token.text).parse(&self.metta.tokenizer().borrow()) {
+            Ok(Some(atom)) => atom,
+            _ => return Ok(Value::Null),
+        };
+        let space = self.document_space(document);
+
+        let mut sections = Vec::new();
+        let types: Vec<String> = get_atom_types(space, &atom).into_iter()
+            .filter(|typ| *typ != ATOM_TYPE_UNDEFINED)
+            .map(|typ| format!("(: {} {})", token.text, typ))
+            .collect();
+        if !types.is_empty() {
+            sections.push(format!("```metta\n{}\n```", types.join("\n")));
+        }
+        sections.extend(documentation(space, &atom));
+        if sections.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(json!({
+            "contents": {"kind": "markdown", "value": sections.join("\n\n")},
+            "range": range(document, token.src_range),
+        }))
+    }
+
+    fn completion(&self, params: &Value) -> Result<Value, String> {
+        let (_uri, document, offset) = self.document_position(params)?;
+        let prefix = match document.token_at(offset) {
+            Some(token) if matches!(token.node_type, SyntaxNodeType::WordToken) =>
+                token.text[..offset - token.src_range.start].to_string(),
+            _ => String::new(),
+        };
+        let space = self.document_space(document);
+
+        let mut candidates: Vec<(String, i64)> = Vec::new();
+        for bindings in space.query(&expr!(":" s t)) {
+            if let (Some(name), Some(typ)) = (bindings.resolve(&VariableAtom::new("s")), bindings.resolve(&VariableAtom::new("t"))) {
+                let is_function = matches!(&typ, Atom::Expression(expr) if expr.children().first() == Some(&ARROW_SYMBOL));
+                let kind = if is_function { COMPLETION_KIND_FUNCTION } else { COMPLETION_KIND_CONSTANT };
+                candidates.push((name.to_string(), kind));
+            }
+        }
+        for bindings in space.query(&expr!("=" h b)) {
+            let head = match bindings.resolve(&VariableAtom::new("h")) {
+                Some(Atom::Expression(expr)) => expr.children().first().cloned(),
+                head => head,
+            };
+            if let Some(head @ (Atom::Symbol(_) | Atom::Grounded(_))) = head {
+                candidates.push((head.to_string(), COMPLETION_KIND_FUNCTION));
+            }
+        }
+        for token in self.metta.tokenizer().borrow().literal_tokens() {
+            candidates.push((token, COMPLETION_KIND_KEYWORD));
+        }
+
+        let mut labels = HashMap::new();
+        for (label, kind) in candidates.into_iter().filter(|(label, _)| label.starts_with(&prefix)) {
+            labels.entry(label).or_insert(kind);
+        }
+        let mut labels: Vec<(String, i64)> = labels.into_iter().collect();
+        labels.sort();
+        let items: Vec<Value> = labels.into_iter()
+            .map(|(label, kind)| json!({"label": label, "kind": kind}))
+            .collect();
+        Ok(json!({"isIncomplete": false, "items": items}))
+    }
+
+    fn document_position<'a>(&'a self, params: &'a Value) -> Result<(&'a str, &'a Document, usize), String> {
+        let uri = params["textDocument"]["uri"].as_str()
+            .ok_or_else(|| "textDocument.uri is expected".to_string())?;
+        let document = self.documents.get(uri)
+            .ok_or_else(|| format!("Document is not opened: {uri}"))?;
+        let line = params["position"]["line"].as_u64();
+        let character = params["position"]["character"].as_u64();
+        match (line, character) {
+            (Some(line), Some(character)) => {
+                let offset = document.offset(Position{ line: line as usize, character: character as usize });
+                Ok((uri, document, offset))
+            },
+            _ => Err("position is expected".to_string()),
+        }
+    }
+
+    /// Returns the space which contains atoms of the `document` and has the space of the
+    /// runner as a sub-space, so the declarations of the imported modules are visible
+    fn document_space<'a>(&self, document: &'a Document) -> &'a GroundingSpace {
+        document.space(|| {
+            let mut space = GroundingSpace::new();
+            space.add(Atom::gnd(self.metta.space().clone()));
+            for atom in document.atoms(&self.metta.tokenizer().borrow()) {
+                space.add(atom);
+            }
+            space
+        })
+    }
+}
+
+/// Returns description, parameters and return value from the `@doc` annotations of the `atom`
+fn documentation(space: &GroundingSpace, atom: &Atom) -> Vec<String> {
+    let mut sections = Vec::new();
+    let function_doc = Atom::expr([sym!("@doc"), atom.clone(), expr!("@desc" d), expr!("@params" p), expr!("@return" r)]);
+    let atom_doc = Atom::expr([sym!("@doc"), atom.clone(), expr!("@desc" d)]);
+    for bindings in space.query(&function_doc).into_iter().chain(space.query(&atom_doc)) {
+        if let Some(desc) = bindings.resolve(&VariableAtom::new("d")) {
+            sections.push(doc_text(&desc));
+        }
+        if let Some(Atom::Expression(params)) = bindings.resolve(&VariableAtom::new("p")) {
+            let params: Vec<String> = params.children().iter()
+                .filter_map(|param| match param {
+                    Atom::Expression(param) => param.children().get(1).map(|desc| format!("- {}", doc_text(desc))),
+                    _ => None,
+                })
+                .collect();
+            if !params.is_empty() {
+                sections.push(format!("Parameters:\n{}", params.join("\n")));
+            }
+        }
+        if let Some(ret) = bindings.resolve(&VariableAtom::new("r")) {
+            sections.push(format!("Returns: {}", doc_text(&ret)));
+        }
+    }
+    sections
+}
+
+fn doc_text(atom: &Atom) -> String {
+    match atom.as_gnd::<Str>() {
+        Some(text) => text.as_str().to_string(),
+        None => atom.to_string(),
+    }
+}
+
+fn range(document: &Document, src_range: std::ops::Range<usize>) -> Value {
+    let start = document.position(src_range.start);
+    let end = document.position(src_range.end);
+    json!({
+        "start": {"line": start.line, "character": start.character},
+        "end": {"line": end.line, "character": end.character},
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+fn error_response(id: &Value, code: i64, message: String) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyperon::metta::runner::EnvBuilder;
+
+    const URI: &str = "file:///test.metta";
+
+    fn server_with_document(text: &str) -> (Server, Vec<Value>) {
+        let mut server = Server::new(|| Metta::new(Some(EnvBuilder::test_env())));
+        server.handle_message(&json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {}}));
+        let messages = server.handle_message(&json!({"jsonrpc": "2.0", "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": URI, "languageId": "metta", "version": 1, "text": text}}}));
+        (server, messages)
+    }
+
+    fn request(server: &mut Server, method: &str, line: usize, character: usize) -> Value {
+        let mut responses = server.handle_message(&json!({"jsonrpc": "2.0", "id": 1, "method": method,
+            "params": {"textDocument": {"uri": URI}, "position": {"line": line, "character": character}}}));
+        assert_eq!(responses.len(), 1);
+        responses.remove(0)["result"].take()
+    }
+
+    #[test]
+    fn server_initialize_and_shutdown() {
+        let mut server = Server::new(|| Metta::new(Some(EnvBuilder::test_env())));
+        let response = server.handle_message(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}));
+        assert_eq!(response[0]["result"]["capabilities"]["hoverProvider"], json!(true));
+        let response = server.handle_message(&json!({"jsonrpc": "2.0", "id": 2, "method": "unknown"}));
+        assert_eq!(response[0]["error"]["code"], json!(METHOD_NOT_FOUND));
+        let response = server.handle_message(&json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}));
+        assert_eq!(response, vec![json!({"jsonrpc": "2.0", "id": 3, "result": null})]);
+        assert!(server.handle_message(&json!({"jsonrpc": "2.0", "method": "exit"})).is_empty());
+        assert!(server.exit_requested);
+    }
+
+    #[test]
+    fn server_publishes_diagnostics() {
+        let (mut server, messages) = server_with_document("(: a A)\n(: foo (-> B B))\n!(foo a)");
+        assert_eq!(messages, vec![publish_diagnostics(URI, vec![json!({
+            "range": {"start": {"line": 2, "character": 1}, "end": {"line": 2, "character": 8}},
+            "severity": SEVERITY_ERROR,
+            "source": "metta",
+            "message": "(foo a) is badly typed",
+        })])]);
+
+        let messages = server.handle_message(&json!({"jsonrpc": "2.0", "method": "textDocument/didChange",
+            "params": {"textDocument": {"uri": URI, "version": 2}, "contentChanges": [{"text": "(a\n  (b"}]}}));
+        assert_eq!(messages[0]["params"]["diagnostics"][0]["message"], json!("Unexpected end of expression"));
+        assert_eq!(messages[0]["params"]["diagnostics"][0]["range"]["start"], json!({"line": 1, "character": 2}));
+
+        let messages = server.handle_message(&json!({"jsonrpc": "2.0", "method": "textDocument/didClose",
+            "params": {"textDocument": {"uri": URI}}}));
+        assert_eq!(messages, vec![publish_diagnostics(URI, vec![])]);
+    }
+
+    #[test]
+    fn server_checks_each_document_by_its_own_runner() {
+        let (mut server, _) = server_with_document("(: foo (-> A A))");
+        server.handle_message(&json!({"jsonrpc": "2.0", "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": "file:///other.metta", "languageId": "metta", "version": 1, "text": "!(foo)"}}}));
+        assert_eq!(server.runners.len(), 2);
+        assert!(server.runners[URI].space() != server.runners["file:///other.metta"].space());
+        assert!(server.runners[URI].space() != server.metta.space());
+        let atom_count = server.runners[URI].space().borrow().atom_count();
+
+        server.handle_message(&json!({"jsonrpc": "2.0", "method": "textDocument/didChange",
+            "params": {"textDocument": {"uri": URI, "version": 2}, "contentChanges": [{"text": "(: bar B)\n!(bar)"}]}}));
+        assert_eq!(server.runners[URI].space().borrow().atom_count(), atom_count);
+
+        server.handle_message(&json!({"jsonrpc": "2.0", "method": "textDocument/didClose",
+            "params": {"textDocument": {"uri": URI}}}));
+        assert_eq!(server.runners.keys().collect::<Vec<_>>(), vec!["file:///other.metta"]);
+    }
+
+    #[test]
+    fn server_goes_to_definition() {
+        let (mut server, _) = server_with_document("(: foo (-> Number Number))\n(= (foo $x) $x)\n!(foo 1)");
+        assert_eq!(request(&mut server, "textDocument/definition", 2, 3), json!([
+            {"uri": URI, "range": {"start": {"line": 0, "character": 3}, "end": {"line": 0, "character": 6}}},
+            {"uri": URI, "range": {"start": {"line": 1, "character": 4}, "end": {"line": 1, "character": 7}}},
+        ]));
+        assert_eq!(request(&mut server, "textDocument/definition", 1, 9), Value::Null);
+    }
+
+    #[test]
+    fn server_hover_shows_type_and_doc() {
+        let (mut server, _) = server_with_document("(: foo (-> Number Number))\n(@doc foo (@desc \"Returns \\\"x\\\"\") (@params ((@param \"Value\"))) (@return \"Same value\"))\n!(foo (if True 1 unknown))");
+        assert_eq!(request(&mut server, "textDocument/hover", 2, 2), json!({
+            "contents": {"kind": "markdown", "value": "```metta\n(: foo (-> Number Number))\n```\n\nReturns \"x\"\n\nParameters:\n- Value\n\nReturns: Same value"},
+            "range": {"start": {"line": 2, "character": 2}, "end": {"line": 2, "character": 5}},
+        }));
+        let hover = request(&mut server, "textDocument/hover", 2, 8);
+        let hover = hover["contents"]["value"].as_str().unwrap();
+        assert!(hover.starts_with("```metta\n(: if (-> Bool Atom Atom"), "{hover}");
+        assert!(hover.contains("Replace itself by one of the arguments depending on condition."), "{hover}");
+        assert_eq!(request(&mut server, "textDocument/hover", 2, 15)["contents"]["value"], json!("```metta\n(: 1 Number)\n```"));
+        assert_eq!(request(&mut server, "textDocument/hover", 2, 18), Value::Null);
+    }
+
+    #[test]
+    fn server_caches_document_space_per_version() {
+        let (mut server, _) = server_with_document("(: foo A)\n!(foo)");
+        let document = &server.documents[URI];
+        assert!(std::ptr::eq(server.document_space(document), server.document_space(document)));
+        assert_eq!(request(&mut server, "textDocument/hover", 1, 2)["contents"]["value"], json!("```metta\n(: foo A)\n```"));
+
+        server.handle_message(&json!({"jsonrpc": "2.0", "method": "textDocument/didChange",
+            "params": {"textDocument": {"uri": URI, "version": 2}, "contentChanges": [{"text": "(: foo B)\n!(foo)"}]}}));
+        assert_eq!(request(&mut server, "textDocument/hover", 1, 2)["contents"]["value"], json!("```metta\n(: foo B)\n```"));
+    }
+
+    #[test]
+    fn server_completes_symbols() {
+        let (mut server, _) = server_with_document("(: fooBar (-> A A))\n(: fooConst A)\n(= (fooBaz) 1)\n!(foo\n!(colla");
+        let labels = |result: Value| -> Vec<(String, i64)> {
+            result["items"].as_array().unwrap().iter()
+                .map(|item| (item["label"].as_str().unwrap().to_string(), item["kind"].as_i64().unwrap()))
+                .collect()
+        };
+        assert_eq!(labels(request(&mut server, "textDocument/completion", 3, 5)), vec![
+            ("fooBar".to_string(), COMPLETION_KIND_FUNCTION),
+            ("fooBaz".to_string(), COMPLETION_KIND_FUNCTION),
+            ("fooConst".to_string(), COMPLETION_KIND_CONSTANT),
+        ]);
+        assert_eq!(labels(request(&mut server, "textDocument/completion", 4, 7)), vec![
+            ("collapse".to_string(), COMPLETION_KIND_KEYWORD),
+        ]);
+    }
+}
@@ -0,0 +1,69 @@
+//! Framing of the JSON-RPC messages exchanged by the Language Server Protocol: each message is
+//! a `Content-Length` header followed by an empty line and the JSON content.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+/// Reads the next message from `reader`. Returns `None` when the input is closed.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let length = value.trim().parse::<usize>()
+                    .map_err(|err| invalid_data(format!("Invalid Content-Length header: {err}")))?;
+                content_length = Some(length);
+            }
+        }
+    }
+    let content_length = content_length
+        .ok_or_else(|| invalid_data("Content-Length header is expected".to_string()))?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| invalid_data(format!("Invalid JSON message: {err}")))
+}
+
+/// Writes `message` into `writer` and flushes it
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    writer.flush()
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn write_and_read_message() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({"jsonrpc": "2.0", "method": "exit"})).unwrap();
+        write_message(&mut buffer, &json!({"text": "café"})).unwrap();
+        let mut reader = io::Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(json!({"jsonrpc": "2.0", "method": "exit"})));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(json!({"text": "café"})));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn read_message_without_length() {
+        let mut reader = io::Cursor::new("Content-Type: x\r\n\r\n{}");
+        assert!(read_message(&mut reader).is_err());
+    }
+}
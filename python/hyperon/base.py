@@ -394,6 +394,16 @@ class SExprParser:
         cnode = self.cparser.parse_to_syntax_tree()
         return SyntaxNode(cnode) if cnode is not None else None
 
+def format_source(text, width=80):
+    """
+    Formats the MeTTa source text trying to keep lines not longer than width chars.
+    Raises SyntaxError if the text cannot be parsed.
+    """
+    formatted, result = hp.format_source(text, width)
+    if not formatted:
+        raise SyntaxError(result)
+    return result
+
 class Interpreter:
     """
     A wrapper class for the MeTTa interpreter that handles the interpretation of expressions in a given grounding space.
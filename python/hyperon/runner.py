@@ -195,6 +195,13 @@ class MeTTa:
             raise RuntimeError(err_str)
         return mod_id
 
+    def check(self, program):
+        """
+        Checks types of the MeTTa code from the program string without running it, returns the
+        list of the errors found
+        """
+        return hp.metta_check(self.cmetta, program)
+
     def run(self, program, flat=False):
         """Runs the MeTTa code from the program string containing S-Expression MeTTa syntax"""
         parser = SExprParser(program)
@@ -91,6 +91,11 @@ static void copy_atoms(const atom_vec_t* atoms, void* context) {
     }
 }
 
+static void copy_str(const char* str, void* context) {
+    py::list* list = static_cast<py::list*>(context);
+    list->append(std::string(str));
+}
+
 static void copy_atom_to_dict(atom_ref_t var, atom_ref_t atom, void* context) {
     py::dict& pybindings = *static_cast<py::dict*>(context);
     std::string var_name = func_to_string((write_to_buf_func_t)&atom_get_name, &var);
@@ -882,6 +887,11 @@ PYBIND11_MODULE(hyperonpy, m) {
         .value("LEFTOVER_TEXT", syntax_node_type_t::LEFTOVER_TEXT)
         .value("EXPRESSION_GROUP", syntax_node_type_t::EXPRESSION_GROUP)
         .value("ERROR_GROUP", syntax_node_type_t::ERROR_GROUP)
+        .value("BLOCK_COMMENT", syntax_node_type_t::BLOCK_COMMENT)
+        .value("RAW_STRING_TOKEN", syntax_node_type_t::RAW_STRING_TOKEN)
+        .value("QUOTE_PREFIX", syntax_node_type_t::QUOTE_PREFIX)
+        .value("QUASI_QUOTE_GROUP", syntax_node_type_t::QUASI_QUOTE_GROUP)
+        .value("UNQUOTE_GROUP", syntax_node_type_t::UNQUOTE_GROUP)
         .export_values();
 
     py::class_<CSyntaxNode>(m, "CSyntaxNode");
@@ -906,6 +916,11 @@ PYBIND11_MODULE(hyperonpy, m) {
         .def("parse", &CSExprParser::parse,  "Return next parsed atom, None, or an error expression")
         .def("sexpr_parser_err_str", &CSExprParser::err_str,  "Return the parse error from the previous parse operation or None")
         .def("parse_to_syntax_tree", &CSExprParser::parse_to_syntax_tree,  "Return next parser atom or None, as a syntax node at the root of a syntax tree");
+    m.def("format_source", [](char const* text, size_t width) {
+            py::list result;
+            bool formatted = format_source(text, width, copy_str, &result);
+            return py::make_tuple(formatted, result[0]);
+        }, "Format MeTTa source text, returns formatted text or syntax error with the success flag");
 
     py::class_<CStepResult>(m, "CStepResult")
         .def("__str__", [](CStepResult step) {
@@ -1014,6 +1029,11 @@ PYBIND11_MODULE(hyperonpy, m) {
             metta_evaluate_atom(metta.ptr(), atom_clone(atom.ptr()), copy_atoms, &atoms);
             return atoms;
         }, "Run MeTTa interpreter on an atom");
+    m.def("metta_check", [](CMetta& metta, char const* text) {
+            py::list errors;
+            metta_check(metta.ptr(), text, copy_str, &errors);
+            return errors;
+        }, "Check types of the MeTTa code without running it");
 
     py::class_<CRunnerState>(m, "CRunnerState")
         .def("__str__", [](CRunnerState state) {
@@ -59,11 +59,12 @@ class GroundedTypeTest(unittest.TestCase):
         self.assertEqual(metta.run("!(id_num untyp)"), [metta.parse_all("(id_num untyp)")])
         # Typed symbols cause type error when evaluated
         metta.run("(: myAtom myType)")
-        self.assertEqual(metta.run('''
+        self.assertEqual(repr(metta.run('''
             !(id_num myAtom)
             !(id_num False)
-            '''),
-            [[E(S('Error'), S('myAtom'), S('BadType'))]])
+            ''')),
+            '[[(Error myAtom BadType "argument 1 of (id_num myAtom) should have type Number, '
+            'but myAtom has type myType")]]')
         ### Grounded functions over Atom
         ### (should use unwrap=False to deal with non-grounded atoms)
         # All grounded and ungrounded, typed and untyped symbols should be processed
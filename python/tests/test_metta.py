@@ -56,7 +56,8 @@ class MettaTest(unittest.TestCase):
         runner = MeTTa(env_builder=Environment.test_env())
         result = runner.run(program)
 
-        self.assertEqual([[E(S('Error'), ValueAtom('String'), S('BadType'))]], result)
+        self.assertEqual(repr(result), '[[(Error "String" BadType "argument 2 of (+ 2 \\"String\\") '
+            'should have type Number, but \\"String\\" has type String")]]')
 
     def test_runner_error(self):
         program = '''
@@ -68,3 +69,13 @@ class MettaTest(unittest.TestCase):
             self.assertTrue(False, "Parse error expected")
         except RuntimeError as e:
             self.assertEqual(e.args[0], 'Unexpected end of expression')
+
+    def test_check(self):
+        program = '''
+          (: foo (-> Number Number))
+          !(foo "a")
+        '''
+        runner = MeTTa(env_builder=Environment.test_env())
+        errors = runner.check(program)
+        self.assertEqual(len(errors), 1)
+        self.assertIn('(foo "a") is badly typed', errors[0])
@@ -44,3 +44,13 @@ class HyperonTestCase(unittest.TestCase):
         parser = SExprParser("(+ one \"one\")")
         parsed_atom = parser.parse(tokenizer)
         self.assertTrue(parsed_atom is not None)
+
+    def testFormatSource(self):
+        self.assertEqual(format_source("(a   b\n (c  $d))(e)"), "(a b (c $d))\n(e)\n")
+        self.assertEqual(format_source("(= (foo $x $y) (bar (baz $x) (qux $y)))", 20),
+            "(= (foo $x $y)\n  (bar (baz $x)\n    (qux $y)))\n")
+        try:
+            format_source("(a))")
+            self.assertTrue(False, "Syntax error expected")
+        except SyntaxError as e:
+            self.assertEqual(e.args[0], '1:4: Unexpected right bracket')
@@ -79,13 +79,15 @@ impl Highlighter for ReplHelper {
 
             //Set up the style for the node
             match node_type {
-                SyntaxNodeType::Comment => {
+                SyntaxNodeType::Comment |
+                SyntaxNodeType::BlockComment => {
                     style_sequence.push(&self.style.comment_style);
                 },
                 SyntaxNodeType::VariableToken => {
                     style_sequence.push(&self.style.variable_style);
                 },
-                SyntaxNodeType::StringToken => {
+                SyntaxNodeType::StringToken |
+                SyntaxNodeType::RawStringToken => {
                     style_sequence.push(&self.style.string_style);
                 },
                 SyntaxNodeType::WordToken => {
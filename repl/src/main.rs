@@ -8,7 +8,7 @@ use rustyline::error::ReadlineError;
 use rustyline::{Cmd, CompletionType, Config, EditMode, Editor, KeyEvent, KeyCode, Modifiers, EventContext, RepeatCount, EventHandler, ConditionalEventHandler, Event};
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use signal_hook::{consts::SIGINT, iterator::Signals};
 
 mod metta_shim;
@@ -23,7 +23,7 @@ use interactive_helper::*;
 static SIGINT_RECEIVED_COUNT: Mutex<usize> = Mutex::new(0);
 
 #[derive(Parser)]
-#[command(version, about)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
 struct CliArgs {
     /// .metta file to execute.  `metta` will run in interactive mode if no file is supplied
     file: Option<PathBuf>,
@@ -31,11 +31,40 @@ struct CliArgs {
     /// Additional include directory paths
     #[arg(short, long)]
     include_paths: Vec<PathBuf>,
+
+    /// Check types of the .metta file and report all errors found, without running the file
+    #[arg(long, requires = "file")]
+    check: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Rewrite .metta files using the canonical formatting
+    Fmt {
+        /// .metta files to format
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Report the files which are not formatted instead of rewriting them
+        #[arg(long)]
+        check: bool,
+
+        /// Maximal width of the formatted lines
+        #[arg(long, default_value_t = 80)]
+        width: usize,
+    },
 }
 
 fn main() -> Result<()> {
     let cli_args = CliArgs::parse();
 
+    if let Some(Command::Fmt{ files, check, width }) = &cli_args.command {
+        return format_files(files, *check, *width);
+    }
+
     //If we have a metta_file, then the working dir is the parent of that file
     //If we are running in interactive mode, it's the working dir at the time the repl is invoked
     let metta_working_dir: PathBuf = match &cli_args.file {
@@ -78,8 +107,21 @@ fn main() -> Result<()> {
     //If we have .metta files to run, then run them
     if let Some(metta_file) = &cli_args.file {
 
-        //Only print the output from the primary .metta file
         let metta_code = std::fs::read_to_string(metta_file)?;
+
+        //Report type errors instead of running the file
+        if cli_args.check {
+            let errors = metta.check(metta_code.as_str()).map_err(anyhow::Error::msg)?;
+            for err in errors.iter() {
+                println!("{}:{err}", metta_file.display());
+            }
+            if !errors.is_empty() {
+                exit(1);
+            }
+            return Ok(())
+        }
+
+        //Only print the output from the primary .metta file
         metta.exec(metta_code.as_str());
         metta.print_result();
         Ok(())
@@ -91,6 +133,31 @@ fn main() -> Result<()> {
     }
 }
 
+/// Formats the `files` in place, or only reports the files which are not formatted when
+/// `check` is set. Exits with an error code if any file is not formatted or cannot be parsed
+fn format_files(files: &[PathBuf], check: bool, width: usize) -> Result<()> {
+    let mut failed = false;
+    for file in files {
+        let code = std::fs::read_to_string(file)?;
+        match MettaShim::format_source(&code, width) {
+            Ok(formatted) if formatted == code => {},
+            Ok(_) if check => {
+                println!("{} is not formatted", file.display());
+                failed = true;
+            },
+            Ok(formatted) => std::fs::write(file, formatted)?,
+            Err(err) => {
+                eprintln!("{}:{err}", file.display());
+                failed = true;
+            },
+        }
+    }
+    if failed {
+        exit(1);
+    }
+    Ok(())
+}
+
 // To debug rustyline:
 // RUST_LOG=rustyline=debug cargo run --example example 2> debug.log
 fn start_interactive_mode(repl_params: ReplParams, mut metta: MettaShim) -> rustyline::Result<()> {
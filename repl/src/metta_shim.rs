@@ -183,6 +183,26 @@ pub mod metta_interface_mod {
             }).unwrap()
         }
 
+        pub fn check(&mut self, code: &str) -> Result<Vec<String>, String> {
+            Python::with_gil(|py| -> PyResult<Vec<String>> {
+                let py_code = PyString::new(py, code);
+                let py_metta = self.py_metta.as_ref(py);
+                let args = PyTuple::new(py, &[py_metta, py_code]);
+                let module: &PyModule = self.py_mod.as_ref(py);
+                let func = module.getattr("check")?;
+                func.call1(args)?.extract()
+            }).map_err(|err| format!("{err}"))
+        }
+
+        pub fn format_source(code: &str, width: usize) -> Result<String, String> {
+            Python::with_gil(|py| -> PyResult<Result<String, String>> {
+                let py_mod = PyModule::from_code(py, Self::PY_CODE, "", "")?;
+                let func = py_mod.getattr("format_code")?;
+                let (formatted, result): (bool, String) = func.call1((code, width))?.extract()?;
+                Ok(if formatted { Ok(result) } else { Err(result) })
+            }).map_err(|err| format!("{err}"))?
+        }
+
         pub fn parse_and_unroll_syntax_tree(&self, line: &str) -> Vec<(SyntaxNodeType, std::ops::Range<usize>)> {
 
             Python::with_gil(|py| -> PyResult<Vec<(SyntaxNodeType, std::ops::Range<usize>)>> {
@@ -275,6 +295,11 @@ pub mod metta_interface_mod {
         LeftoverText,
         ExpressionGroup,
         ErrorGroup,
+        BlockComment,
+        RawStringToken,
+        QuotePrefix,
+        QuasiQuoteGroup,
+        UnquoteGroup,
     }
 
     impl SyntaxNodeType {
@@ -290,6 +315,11 @@ pub mod metta_interface_mod {
                 "SyntaxNodeType.LEFTOVER_TEXT" => Self::LeftoverText,
                 "SyntaxNodeType.EXPRESSION_GROUP" => Self::ExpressionGroup,
                 "SyntaxNodeType.ERROR_GROUP" => Self::ErrorGroup,
+                "SyntaxNodeType.BLOCK_COMMENT" => Self::BlockComment,
+                "SyntaxNodeType.RAW_STRING_TOKEN" => Self::RawStringToken,
+                "SyntaxNodeType.QUOTE_PREFIX" => Self::QuotePrefix,
+                "SyntaxNodeType.QUASI_QUOTE_GROUP" => Self::QuasiQuoteGroup,
+                "SyntaxNodeType.UNQUOTE_GROUP" => Self::UnquoteGroup,
                 _ => panic!("Unrecognized syntax node type: {the_str}")
             }
         }
@@ -394,6 +424,14 @@ pub mod metta_interface_mod {
             }
         }
 
+        pub fn check(&mut self, code: &str) -> Result<Vec<String>, String> {
+            Ok(self.metta.check(code).iter().map(|err| err.to_string()).collect())
+        }
+
+        pub fn format_source(code: &str, width: usize) -> Result<String, String> {
+            hyperon::metta::format::format_source(code, width)
+        }
+
         pub fn exec(&mut self, line: &str) {
             let parser = SExprParser::new(line);
             let mut runner_state = RunnerState::new_with_parser(&self.metta, Box::new(parser));
@@ -17,6 +17,15 @@ def run_step(runner_state):
     runner_state.run_step()
     return runner_state.current_results()
 
+def check(metta, program):
+    return metta.check(program)
+
+def format_code(code, width):
+    try:
+        return (True, format_source(code, width))
+    except SyntaxError as e:
+        return (False, e.args[0])
+
 def parse_line(metta, line):
     tokenizer = metta.tokenizer()
     parser = SExprParser(line)